@@ -1,40 +1,79 @@
-use std::collections::HashMap;
-
-use iced::widget::{self, button, column, container, row, text};
+use iced::widget::{self, button, column, container, row, text, text_input};
 use iced::{Color, Element};
 
 use rust_i18n::t;
 
-use crate::sorting::tag_badge_color;
+use crate::file_ops;
+use crate::format::{format_count, format_file_size};
+use crate::share_export::view_share_export_form;
+use crate::sorting::{tag_totals, view_remove_tag_button, SortingMessage, TagTotal, REJECT_TAG};
+use crate::time_shift::view_time_shift_form;
+use crate::upload::view_upload_form;
+use crate::zip_export::view_zip_form;
 use crate::{Message, Tag, TagNames};
 
-pub fn view_actions_tab(
-    selected_action_tag: &Option<Tag>,
-    tag_names: TagNames,
-    tag_counts: &HashMap<Tag, u32>,
-) -> Element<'static, Message> {
-    if let Some(tag) = selected_action_tag {
+// The tag names are passed apart, they are the defaults when not sorting
+pub fn view_actions_tab(model: &crate::Model, tag_names: TagNames) -> Element<'static, Message> {
+    let tag_totals = &tag_totals(&model.pathlist.paths, &tag_names);
+    let time_shift_form = &model.time_shift_form;
+    let share_export_form = &model.share_export_form;
+    let status = model.action_status.clone().map(text);
+
+    if let Some(tag) = model.selected_action_tag {
         // Show tag action view
-        let tag_name = tag_names.get(tag).to_string();
+        let tag_name = tag_names.get(&tag).to_string();
 
         container(
             column![
                 row![
                     button(text(t!("← Back"))).on_press(Message::UserPressedActionBack),
-                    text(tag_name).size(24),
+                    text(tag_name.clone()).size(24),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+                row![
+                    text(t!("Destination folder")),
+                    text_input(&tag_name, tag_names.custom_destination(&tag).unwrap_or(""))
+                        .on_input(move |text| Message::UserChangedTagDestination(tag, text))
+                        .width(300),
+                    button(text(t!("Choose...")))
+                        .on_press(Message::UserPressedPickTagDestination(tag)),
                 ]
                 .spacing(10)
                 .align_y(iced::Alignment::Center),
+                text(t!(
+                    "Put %{relpath} in the folder to keep the subfolders the images are in",
+                    relpath = file_ops::RELPATH
+                ))
+                .size(12),
                 column![
                     button(text(t!("Delete"))).width(200),
                     button(text(t!("Move")))
                         .width(200)
-                        .on_press(Message::UserPressedActionCopy(*tag)),
-                    button(text(t!("Copy"))).width(200),
+                        .on_press(Message::UserPressedActionMove(tag)),
+                    button(text(t!("Copy")))
+                        .width(200)
+                        .on_press(Message::UserPressedActionCopy(tag)),
+                    button(text(t!("Set file times from EXIF")))
+                        .width(200)
+                        .on_press(Message::UserPressedActionFixTimestamps(Some(tag))),
+                    view_time_shift_form(time_shift_form, Some(tag)),
+                    view_share_export_form(share_export_form, tag),
+                    view_zip_form(model.zip_resized, share_export_form, tag),
+                    view_upload_form(&tag_names, tag),
+                    button(text(t!("Print...")))
+                        .width(200)
+                        .on_press(Message::UserPressedPrint(tag)),
+                    view_remove_tag_button(
+                        tag,
+                        tag_totals.get(&tag).map_or(0, |total| total.count),
+                        model.removing_tag == Some(tag),
+                    ),
                 ]
                 .spacing(10)
                 .padding(20),
             ]
+            .push_maybe(status)
             .spacing(20),
         )
         .padding(20)
@@ -44,42 +83,84 @@ pub fn view_actions_tab(
         let mut buttons = Vec::new();
 
         for (tag, name) in tag_names.enumerate() {
-            if let Some(count) = tag_counts.get(&tag) {
-                buttons.push(view_action_tag_button(tag, name.clone(), *count));
-            }
+            let total = tag_totals.get(&tag).copied().unwrap_or_default();
+            let color = tag_names.color(&tag);
+            buttons.push(view_action_tag_button(tag, name.clone(), color, total));
         }
 
-        let buttons_col = column(buttons).spacing(10);
+        // The estimate for the tag given by the Delete key
+        let freed = match tag_totals.get(&REJECT_TAG) {
+            Some(total) if total.count > 0 => Some(
+                text(t!(
+                    "Deleting the %{count} images tagged %{tag} would free %{size}",
+                    count = format_count(total.count as usize),
+                    tag = tag_names.get(&REJECT_TAG),
+                    size = format_total_size(total)
+                ))
+                .size(14),
+            ),
+            _ => None,
+        };
+        let buttons_col = column(buttons).push_maybe(freed).spacing(10);
 
         let tag_buttons = column![
             text(t!("Actions")).size(24),
             text(t!("Select a tag to perform actions:")).size(16),
             buttons_col,
+            button(text(t!("Add tag")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedAddTag)),
+            button(text(t!("Set file times from EXIF for all files")))
+                .on_press(Message::UserPressedActionFixTimestamps(None)),
+            view_time_shift_form(time_shift_form, None),
+            row![
+                button(text(t!("Export tags..."))).on_press(Message::UserPressedExportTags),
+                button(text(t!("Import tags..."))).on_press(Message::UserPressedImportTags),
+                button(text(t!("Export session..."))).on_press(Message::UserPressedExportSession),
+                button(text(t!("Merge session..."))).on_press(Message::UserPressedMergeSession),
+                button(text(t!("Compare two folders...")))
+                    .on_press(Message::UserPressedCompareFolders),
+            ]
+            .spacing(10),
         ]
+        .push_maybe(status)
         .spacing(15);
 
         container(tag_buttons).padding(20).into()
     }
 }
 
-fn view_action_tag_button(tag: Tag, name: String, count: u32) -> Element<'static, Message> {
-    let tag_name = format!("{name} ({count})");
+// While the sizes are still being read the total is a lower bound
+fn format_total_size(total: &TagTotal) -> String {
+    match total.partial {
+        true => t!("at least %{size}", size = format_file_size(total.size)).to_string(),
+        false => format_file_size(total.size),
+    }
+}
+
+fn view_action_tag_button(
+    tag: Tag,
+    name: String,
+    color: Color,
+    total: TagTotal,
+) -> Element<'static, Message> {
+    let tag_name = format!("{name} ({})", format_count(total.count as usize));
 
-    widget::button(text(tag_name))
+    let button = widget::button(text(tag_name))
         .width(200)
-        .style(move |_theme, _status| {
-            let color = tag_badge_color(&tag);
-            widget::button::Style {
-                background: Some(iced::Background::Color(color)),
-                text_color: Color::WHITE,
-                border: iced::Border {
-                    color,
-                    width: 1.0,
-                    radius: 4.0.into(),
-                },
-                shadow: iced::Shadow::default(),
-            }
+        .style(move |_theme, _status| widget::button::Style {
+            background: Some(iced::Background::Color(color)),
+            text_color: Color::WHITE,
+            border: iced::Border {
+                color,
+                width: 1.0,
+                radius: 4.0.into(),
+            },
+            shadow: iced::Shadow::default(),
         })
-        .on_press(Message::UserPressedActionTag(tag))
+        .on_press(Message::UserPressedActionTag(tag));
+    row![button]
+        .push_maybe((total.count > 0).then(|| text(format_total_size(&total))))
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
         .into()
 }
@@ -0,0 +1,243 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::exif_data;
+use crate::image_size::read_pixel_size;
+use crate::metadata_db::content_hash;
+use crate::session;
+use crate::sorting::TagNames;
+use crate::{get_files_in_folder, FolderScan};
+
+// Listings of a folder for scripts, without opening the window. Each
+// subcommand prints one line per image with its path first and the other
+// fields tab separated, or a JSON array with --json. Fields that are not known
+// for an image are left out.
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// List the images of a folder with their file size, tag and rating
+    List(BatchArgs),
+    /// List the capture time, camera, pixel size and orientation of the images of a folder
+    Exif(BatchArgs),
+    /// List the SHA-256 of the contents of the images of a folder
+    Hashes(BatchArgs),
+}
+
+#[derive(clap::Args)]
+pub struct BatchArgs {
+    /// Folder to list
+    #[arg(default_value = ".")]
+    folder: PathBuf,
+
+    /// Print a JSON array instead of one line per image
+    #[arg(long)]
+    json: bool,
+
+    /// Also list the images in subfolders
+    #[arg(long)]
+    recursive: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+struct Entry {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<u8>,
+    // Unix time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    taken: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    camera: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orientation: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+impl Entry {
+    fn to_line(&self) -> String {
+        let fields = [
+            self.size.map(|size| size.to_string()),
+            self.tag.clone(),
+            self.rating.map(|rating| rating.to_string()),
+            self.taken.map(|taken| taken.to_string()),
+            self.camera.clone(),
+            self.width
+                .zip(self.height)
+                .map(|(width, height)| format!("{width}x{height}")),
+            self.orientation.map(|orientation| orientation.to_string()),
+            self.sha256.clone(),
+        ];
+        std::iter::once(self.path.clone())
+            .chain(fields.into_iter().flatten())
+            .collect::<Vec<_>>()
+            .join("\t")
+    }
+}
+
+// The exit code
+pub fn run(command: Command) -> i32 {
+    let (args, entries) = match command {
+        Command::List(args) => {
+            let entries = list(&args);
+            (args, entries)
+        }
+        Command::Exif(args) => {
+            let entries = exif(&args);
+            (args, entries)
+        }
+        Command::Hashes(args) => {
+            let entries = hashes(&args);
+            (args, entries)
+        }
+    };
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error listing {}: {e}", args.folder.display());
+            return 1;
+        }
+    };
+    if args.json {
+        match serde_json::to_string_pretty(&entries) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error writing JSON: {e}");
+                return 1;
+            }
+        }
+    } else {
+        for entry in &entries {
+            println!("{}", entry.to_line());
+        }
+    }
+    0
+}
+
+fn files(args: &BatchArgs) -> std::io::Result<Vec<String>> {
+    let scan = FolderScan {
+        recursive: args.recursive,
+        skip_dirs: Vec::new(),
+    };
+    get_files_in_folder(&args.folder.to_string_lossy(), &scan)
+}
+
+// The tags and ratings are those of the session of the listed folder, which
+// has those of its subfolders too when they were sorted along
+fn list(args: &BatchArgs) -> std::io::Result<Vec<Entry>> {
+    let session = session::load(&args.folder);
+    let tag_names = session.tag_names.clone().unwrap_or_else(TagNames::new);
+    let by_file_name = session::by_file_name(session.tags.keys().chain(session.ratings.keys()));
+    Ok(files(args)?
+        .into_iter()
+        .map(|path| {
+            let in_folder = Path::new(&path)
+                .strip_prefix(&args.folder)
+                .map(|relative| relative.to_string_lossy().to_string())
+                .unwrap_or_else(|_| path.clone());
+            Entry {
+                size: std::fs::metadata(&path).map(|metadata| metadata.len()).ok(),
+                tag: session::lookup(&session.tags, &in_folder, by_file_name)
+                    .map(|tag| tag_names.get(tag).to_owned()),
+                rating: session::lookup(&session.ratings, &in_folder, by_file_name).copied(),
+                path,
+                ..Entry::default()
+            }
+        })
+        .collect())
+}
+
+fn exif(args: &BatchArgs) -> std::io::Result<Vec<Entry>> {
+    Ok(files(args)?
+        .into_iter()
+        .map(|path| {
+            let pixel_size = read_pixel_size(&path);
+            Entry {
+                taken: exif_data::read_capture_time(&path).map(|taken| taken.unix_timestamp()),
+                camera: exif_data::read_camera(&path),
+                width: pixel_size.map(|(width, _)| width),
+                height: pixel_size.map(|(_, height)| height),
+                orientation: exif_data::read_orientation(&path),
+                path,
+                ..Entry::default()
+            }
+        })
+        .collect())
+}
+
+fn hashes(args: &BatchArgs) -> std::io::Result<Vec<Entry>> {
+    Ok(files(args)?
+        .into_iter()
+        .map(|path| Entry {
+            sha256: content_hash(&path).ok(),
+            path,
+            ..Entry::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::Tag;
+
+    #[test]
+    fn test_list() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("a.jpg"), "abc").unwrap();
+        std::fs::write(dir.join("b.jpg"), "").unwrap();
+        let mut tag_names = TagNames::new();
+        tag_names.update(Tag::new(1), "Keep".to_owned());
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/a.jpg"), "abcd").unwrap();
+        let session = session::Session {
+            tags: [
+                ("a.jpg".to_owned(), Tag::new(1)),
+                ("sub/a.jpg".to_owned(), Tag::new(2)),
+            ]
+            .into_iter()
+            .collect(),
+            tag_names: Some(tag_names),
+            ratings: [("a.jpg".to_owned(), 4)].into_iter().collect(),
+            ..session::Session::default()
+        };
+        session::save(dir, &session).unwrap();
+
+        let args = BatchArgs {
+            folder: dir.to_path_buf(),
+            json: false,
+            recursive: false,
+        };
+        let entries = list(&args).unwrap();
+        let a = format!("{}/a.jpg", dir.display());
+        assert_eq!(entries[0].to_line(), format!("{a}\t3\tKeep\t4"));
+        assert_eq!(entries[1].tag, None);
+        assert_eq!(
+            serde_json::to_value(&entries[0]).unwrap(),
+            serde_json::json!({"path": a, "size": 3, "tag": "Keep", "rating": 4})
+        );
+
+        // Subfolders are kept in the session of the folder sorted
+        let args = BatchArgs {
+            recursive: true,
+            ..args
+        };
+        let entries = list(&args).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[2].tag.as_deref(),
+            Some(TagNames::new().get(&Tag::new(2)))
+        );
+        assert_eq!(entries[2].rating, None);
+    }
+}
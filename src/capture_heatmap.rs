@@ -0,0 +1,104 @@
+use iced::widget::{column, container, row, text, Column, Row};
+use iced::{Color, Element};
+use rust_i18n::t;
+use time::OffsetDateTime;
+
+use crate::format::format_count;
+use crate::Message;
+
+// Number of photos per weekday and hour of capture, to see how a folder is
+// spread over an event
+
+const CELL_SIZE: f32 = 14.0;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaptureHeatmap {
+    // Monday first, hours 0-23 in the local time of the capture
+    counts: [[usize; 24]; 7],
+    without_time: usize,
+}
+
+impl CaptureHeatmap {
+    pub fn from_capture_times(times: &[Option<OffsetDateTime>]) -> Self {
+        let mut heatmap = Self::default();
+        for time in times {
+            match time {
+                Some(time) => {
+                    let weekday = time.weekday().number_days_from_monday() as usize;
+                    heatmap.counts[weekday][time.hour() as usize] += 1;
+                }
+                None => heatmap.without_time += 1,
+            }
+        }
+        heatmap
+    }
+
+    fn max(&self) -> usize {
+        self.counts.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let max = self.max().max(1);
+        let weekdays = [
+            t!("Mon"),
+            t!("Tue"),
+            t!("Wed"),
+            t!("Thu"),
+            t!("Fri"),
+            t!("Sat"),
+            t!("Sun"),
+        ];
+        let rows = self.counts.iter().zip(weekdays).map(|(hours, weekday)| {
+            let cells = hours.iter().map(|&count| {
+                let intensity = count as f32 / max as f32;
+                let color = if count == 0 {
+                    Color::from_rgb(0.9, 0.9, 0.9)
+                } else {
+                    Color::from_rgb(1.0 - intensity, 1.0 - 0.6 * intensity, 1.0)
+                };
+                container(text(""))
+                    .width(CELL_SIZE)
+                    .height(CELL_SIZE)
+                    .style(move |_: &iced::Theme| container::Style {
+                        background: Some(iced::Background::Color(color)),
+                        ..container::Style::default()
+                    })
+                    .into()
+            });
+            row![text(weekday).width(40)]
+                .push(Row::from_iter(cells).spacing(1))
+                .into()
+        });
+
+        column![text(t!("Capture times by weekday and hour"))]
+            .push(Column::from_iter(rows).spacing(1))
+            .push(text(t!(
+                "%{count} photos without a capture time",
+                count = format_count(self.without_time)
+            )))
+            .spacing(5)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month, PrimitiveDateTime, Time};
+
+    fn at(day: u8, hour: u8) -> Option<OffsetDateTime> {
+        let date = Date::from_calendar_date(2024, Month::June, day).unwrap();
+        let time = Time::from_hms(hour, 30, 0).unwrap();
+        Some(PrimitiveDateTime::new(date, time).assume_utc())
+    }
+
+    #[test]
+    fn test_from_capture_times() {
+        // 2024-06-01 is a Saturday
+        let heatmap = CaptureHeatmap::from_capture_times(&[at(1, 14), at(1, 14), at(3, 9), None]);
+        assert_eq!(heatmap.counts[5][14], 2);
+        assert_eq!(heatmap.counts[0][9], 1);
+        assert_eq!(heatmap.without_time, 1);
+        assert_eq!(heatmap.max(), 2);
+    }
+}
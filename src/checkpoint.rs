@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::sorting::{Tag, TagNames};
+
+// Snapshot of the in-memory sorting state, written periodically so that a
+// session can be restored after a crash. The file is removed on a clean exit,
+// so finding one at startup means the previous run ended abnormally.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub directory: String,
+    pub current: Option<String>,
+    pub images: Vec<CheckpointImage>,
+    pub tag_names: TagNames,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointImage {
+    pub path: String,
+    pub tag: Option<Tag>,
+    #[serde(default)]
+    pub dimensions: Option<(u32, u32)>,
+}
+
+fn checkpoint_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("imgsort").join("checkpoint.json"))
+}
+
+pub fn current_directory() -> String {
+    std::env::current_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn save(checkpoint: &Checkpoint) -> std::io::Result<()> {
+    let Some(path) = checkpoint_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string(checkpoint)?;
+
+    // Write to a temporary file first so a crash mid-write can't corrupt it
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)?;
+    std::fs::rename(tmp_path, &path)?;
+    debug!("Wrote checkpoint to {}", path.display());
+    Ok(())
+}
+
+pub fn load_for_directory(directory: &str) -> Option<Checkpoint> {
+    let path = checkpoint_path()?;
+    let json = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<Checkpoint>(&json) {
+        Ok(checkpoint) if checkpoint.directory == directory => Some(checkpoint),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Could not parse checkpoint: {e}");
+            None
+        }
+    }
+}
+
+pub fn remove() {
+    if let Some(path) = checkpoint_path() {
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Could not remove checkpoint {}: {e}", path.display());
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+
+use log::{debug, warn};
+
+use crate::{print, Config};
+
+// The settings, written whenever they are saved in the settings tab and read
+// at startup. Anything missing from the file or not understood keeps its
+// default, so the file can be edited by hand.
+
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("imgsort").join("config.toml"))
+}
+
+pub fn load() -> Option<Config> {
+    let path = config_path()?;
+    let text = std::fs::read_to_string(&path).ok()?;
+    match toml::from_str(&text) {
+        Ok(config) => {
+            debug!("Read config from {}", path.display());
+            Some(with_valid_values(config))
+        }
+        Err(e) => {
+            warn!("Could not read {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+// Values that can't work are put back to their defaults, as a zero
+// for the parallel loads would never load an image
+fn with_valid_values(mut config: Config) -> Config {
+    let default = Config::default();
+    let reset = |name: &str, invalid: bool| {
+        if invalid {
+            warn!("Invalid {name} in the config file, using the default");
+        }
+        invalid
+    };
+    if reset("io_concurrency", config.io_concurrency == 0) {
+        config.io_concurrency = default.io_concurrency;
+    }
+    if reset("slideshow_seconds", config.slideshow_seconds == 0) {
+        config.slideshow_seconds = default.slideshow_seconds;
+    }
+    if reset(
+        "print_per_page",
+        !print::PER_PAGE_CHOICES.contains(&config.print_per_page),
+    ) {
+        config.print_per_page = default.print_per_page;
+    }
+    if reset(
+        "scale_down_size",
+        config.scale_down_size.0 == 0 || config.scale_down_size.1 == 0,
+    ) {
+        config.scale_down_size = default.scale_down_size;
+    }
+    if reset(
+        "thumbnail_size",
+        config.thumbnail_size.width == 0 || config.thumbnail_size.height == 0,
+    ) {
+        config.thumbnail_size = default.thumbnail_size;
+    }
+    config
+}
+
+pub fn save(config: &Config) -> Result<(), String> {
+    let Some(path) = config_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let text = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, text).map_err(|e| e.to_string())?;
+    debug!("Wrote config to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::KeyAction;
+    use crate::sorting::Tag;
+    use crate::SortingViewStyle;
+
+    #[test]
+    fn test_round_trip() {
+        let mut config = Config {
+            thumbnail_style: SortingViewStyle::NoThumbnails,
+            ..Config::default()
+        };
+        config
+            .keymap
+            .set_keys(KeyAction::Tag(Tag::new(1)), vec!["1".to_owned()]);
+        let text = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn test_partial_file() {
+        let config: Config = toml::from_str(
+            "preload_front_num = 50\n\n[keymap]\ntag2 = [\"2\"]\nunknown = [\"x\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.preload_front_num, 50);
+        assert_eq!(config.preload_back_num, Config::default().preload_back_num);
+        assert_eq!(config.keymap.keys(KeyAction::Tag(Tag::new(2))), ["2"]);
+        assert_eq!(
+            config.keymap.keys(KeyAction::NextImage),
+            ["t", "l", "ArrowRight"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let config: Config = toml::from_str(
+            "io_concurrency = 0
+print_per_page = 5
+preload_back_num = 0
+",
+        )
+        .unwrap();
+        let config = with_valid_values(config);
+        assert_eq!(config.io_concurrency, Config::default().io_concurrency);
+        assert_eq!(config.print_per_page, Config::default().print_per_page);
+        // Zero is fine where it only turns something off
+        assert_eq!(config.preload_back_num, 0);
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use iced::widget::{column, row, text};
+use iced::Element;
+use rust_i18n::t;
+
+use crate::format::{format_count, format_decimal};
+use crate::Message;
+
+// Histogram of how long images took to decode and resize during this session
+
+const BUCKET_LIMITS_MS: [u64; 5] = [50, 100, 250, 500, 1000];
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Default)]
+pub struct DecodeHistogram {
+    buckets: [usize; BUCKET_LIMITS_MS.len() + 1],
+    total: Duration,
+    count: usize,
+}
+
+impl DecodeHistogram {
+    pub fn record(&mut self, duration: Duration) {
+        self.buckets[bucket_index(duration)] += 1;
+        self.total += duration;
+        self.count += 1;
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let max = self.buckets.iter().copied().max().unwrap_or(0).max(1);
+        let rows = self.buckets.iter().enumerate().map(|(i, &count)| {
+            let label = match (i.checked_sub(1), BUCKET_LIMITS_MS.get(i)) {
+                (None, Some(upper)) => format!("< {upper} ms"),
+                (Some(lower), Some(upper)) => format!("{}-{upper} ms", BUCKET_LIMITS_MS[lower]),
+                _ => format!("> {} ms", BUCKET_LIMITS_MS[BUCKET_LIMITS_MS.len() - 1]),
+            };
+            let bar = "#".repeat(count * BAR_WIDTH / max);
+            row![
+                text(label).width(100),
+                text(bar).font(iced::Font::MONOSPACE),
+                text(format_count(count)),
+            ]
+            .spacing(5)
+            .into()
+        });
+
+        let average = if self.count > 0 {
+            self.total.as_secs_f64() * 1000.0 / self.count as f64
+        } else {
+            0.0
+        };
+        column![text(t!(
+            "Decoded %{count} images, average %{average} ms",
+            count = format_count(self.count),
+            average = format_decimal(average, 1)
+        ))]
+        .extend(rows)
+        .into()
+    }
+}
+
+fn bucket_index(duration: Duration) -> usize {
+    let ms = duration.as_millis() as u64;
+    BUCKET_LIMITS_MS
+        .iter()
+        .position(|&limit| ms < limit)
+        .unwrap_or(BUCKET_LIMITS_MS.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_index() {
+        assert_eq!(bucket_index(Duration::from_millis(0)), 0);
+        assert_eq!(bucket_index(Duration::from_millis(49)), 0);
+        assert_eq!(bucket_index(Duration::from_millis(50)), 1);
+        assert_eq!(bucket_index(Duration::from_millis(999)), 4);
+        assert_eq!(bucket_index(Duration::from_secs(3)), 5);
+    }
+}
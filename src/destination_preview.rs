@@ -0,0 +1,81 @@
+use iced::widget::{column, image, text, Column, Row};
+use iced::{Element, Length};
+use rust_i18n::t;
+
+use crate::format::format_count;
+use crate::sorting::{Dim, Tag};
+use crate::{get_files_in_folder, get_resized_image, FolderScan, ImageData, Message};
+
+// What the destination folder of a tag already holds, shown next to the
+// sorting view when appending to an existing archive
+
+const PREVIEW_THUMBS: usize = 12;
+const THUMBS_PER_ROW: usize = 3;
+
+#[derive(Debug)]
+pub struct DestinationPreview {
+    pub tag: Tag,
+    // None while it is being read
+    pub contents: Option<DestinationContents>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DestinationContents {
+    pub count: usize,
+    // Newest first
+    pub thumbs: Vec<ImageData>,
+}
+
+pub fn read_destination(folder: &str, scan: &FolderScan, thumb_size: Dim) -> DestinationContents {
+    let Ok(mut paths) = get_files_in_folder(folder, scan) else {
+        // Nothing moved there yet
+        return DestinationContents::default();
+    };
+    let modified = |path: &String| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    paths.sort_by_cached_key(|path| std::cmp::Reverse(modified(path)));
+    let thumbs = paths
+        .iter()
+        .take(PREVIEW_THUMBS)
+        .filter_map(|path| get_resized_image(path, thumb_size).ok())
+        .collect();
+    DestinationContents {
+        count: paths.len(),
+        thumbs,
+    }
+}
+
+pub fn view<'a>(
+    preview: Option<&'a DestinationPreview>,
+    folder_name: Option<&str>,
+    thumb_size: Dim,
+) -> Element<'a, Message> {
+    let (Some(preview), Some(folder_name)) = (preview, folder_name) else {
+        return text(t!("Tag an image to see what its folder holds")).into();
+    };
+    let Some(contents) = &preview.contents else {
+        return text(t!("Reading %{folder}...", folder = folder_name)).into();
+    };
+    let rows = contents.thumbs.chunks(THUMBS_PER_ROW).map(|chunk| {
+        Row::from_iter(chunk.iter().map(|thumb| {
+            image(thumb.handle.clone())
+                .width(Length::Fixed(thumb_size.width as f32))
+                .height(Length::Fixed(thumb_size.height as f32))
+                .into()
+        }))
+        .spacing(5)
+        .into()
+    });
+    column![text(t!(
+        "%{folder}: %{count} images",
+        folder = folder_name,
+        count = format_count(contents.count)
+    ))
+    .size(18)]
+    .push(Column::from_iter(rows).spacing(5))
+    .spacing(10)
+    .into()
+}
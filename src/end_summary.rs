@@ -0,0 +1,46 @@
+use iced::widget::{button, column, row, text};
+use iced::Element;
+use rust_i18n::t;
+
+use crate::format::format_count;
+use crate::sorting::{count_tags, SortingMessage, TagNames};
+use crate::{ImageInfo, Message, TabId};
+
+// Shown instead of the image after stepping past the last one, when that is
+// chosen in the settings. Any key goes back to sorting.
+
+pub fn view<'a>(paths: &[ImageInfo], tag_names: &TagNames) -> Element<'a, Message> {
+    let tag_counts = count_tags(paths);
+    let untagged = paths
+        .iter()
+        .filter(|info| info.metadata.tag.is_none())
+        .count();
+    let counts = tag_names.enumerate().filter_map(|(tag, name)| {
+        let count = *tag_counts.get(&tag)?;
+        let color = tag_names.color(&tag);
+        Some(
+            text(format!("{name}: {}", format_count(count as usize)))
+                .color(color)
+                .into(),
+        )
+    });
+
+    column![
+        text(t!("End of the folder")).size(24),
+        column(counts).spacing(5),
+        text(t!("Untagged: %{count}", count = format_count(untagged))),
+        row![
+            button(text(t!("Keep sorting")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedKeepSorting)),
+            button(text(t!("Go to the first untagged image"))).on_press_maybe(
+                (untagged > 0)
+                    .then_some(Message::Sorting(SortingMessage::UserPressedFirstUntagged))
+            ),
+            button(text(t!("Actions"))).on_press(Message::UserSelectedTab(TabId::Actions)),
+        ]
+        .spacing(10),
+    ]
+    .spacing(15)
+    .padding(20)
+    .into()
+}
@@ -0,0 +1,235 @@
+use std::io::BufReader;
+use std::sync::OnceLock;
+
+use log::warn;
+
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+static LOCAL_OFFSET: OnceLock<UtcOffset> = OnceLock::new();
+
+// The local offset can only be determined reliably before other threads are
+// started, so this has to be called first thing in main
+pub fn init_local_offset() {
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let _ = LOCAL_OFFSET.set(offset);
+}
+
+pub fn local_offset() -> UtcOffset {
+    *LOCAL_OFFSET.get().unwrap_or(&UtcOffset::UTC)
+}
+
+fn read_exif(path: &str) -> Option<exif::Exif> {
+    let file = std::fs::File::open(path).ok()?;
+    exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+fn ascii_field(exif: &exif::Exif, tag: exif::Tag) -> Option<&[u8]> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => values.first().map(|v| v.as_slice()),
+        _ => None,
+    }
+}
+
+/// Capture time from DateTimeOriginal, falling back to DateTime. Times without
+/// an OffsetTimeOriginal field are assumed to be in the local time zone.
+pub fn read_capture_time(path: &str) -> Option<OffsetDateTime> {
+    let exif = read_exif(path)?;
+    let datetime = ascii_field(&exif, exif::Tag::DateTimeOriginal)
+        .or_else(|| ascii_field(&exif, exif::Tag::DateTime))?;
+    let offset = ascii_field(&exif, exif::Tag::OffsetTimeOriginal);
+    parse_exif_datetime(datetime, offset)
+}
+
+/// Camera model, with the make in front unless the model already starts with it
+pub fn read_camera(path: &str) -> Option<String> {
+    let exif = read_exif(path)?;
+    let field = |tag| {
+        ascii_field(&exif, tag)
+            .map(|value| String::from_utf8_lossy(value).trim().to_owned())
+            .filter(|value| !value.is_empty())
+    };
+    match (field(exif::Tag::Make), field(exif::Tag::Model)) {
+        (Some(make), Some(model)) if !model.starts_with(&make) => Some(format!("{make} {model}")),
+        (_, Some(model)) => Some(model),
+        (make, None) => make,
+    }
+}
+
+/// Exposure time, aperture, ISO and focal length, those that are recorded
+pub fn read_exposure(path: &str) -> Option<String> {
+    let exif = read_exif(path)?;
+    let field = |tag| {
+        let field = exif.get_field(tag, exif::In::PRIMARY)?;
+        Some(field.display_value().with_unit(&exif).to_string())
+    };
+    let iso = field(exif::Tag::PhotographicSensitivity).map(|iso| format!("ISO {iso}"));
+    let parts: Vec<String> = [
+        field(exif::Tag::ExposureTime),
+        field(exif::Tag::FNumber),
+        iso,
+        field(exif::Tag::FocalLength),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join("  "))
+}
+
+/// The EXIF orientation, 1 to 8
+pub fn read_orientation(path: &str) -> Option<u8> {
+    let exif = read_exif(path)?;
+    let orientation = exif
+        .get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)?;
+    u8::try_from(orientation).ok()
+}
+
+pub struct EmbeddedThumbnail {
+    pub jpeg: Vec<u8>,
+    // Of the image itself, when recorded
+    pub pixel_size: Option<(u32, u32)>,
+    pub orientation: Option<u8>,
+}
+
+/// The small JPEG many cameras store in the EXIF, turned as the image is
+pub fn embedded_thumbnail(path: &str) -> Option<EmbeddedThumbnail> {
+    let exif = read_exif(path)?;
+    let uint = |tag, ifd| exif.get_field(tag, ifd)?.value.get_uint(0);
+    let offset = uint(exif::Tag::JPEGInterchangeFormat, exif::In::THUMBNAIL)? as usize;
+    let length = uint(exif::Tag::JPEGInterchangeFormatLength, exif::In::THUMBNAIL)? as usize;
+    let jpeg = exif
+        .buf()
+        .get(offset..offset.checked_add(length)?)?
+        .to_vec();
+    let pixel_size = uint(exif::Tag::PixelXDimension, exif::In::PRIMARY)
+        .zip(uint(exif::Tag::PixelYDimension, exif::In::PRIMARY));
+    let orientation = uint(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|orientation| u8::try_from(orientation).ok());
+    Some(EmbeddedThumbnail {
+        jpeg,
+        pixel_size,
+        orientation,
+    })
+}
+
+// Written by Windows and Lightroom, but not one of the standard tags
+const RATING: exif::Tag = exif::Tag(exif::Context::Tiff, 0x4746);
+
+fn rating_field(rating: u8) -> exif::Field {
+    exif::Field {
+        tag: RATING,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Short(vec![rating as u16]),
+    }
+}
+
+/// EXIF with only a star rating, for copies without the rest
+pub fn rating_exif(rating: u8) -> Option<Vec<u8>> {
+    let field = rating_field(rating);
+    let mut writer = exif::experimental::Writer::new();
+    writer.push_field(&field);
+    let mut buf = std::io::Cursor::new(Vec::new());
+    match writer.write(&mut buf, false) {
+        Ok(()) => Some(buf.into_inner()),
+        Err(e) => {
+            warn!("Could not write the rating to EXIF: {e}");
+            None
+        }
+    }
+}
+
+/// The EXIF of `path` written anew for a copy whose pixels are already turned
+/// upright, so without the orientation. Only the descriptive fields are kept,
+/// the ones about the layout of the original image data would be wrong. A
+/// star rating is added when given.
+pub fn upright_exif(path: &str, rating: Option<u8>) -> Option<Vec<u8>> {
+    const KEPT_TIFF_TAGS: [exif::Tag; 7] = [
+        exif::Tag::Make,
+        exif::Tag::Model,
+        exif::Tag::DateTime,
+        exif::Tag::ImageDescription,
+        exif::Tag::Artist,
+        exif::Tag::Copyright,
+        exif::Tag::Software,
+    ];
+    let exif = read_exif(path)?;
+    let rating = rating.map(rating_field);
+    let mut writer = exif::experimental::Writer::new();
+    if let Some(field) = &rating {
+        writer.push_field(field);
+    }
+    for field in exif
+        .fields()
+        .filter(|field| field.ifd_num == exif::In::PRIMARY)
+    {
+        let kept = match field.tag.context() {
+            exif::Context::Tiff => KEPT_TIFF_TAGS.contains(&field.tag),
+            exif::Context::Exif | exif::Context::Gps => field.tag != exif::Tag::MakerNote,
+            _ => false,
+        };
+        if kept {
+            writer.push_field(field);
+        }
+    }
+    let mut buf = std::io::Cursor::new(Vec::new());
+    match writer.write(&mut buf, exif.little_endian()) {
+        Ok(()) => Some(buf.into_inner()),
+        Err(e) => {
+            warn!("Could not write EXIF from {path}: {e}");
+            None
+        }
+    }
+}
+
+fn parse_exif_datetime(datetime: &[u8], offset: Option<&[u8]>) -> Option<OffsetDateTime> {
+    let mut parsed = exif::DateTime::from_ascii(datetime).ok()?;
+    if let Some(offset) = offset {
+        let _ = parsed.parse_offset(offset);
+    }
+    let date = Date::from_calendar_date(
+        parsed.year as i32,
+        Month::try_from(parsed.month).ok()?,
+        parsed.day,
+    )
+    .ok()?;
+    let time = Time::from_hms(parsed.hour, parsed.minute, parsed.second).ok()?;
+    let offset = match parsed.offset {
+        Some(minutes) => UtcOffset::from_whole_seconds(minutes as i32 * 60).ok()?,
+        None => local_offset(),
+    };
+    Some(PrimitiveDateTime::new(date, time).assume_offset(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exif_datetime_with_offset() {
+        let parsed = parse_exif_datetime(b"2023:07:14 12:30:45", Some(b"+02:00")).unwrap();
+        assert_eq!(parsed.year(), 2023);
+        assert_eq!(parsed.month(), Month::July);
+        assert_eq!(parsed.day(), 14);
+        assert_eq!(parsed.hour(), 12);
+        assert_eq!(parsed.offset(), UtcOffset::from_hms(2, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_rating_exif() {
+        let exif = exif::Reader::new()
+            .read_raw(rating_exif(4).unwrap())
+            .unwrap();
+        let field = exif.get_field(RATING, exif::In::PRIMARY).unwrap();
+        assert_eq!(field.value.get_uint(0), Some(4));
+    }
+
+    #[test]
+    fn test_parse_exif_datetime_invalid() {
+        assert!(parse_exif_datetime(b"    :  :     :  :  ", None).is_none());
+        assert!(parse_exif_datetime(b"2023:13:14 12:30:45", None).is_none());
+        assert!(parse_exif_datetime(b"2023-07-14", None).is_none());
+    }
+}
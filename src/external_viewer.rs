@@ -0,0 +1,45 @@
+use iced::Task;
+use log::warn;
+
+use crate::Message;
+
+// Opens a file in the system default application for its type, or a folder
+// in the file manager
+
+#[cfg(target_os = "macos")]
+fn command(path: &str) -> std::process::Command {
+    let mut command = std::process::Command::new("open");
+    command.arg(path);
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn command(path: &str) -> std::process::Command {
+    let mut command = std::process::Command::new("cmd");
+    command.args(["/C", "start", "", path]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn command(path: &str) -> std::process::Command {
+    let mut command = std::process::Command::new("xdg-open");
+    command.arg(path);
+    command
+}
+
+pub fn open_task(path: String) -> Task<Message> {
+    Task::future(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            // Wait for the launcher, not the viewer, so the zombie is reaped
+            command(&path).status()
+        })
+        .await;
+        match result {
+            Ok(Ok(status)) if status.success() => (),
+            Ok(Ok(status)) => warn!("External viewer exited with {status}"),
+            Ok(Err(e)) => warn!("Could not start external viewer: {e}"),
+            Err(e) => warn!("Could not spawn external viewer task: {e}"),
+        }
+    })
+    .discard()
+}
@@ -0,0 +1,430 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+
+use crate::move_plan::{self, CollisionChoice, MovePlan, PlannedMove};
+use crate::{get_files_in_folder, throttle, FolderScan, MoveLayout, PICTURE_DIR};
+
+// Hidden folder for files that are kept out of the way without deleting them
+pub const QUARANTINE_DIR: &str = ".imgsort-quarantine";
+
+#[derive(Debug, Clone)]
+pub struct MoveResult {
+    pub destination: String,
+    pub destination_path: Option<PathBuf>,
+    pub moved_files: Vec<MovedFile>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CopyResult {
+    pub destination: String,
+    pub copied: usize,
+    pub error: Option<String>,
+}
+
+// Absolute paths, so a move can be undone from any working directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedFile {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    // Where the file that was overwritten at the destination is kept
+    pub replaced: Option<PathBuf>,
+}
+
+pub async fn mv_then_ls_async(
+    files: Vec<String>,
+    destination: String,
+    layout: MoveLayout,
+    scan: FolderScan,
+) -> (MoveResult, Vec<String>) {
+    match tokio::task::spawn_blocking(move || {
+        let result = mv_files(files, destination, layout);
+        (result, get_files_in_folder(PICTURE_DIR, &scan))
+    })
+    .await
+    .expect("Could not spawn task")
+    {
+        (result, Ok(files_in_folder)) => (result, files_in_folder),
+        (_, Err(_)) => panic!("Io Error when listing directory after move"),
+    }
+}
+
+// Moves as confirmed by the user, with their choices for taken names. The
+// folder is listed once, after all destinations are done.
+pub async fn mv_planned_then_ls_async(
+    plans: Vec<MovePlan>,
+    scan: FolderScan,
+) -> (MoveResult, Vec<String>) {
+    tokio::task::spawn_blocking(move || {
+        let results = plans
+            .into_iter()
+            .map(|plan| mv_planned(&plan.moves, plan.destination))
+            .collect();
+        let result = merge_move_results(results);
+        let files_in_folder = get_files_in_folder(PICTURE_DIR, &scan)
+            .expect("Io Error when listing directory after move");
+        (result, files_in_folder)
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+// Taken names are given a number
+fn mv_files(files: Vec<String>, destination: String, layout: MoveLayout) -> MoveResult {
+    let moves = move_plan::plan_moves(&files, &destination, layout);
+    mv_planned(&moves, destination)
+}
+
+// One result for the banner and undo, only a single destination can be reviewed
+fn merge_move_results(results: Vec<MoveResult>) -> MoveResult {
+    let single = results.len() == 1;
+    let mut merged = MoveResult {
+        destination: results
+            .iter()
+            .map(|result| result.destination.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        destination_path: None,
+        moved_files: Vec::new(),
+        error: None,
+    };
+    for result in results {
+        if single {
+            merged.destination_path = result.destination_path;
+        }
+        merged.moved_files.extend(result.moved_files);
+        merged.error = merged.error.or(result.error);
+    }
+    merged
+}
+
+fn mv_planned(moves: &[PlannedMove], destination: String) -> MoveResult {
+    let mut result = MoveResult {
+        destination: destination.clone(),
+        destination_path: None,
+        moved_files: Vec::new(),
+        error: None,
+    };
+    if let Err(e) = mv_planned_recording(moves, &destination, &mut result) {
+        warn!("Error moving files to {destination}: {e}");
+        result.error = Some(e.to_string());
+    }
+    result
+}
+
+fn mv_planned_recording(
+    moves: &[PlannedMove],
+    destination: &str,
+    result: &mut MoveResult,
+) -> std::io::Result<()> {
+    // Create directory if it doesn't exist
+    let dest_path = Path::new(destination);
+    if !dest_path.exists() {
+        std::fs::create_dir_all(dest_path)?;
+    }
+    let dest_path = Path::new(destination).canonicalize()?;
+    result.destination_path = Some(dest_path.clone());
+    let mut created = HashSet::new();
+    for planned in moves {
+        let file = &planned.from;
+        let dest = dest_path.join(&planned.to);
+        let (dest, overwrite) = match (planned.collision, planned.choice) {
+            (Some(_), CollisionChoice::Skip) => continue,
+            (Some(_), CollisionChoice::Overwrite) => (dest, true),
+            // Also when the name was taken after the plan was made
+            _ => (free_destination(dest), false),
+        };
+        println!("Moving {file} to {destination}");
+        if let Some(parent) = dest.parent() {
+            if created.insert(parent.to_path_buf()) {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let from = std::path::absolute(file)?;
+        let replaced = match overwrite && dest.exists() {
+            true => Some(set_aside(&dest)?),
+            false => None,
+        };
+        throttle::throttle_file(file);
+        move_file(Path::new(file), &dest)?;
+        result.moved_files.push(MovedFile {
+            from,
+            to: dest,
+            replaced,
+        });
+    }
+    Ok(())
+}
+
+// An overwritten file is moved into the quarantine of the sorted folder, so
+// undoing the move can put it back
+fn set_aside(file: &Path) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(QUARANTINE_DIR)?;
+    let kept = std::path::absolute(free_destination(
+        Path::new(QUARANTINE_DIR).join(file.file_name().unwrap_or_default()),
+    ))?;
+    move_file(file, &kept)?;
+    Ok(kept)
+}
+
+// Copies leave the originals, and their tags, where they are
+pub async fn copy_then_ls_async(
+    files: Vec<String>,
+    destination: String,
+    layout: MoveLayout,
+    scan: FolderScan,
+) -> (CopyResult, Vec<String>) {
+    tokio::task::spawn_blocking(move || {
+        let result = copy_files(&files, &destination, layout);
+        let files_in_folder = get_files_in_folder(PICTURE_DIR, &scan)
+            .expect("Io Error when listing directory after copy");
+        (result, files_in_folder)
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+fn copy_files(files: &[String], destination: &str, layout: MoveLayout) -> CopyResult {
+    let mut result = CopyResult {
+        destination: destination_folder(destination).to_owned(),
+        copied: 0,
+        error: None,
+    };
+    let dest_path = Path::new(destination_folder(destination));
+    for file in files {
+        let dest = free_destination(dest_path.join(path_in_destination(destination, file, layout)));
+        if let Err(e) = copy_file(Path::new(file), &dest) {
+            warn!("Error copying {file} to {}: {e}", dest.display());
+            result.error = Some(format!("{file}: {e}"));
+            break;
+        }
+        result.copied += 1;
+    }
+    result
+}
+
+// Unlike a rename this works across file systems. The modification time is
+// kept, it is the only date some images have.
+pub fn copy_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    throttle::throttle_file(&from.to_string_lossy());
+    let copied = std::fs::copy(from, to).and_then(|_| {
+        let modified = std::fs::metadata(from)?.modified()?;
+        std::fs::File::options()
+            .write(true)
+            .open(to)?
+            .set_modified(modified)
+    });
+    if copied.is_err() {
+        // Don't leave a partial copy behind
+        let _ = std::fs::remove_file(to);
+    }
+    copied
+}
+
+// A rename fails between file systems, such as to another drive, the file is
+// then copied and the original removed
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_file(from, to)?;
+            std::fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+pub const RELPATH: &str = "{relpath}";
+
+// A destination with {relpath} in it mirrors the subfolders of the sorted
+// folder at that point, whatever the layout. This is the folder before it.
+pub fn destination_folder(destination: &str) -> &str {
+    match destination.split_once(RELPATH) {
+        Some(("", _)) => ".",
+        Some((folder, _)) => folder,
+        None => destination,
+    }
+}
+
+// Where a file goes within the destination folder
+pub fn path_in_destination(destination: &str, file: &str, layout: MoveLayout) -> PathBuf {
+    let Some((_, rest)) = destination.split_once(RELPATH) else {
+        return relative_destination(file, layout);
+    };
+    let path = Path::new(file);
+    let subfolder = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(PICTURE_DIR).ok())
+        .unwrap_or(Path::new(""));
+    subfolder
+        .join(rest.trim_start_matches('/'))
+        .join(path.file_name().unwrap())
+}
+
+// Files from subfolders of the sorted folder keep their subfolder unless flattened
+pub fn relative_destination(file: &str, layout: MoveLayout) -> PathBuf {
+    let path = Path::new(file);
+    match (layout, path.strip_prefix(PICTURE_DIR)) {
+        (MoveLayout::Preserve, Ok(relative)) => relative.to_owned(),
+        _ => PathBuf::from(path.file_name().unwrap()),
+    }
+}
+
+// Files with the same name from different subfolders must not replace each other
+fn free_destination(dest: PathBuf) -> PathBuf {
+    if !dest.exists() {
+        return dest;
+    }
+    let stem = dest
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let extension = dest
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| dest.with_file_name(format!("{stem}-{n}{extension}")))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+// Moves files back where they came from, and overwritten files back in their
+// place, then lists the current directory
+pub async fn undo_moves_then_ls_async(
+    moves: Vec<MovedFile>,
+    scan: FolderScan,
+) -> (Vec<MovedFile>, Result<(), String>, Vec<String>) {
+    tokio::task::spawn_blocking(move || {
+        let (undone, result) = undo_moves(moves);
+        let files = get_files_in_folder(PICTURE_DIR, &scan).unwrap_or_default();
+        (undone, result, files)
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+fn undo_moves(moves: Vec<MovedFile>) -> (Vec<MovedFile>, Result<(), String>) {
+    let mut undone = Vec::new();
+    for moved in moves {
+        if moved.from.exists() {
+            return (
+                undone,
+                Err(format!("{} already exists", moved.from.display())),
+            );
+        }
+        if let Err(e) = move_file(&moved.to, &moved.from) {
+            warn!("Could not move {} back: {e}", moved.to.display());
+            return (undone, Err(e.to_string()));
+        }
+        let restored = match &moved.replaced {
+            Some(replaced) => move_file(replaced, &moved.to),
+            None => Ok(()),
+        };
+        undone.push(moved);
+        if let Err(e) = restored {
+            warn!("Could not put the overwritten file back: {e}");
+            return (undone, Err(e.to_string()));
+        }
+    }
+    (undone, Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_destination() {
+        assert_eq!(
+            relative_destination("./2023/summer/a.jpg", MoveLayout::Preserve),
+            PathBuf::from("2023/summer/a.jpg")
+        );
+        assert_eq!(
+            relative_destination("./2023/summer/a.jpg", MoveLayout::Flatten),
+            PathBuf::from("a.jpg")
+        );
+        assert_eq!(
+            relative_destination("./a.jpg", MoveLayout::Preserve),
+            PathBuf::from("a.jpg")
+        );
+
+        let template = "/backup/{relpath}/keep";
+        assert_eq!(destination_folder(template), "/backup/");
+        assert_eq!(
+            path_in_destination(template, "./2023/summer/a.jpg", MoveLayout::Flatten),
+            PathBuf::from("2023/summer/keep/a.jpg")
+        );
+        assert_eq!(
+            path_in_destination(template, "./a.jpg", MoveLayout::Flatten),
+            PathBuf::from("keep/a.jpg")
+        );
+        assert_eq!(destination_folder("{relpath}"), ".");
+    }
+
+    #[test]
+    fn test_copy_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("tag")).unwrap();
+        let file = dir.join("a.jpg");
+        std::fs::write(&file, "image").unwrap();
+        std::fs::write(dir.join("tag/a.jpg"), "other").unwrap();
+        let file = file.to_string_lossy().to_string();
+        let tag = dir.join("tag").to_string_lossy().to_string();
+
+        let result = copy_files(std::slice::from_ref(&file), &tag, MoveLayout::Flatten);
+        assert_eq!((result.copied, result.error), (1, None));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "image");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("tag/a-2.jpg")).unwrap(),
+            "image"
+        );
+        assert_eq!(
+            std::fs::metadata(dir.join("tag/a-2.jpg"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            std::fs::metadata(&file).unwrap().modified().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_free_destination() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        assert_eq!(free_destination(dir.join("a.jpg")), dir.join("a.jpg"));
+        std::fs::write(dir.join("a.jpg"), "").unwrap();
+        std::fs::write(dir.join("a-2.jpg"), "").unwrap();
+        assert_eq!(free_destination(dir.join("a.jpg")), dir.join("a-3.jpg"));
+    }
+
+    #[test]
+    fn test_undo_overwrite() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("tag")).unwrap();
+        std::fs::create_dir_all(dir.join(QUARANTINE_DIR)).unwrap();
+        std::fs::write(dir.join("tag/a.jpg"), "moved").unwrap();
+        std::fs::write(dir.join(QUARANTINE_DIR).join("a.jpg"), "overwritten").unwrap();
+        let moved = MovedFile {
+            from: dir.join("a.jpg"),
+            to: dir.join("tag/a.jpg"),
+            replaced: Some(dir.join(QUARANTINE_DIR).join("a.jpg")),
+        };
+
+        let (undone, result) = undo_moves(vec![moved.clone()]);
+        assert_eq!((undone, result), (vec![moved], Ok(())));
+        assert_eq!(std::fs::read_to_string(dir.join("a.jpg")).unwrap(), "moved");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("tag/a.jpg")).unwrap(),
+            "overwritten"
+        );
+        assert!(!dir.join(QUARANTINE_DIR).join("a.jpg").exists());
+    }
+}
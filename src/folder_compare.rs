@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use iced::widget::{button, column, row, scrollable, text};
+use iced::{Element, Length};
+use log::warn;
+use rust_i18n::t;
+
+use crate::file_ops::copy_file;
+use crate::format::format_count;
+use crate::metadata_db::content_hash;
+use crate::{get_files_in_folder, FolderScan, Message};
+
+// Two copies of a shoot, such as a sorted folder and the raw dump it came
+// from, reconciled. Images are the same when they have the same path within
+// their folder, or else the same contents under another name. Only files of a
+// size found on the other side are hashed.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderComparison {
+    pub a: String,
+    pub b: String,
+    // Paths within their folder
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    pub both: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyDirection {
+    AToB,
+    BToA,
+}
+
+impl FolderComparison {
+    // Source folder, destination folder and the files missing there
+    pub fn missing(&self, direction: CopyDirection) -> (String, String, Vec<String>) {
+        match direction {
+            CopyDirection::AToB => (self.a.clone(), self.b.clone(), self.only_a.clone()),
+            CopyDirection::BToA => (self.b.clone(), self.a.clone(), self.only_b.clone()),
+        }
+    }
+}
+
+pub fn compare_folders(a: &str, b: &str) -> std::io::Result<FolderComparison> {
+    let scan = FolderScan {
+        recursive: true,
+        skip_dirs: Vec::new(),
+    };
+    let relative = |folder: &str, files: Vec<String>| -> Vec<String> {
+        files
+            .iter()
+            .filter_map(|file| Path::new(file).strip_prefix(folder).ok())
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect()
+    };
+    let files_a = relative(a, get_files_in_folder(a, &scan)?);
+    let files_b = relative(b, get_files_in_folder(b, &scan)?);
+
+    let names_b: HashSet<&String> = files_b.iter().collect();
+    let names_a: HashSet<&String> = files_a.iter().collect();
+    let mut only_a: Vec<String> = files_a
+        .iter()
+        .filter(|name| !names_b.contains(name))
+        .cloned()
+        .collect();
+    let mut only_b: Vec<String> = files_b
+        .iter()
+        .filter(|name| !names_a.contains(name))
+        .cloned()
+        .collect();
+    let mut both = files_a.len() - only_a.len();
+
+    let renamed = matched_by_contents(a, &only_a, b, &only_b);
+    both += renamed.len();
+    let (renamed_a, renamed_b): (HashSet<String>, HashSet<String>) = renamed.into_iter().unzip();
+    only_a.retain(|name| !renamed_a.contains(name));
+    only_b.retain(|name| !renamed_b.contains(name));
+    Ok(FolderComparison {
+        a: a.to_owned(),
+        b: b.to_owned(),
+        only_a,
+        only_b,
+        both,
+    })
+}
+
+// Pairs of names with the same contents
+fn matched_by_contents(
+    a: &str,
+    only_a: &[String],
+    b: &str,
+    only_b: &[String],
+) -> Vec<(String, String)> {
+    let size = |folder: &str, name: &str| {
+        std::fs::metadata(Path::new(folder).join(name))
+            .map(|metadata| metadata.len())
+            .ok()
+    };
+    let hash = |folder: &str, name: &str| {
+        content_hash(&Path::new(folder).join(name).to_string_lossy()).ok()
+    };
+    let sizes_a: HashSet<u64> = only_a.iter().filter_map(|name| size(a, name)).collect();
+    let mut hashes_b: HashMap<String, Vec<&String>> = HashMap::new();
+    for name in only_b {
+        if size(b, name).is_some_and(|size| sizes_a.contains(&size)) {
+            if let Some(hash) = hash(b, name) {
+                hashes_b.entry(hash).or_default().push(name);
+            }
+        }
+    }
+    if hashes_b.is_empty() {
+        return Vec::new();
+    }
+    let mut matched = Vec::new();
+    for name in only_a {
+        let Some(hash) = hash(a, name) else {
+            continue;
+        };
+        if let Some(in_b) = hashes_b.get_mut(&hash).and_then(|names| names.pop()) {
+            matched.push((name.clone(), in_b.clone()));
+        }
+    }
+    matched
+}
+
+// Keeping the paths within the folder. Files already there are left alone.
+pub fn copy_missing(from: &str, to: &str, names: &[String]) -> (usize, Vec<String>) {
+    let mut copied = 0;
+    let mut errors = Vec::new();
+    for name in names {
+        let target = Path::new(to).join(name);
+        if target.exists() {
+            errors.push(format!("{}: {}", target.display(), t!("already exists")));
+            continue;
+        }
+        match copy_file(&Path::new(from).join(name), &target) {
+            Ok(()) => copied += 1,
+            Err(e) => {
+                warn!("Could not copy {name} from {from} to {to}: {e}");
+                errors.push(format!("{name}: {e}"));
+            }
+        }
+    }
+    (copied, errors)
+}
+
+pub fn view_comparison(comparison: &FolderComparison) -> Element<'_, Message> {
+    let side = |title: String, names: &[String], direction: CopyDirection, folder: &str| {
+        let copy = t!(
+            "Copy %{count} to %{folder}",
+            count = format_count(names.len()),
+            folder = folder
+        );
+        column![
+            text(title).size(20),
+            button(text(copy)).on_press_maybe(
+                (!names.is_empty()).then_some(Message::UserPressedCopyMissing(direction))
+            ),
+            scrollable(column(names.iter().map(|name| text(name.clone()).into())))
+                .height(Length::Fill),
+        ]
+        .spacing(10)
+        .width(Length::FillPortion(1))
+    };
+    column![
+        row![
+            text(t!(
+                "%{count} images are in both folders",
+                count = format_count(comparison.both)
+            ))
+            .size(20),
+            button(text(t!("Close"))).on_press(Message::UserPressedCloseComparison),
+        ]
+        .spacing(20),
+        row![
+            side(
+                t!("Only in %{folder}", folder = comparison.a).to_string(),
+                &comparison.only_a,
+                CopyDirection::AToB,
+                &comparison.b,
+            ),
+            side(
+                t!("Only in %{folder}", folder = comparison.b).to_string(),
+                &comparison.only_b,
+                CopyDirection::BToA,
+                &comparison.a,
+            ),
+        ]
+        .spacing(20),
+    ]
+    .spacing(15)
+    .padding(20)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_folders() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let (a, b) = (dir.join("sorted"), dir.join("dump"));
+        std::fs::create_dir_all(a.join("keep")).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+        let write =
+            |path: std::path::PathBuf, contents: &str| std::fs::write(path, contents).unwrap();
+        write(a.join("same.jpg"), "same");
+        write(a.join("keep/renamed.jpg"), "moved into a subfolder");
+        write(a.join("only_sorted.jpg"), "edited");
+        write(b.join("same.jpg"), "same");
+        write(b.join("IMG_0002.jpg"), "moved into a subfolder");
+        write(b.join("only_dump.jpg"), "raw");
+
+        let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+        let comparison = compare_folders(&a, &b).unwrap();
+        assert_eq!(comparison.both, 2);
+        assert_eq!(comparison.only_a, ["only_sorted.jpg"]);
+        assert_eq!(comparison.only_b, ["only_dump.jpg"]);
+        // As picked with a trailing slash
+        let slashed = compare_folders(&format!("{a}/"), &b).unwrap();
+        assert_eq!(
+            (slashed.both, slashed.only_a),
+            (2, comparison.only_a.clone())
+        );
+
+        let (from, to, names) = comparison.missing(CopyDirection::BToA);
+        assert_eq!(copy_missing(&from, &to, &names), (1, Vec::new()));
+        assert!(compare_folders(&a, &b).unwrap().only_b.is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use iced::widget::{column, row, text};
+use iced::Element;
+use rust_i18n::t;
+
+use crate::format::{format_count, format_date, format_file_size};
+use crate::{is_supported_image, Message};
+
+// Summary of every file in a folder, not only the supported images, so that a
+// folder of unsupported files doesn't look empty without explanation
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FolderStats {
+    // Extension, as written, to file count and total size. Listing for sorting
+    // tells .JPG from .jpg, so they are counted apart.
+    pub by_extension: BTreeMap<String, (usize, u64)>,
+    pub total_size: u64,
+    pub oldest: Option<time::Date>,
+    pub newest: Option<time::Date>,
+}
+
+impl FolderStats {
+    fn add(&mut self, file_name: &str, size: u64, modified: Option<time::Date>) {
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let entry = self.by_extension.entry(extension).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+        self.total_size += size;
+        if let Some(date) = modified {
+            self.oldest = Some(self.oldest.map_or(date, |oldest| oldest.min(date)));
+            self.newest = Some(self.newest.map_or(date, |newest| newest.max(date)));
+        }
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.by_extension.values().map(|(count, _)| count).sum()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let rows = self.by_extension.iter().map(|(extension, (count, size))| {
+            let name = if extension.is_empty() {
+                t!("(no extension)").to_string()
+            } else {
+                format!(".{extension}")
+            };
+            let supported = is_supported_image(&format!("file.{extension}"));
+            row![
+                text(name).width(120),
+                text(format_count(*count)).width(80),
+                text(format_file_size(*size)).width(100),
+            ]
+            .push_maybe((!supported).then(|| text(t!("not supported"))))
+            .spacing(5)
+            .into()
+        });
+
+        let date_range = match (self.oldest, self.newest) {
+            (Some(oldest), Some(newest)) => t!(
+                "Modified %{from} to %{to}",
+                from = format_date(oldest),
+                to = format_date(newest)
+            )
+            .to_string(),
+            _ => String::new(),
+        };
+        column![
+            text(t!(
+                "%{count} files, %{size}",
+                count = format_count(self.file_count()),
+                size = format_file_size(self.total_size)
+            )),
+            text(date_range),
+        ]
+        .extend(rows)
+        .spacing(5)
+        .into()
+    }
+}
+
+pub fn read_folder_stats(folder_path: &str) -> std::io::Result<FolderStats> {
+    let mut stats = FolderStats::default();
+    for entry in std::fs::read_dir(folder_path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata
+            .modified()
+            .ok()
+            .map(|modified| time::OffsetDateTime::from(modified).date());
+        stats.add(
+            &entry.file_name().to_string_lossy(),
+            metadata.len(),
+            modified,
+        );
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    fn date(year: i32, month: Month, day: u8) -> time::Date {
+        time::Date::from_calendar_date(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_add() {
+        let mut stats = FolderStats::default();
+        stats.add("a.JPG", 100, Some(date(2024, Month::May, 1)));
+        stats.add("b.jpg", 50, Some(date(2023, Month::January, 10)));
+        stats.add("c.cr2", 1000, None);
+        stats.add("README", 10, Some(date(2024, Month::June, 1)));
+
+        assert_eq!(stats.file_count(), 4);
+        assert_eq!(stats.total_size, 1160);
+        assert_eq!(stats.by_extension["JPG"], (1, 100));
+        assert_eq!(stats.by_extension["jpg"], (1, 50));
+        assert_eq!(stats.by_extension["cr2"], (1, 1000));
+        assert_eq!(stats.by_extension[""], (1, 10));
+        assert_eq!(stats.oldest, Some(date(2023, Month::January, 10)));
+        assert_eq!(stats.newest, Some(date(2024, Month::June, 1)));
+    }
+}
@@ -0,0 +1,111 @@
+use rust_i18n::t;
+
+// Locale aware formatting of numbers, file sizes and dates. The separators and
+// patterns come from the translation files so that each locale can define them.
+
+const SIZE_UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+pub fn format_count(n: usize) -> String {
+    group_thousands(&n.to_string(), &thousands_separator())
+}
+
+pub fn format_decimal(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (int_part, frac_part) = match formatted.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (formatted.as_str(), None),
+    };
+
+    let mut s = String::new();
+    if value < 0.0 && formatted.chars().any(|c| c != '0' && c != '.') {
+        s.push('-');
+    }
+    s.push_str(&group_thousands(int_part, &thousands_separator()));
+    if let Some(frac_part) = frac_part {
+        s.push_str(&decimal_separator());
+        s.push_str(frac_part);
+    }
+    s
+}
+
+pub fn format_file_size(bytes: u64) -> String {
+    let (value, unit_index) = scale_file_size(bytes);
+    let unit = t!(format!("format.unit.{}", SIZE_UNITS[unit_index]));
+    let value = if unit_index == 0 {
+        format_count(bytes as usize)
+    } else {
+        format_decimal(value, 1)
+    };
+    t!("format.file_size", value = value, unit = unit).to_string()
+}
+
+pub fn format_date(date: time::Date) -> String {
+    t!(
+        "format.date",
+        year = date.year(),
+        month = format!("{:02}", date.month() as u8),
+        day = format!("{:02}", date.day()),
+    )
+    .to_string()
+}
+
+pub fn format_datetime(datetime: time::PrimitiveDateTime) -> String {
+    t!(
+        "format.datetime",
+        date = format_date(datetime.date()),
+        hour = format!("{:02}", datetime.hour()),
+        minute = format!("{:02}", datetime.minute()),
+    )
+    .to_string()
+}
+
+fn thousands_separator() -> String {
+    t!("format.thousands_separator").to_string()
+}
+
+fn decimal_separator() -> String {
+    t!("format.decimal_separator").to_string()
+}
+
+fn scale_file_size(bytes: u64) -> (f64, usize) {
+    let mut value = bytes as f64;
+    let mut unit_index = 0;
+    while value >= 1024.0 && unit_index < SIZE_UNITS.len() - 1 {
+        value /= 1024.0;
+        unit_index += 1;
+    }
+    (value, unit_index)
+}
+
+fn group_thousands(digits: &str, separator: &str) -> String {
+    let mut s = String::new();
+    let len = digits.len();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (len - i).is_multiple_of(3) {
+            s.push_str(separator);
+        }
+        s.push(c);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands("0", ","), "0");
+        assert_eq!(group_thousands("999", ","), "999");
+        assert_eq!(group_thousands("1000", ","), "1,000");
+        assert_eq!(group_thousands("1234567", " "), "1 234 567");
+    }
+
+    #[test]
+    fn test_scale_file_size() {
+        assert_eq!(scale_file_size(0), (0.0, 0));
+        assert_eq!(scale_file_size(1023), (1023.0, 0));
+        assert_eq!(scale_file_size(1024), (1.0, 1));
+        assert_eq!(scale_file_size(1536 * 1024), (1.5, 2));
+    }
+}
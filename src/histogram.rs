@@ -0,0 +1,135 @@
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke};
+use iced::{mouse, Color, Element, Point, Rectangle, Size, Theme};
+
+use crate::Message;
+
+// How the tones of the current image are spread, to judge its exposure. Made
+// from the preview while it is decoded, which is close enough to the file.
+
+const BINS: usize = 64;
+const WIDTH: f32 = 256.0;
+const HEIGHT: f32 = 100.0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    pub red: [u32; BINS],
+    pub green: [u32; BINS],
+    pub blue: [u32; BINS],
+    pub luma: [u32; BINS],
+}
+
+pub fn from_rgba(data: &[u8]) -> Histogram {
+    let mut histogram = Histogram {
+        red: [0; BINS],
+        green: [0; BINS],
+        blue: [0; BINS],
+        luma: [0; BINS],
+    };
+    let bin = |value: u32| value as usize * BINS / 256;
+    for pixel in data.chunks_exact(4) {
+        let (r, g, b) = (pixel[0] as u32, pixel[1] as u32, pixel[2] as u32);
+        histogram.red[bin(r)] += 1;
+        histogram.green[bin(g)] += 1;
+        histogram.blue[bin(b)] += 1;
+        // Rec. 709
+        histogram.luma[bin((2126 * r + 7152 * g + 722 * b) / 10000)] += 1;
+    }
+    histogram
+}
+
+struct HistogramCanvas<'a> {
+    histogram: &'a Histogram,
+}
+
+impl canvas::Program<Message> for HistogramCanvas<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &iced::Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            Color::from_rgba(0.0, 0.0, 0.0, 0.6),
+        );
+        let histogram = self.histogram;
+        let max = [
+            &histogram.red,
+            &histogram.green,
+            &histogram.blue,
+            &histogram.luma,
+        ]
+        .iter()
+        .flat_map(|bins| bins.iter())
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+        let bin_width = bounds.width / BINS as f32;
+        let height = |count: u32| bounds.height * count as f32 / max;
+
+        // Luminance filled, the channels as lines on top
+        for (i, &count) in histogram.luma.iter().enumerate() {
+            let h = height(count);
+            frame.fill_rectangle(
+                Point::new(i as f32 * bin_width, bounds.height - h),
+                Size::new(bin_width, h),
+                Color::from_rgba(1.0, 1.0, 1.0, 0.5),
+            );
+        }
+        for (bins, color) in [
+            (&histogram.red, Color::from_rgb(1.0, 0.3, 0.3)),
+            (&histogram.green, Color::from_rgb(0.3, 1.0, 0.3)),
+            (&histogram.blue, Color::from_rgb(0.4, 0.5, 1.0)),
+        ] {
+            let line = Path::new(|builder| {
+                for (i, &count) in bins.iter().enumerate() {
+                    let point =
+                        Point::new((i as f32 + 0.5) * bin_width, bounds.height - height(count));
+                    if i == 0 {
+                        builder.move_to(point);
+                    } else {
+                        builder.line_to(point);
+                    }
+                }
+            });
+            frame.stroke(&line, Stroke::default().with_color(color).with_width(1.5));
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+pub fn view(histogram: &Histogram) -> Element<'_, Message> {
+    canvas::Canvas::new(HistogramCanvas { histogram })
+        .width(WIDTH)
+        .height(HEIGHT)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rgba() {
+        let data = [
+            0, 0, 0, 255, // Black
+            255, 255, 255, 255, // White
+            255, 0, 0, 255, // Red
+        ];
+        let histogram = from_rgba(&data);
+        assert_eq!(histogram.red[0], 1);
+        assert_eq!(histogram.red[BINS - 1], 2);
+        assert_eq!(histogram.green[0], 2);
+        assert_eq!(histogram.luma[0], 1);
+        assert_eq!(histogram.luma[BINS - 1], 1);
+        // Red is a dark tone
+        assert_eq!(histogram.luma[54 * BINS / 256], 1);
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use log::warn;
+
+// Per-folder rules for files that should never show up in a sorting session.
+// A `.nomedia` file hides the whole folder, like on Android. An
+// `.imgsortignore` file has one glob per line, matched against file and
+// folder names, where `*` matches any run of characters and `?` any single
+// one. Lines starting with `#` are comments, a trailing `/` only matches
+// folders.
+
+pub const IGNORE_FILE: &str = ".imgsortignore";
+pub const NOMEDIA_FILE: &str = ".nomedia";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    ignore_all: bool,
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    glob: String,
+    only_dirs: bool,
+}
+
+impl IgnoreRules {
+    pub fn load(dir: &Path) -> Self {
+        if dir.join(NOMEDIA_FILE).exists() {
+            return Self {
+                ignore_all: true,
+                patterns: Vec::new(),
+            };
+        }
+        match std::fs::read_to_string(dir.join(IGNORE_FILE)) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Could not read {IGNORE_FILE} in {}: {e}", dir.display());
+                Self::default()
+            }
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_suffix('/') {
+                Some(glob) => Pattern {
+                    glob: glob.to_owned(),
+                    only_dirs: true,
+                },
+                None => Pattern {
+                    glob: line.to_owned(),
+                    only_dirs: false,
+                },
+            })
+            .collect();
+        Self {
+            ignore_all: false,
+            patterns,
+        }
+    }
+
+    pub fn is_ignored(&self, name: &str, is_dir: bool) -> bool {
+        self.ignore_all
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| (is_dir || !pattern.only_dirs) && glob_match(&pattern.glob, name))
+    }
+}
+
+fn glob_match(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position to go back to after the last `*`, and how much it has matched
+    let mut star: Option<(usize, usize)> = None;
+    let (mut g, mut n) = (0, 0);
+    while n < name.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == name[n]) {
+            g += 1;
+            n += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, n));
+            g += 1;
+        } else if let Some((star_g, star_n)) = star {
+            star = Some((star_g, star_n + 1));
+            g = star_g + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.jpg", "a.jpg"));
+        assert!(glob_match("*_preview*", "IMG_1_preview.jpg"));
+        assert!(glob_match("IMG_????.jpg", "IMG_0001.jpg"));
+        assert!(!glob_match("IMG_????.jpg", "IMG_01.jpg"));
+        assert!(!glob_match("*.jpg", "a.png"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let rules = IgnoreRules::parse("# Generated\n*_thumb.jpg\n\nexport/\n");
+        assert!(rules.is_ignored("a_thumb.jpg", false));
+        assert!(!rules.is_ignored("a.jpg", false));
+        assert!(rules.is_ignored("export", true));
+        assert!(!rules.is_ignored("export", false));
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use crate::{exif_data, raw, tag_io, video, ImageInfo};
+
+// A quick guess at what an image is, for clearing out downloads and phone
+// folders. Camera EXIF makes a photo. Without it, a PNG shaped like a screen,
+// or a file named as a screenshot, is taken for a screenshot. The rest, such as
+// images with the EXIF stripped by a messaging app, is left as other.
+
+// Width to height of common monitors, tablets and phones
+const SCREEN_RATIOS: [f32; 7] = [
+    4.0 / 3.0,
+    3.0 / 2.0,
+    16.0 / 10.0,
+    16.0 / 9.0,
+    2.0,
+    19.5 / 9.0,
+    20.0 / 9.0,
+];
+const SCREEN_RATIO_TOLERANCE: f32 = 0.02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageKind {
+    Photo,
+    Screenshot,
+    Other,
+}
+
+impl ImageKind {
+    pub fn all_variants() -> [ImageKind; 3] {
+        [ImageKind::Photo, ImageKind::Screenshot, ImageKind::Other]
+    }
+}
+
+// Images not classified yet pass any filter
+pub fn kind_matches(filter: Option<ImageKind>, info: &ImageInfo) -> bool {
+    match (filter, info.metadata.kind) {
+        (Some(filter), Some(kind)) => filter == kind,
+        _ => true,
+    }
+}
+
+pub fn classify(path: &str, pixel_size: Option<(u32, u32)>) -> ImageKind {
+    if raw::is_raw(path) {
+        return ImageKind::Photo;
+    }
+    if video::is_video(path) {
+        return ImageKind::Other;
+    }
+    if exif_data::read_camera(path).is_some() {
+        return ImageKind::Photo;
+    }
+    let name = tag_io::file_name(path).to_lowercase();
+    if name.contains("screenshot") || name.contains("screen shot") {
+        return ImageKind::Screenshot;
+    }
+    let is_png = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    match pixel_size {
+        Some((width, height)) if is_png && is_screen_shaped(width, height) => ImageKind::Screenshot,
+        _ => ImageKind::Other,
+    }
+}
+
+// Either way up
+fn is_screen_shaped(width: u32, height: u32) -> bool {
+    let ratio = width.max(height) as f32 / width.min(height).max(1) as f32;
+    SCREEN_RATIOS
+        .iter()
+        .any(|screen| (ratio - screen).abs() < SCREEN_RATIO_TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify("Screenshot_20240101.jpg", None),
+            ImageKind::Screenshot
+        );
+        // An iPhone screen, standing up
+        assert_eq!(
+            classify("IMG_0001.png", Some((1170, 2532))),
+            ImageKind::Screenshot
+        );
+        assert_eq!(
+            classify("IMG_0001.jpg", Some((1170, 2532))),
+            ImageKind::Other
+        );
+        assert_eq!(classify("logo.png", Some((512, 512))), ImageKind::Other);
+        assert_eq!(classify("IMG_0001.CR2", None), ImageKind::Photo);
+    }
+}
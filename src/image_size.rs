@@ -0,0 +1,105 @@
+use crate::{exif_data, raw, video, ImageInfo};
+
+// Resolution and shape of the original images, read from the file headers
+// without decoding, for filtering out small images and picking by shape.
+// RAW files and videos are not read, they always pass the filters.
+
+pub const MIN_MEGAPIXEL_CHOICES: [u32; 4] = [0, 1, 4, 12];
+
+// Within this ratio of the sides an image counts as square, from the
+// panorama ratio on it counts as a panorama whichever way it is turned
+const SQUARE_RATIO: f32 = 1.1;
+const PANORAMA_RATIO: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AspectClass {
+    Portrait,
+    Landscape,
+    Panorama,
+    Square,
+}
+
+impl AspectClass {
+    pub fn of(width: u32, height: u32) -> AspectClass {
+        let (long, short) = (width.max(height) as f32, width.min(height).max(1) as f32);
+        match long / short {
+            ratio if ratio < SQUARE_RATIO => AspectClass::Square,
+            ratio if ratio >= PANORAMA_RATIO => AspectClass::Panorama,
+            _ if width > height => AspectClass::Landscape,
+            _ => AspectClass::Portrait,
+        }
+    }
+
+    pub fn all_variants() -> [AspectClass; 4] {
+        [
+            AspectClass::Portrait,
+            AspectClass::Landscape,
+            AspectClass::Panorama,
+            AspectClass::Square,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SizeFilter {
+    // Zero lets all through
+    pub min_megapixels: u32,
+    pub shape: Option<AspectClass>,
+}
+
+impl SizeFilter {
+    pub fn matches(&self, info: &ImageInfo) -> bool {
+        let Some((width, height)) = info.metadata.pixel_size else {
+            return true;
+        };
+        width as u64 * height as u64 >= self.min_megapixels as u64 * 1_000_000
+            && self
+                .shape
+                .is_none_or(|shape| AspectClass::of(width, height) == shape)
+    }
+}
+
+// Upright, as the image is shown
+pub fn read_pixel_size(path: &str) -> Option<(u32, u32)> {
+    if raw::is_raw(path) || video::is_video(path) {
+        return None;
+    }
+    let (width, height) = image::image_dimensions(path).ok()?;
+    // Orientations 5 to 8 turn the image on its side
+    match exif_data::read_orientation(path) {
+        Some(5..=8) => Some((height, width)),
+        _ => Some((width, height)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metadata, PreloadImage};
+
+    #[test]
+    fn test_size_filter() {
+        assert_eq!(AspectClass::of(4000, 3000), AspectClass::Landscape);
+        assert_eq!(AspectClass::of(3000, 4000), AspectClass::Portrait);
+        assert_eq!(AspectClass::of(1000, 1050), AspectClass::Square);
+        assert_eq!(AspectClass::of(1000, 3000), AspectClass::Panorama);
+
+        let info = |pixel_size| ImageInfo {
+            path: "a.jpg".to_owned(),
+            data: PreloadImage::NotLoading,
+            metadata: Metadata {
+                pixel_size,
+                ..Metadata::default()
+            },
+        };
+        let filter = SizeFilter {
+            min_megapixels: 4,
+            shape: Some(AspectClass::Landscape),
+        };
+        assert!(filter.matches(&info(Some((4000, 3000)))));
+        assert!(!filter.matches(&info(Some((1600, 1200)))));
+        assert!(!filter.matches(&info(Some((3000, 4000)))));
+        // Not read yet
+        assert!(filter.matches(&info(None)));
+    }
+}
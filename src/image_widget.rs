@@ -1,20 +1,100 @@
 use iced::{
     mouse,
-    widget::canvas::{self, Frame, Geometry},
-    Point, Rectangle, Size, Theme,
+    widget::canvas::{self, path, Frame, Geometry, Path, Stroke},
+    Point, Radians, Rectangle, Size, Theme,
 };
 
 use crate::sorting::Dim;
 use crate::{ImageData, Message};
 
+const ZOOM_FACTOR: f32 = 1.25;
+const MAX_ZOOM_STEPS: u8 = 16;
+
 #[derive(Debug, Clone)]
 pub enum PixelCanvasMessage {
     CanvasSized(Dim),
+    // Zoom steps to add and the point under the mouse, as a fraction of the
+    // shown image
+    UserScrolled(i8, (f32, f32)),
+    // The same for the reference image in compare mode
+    UserScrolledReference(i8, (f32, f32)),
+}
+
+// How the user has turned and zoomed an image to inspect it, kept with the
+// image while it stays in the list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewState {
+    // Clockwise
+    pub quarter_turns: u8,
+    pub zoom_steps: u8,
+    // The point kept in the middle when zoomed in, as a fraction of the
+    // turned image
+    pub focus: (f32, f32),
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            quarter_turns: 0,
+            zoom_steps: 0,
+            focus: (0.5, 0.5),
+        }
+    }
+}
+
+impl ViewState {
+    pub fn turned(self, clockwise: bool) -> Self {
+        let turn = if clockwise { 1 } else { 3 };
+        Self {
+            quarter_turns: (self.quarter_turns + turn) % 4,
+            // The focus point would be somewhere else after turning
+            focus: (0.5, 0.5),
+            ..self
+        }
+    }
+
+    pub fn zoomed(self, steps: i8, focus: Option<(f32, f32)>) -> Self {
+        let zoom_steps = (self.zoom_steps as i8 + steps).clamp(0, MAX_ZOOM_STEPS as i8) as u8;
+        Self {
+            zoom_steps,
+            focus: match zoom_steps {
+                0 => (0.5, 0.5),
+                _ => focus.unwrap_or(self.focus),
+            },
+            ..self
+        }
+    }
+
+    // Thumbnails are turned along with the image but never zoomed
+    pub fn unzoomed(self) -> Self {
+        Self {
+            quarter_turns: self.quarter_turns,
+            ..Self::default()
+        }
+    }
+
+    fn zoom(&self) -> f32 {
+        ZOOM_FACTOR.powi(self.zoom_steps as i32)
+    }
 }
 
 pub struct PixelCanvas<'a> {
     image_data: Option<&'a ImageData>,
     send_resize_messages: bool,
+    // The pinned image of compare mode, zoomed on its own
+    reference: bool,
+    // Scaled to cover the bounds instead of fitting inside them
+    fill: bool,
+    placeholder: Option<Placeholder>,
+    view: ViewState,
+}
+
+// Shown instead of the image while it is loading
+pub struct Placeholder {
+    pub label: String,
+    pub spinner_phase: f32,
+    // Outline of the image when its size is known from an earlier session
+    pub size: Option<(u32, u32)>,
 }
 
 impl<'a> PixelCanvas<'a> {
@@ -22,8 +102,107 @@ impl<'a> PixelCanvas<'a> {
         Self {
             image_data,
             send_resize_messages,
+            reference: false,
+            fill: false,
+            placeholder: None,
+            view: ViewState::default(),
         }
     }
+
+    pub fn with_view(mut self, view: ViewState) -> Self {
+        self.view = view;
+        self
+    }
+
+    // Where the turned image is shown. Fitted to the bounds and centered, or
+    // when zoomed, moved so that the focus point is in the middle without
+    // leaving empty space at the edges.
+    fn placement(&self, image_data: &ImageData, bounds: Rectangle) -> Rectangle {
+        let (width, height) = match self.view.quarter_turns % 2 {
+            0 => (image_data.width as f32, image_data.height as f32),
+            _ => (image_data.height as f32, image_data.width as f32),
+        };
+        let (width_scale, height_scale) = (bounds.width / width, bounds.height / height);
+        let scale = match self.fill {
+            true => width_scale.max(height_scale),
+            false => width_scale.min(height_scale),
+        } * self.view.zoom();
+        let size = Size::new(width * scale, height * scale);
+        let position = |bounds: f32, size: f32, focus: f32| {
+            if size <= bounds {
+                (bounds - size) / 2.0
+            } else {
+                (bounds / 2.0 - focus * size).clamp(bounds - size, 0.0)
+            }
+        };
+        Rectangle::new(
+            Point::new(
+                position(bounds.width, size.width, self.view.focus.0),
+                position(bounds.height, size.height, self.view.focus.1),
+            ),
+            size,
+        )
+    }
+
+    pub fn with_fill(mut self, fill: bool) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    pub fn reference(mut self) -> Self {
+        self.reference = true;
+        self
+    }
+
+    pub fn with_placeholder(mut self, placeholder: Option<Placeholder>) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+}
+
+fn draw_placeholder(frame: &mut Frame, bounds: Rectangle, placeholder: &Placeholder) {
+    let center = Point::new(bounds.width / 2.0, bounds.height / 2.0);
+    if let Some((width, height)) = placeholder.size {
+        let scale = (bounds.width / width as f32).min(bounds.height / height as f32);
+        let size = iced::Size::new(width as f32 * scale, height as f32 * scale);
+        let outline = Path::rectangle(
+            Point::new(center.x - size.width / 2.0, center.y - size.height / 2.0),
+            size,
+        );
+        frame.stroke(
+            &outline,
+            Stroke::default()
+                .with_width(1.0)
+                .with_color(iced::Color::from_rgb(0.3, 0.3, 0.3)),
+        );
+    }
+    draw_spinner(frame, bounds, placeholder);
+}
+
+fn draw_spinner(frame: &mut Frame, bounds: Rectangle, placeholder: &Placeholder) {
+    let center = Point::new(bounds.width / 2.0, bounds.height / 2.0);
+    let spinner = Path::new(|builder| {
+        builder.arc(path::Arc {
+            center,
+            radius: 20.0,
+            start_angle: Radians(placeholder.spinner_phase),
+            end_angle: Radians(placeholder.spinner_phase + 4.5),
+        })
+    });
+    frame.stroke(
+        &spinner,
+        Stroke::default()
+            .with_width(4.0)
+            .with_color(iced::Color::from_rgb(0.5, 0.5, 0.5)),
+    );
+    frame.fill_text(canvas::Text {
+        content: placeholder.label.clone(),
+        position: Point::new(center.x, center.y + 40.0),
+        color: iced::Color::from_rgb(0.5, 0.5, 0.5),
+        horizontal_alignment: iced::alignment::Horizontal::Center,
+        vertical_alignment: iced::alignment::Vertical::Top,
+        ..canvas::Text::default()
+    });
 }
 
 impl<'a> canvas::Program<Message> for PixelCanvas<'a> {
@@ -42,52 +221,40 @@ impl<'a> canvas::Program<Message> for PixelCanvas<'a> {
         let image_data = if let Some(image_data) = &self.image_data {
             image_data
         } else {
-            // TODO show loading image here
+            if let Some(placeholder) = &self.placeholder {
+                draw_placeholder(&mut frame, bounds, placeholder);
+            }
             return vec![frame.into_geometry()];
         };
+        // A thumbnail standing in for the image is shown blurred under the
+        // spinner, instead of looking like a sharp but blocky image
+        let (drawn, placeholder) = match (&self.placeholder, &image_data.blurred) {
+            (Some(placeholder), Some(blurred)) => (blurred.as_ref(), Some(placeholder)),
+            _ => (*image_data, None),
+        };
 
-        // Calculate scaling to fit the image within bounds while maintaining aspect ratio
-        let image_aspect = image_data.width as f32 / image_data.height as f32;
-        let bounds_aspect = bounds.width / bounds.height;
-
-        let (draw_width, draw_height) = if image_aspect > bounds_aspect {
-            // Image is wider than bounds - scale by width
-            (bounds.width, bounds.width / image_aspect)
-        } else {
-            // Image is taller than bounds - scale by height
-            (bounds.height * image_aspect, bounds.height)
+        let shown = self.placement(image_data, bounds);
+        // The image is drawn unturned and turned around its center
+        let size = match self.view.quarter_turns % 2 {
+            0 => shown.size(),
+            _ => Size::new(shown.height, shown.width),
         };
+        let unturned = Rectangle::new(
+            shown.center() - iced::Vector::new(size.width / 2.0, size.height / 2.0),
+            size,
+        );
+        let rotation = Radians(self.view.quarter_turns as f32 * std::f32::consts::FRAC_PI_2);
 
-        // Center the image in the bounds
-        let x_offset = (bounds.width - draw_width) / 2.0;
-        let y_offset = (bounds.height - draw_height) / 2.0;
-
-        // Calculate pixel size for rendering
-        let pixel_width = draw_width / image_data.width as f32;
-        let pixel_height = draw_height / image_data.height as f32;
-
-        // Draw each pixel as a small filled rectangle
-        for y in 0..image_data.height {
-            for x in 0..image_data.width {
-                let pixel_index = ((y * image_data.width + x) * 4) as usize;
-                if pixel_index + 3 < image_data.data.len() {
-                    let r = image_data.data[pixel_index] as f32 / 255.0;
-                    let g = image_data.data[pixel_index + 1] as f32 / 255.0;
-                    let b = image_data.data[pixel_index + 2] as f32 / 255.0;
-                    let a = image_data.data[pixel_index + 3] as f32 / 255.0;
-
-                    let color = iced::Color::from_rgba(r, g, b, a);
-
-                    frame.fill_rectangle(
-                        Point::new(
-                            x_offset + x as f32 * pixel_width,
-                            y_offset + y as f32 * pixel_height,
-                        ),
-                        Size::new(pixel_width, pixel_height),
-                        color,
-                    );
-                }
-            }
+        // A single textured quad, the GPU does the scaling. Clipped, a zoomed
+        // image is larger than the canvas.
+        frame.with_clip(Rectangle::with_size(bounds.size()), |frame| {
+            frame.draw_image(
+                unturned,
+                canvas::Image::new(&drawn.handle).rotation(rotation),
+            );
+        });
+        if let Some(placeholder) = placeholder {
+            draw_spinner(&mut frame, bounds, placeholder);
         }
 
         vec![frame.into_geometry()]
@@ -96,10 +263,44 @@ impl<'a> canvas::Program<Message> for PixelCanvas<'a> {
     fn update(
         &self,
         _state: &mut Self::State,
-        _event: canvas::Event,
+        event: canvas::Event,
         bounds: Rectangle,
-        _cursor: mouse::Cursor,
+        cursor: mouse::Cursor,
     ) -> (canvas::event::Status, Option<Message>) {
+        // Only the main image and the reference image can be zoomed
+        if let (
+            true,
+            Some(image_data),
+            Some(position),
+            canvas::Event::Mouse(mouse::Event::WheelScrolled { delta }),
+        ) = (
+            self.send_resize_messages || self.reference,
+            self.image_data,
+            cursor.position_in(bounds),
+            event,
+        ) {
+            let y = match delta {
+                mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. } => y,
+            };
+            let shown = self.placement(image_data, bounds);
+            let focus = (
+                ((position.x - shown.x) / shown.width).clamp(0.0, 1.0),
+                ((position.y - shown.y) / shown.height).clamp(0.0, 1.0),
+            );
+            let steps = if y > 0.0 { 1 } else { -1 };
+            if y == 0.0 {
+                return (canvas::event::Status::Ignored, None);
+            }
+            let message = match self.reference {
+                true => PixelCanvasMessage::UserScrolledReference(steps, focus),
+                false => PixelCanvasMessage::UserScrolled(steps, focus),
+            };
+            return (
+                canvas::event::Status::Captured,
+                Some(Message::PixelCanvas(message)),
+            );
+        }
+
         // Only send size change messages if enabled
         let message = if self.send_resize_messages {
             Some(Message::PixelCanvas(PixelCanvasMessage::CanvasSized(Dim {
@@ -113,3 +314,48 @@ impl<'a> canvas::Program<Message> for PixelCanvas<'a> {
         (canvas::event::Status::Ignored, message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement() {
+        let image = ImageData::new(400, 200, vec![0; 400 * 200 * 4]);
+        let bounds = Rectangle::with_size(Size::new(400.0, 400.0));
+        let placement = |view: ViewState| {
+            PixelCanvas::new(Some(&image), true)
+                .with_view(view)
+                .placement(&image, bounds)
+        };
+
+        let fitted = placement(ViewState::default());
+        assert_eq!(
+            fitted,
+            Rectangle::new(Point::new(0.0, 100.0), Size::new(400.0, 200.0))
+        );
+
+        // Turned on its side it fills the height instead
+        let turned = placement(ViewState::default().turned(true));
+        assert_eq!(
+            turned,
+            Rectangle::new(Point::new(100.0, 0.0), Size::new(200.0, 400.0))
+        );
+        assert_eq!(ViewState::default().turned(false).quarter_turns, 3);
+
+        // Zoomed in on the left edge, which stays at the edge of the canvas
+        let zoomed = placement(ViewState::default().zoomed(4, Some((0.0, 0.5))));
+        assert_eq!(zoomed.x, 0.0);
+        assert!(zoomed.width > 400.0);
+        assert_eq!(ViewState::default().zoomed(-1, None), ViewState::default());
+
+        // Filling a square cell crops the sides, centered
+        let filled = PixelCanvas::new(Some(&image), false)
+            .with_fill(true)
+            .placement(&image, bounds);
+        assert_eq!(
+            filled,
+            Rectangle::new(Point::new(-200.0, 0.0), Size::new(800.0, 400.0))
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use iced::keyboard::{Key, Modifiers};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::sorting::{Tag, TagNames};
+
+// Rebindable keys of the sorting view. Every action can have several keys,
+// each either a character or the name of a special key such as "ArrowRight"
+// or "Delete". The keymap is consulted before the fixed keys, so binding for
+// example "d" to a tag takes it from the visual diff.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Tag(Tag),
+    NextImage,
+    PreviousImage,
+    Delete,
+    ToggleHistogram,
+}
+
+impl KeyAction {
+    pub fn all_variants(tag_names: &TagNames) -> Vec<KeyAction> {
+        tag_names
+            .enumerate()
+            .map(|(tag, _)| KeyAction::Tag(tag))
+            .chain([
+                KeyAction::NextImage,
+                KeyAction::PreviousImage,
+                KeyAction::Delete,
+                KeyAction::ToggleHistogram,
+            ])
+            .collect()
+    }
+
+    pub fn display_name(&self) -> String {
+        match self {
+            KeyAction::Tag(tag) => t!("Tag %{number}", number = tag.number()).to_string(),
+            KeyAction::NextImage => t!("Next image").to_string(),
+            KeyAction::PreviousImage => t!("Previous image").to_string(),
+            KeyAction::Delete => t!("Delete").to_string(),
+            KeyAction::ToggleHistogram => t!("Show histogram").to_string(),
+        }
+    }
+
+    // Name in the config file
+    fn config_name(&self) -> String {
+        match self {
+            KeyAction::Tag(tag) => format!("tag{}", tag.number()),
+            KeyAction::NextImage => "next_image".to_owned(),
+            KeyAction::PreviousImage => "previous_image".to_owned(),
+            KeyAction::Delete => "delete".to_owned(),
+            KeyAction::ToggleHistogram => "toggle_histogram".to_owned(),
+        }
+    }
+
+    fn from_config_name(name: &str) -> Option<KeyAction> {
+        match name {
+            "next_image" => Some(KeyAction::NextImage),
+            "previous_image" => Some(KeyAction::PreviousImage),
+            "delete" => Some(KeyAction::Delete),
+            "toggle_histogram" => Some(KeyAction::ToggleHistogram),
+            _ => {
+                let number = name.strip_prefix("tag")?.parse().ok()?;
+                (number > 0).then_some(KeyAction::Tag(Tag::new(number)))
+            }
+        }
+    }
+}
+
+// Stored as a table of action names to keys, actions missing there keep their
+// default keys. Tags added later have no keys until some are set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct Keymap {
+    bindings: Vec<(KeyAction, Vec<String>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = |keys: &[&str]| keys.iter().map(|&key| key.to_owned()).collect();
+        Self {
+            bindings: vec![
+                (KeyAction::Tag(Tag::new(1)), keys(&["a"])),
+                (KeyAction::Tag(Tag::new(2)), keys(&["o"])),
+                (KeyAction::Tag(Tag::new(3)), keys(&["e"])),
+                (KeyAction::Tag(Tag::new(4)), keys(&["u"])),
+                (KeyAction::Tag(Tag::new(5)), keys(&["p"])),
+                (KeyAction::Tag(Tag::new(6)), keys(&["y"])),
+                (KeyAction::Tag(Tag::new(7)), keys(&["j"])),
+                (KeyAction::Tag(Tag::new(8)), keys(&["k"])),
+                (KeyAction::NextImage, keys(&["t", "l", "ArrowRight"])),
+                (KeyAction::PreviousImage, keys(&["h", "ArrowLeft"])),
+                (KeyAction::Delete, keys(&["Delete"])),
+                (KeyAction::ToggleHistogram, keys(&["w"])),
+            ],
+        }
+    }
+}
+
+impl From<BTreeMap<String, Vec<String>>> for Keymap {
+    fn from(table: BTreeMap<String, Vec<String>>) -> Self {
+        let mut keymap = Keymap::default();
+        for (name, keys) in table {
+            if let Some(action) = KeyAction::from_config_name(&name) {
+                keymap.set_keys(action, keys);
+            }
+        }
+        keymap
+    }
+}
+
+impl From<Keymap> for BTreeMap<String, Vec<String>> {
+    fn from(keymap: Keymap) -> Self {
+        keymap
+            .bindings
+            .into_iter()
+            .map(|(action, keys)| (action.config_name(), keys))
+            .collect()
+    }
+}
+
+impl Keymap {
+    pub fn actions(&self) -> impl Iterator<Item = KeyAction> + '_ {
+        self.bindings.iter().map(|(action, _)| *action)
+    }
+
+    pub fn keys(&self, action: KeyAction) -> &[String] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn set_keys(&mut self, action: KeyAction, keys: Vec<String>) {
+        match self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+            Some((_, bound_keys)) => *bound_keys = keys,
+            None => self.bindings.push((action, keys)),
+        }
+    }
+
+    pub fn action(&self, key: Key<&str>, modifiers: Modifiers) -> Option<KeyAction> {
+        let name = match key {
+            // Control combinations are left for the application
+            Key::Character(_) if modifiers.control() => return None,
+            Key::Character(c) => c.to_owned(),
+            Key::Named(named) => format!("{named:?}"),
+            Key::Unidentified => return None,
+        };
+        self.action_for_name(&name)
+    }
+
+    // A key bound to two actions, only the first one would ever be used
+    pub fn conflict(&self) -> Option<(&str, KeyAction)> {
+        self.bindings.iter().find_map(|(action, keys)| {
+            keys.iter()
+                .find(|key| self.action_for_name(key) != Some(*action))
+                .map(|key| (key.as_str(), *action))
+        })
+    }
+
+    fn action_for_name(&self, name: &str) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|key| key == name))
+            .map(|(action, _)| *action)
+    }
+}
+
+// Space separated, as typed in the settings
+pub fn parse_keys(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_owned).collect()
+}
+
+pub fn format_keys(keys: &[String]) -> String {
+    keys.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::keyboard::key::Named;
+
+    #[test]
+    fn test_action() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(Key::Character("a"), Modifiers::empty()),
+            Some(KeyAction::Tag(Tag::new(1)))
+        );
+        assert_eq!(keymap.action(Key::Character("a"), Modifiers::CTRL), None);
+        assert_eq!(
+            keymap.action(Key::Named(Named::ArrowRight), Modifiers::empty()),
+            Some(KeyAction::NextImage)
+        );
+        assert_eq!(
+            keymap.action(Key::Named(Named::Delete), Modifiers::SHIFT),
+            Some(KeyAction::Delete)
+        );
+        assert_eq!(
+            keymap.action(Key::Character("w"), Modifiers::empty()),
+            Some(KeyAction::ToggleHistogram)
+        );
+
+        keymap.set_keys(KeyAction::Tag(Tag::new(1)), parse_keys(" 1  F1 "));
+        assert_eq!(keymap.action(Key::Character("a"), Modifiers::empty()), None);
+        assert_eq!(
+            keymap.action(Key::Named(Named::F1), Modifiers::empty()),
+            Some(KeyAction::Tag(Tag::new(1)))
+        );
+        assert_eq!(
+            format_keys(keymap.keys(KeyAction::Tag(Tag::new(1)))),
+            "1 F1"
+        );
+    }
+
+    #[test]
+    fn test_conflict() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.conflict(), None);
+        keymap.set_keys(KeyAction::Tag(Tag::new(8)), parse_keys("t"));
+        assert_eq!(keymap.conflict(), Some(("t", KeyAction::NextImage)));
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+// The language of the interface, by default the one of the system. English is
+// used when the system language has no translations. The Swedish translations
+// are under "se" in locales/app.yml.
+
+const FALLBACK_LOCALE: &str = "en";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    System,
+    English,
+    Swedish,
+}
+
+impl Language {
+    // In their own language, so they can be found from any other
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Language::System => "System language",
+            Language::English => "English",
+            Language::Swedish => "Svenska",
+        }
+    }
+
+    pub fn all_variants() -> Vec<Language> {
+        vec![Language::System, Language::English, Language::Swedish]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<Language> {
+        Self::all_variants()
+            .into_iter()
+            .find(|language| language.display_name() == name)
+    }
+
+    pub fn locale(&self) -> &'static str {
+        match self {
+            Language::System => sys_locale::get_locale()
+                .and_then(|system| supported_locale(&system))
+                .unwrap_or(FALLBACK_LOCALE),
+            Language::English => "en",
+            Language::Swedish => "se",
+        }
+    }
+}
+
+// Such as "sv-SE" or "sv_SE.UTF-8"
+fn supported_locale(system: &str) -> Option<&'static str> {
+    let language = system.split(['-', '_', '.']).next()?;
+    match language.to_ascii_lowercase().as_str() {
+        "en" => Some("en"),
+        "sv" => Some("se"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_locale() {
+        assert_eq!(supported_locale("sv-SE"), Some("se"));
+        assert_eq!(supported_locale("sv_FI.UTF-8"), Some("se"));
+        assert_eq!(supported_locale("en-GB"), Some("en"));
+        assert_eq!(supported_locale("de-DE"), None);
+    }
+}
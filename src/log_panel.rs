@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use iced::widget::{button, column, row, scrollable, text};
+use iced::{Color, Element, Length};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rust_i18n::t;
+use simplelog::SharedLogger;
+
+use crate::{exif_data, Message};
+
+// Recent warnings and errors, such as files that could not be decoded or
+// moved, kept in memory for the Log tab. Problems can then be copied into a
+// report without looking for imgsort.log. The oldest entries are dropped.
+
+const MAX_ENTRIES: usize = 500;
+
+static ENTRIES: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    // Local time of day
+    pub time: String,
+    pub level: Level,
+    pub message: String,
+}
+
+impl LogEntry {
+    fn line(&self) -> String {
+        format!("{} {} {}", self.time, self.level, self.message)
+    }
+}
+
+pub struct PanelLogger;
+
+impl Log for PanelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn && metadata.target().starts_with("imgsort")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = time::OffsetDateTime::now_utc().to_offset(exif_data::local_offset());
+        let entry = LogEntry {
+            time: format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second()),
+            level: record.level(),
+            message: record.args().to_string(),
+        };
+        if let Ok(mut entries) = ENTRIES.lock() {
+            push_entry(&mut entries, entry);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for PanelLogger {
+    fn level(&self) -> LevelFilter {
+        LevelFilter::Warn
+    }
+
+    fn config(&self) -> Option<&simplelog::Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        self
+    }
+}
+
+fn push_entry(entries: &mut VecDeque<LogEntry>, entry: LogEntry) {
+    if entries.len() == MAX_ENTRIES {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+pub fn entries() -> Vec<LogEntry> {
+    ENTRIES
+        .lock()
+        .map(|entries| entries.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut entries) = ENTRIES.lock() {
+        entries.clear();
+    }
+}
+
+// One line per entry, oldest first, as copied to the clipboard
+pub fn as_text(entries: &[LogEntry]) -> String {
+    entries.iter().map(|entry| entry.line() + "\n").collect()
+}
+
+pub fn view<'a>() -> Element<'a, Message> {
+    let entries = entries();
+    let buttons = row![
+        button(text(t!("Copy to clipboard")))
+            .on_press_maybe((!entries.is_empty()).then_some(Message::UserPressedCopyLog)),
+        button(text(t!("Clear")))
+            .on_press_maybe((!entries.is_empty()).then_some(Message::UserPressedClearLog)),
+    ]
+    .spacing(10);
+    if entries.is_empty() {
+        return column![buttons, text(t!("No warnings or errors"))]
+            .spacing(10)
+            .into();
+    }
+    // Newest first
+    let lines = entries.iter().rev().map(|entry| {
+        let color = match entry.level {
+            Level::Error => Color::from_rgb(0.8, 0.1, 0.1),
+            _ => Color::from_rgb(0.8, 0.5, 0.0),
+        };
+        text(entry.line()).color(color).into()
+    });
+    column![
+        buttons,
+        scrollable(column(lines).spacing(2)).height(Length::Fill)
+    ]
+    .spacing(10)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_entry() {
+        let entry = |n: usize| LogEntry {
+            time: "12:00:00".to_owned(),
+            level: Level::Warn,
+            message: n.to_string(),
+        };
+        let mut entries = VecDeque::new();
+        for n in 0..MAX_ENTRIES + 2 {
+            push_entry(&mut entries, entry(n));
+        }
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries.front(), Some(&entry(2)));
+
+        let entries: Vec<LogEntry> = entries.into_iter().take(2).collect();
+        assert_eq!(as_text(&entries), "12:00:00 WARN 2\n12:00:00 WARN 3\n");
+    }
+}
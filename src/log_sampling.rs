@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::mem::Discriminant;
+use std::time::{Duration, Instant};
+
+use log::{debug, log_enabled, trace, Level};
+
+// The messages and effects logged at debug level, a few of each kind a second.
+// Key repeats, mouse moves and preloads otherwise write megabytes of log and
+// slow down the update loop. The ones left out are counted, and the count is
+// logged when the second is over. At trace level everything is logged.
+
+const WINDOW: Duration = Duration::from_secs(1);
+const PER_WINDOW: u32 = 5;
+
+#[derive(Debug)]
+pub struct LogSampler<T> {
+    kinds: HashMap<Discriminant<T>, KindWindow>,
+}
+
+#[derive(Debug)]
+struct KindWindow {
+    start: Instant,
+    logged: u32,
+    dropped: u32,
+    // The variant, from the first one logged
+    name: String,
+}
+
+impl<T> Default for LogSampler<T> {
+    fn default() -> Self {
+        Self {
+            kinds: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Debug> LogSampler<T> {
+    pub fn log(&mut self, label: &str, value: &T) {
+        if log_enabled!(Level::Trace) {
+            trace!("{label}: {value:?}");
+        } else if log_enabled!(Level::Debug) {
+            for line in self.sample(label, value, Instant::now()) {
+                debug!("{line}");
+            }
+        }
+    }
+
+    fn sample(&mut self, label: &str, value: &T, now: Instant) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.kinds.retain(|_, window| {
+            let over = now.duration_since(window.start) >= WINDOW;
+            if over && window.dropped > 0 {
+                lines.push(format!(
+                    "{label}: {} more {} left out",
+                    window.dropped, window.name
+                ));
+            }
+            !over
+        });
+        let window = self
+            .kinds
+            .entry(std::mem::discriminant(value))
+            .or_insert_with(|| KindWindow {
+                start: now,
+                logged: 0,
+                dropped: 0,
+                name: String::new(),
+            });
+        if window.logged < PER_WINDOW {
+            let text = format!("{value:?}");
+            window.logged += 1;
+            window.name = variant_name(&text).to_owned();
+            lines.push(format!("{label}: {text}"));
+        } else {
+            window.dropped += 1;
+        }
+        lines
+    }
+}
+
+fn variant_name(text: &str) -> &str {
+    text.split(['(', ' ', '{']).next().unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let mut sampler = LogSampler::default();
+        let start = Instant::now();
+        let logged: usize = (0..20)
+            .map(|i| sampler.sample("Event", &Some(i), start).len())
+            .sum();
+        assert_eq!(logged, PER_WINDOW as usize);
+        // Other kinds have their own count
+        assert_eq!(sampler.sample("Event", &None, start), ["Event: None"]);
+
+        let later = start + WINDOW;
+        assert_eq!(
+            sampler.sample("Event", &Some(20), later),
+            ["Event: 15 more Some left out", "Event: Some(20)"]
+        );
+    }
+}
@@ -1,77 +1,346 @@
 use clap::Parser;
 
+use iced::advanced::image::Bytes;
 use iced::event::{self, Event};
+use iced::futures::{SinkExt, StreamExt};
 use iced::widget::{self, column};
 use iced::{Element, Subscription, Task};
 use iced_aw::Tabs;
 use image::ImageReader;
 use image::{DynamicImage, ImageDecoder};
-use log::debug;
+use log::{debug, trace, warn};
 
 rust_i18n::i18n!("locales");
 
 mod actions;
+mod batch;
+mod capture_heatmap;
+mod checkpoint;
+mod config_file;
+mod decode_stats;
+mod destination_preview;
+mod end_summary;
+mod exif_data;
+mod external_viewer;
+mod file_ops;
+mod folder_compare;
+mod folder_stats;
+mod format;
+mod histogram;
+mod ignore_rules;
+mod image_kind;
+mod image_size;
 mod image_widget;
+mod keymap;
+mod language;
+mod log_panel;
+mod log_sampling;
+mod merge_review;
+mod metadata_db;
+mod montage;
+mod move_plan;
+mod notifications;
 mod pathlist;
+mod print;
+mod raw;
+mod session;
 mod settings;
+mod share_export;
+mod shared_session;
+mod sort_order;
 mod sorting;
+mod tag_defaults;
+mod tag_io;
+mod takeout;
 mod task_manager;
+mod throttle;
+mod time_shift;
+mod timestamps;
 mod ui;
+mod upload;
+mod video;
+mod visual_diff;
+mod xmp;
+mod zip_export;
 
-use image_widget::PixelCanvasMessage;
-use pathlist::PathList;
+use image_widget::{PixelCanvasMessage, ViewState};
+use pathlist::{PathList, ViewFilter};
 
 use rust_i18n::t;
 use settings::{SettingsMessage, SettingsModel};
-use sorting::{SortingMessage, Tag, TagNames};
+use sorting::{CompareReference, HeldStep, SortingMessage, Tag, TagFlash, TagNames};
 use task_manager::{TaskId, TaskManager, TaskType};
 
+use crate::capture_heatmap::CaptureHeatmap;
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::{Checkpoint, CheckpointImage};
+use crate::decode_stats::DecodeHistogram;
+use crate::destination_preview::{DestinationContents, DestinationPreview};
+use crate::file_ops::{CopyResult, MoveResult, MovedFile};
+use crate::folder_compare::{CopyDirection, FolderComparison};
+use crate::folder_stats::FolderStats;
+use crate::ignore_rules::IgnoreRules;
+use crate::image_kind::ImageKind;
+use crate::keymap::Keymap;
+use crate::language::Language;
+use crate::log_sampling::LogSampler;
+use crate::merge_review::{MergeChoice, MergeReview};
+use crate::metadata_db::FileHistory;
+use crate::move_plan::{CollisionChoice, MovePlan};
+use crate::pathlist::FileIdentity;
+use crate::session::{Session, SessionRatings, SessionTags};
+use crate::share_export::{ShareExportForm, ShareExportSettings};
+use crate::shared_session::SessionMerge;
+use crate::sort_order::SortOrder;
 use crate::sorting::Dim;
+use crate::tag_io::TagAssignment;
+use crate::takeout::SidecarMetadata;
 use crate::task_manager::TaskCompleteResult;
+use crate::time_shift::{TimeShift, TimeShiftForm};
+use crate::timestamps::TimestampChange;
+use crate::upload::UploadProgress;
+use crate::visual_diff::VisualDiff;
+use crate::xmp::XmpSidecar;
+use crate::zip_export::ZipProgress;
 
 const PICTURE_DIR: &str = ".";
 pub const PRELOAD_IN_FLIGHT: usize = 8;
-#[allow(dead_code)]
-const PRELOAD_CACHE_SIZE: usize = 100;
+const IDLE_PRELOAD_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
+const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+// Stepping through images only saves the image stopped at
+const POSITION_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+const SPINNER_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+const MIN_WINDOW_SIZE: iced::Size = iced::Size::new(480.0, 400.0);
+// Below this window width the sorting view switches to a compact layout
+const COMPACT_LAYOUT_WIDTH: f32 = 900.0;
+// Previews within this fraction of the size they would be decoded at now are
+// kept when the window is resized
+pub const PREVIEW_SIZE_TOLERANCE: f32 = 0.15;
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<batch::Command>,
+
+    /// Folders to sort, the ones after the first are queued
     #[arg(default_value = ".")]
-    input_dir: String,
+    input_dirs: Vec<String>,
+
+    /// Tag files from a CSV or JSON file when the folder is opened
+    #[arg(long, value_name = "FILE")]
+    import_tags: Option<std::path::PathBuf>,
+
+    /// Write the tags of the last session in the folder to a CSV or JSON file, then exit
+    #[arg(long, value_name = "FILE")]
+    export_tags: Option<std::path::PathBuf>,
+
+    /// Keep file metadata in a database shared across folders, in the user data folder unless FILE is given
+    #[arg(long, value_name = "FILE")]
+    metadata_db: Option<Option<std::path::PathBuf>>,
+
+    /// Mark images that already exist somewhere under DIR, implies --metadata-db
+    #[arg(long, value_name = "DIR")]
+    archive_root: Option<std::path::PathBuf>,
+
+    /// Start with the default settings, without restoring the session and without background work, for when a normal start fails
+    #[arg(long)]
+    safe_mode: bool,
+
+    /// Open the folder sorted last, at the image it was left on
+    #[arg(long, conflicts_with = "input_dirs")]
+    resume: bool,
+
+    /// Language of the interface, instead of the one from the settings or the system
+    #[arg(long, value_parser = rust_i18n::available_locales!())]
+    locale: Option<String>,
+
+    /// Least severe messages logged to the terminal and the log file: off, error, warn, info, debug or trace
+    #[arg(long, value_name = "LEVEL", default_value = "debug")]
+    log_level: simplelog::LevelFilter,
+
+    /// Where to write the log, by default imgsort.log in the user's cache folder
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Don't write a log file
+    #[arg(long, conflicts_with = "log_file")]
+    no_log_file: bool,
+}
+
+// Not in the sorted folder, which is often a camera card or a shared drive
+fn default_log_file() -> Option<std::path::PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("imgsort").join("imgsort.log"))
+}
+
+#[derive(Debug, Default)]
+struct StartupOptions {
+    imported_tags: Vec<(String, Tag)>,
+    folder_queue: Vec<std::path::PathBuf>,
+    metadata_db: Option<std::path::PathBuf>,
+    archive_root: Option<std::path::PathBuf>,
+    safe_mode: bool,
+    locale: Option<String>,
 }
 
 pub fn main() -> iced::Result {
-    simplelog::CombinedLogger::init(vec![
+    exif_data::init_local_offset();
+
+    let args = Args::parse();
+    // Before the logger, which would mix into the listing
+    if let Some(command) = args.command {
+        std::process::exit(batch::run(command));
+    }
+
+    let log_config = || {
+        simplelog::ConfigBuilder::new()
+            .add_filter_allow_str("imgsort")
+            .build()
+    };
+    let mut loggers: Vec<Box<dyn simplelog::SharedLogger>> = vec![
         simplelog::TermLogger::new(
-            simplelog::LevelFilter::Debug,
-            simplelog::ConfigBuilder::new()
-                .add_filter_allow_str("imgsort")
-                .build(),
+            args.log_level,
+            log_config(),
             simplelog::TerminalMode::Mixed,
             simplelog::ColorChoice::Auto,
         ),
-        simplelog::WriteLogger::new(
-            simplelog::LevelFilter::Debug,
-            simplelog::ConfigBuilder::new()
-                .add_filter_allow_str("imgsort")
-                .build(),
-            std::fs::File::create("imgsort.log").unwrap(),
-        ),
-    ])
-    .unwrap();
-
-    rust_i18n::set_locale("se");
+        Box::new(log_panel::PanelLogger),
+    ];
+    let log_file = match args.no_log_file {
+        true => None,
+        false => args.log_file.clone().or_else(default_log_file),
+    };
+    if let Some(log_file) = log_file {
+        let created = log_file
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::File::create(&log_file));
+        match created {
+            Ok(file) => loggers.push(simplelog::WriteLogger::new(
+                args.log_level,
+                log_config(),
+                file,
+            )),
+            Err(e) => eprintln!("Error creating log file {}: {e}", log_file.display()),
+        }
+    }
+    simplelog::CombinedLogger::init(loggers).unwrap();
 
-    let args = Args::parse();
+    // Resolve file arguments before changing directory
+    let import_tags = args.import_tags.as_deref().map(absolute_path);
+    let export_tags = args.export_tags.as_deref().map(absolute_path);
+    let archive_root = args
+        .archive_root
+        .map(|dir| match std::fs::canonicalize(&dir) {
+            Ok(dir) => dir,
+            Err(e) => {
+                println!("Error opening archive {}: {e}", dir.display());
+                std::process::exit(1);
+            }
+        });
+    let metadata_db = match args.metadata_db {
+        Some(Some(path)) => Some(absolute_path(&path)),
+        Some(None) => metadata_db::default_path(),
+        None if archive_root.is_some() => metadata_db::default_path(),
+        None => None,
+    };
+    let folder_queue = args
+        .input_dirs
+        .iter()
+        .skip(1)
+        .map(|dir| absolute_path(std::path::Path::new(dir)))
+        .collect();
 
-    if std::env::set_current_dir(&args.input_dir).is_err() {
-        println!("Error opening directory {}", args.input_dir);
+    let input_dir = match args.resume {
+        true => match session::last_folder() {
+            Some(folder) => folder.to_string_lossy().to_string(),
+            None => {
+                println!("No last session to resume");
+                std::process::exit(1);
+            }
+        },
+        false => args.input_dirs[0].clone(),
+    };
+    if std::env::set_current_dir(&input_dir).is_err() {
+        println!("Error opening directory {input_dir}");
         std::process::exit(1);
     }
 
+    if let Some(export_path) = export_tags {
+        std::process::exit(export_tags_from_cli(&export_path));
+    }
+
+    let mut startup = StartupOptions {
+        folder_queue,
+        metadata_db,
+        archive_root,
+        safe_mode: args.safe_mode,
+        locale: args.locale,
+        ..StartupOptions::default()
+    };
+    if let Some(import_path) = import_tags {
+        match tag_io::import_tags(&import_path, &TagNames::new()) {
+            Ok(tags) => startup.imported_tags = tags,
+            Err(e) => {
+                println!("Error importing tags from {}: {e}", import_path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
     iced::application(Model::title, Model::update_with_task, Model::view)
         .subscription(Model::subscription)
-        .run_with(Model::new_with_task)
+        .exit_on_close_request(false)
+        .window(iced::window::Settings {
+            min_size: Some(MIN_WINDOW_SIZE),
+            ..iced::window::Settings::default()
+        })
+        .run_with(move || Model::new_with_task(startup))
+}
+
+fn absolute_path(path: &std::path::Path) -> std::path::PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_owned())
+}
+
+// The tags of the session file in the folder, or of the checkpoint left by a
+// run that did not exit cleanly
+fn export_tags_from_cli(export_path: &std::path::Path) -> i32 {
+    let session = std::env::current_dir()
+        .map(|directory| session::load(&directory))
+        .unwrap_or_default();
+    // A checkpoint is only left behind by a sort that didn't end cleanly, and
+    // then has tags the session may not have yet
+    let assignments = if let Some(checkpoint) =
+        checkpoint::load_for_directory(&checkpoint::current_directory())
+    {
+        tag_io::assignments_from(
+            checkpoint
+                .images
+                .into_iter()
+                .map(|image| (image.path, image.tag)),
+            &checkpoint.tag_names,
+        )
+    } else if !session.tags.is_empty() {
+        let tag_names = session.tag_names.unwrap_or_else(TagNames::new);
+        tag_io::assignments_from(
+            session
+                .tags
+                .into_iter()
+                .map(|(file, tag)| (file, Some(tag))),
+            &tag_names,
+        )
+    } else {
+        println!("No session found for this directory");
+        return 1;
+    };
+    match tag_io::export_tags(export_path, &assignments) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error exporting tags to {}: {e}", export_path.display());
+            1
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -80,28 +349,167 @@ struct Model {
     state: ModelState,
     settings: SettingsModel,
     active_tab: TabId,
+    // Held modifier keys, for clicks that select
+    modifiers: iced::keyboard::Modifiers,
     selected_action_tag: Option<Tag>,
     task_manager: TaskManager,
     pathlist: PathList,
     editing_tag_name: Option<(Tag, String, widget::text_input::Id)>,
+    // Remove was pressed once for a tag that images still have
+    removing_tag: Option<Tag>,
+    renaming_file: Option<(String, widget::text_input::Id)>,
+    // Number typed after g, of the image to jump to
+    going_to: Option<(String, widget::text_input::Id)>,
+    rename_error: Option<String>,
     tag_names: TagNames,
     canvas_dimensions: Option<Dim>,
+    window_focused: bool,
+    message_log: LogSampler<Message>,
+    effect_log: LogSampler<Effect>,
+    // Started with --safe-mode: the settings file, the session and checkpoint
+    // of the folder and the background analysis are left alone
+    safe_mode: bool,
+    background_paused: bool,
+    last_navigation: std::time::Instant,
+    // Set by messages that may change what is saved in the session, so the
+    // frequent ones don't rebuild it to compare
+    session_dirty: bool,
+    last_checkpoint: Option<Checkpoint>,
+    pending_recovery: Option<Checkpoint>,
+    timestamp_plan: Option<Vec<TimestampChange>>,
+    // Shown for confirmation while not empty
+    move_plans: Vec<MovePlan>,
+    // Moved when the session is finished, with deferred moves on
+    queued_moves: Vec<Tag>,
+    time_shift_form: TimeShiftForm,
+    share_export_form: ShareExportForm,
+    zip_resized: bool,
+    time_shift_plan: Option<Vec<TimeShift>>,
+    action_status: Option<String>,
+    visual_diff: Option<VisualDiff>,
+    pending_tag_import: Vec<(String, Tag)>,
+    spinner_phase: f32,
+    held_step: Option<HeldStep>,
+    // Steps through the untagged images on its own until a tag is given
+    slideshow: bool,
+    tag_flash: Option<TagFlash>,
+    compare: Option<CompareReference>,
+    // Only the image is shown, until the mouse moves
+    controls_hidden: bool,
+    // Only the image and its badges are shown, on the whole screen
+    fullscreen: bool,
+    last_mouse_move: std::time::Instant,
+    show_info_panel: bool,
+    show_histogram: bool,
+    show_destination_preview: bool,
+    destination_preview: Option<DestinationPreview>,
+    decode_histogram: DecodeHistogram,
+    folder_queue: std::collections::VecDeque<std::path::PathBuf>,
+    // Sorted in the last session, offered when the folder opened is empty
+    last_folder: Option<std::path::PathBuf>,
+    last_move: Option<MoveResult>,
+    review: Option<ReviewSession>,
+    merge_review: Option<MergeReview>,
+    end_summary: bool,
+    folder_stats: Option<FolderStats>,
+    // Shown in the Actions tab while open
+    folder_comparison: Option<FolderComparison>,
+    // First press of a guarded destructive key, waiting for the second one
+    destructive_key_pressed: Option<std::time::Instant>,
+    window_width: f32,
+    scale_factor: f32,
+    capture_heatmap: Option<CaptureHeatmap>,
+    metadata_db: Option<std::path::PathBuf>,
+    archive_root: Option<std::path::PathBuf>,
+    // Tags as last written to the session file of the folder
+    saved_session: Session,
+    // Of the random order, kept while the program runs
+    shuffle_seed: u64,
 }
 
+// Read-only look at the destination of a move, with the option to pull files
+// back to where they came from
 #[derive(Debug)]
+struct ReviewSession {
+    return_dir: std::path::PathBuf,
+    moves: Vec<MovedFile>,
+}
+
+#[derive(Debug, Clone, Copy)]
 enum ModelState {
     LoadingListDir,
     EmptyDirectory,
     Sorting,
 }
 
-#[derive(Debug, Clone)]
+// Stored in the config file, settings missing there keep their defaults
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     preload_back_num: usize,
     preload_front_num: usize,
+    // Images further away than this only get a thumbnail
+    preload_full_num: usize,
     scale_down_size: (u32, u32),
     thumbnail_size: Dim,
     thumbnail_style: SortingViewStyle,
+    thumbnail_shape: ThumbnailShape,
+    // Only the image is shown while sorting with the keyboard
+    auto_hide_controls: bool,
+    preview_quality: PreviewQuality,
+    // Full previews beyond this are dropped, furthest first
+    preview_memory_mb: usize,
+    destructive_key_guard: DestructiveKeyGuard,
+    io_concurrency: usize,
+    io_max_bytes_per_sec: u64,
+    keymap: Keymap,
+    recursive_scan: bool,
+    move_layout: MoveLayout,
+    // Tags are moved together when the session is finished
+    defer_moves: bool,
+    print_per_page: u32,
+    slideshow_seconds: u64,
+    end_of_list: EndOfList,
+    // For folders without a session, instead of the colors
+    default_tag_names: Vec<String>,
+    // Labels and ratings are also kept in sidecars other programs read
+    xmp_sidecars: bool,
+    language: Language,
+    sort_order: SortOrder,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            preload_back_num: 10,
+            preload_front_num: 30,
+            preload_full_num: 5,
+            scale_down_size: (800, 100),
+            thumbnail_size: Dim {
+                width: 100,
+                height: 100,
+            },
+            thumbnail_style: SortingViewStyle::ThumbsAbove,
+            thumbnail_shape: ThumbnailShape::Fit,
+            auto_hide_controls: false,
+            preview_quality: PreviewQuality::Canvas,
+            preview_memory_mb: 1024,
+            destructive_key_guard: DestructiveKeyGuard::Off,
+            io_concurrency: PRELOAD_IN_FLIGHT,
+            io_max_bytes_per_sec: 0,
+            keymap: Keymap::default(),
+            recursive_scan: false,
+            move_layout: MoveLayout::Flatten,
+            defer_moves: false,
+            print_per_page: 1,
+            slideshow_seconds: 3,
+            end_of_list: EndOfList::Stop,
+            default_tag_names: Vec::new(),
+            xmp_sidecars: false,
+            language: Language::System,
+            sort_order: SortOrder::Name,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -111,23 +519,118 @@ pub struct ImageInfo {
     pub metadata: Metadata,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Metadata {
     pub tag: Option<Tag>,
+    // Stars, 0 when not rated. Apart from the tag, which is where it goes.
+    pub rating: u8,
+    // From an XMP sidecar
+    pub keywords: Vec<String>,
+    pub xmp_label: Option<String>,
+    pub decode_time: Option<std::time::Duration>,
+    // Size of the displayed image, remembered between sessions
+    pub dimensions: Option<(u32, u32)>,
+    // Of the original, upright
+    pub pixel_size: Option<(u32, u32)>,
+    pub kind: Option<ImageKind>,
+    pub file_size: Option<u64>,
+    pub identity: Option<FileIdentity>,
+    // Only with the metadata database
+    pub content_hash: Option<String>,
+    pub history: Option<FileHistory>,
+    // A copy was found under the archive root
+    pub archived: bool,
+    pub sidecar: Option<SidecarMetadata>,
+    pub view: ViewState,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct ImageData {
     pub width: u32,
     pub height: u32,
-    pub data: Vec<u8>,
+    // RGBA, shared with the handle
+    pub data: Bytes,
+    // Created once so the renderer can keep the uploaded texture between frames
+    pub handle: widget::image::Handle,
+    // Only for the previews
+    pub histogram: Option<std::sync::Arc<histogram::Histogram>>,
+    // Only for the thumbnails, a few pixels across so that it is blurred when
+    // scaled up while the preview loads
+    pub blurred: Option<Box<ImageData>>,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+impl ImageData {
+    pub fn new(width: u32, height: u32, data: Vec<u8>) -> Self {
+        let data = Bytes::from(data);
+        Self {
+            width,
+            height,
+            handle: widget::image::Handle::from_rgba(width, height, data.clone()),
+            data,
+            histogram: None,
+            blurred: None,
+        }
+    }
+
+    fn with_blurred(mut self) -> Self {
+        const BLUR_FACTOR: u32 = 8;
+        let (width, height) = (
+            (self.width / BLUR_FACTOR).max(1),
+            (self.height / BLUR_FACTOR).max(1),
+        );
+        if let Some(image) = image::RgbaImage::from_raw(self.width, self.height, self.data.to_vec())
+        {
+            let small = image::imageops::resize(
+                &image,
+                width,
+                height,
+                image::imageops::FilterType::Triangle,
+            );
+            self.blurred = Some(Box::new(ImageData::new(width, height, small.into_raw())));
+        }
+        self
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum TabId {
     Main,
     Actions,
     Settings,
+    Log,
+}
+
+// Escape, which also ends the editing, and switching tabs still work while a
+// text field has focus, other keys are only for the text
+fn passes_text_input(event: &iced::keyboard::Event) -> bool {
+    use iced::keyboard::{key::Named, Event, Key};
+    matches!(
+        event,
+        Event::KeyPressed {
+            key: Key::Named(Named::Escape),
+            ..
+        }
+    ) || TabId::from_shortcut(event).is_some()
+}
+
+impl TabId {
+    // Ctrl+1 to Ctrl+4, in the order of the tabs
+    fn from_shortcut(event: &iced::keyboard::Event) -> Option<TabId> {
+        use iced::keyboard::{Event, Key};
+        let Event::KeyPressed { key, modifiers, .. } = event else {
+            return None;
+        };
+        if !modifiers.control() {
+            return None;
+        }
+        match key.as_ref() {
+            Key::Character("1") => Some(TabId::Main),
+            Key::Character("2") => Some(TabId::Actions),
+            Key::Character("3") => Some(TabId::Settings),
+            Key::Character("4") => Some(TabId::Log),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Debug for ImageData {
@@ -143,24 +646,175 @@ impl std::fmt::Debug for ImageData {
 #[derive(Debug, Clone)]
 pub enum Message {
     UserPressedSelectFolder,
+    UserPressedQueueFolder,
+    UserPressedNextFolder,
+    UserPressedResumeLastSession,
+    FolderPicked(FolderPurpose, Option<std::path::PathBuf>),
+    UserChangedTagDestination(Tag, String),
+    UserPressedPickTagDestination(Tag),
+    UserPressedReviewMove,
+    UserPressedDismissMove,
+    UserPressedUndoMove,
+    UserPressedPullBack,
+    UserPressedEndReview,
+    UserPickedMergeTag(MergeChoice),
+    UserPressedEndMergeReview,
+    MovesUndone(TaskId, Vec<MovedFile>, Result<(), String>, Vec<String>),
     UserSelectedTab(TabId),
+    UserPressedCopyLog,
+    UserPressedClearLog,
     UserPressedActionTag(Tag),
     UserPressedActionBack,
+    UserPressedActionMove(Tag),
     UserPressedActionCopy(Tag),
+    UserPressedActionFixTimestamps(Option<Tag>),
+    UserPressedExportTags,
+    UserPressedImportTags,
+    TagsExported(TaskId, Option<Result<String, String>>),
+    TagsImported(TaskId, Option<Result<Vec<(String, Tag)>, String>>),
+    UserPressedExportSession,
+    UserPressedMergeSession,
+    SessionExported(TaskId, Option<Result<(String, usize), String>>),
+    SessionMerged(TaskId, Option<Result<SessionMerge, String>>),
+    UserPressedApplyTimestamps,
+    UserPressedCancelTimestamps,
+    TimestampPlanReady(TaskId, Vec<TimestampChange>),
+    TimestampsApplied(TaskId, usize, Vec<String>),
+    UserEditedTimeShiftOffset(String),
+    UserEditedTimeShiftCamera(String),
+    UserEditedShareMaxEdge(String),
+    UserEditedShareQuality(String),
+    UserToggledShareStripMetadata(bool),
+    UserToggledShareRatingExif(bool),
+    UserToggledShareRatingSidecars(bool),
+    UserPressedShareExport(Tag),
+    SharedExported(TaskId, Option<(usize, Vec<String>)>),
+    UserPressedSend(Tag),
+    SendPrepared(
+        TaskId,
+        Result<(std::path::PathBuf, usize, Vec<String>), String>,
+    ),
+    UserToggledZipResized(bool),
+    UserPressedZip(Tag),
+    UserPressedPrint(Tag),
+    PagesRendered(TaskId, Result<(std::path::PathBuf, Vec<String>), String>),
+    // None when no file was picked
+    MontageExported(TaskId, Option<Result<std::path::PathBuf, String>>),
+    ZipProgressed(TaskId, ZipProgress),
+    UserChangedUploadRemote(Tag, String),
+    UserPressedUpload(Tag),
+    UploadProgressed(TaskId, UploadProgress),
+    UserPressedPlanTimeShift(Option<Tag>),
+    UserPressedApplyTimeShift,
+    UserPressedCancelTimeShift,
+    TimeShiftPlanReady(TaskId, Vec<TimeShift>),
+    TimeShiftApplied(TaskId, usize, Vec<String>),
+    VisualDiffComputed(TaskId, Box<VisualDiff>),
     ListDirCompleted(TaskId, Vec<String>),
-    ImagePreloaded(TaskId, String, ImageData, ImageData),
+    ListingSorted(TaskId, Vec<String>),
+    FileIdentitiesRead(TaskId, Vec<(String, FileIdentity)>),
+    SidecarsRead(TaskId, Vec<(String, SidecarMetadata)>),
+    XmpSidecarsRead(TaskId, Vec<(String, XmpSidecar)>),
+    ImageHeadersRead(TaskId, Vec<ImageHeader>),
+    FolderStatsRead(TaskId, Option<FolderStats>),
+    UserPressedCompareFolders,
+    FoldersCompared(TaskId, Result<Box<FolderComparison>, String>),
+    UserPressedCopyMissing(CopyDirection),
+    MissingCopied(TaskId, (usize, Vec<String>)),
+    UserPressedCloseComparison,
+    CaptureTimesRead(TaskId, Box<CaptureHeatmap>),
+    DestinationRead(TaskId, Tag, DestinationContents),
+    MetadataIndexed(TaskId, Vec<(String, String, FileHistory)>),
+    MovePlanReady(TaskId, Vec<MovePlan>),
+    UserPickedCollisionChoice(usize, usize, CollisionChoice),
+    UserPressedConfirmMove,
+    UserPressedCancelMove,
+    UserPressedFinishSession,
+    UserPressedClearMoveQueue,
+    MoveCompleted(TaskId, MoveResult, Vec<String>),
+    CopyCompleted(TaskId, CopyResult, Vec<String>),
+    FileRenamed(TaskId, String, String, Result<(), String>),
+    ImagePreloaded(
+        TaskId,
+        String,
+        Result<(Option<ImageData>, ImageData), String>,
+        std::time::Duration,
+    ),
     KeyboardEventOccurred(iced::keyboard::Event),
     MousePressed,
+    MouseMoved,
+    WindowFocusChanged(bool),
+    WindowResized(iced::window::Id, f32),
+    WindowMoved(iced::window::Id),
+    ScaleFactorReported(f32),
+    UserToggledPauseBackground,
+    IdleTick,
+    SpinnerTick,
+    CheckpointTick,
+    UserPressedRestoreCheckpoint,
+    UserPressedDiscardCheckpoint,
+    WindowCloseRequested(iced::window::Id),
     Settings(SettingsMessage),
     Sorting(SortingMessage),
     PixelCanvas(PixelCanvasMessage),
 }
 
+impl Message {
+    // Ticks, pointer moves and background results that leave the tags, the
+    // position and the view settings alone. Anything else may change them.
+    fn may_change_session(&self) -> bool {
+        !matches!(
+            self,
+            Message::SpinnerTick
+                | Message::IdleTick
+                | Message::CheckpointTick
+                | Message::MouseMoved
+                | Message::MousePressed
+                | Message::WindowFocusChanged(_)
+                | Message::WindowResized(..)
+                | Message::WindowMoved(_)
+                | Message::ScaleFactorReported(_)
+                | Message::ImagePreloaded(..)
+                | Message::ZipProgressed(..)
+                | Message::UploadProgressed(..)
+                | Message::VisualDiffComputed(..)
+                | Message::FileIdentitiesRead(..)
+                | Message::ImageHeadersRead(..)
+                | Message::FolderStatsRead(..)
+                | Message::CaptureTimesRead(..)
+                | Message::DestinationRead(..)
+                | Message::PixelCanvas(_)
+                | Message::Sorting(
+                    SortingMessage::TagFlashTick
+                        | SortingMessage::UserHoveredThumbnail(_)
+                        | SortingMessage::UserScrolledImage(..)
+                        | SortingMessage::UserScrolledReference(..)
+                        | SortingMessage::ImagePreloaded(..)
+                        | SortingMessage::CanvasResized(_)
+                )
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum PreloadImage {
     Loading(String),
     Loaded(LoadedImageAndThumb),
+    // Far from the current image, only the thumbnail is decoded
+    ThumbnailLoaded(ImageData),
     NotLoading,
+    // Not retried until the image is reloaded
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FolderPurpose {
+    Open,
+    Queue,
+    TagDestination(Tag),
+    // The first folder is picked, then the second
+    CompareFirst,
+    CompareSecond(std::path::PathBuf),
 }
 
 #[derive(Debug)]
@@ -174,21 +828,76 @@ pub enum Effect {
     None,
     LsDir,
     PreloadImages(Vec<String>, Dim),
-    MoveThenLs(Tag),
+    PlanMoves(Vec<Tag>),
+    MoveThenLs(Vec<MovePlan>),
+    CopyThenLs(Tag),
+    RenameFile(String, String),
+    UndoMoves(Vec<MovedFile>),
+    Quarantine(Vec<String>),
+    ReadFileIdentities(Vec<String>),
+    ReadSidecars(Vec<String>),
+    ReadXmpSidecars(Vec<String>),
+    // Path, whether another image shares its stem, label and rating
+    WriteXmpSidecars(Vec<(String, bool, Option<String>, u8)>),
+    ReadImageHeaders(Vec<String>),
+    ReadFolderStats,
+    SortListing(Vec<String>, SortOrder),
+    CompareFolders(String, String),
+    CopyMissing(String, String, Vec<String>),
+    ReadCaptureTimes(Vec<String>),
+    ReadDestination(Tag, String),
+    IndexMetadata(Vec<String>),
+    RecordMoves(Vec<(String, String)>),
+    PlanTimestamps(Vec<String>),
+    ExportTags(Vec<TagAssignment>),
+    ImportTags(TagNames),
+    ExportSession(Vec<(String, Option<String>, Tag)>, TagNames),
+    MergeSession(Vec<(String, Option<String>)>),
+    ApplyTimestamps(Vec<TimestampChange>),
+    PlanTimeShift(Vec<String>, time::Duration, String),
+    ApplyTimeShift(Vec<TimeShift>),
+    ShareExport(Vec<(String, u8)>, ShareExportSettings),
+    PrepareSend(Vec<(String, u8)>, String, ShareExportSettings),
+    ZipTag(Vec<String>, String, Option<ShareExportSettings>),
+    UploadTag(Vec<String>, String),
+    // Paths with their quarter turns
+    Print(Vec<(String, u8)>),
+    ExportMontage([(String, u8); 2]),
+    ComputeVisualDiff(String, ImageData, String, ImageData),
     FocusElement(widget::text_input::Id),
+    CopyToClipboard(String),
+    // Row of the current image and the number of rows
+    ScrollGrid(usize, usize),
+    PickFolder(FolderPurpose),
+    Notify(String, String),
+    SaveCheckpoint(Box<Checkpoint>),
+    SaveSession(std::path::PathBuf, Box<Session>),
+    SaveLastFolder(std::path::PathBuf),
+    SaveConfig(Box<Config>),
+    CloseWindow(iced::window::Id),
+    QueryScaleFactor(iced::window::Id),
+    SetFullscreen(bool),
+    OpenExternally(String),
+    Batch(Vec<Effect>),
 }
 
 impl Model {
-    fn new() -> (Self, Effect) {
-        let config = Config {
-            preload_back_num: 10,
-            preload_front_num: 30,
-            scale_down_size: (800, 100),
-            thumbnail_size: Dim {
-                width: 100,
-                height: 100,
-            },
-            thumbnail_style: SortingViewStyle::ThumbsAbove,
+    fn new(startup: StartupOptions) -> (Self, Effect) {
+        let config = match startup.safe_mode {
+            true => Config::default(),
+            false => config_file::load().unwrap_or_default(),
+        };
+        throttle::set_limit(config.io_max_bytes_per_sec);
+        // Before anything is translated, such as the names of the tags
+        rust_i18n::set_locale(
+            startup
+                .locale
+                .as_deref()
+                .unwrap_or(config.language.locale()),
+        );
+        let read_folder_stats = match startup.safe_mode {
+            true => Effect::None,
+            false => Effect::ReadFolderStats,
         };
         (
             Self {
@@ -196,71 +905,193 @@ impl Model {
                 state: ModelState::LoadingListDir,
                 settings: SettingsModel::new(&config),
                 active_tab: TabId::Main,
+                modifiers: iced::keyboard::Modifiers::default(),
                 selected_action_tag: None,
                 task_manager: TaskManager::new(),
                 pathlist: PathList::new(vec![]),
                 editing_tag_name: None,
+                removing_tag: None,
+                renaming_file: None,
+                going_to: None,
+                rename_error: None,
                 tag_names: TagNames::new(),
                 canvas_dimensions: None,
+                window_focused: true,
+                message_log: LogSampler::default(),
+                effect_log: LogSampler::default(),
+                safe_mode: startup.safe_mode,
+                background_paused: false,
+                last_navigation: std::time::Instant::now(),
+                session_dirty: false,
+                last_checkpoint: None,
+                pending_recovery: None,
+                timestamp_plan: None,
+                move_plans: Vec::new(),
+                queued_moves: Vec::new(),
+                time_shift_form: TimeShiftForm::default(),
+                share_export_form: ShareExportForm::default(),
+                zip_resized: false,
+                time_shift_plan: None,
+                action_status: None,
+                visual_diff: None,
+                pending_tag_import: startup.imported_tags,
+                spinner_phase: 0.0,
+                held_step: None,
+                slideshow: false,
+                tag_flash: None,
+                compare: None,
+                controls_hidden: false,
+                fullscreen: false,
+                last_mouse_move: std::time::Instant::now(),
+                show_info_panel: false,
+                show_histogram: false,
+                show_destination_preview: false,
+                destination_preview: None,
+                decode_histogram: DecodeHistogram::default(),
+                folder_queue: startup.folder_queue.into(),
+                last_folder: match startup.safe_mode {
+                    true => None,
+                    false => session::last_folder(),
+                },
+                last_move: None,
+                review: None,
+                merge_review: None,
+                end_summary: false,
+                folder_stats: None,
+                folder_comparison: None,
+                destructive_key_pressed: None,
+                window_width: iced::window::Settings::default().size.width,
+                scale_factor: 1.0,
+                capture_heatmap: None,
+                metadata_db: startup.metadata_db,
+                archive_root: startup.archive_root,
+                saved_session: Session::default(),
+                shuffle_seed: rand::random(),
             },
-            Effect::LsDir,
+            Effect::Batch(vec![Effect::LsDir, read_folder_stats]),
         )
     }
 
-    fn new_with_task() -> (Self, Task<Message>) {
-        let (mut new_self, effect) = Self::new();
+    fn new_with_task(startup: StartupOptions) -> (Self, Task<Message>) {
+        let (mut new_self, effect) = Self::new(startup);
         let task = effect_to_task(effect, &mut new_self);
         (new_self, task)
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        event::listen_with(Self::subscription_filter)
+        let spinner = if self.is_current_image_loading() {
+            iced::time::every(SPINNER_INTERVAL).map(|_| Message::SpinnerTick)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([
+            event::listen_with(Self::subscription_filter),
+            iced::time::every(IDLE_PRELOAD_DELAY).map(|_| Message::IdleTick),
+            iced::time::every(CHECKPOINT_INTERVAL).map(|_| Message::CheckpointTick),
+            spinner,
+            sorting::held_step_subscription(self.held_step.is_some()),
+            sorting::slideshow_subscription(self.slideshow, self.config.slideshow_seconds),
+            sorting::tag_flash_subscription(self.tag_flash.is_some()),
+        ])
+    }
+
+    // Previews are decoded in physical pixels so that they stay sharp on
+    // monitors with a scale factor above one
+    pub fn decode_size(&self, canvas: Dim) -> Dim {
+        let size = self.config.preview_quality.decode_size(canvas);
+        Dim {
+            width: (size.width as f32 * self.scale_factor).round() as u32,
+            height: (size.height as f32 * self.scale_factor).round() as u32,
+        }
+    }
+
+    pub fn preview_memory_budget(&self) -> usize {
+        self.config.preview_memory_mb * 1024 * 1024
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f32) -> Effect {
+        if scale_factor == self.scale_factor {
+            return Effect::None;
+        }
+        debug!("Scale factor changed to {scale_factor}");
+        self.scale_factor = scale_factor;
+        let Some(dim) = self.canvas_dimensions else {
+            return Effect::None;
+        };
+        if !matches!(self.state, ModelState::Sorting) {
+            return Effect::None;
+        }
+        let unloaded = self
+            .pathlist
+            .unload_mismatched(self.decode_size(dim), PREVIEW_SIZE_TOLERANCE);
+        debug!("Unloaded {unloaded} previews after scale factor change");
+        let paths = self.pathlist.resume_preload(&self.config);
+        if paths.is_empty() {
+            Effect::None
+        } else {
+            Effect::PreloadImages(paths, dim)
+        }
+    }
+
+    pub fn is_compact(&self) -> bool {
+        self.window_width < COMPACT_LAYOUT_WIDTH
+    }
+
+    fn is_current_image_loading(&self) -> bool {
+        matches!(self.state, ModelState::Sorting)
+            && self
+                .pathlist
+                .paths
+                .get(self.pathlist.index)
+                .is_some_and(|info| {
+                    matches!(
+                        info.data,
+                        PreloadImage::Loading(_)
+                            | PreloadImage::NotLoading
+                            | PreloadImage::ThumbnailLoaded(_)
+                    )
+                })
     }
 
     fn subscription_filter(
         event: Event,
-        _status: event::Status,
-        _id: iced::window::Id,
+        status: event::Status,
+        id: iced::window::Id,
     ) -> Option<Message> {
         match event {
+            // Typed into a focused text field
+            Event::Keyboard(keyboard_event @ iced::keyboard::Event::KeyPressed { .. })
+                if status == event::Status::Captured && !passes_text_input(&keyboard_event) =>
+            {
+                None
+            }
             Event::Keyboard(keyboard_event) => Some(Message::KeyboardEventOccurred(keyboard_event)),
             Event::Mouse(iced::mouse::Event::ButtonPressed(_)) => Some(Message::MousePressed),
+            Event::Mouse(iced::mouse::Event::CursorMoved { .. }) => Some(Message::MouseMoved),
+            Event::Window(iced::window::Event::Focused) => Some(Message::WindowFocusChanged(true)),
+            Event::Window(iced::window::Event::Unfocused) => {
+                Some(Message::WindowFocusChanged(false))
+            }
+            Event::Window(iced::window::Event::Resized(size)) => {
+                Some(Message::WindowResized(id, size.width))
+            }
+            Event::Window(iced::window::Event::Moved(_)) => Some(Message::WindowMoved(id)),
+            Event::Window(iced::window::Event::CloseRequested) => {
+                Some(Message::WindowCloseRequested(id))
+            }
             _ => None,
         }
     }
 
     fn go_to_sorting_model(&mut self, paths: Vec<String>) -> Effect {
+        let first_listing = !matches!(self.state, ModelState::Sorting);
+        // The folder's own order is only known once its session is loaded
+        let listed_order = self.config.sort_order.while_listing();
         match self.state {
             ModelState::Sorting => {
                 debug!("In sorting model, received new lsdir, updating");
 
-                // Pathlist
-                let index: usize = {
-                    if let Some(previous_image) = self
-                        .pathlist
-                        .paths
-                        .get(self.pathlist.index)
-                        .map(|info| &info.path)
-                    {
-                        paths.iter().position(|p| p == previous_image).unwrap_or(0)
-                    } else {
-                        0
-                    }
-                };
-
-                // TODO, use previous image data here instead of clearing
-                let paths = paths
-                    .iter()
-                    .map(|path| ImageInfo {
-                        path: path.clone(),
-                        data: PreloadImage::NotLoading,
-                        metadata: Metadata {
-                            tag: self.pathlist.tag_of(path),
-                        },
-                    })
-                    .collect();
-
-                self.pathlist = PathList { index, paths };
+                self.pathlist.merge_listing(paths.clone());
             }
 
             _ => {
@@ -269,102 +1100,1600 @@ impl Model {
                 self.state = ModelState::Sorting;
                 self.pathlist = PathList::new(paths.clone());
                 self.editing_tag_name = None;
-                self.tag_names = TagNames::new();
+                self.removing_tag = None;
+                self.renaming_file = None;
+                self.going_to = None;
+                self.slideshow = false;
+                self.rename_error = None;
+                self.compare = None;
                 self.canvas_dimensions = None;
+                self.last_checkpoint = None;
+                if !self.safe_mode {
+                    self.pending_recovery =
+                        checkpoint::load_for_directory(&checkpoint::current_directory());
+                    self.saved_session = std::env::current_dir()
+                        .map(|directory| session::load(&directory))
+                        .unwrap_or_default();
+                    self.last_folder = std::env::current_dir().ok();
+                }
+                self.tag_names = match (&self.saved_session.tag_names, std::env::current_dir()) {
+                    (Some(tag_names), _) => tag_names.clone(),
+                    (None, Ok(directory)) if !self.safe_mode => tag_defaults::suggest(
+                        &directory,
+                        &self.tag_names,
+                        &self.config.default_tag_names,
+                        self.config.recursive_scan,
+                    ),
+                    (None, _) => self.tag_names.clone(),
+                };
+                let session_tags: Vec<(String, Tag)> = self
+                    .saved_session
+                    .tags
+                    .iter()
+                    .map(|(file, tag)| (file.clone(), *tag))
+                    .collect();
+                self.apply_tags(&session_tags);
+                self.pathlist.apply_ratings(&self.saved_session.ratings);
+                let imported_tags = std::mem::take(&mut self.pending_tag_import);
+                self.apply_tags(&imported_tags);
+                self.restore_session_context();
+                if let Some(recovery) = &self.pending_recovery {
+                    self.pathlist.apply_dimensions(&recovery.images);
+                }
             }
         };
-        let preload_images = self.pathlist.get_initial_preload_images(&self.config);
 
-        if let Some(dimensions) = self.canvas_dimensions {
-            Effect::PreloadImages(preload_images, dimensions)
+        // A session that is likely to be resumed is preloaded from where it
+        // left off, so the saved image is ready when the user restores it. The
+        // first image is preloaded if the recovery is discarded instead.
+        let resume_index = self
+            .pending_recovery
+            .as_ref()
+            .filter(|_| first_listing)
+            .and_then(|recovery| recovery.current.as_deref())
+            .and_then(|current| {
+                self.pathlist
+                    .paths
+                    .iter()
+                    .position(|info| info.path == current)
+            });
+        let preload_images = match resume_index {
+            Some(index) => self
+                .pathlist
+                .get_initial_preload_images_around(index, &self.config),
+            None => self.pathlist.get_initial_preload_images(&self.config),
+        };
+
+        let preload = match self.canvas_dimensions {
+            Some(dimensions) => Effect::PreloadImages(preload_images, dimensions),
+            None => Effect::None,
+        };
+        // Safe mode only lists and shows the images, reading every file in the
+        // background is what could have crashed on the folder
+        if self.safe_mode {
+            return preload;
+        }
+        let capture_times = if self.show_info_panel && self.capture_heatmap.is_none() {
+            Effect::ReadCaptureTimes(paths.clone())
+        } else {
+            Effect::None
+        };
+        let xmp_sidecars = if self.config.xmp_sidecars {
+            Effect::ReadXmpSidecars(paths.clone())
+        } else {
+            Effect::None
+        };
+        // The archive is walked with it, once when the folder is opened
+        let index_metadata = if self.metadata_db.is_some() && first_listing {
+            Effect::IndexMetadata(paths.clone())
         } else {
             Effect::None
+        };
+        let sort = match self.config.sort_order {
+            order if order != listed_order => Effect::SortListing(paths.clone(), order),
+            _ => Effect::None,
+        };
+        let last_folder = match &self.last_folder {
+            Some(folder) if first_listing => Effect::SaveLastFolder(folder.clone()),
+            _ => Effect::None,
+        };
+        Effect::Batch(vec![
+            preload,
+            Effect::ReadFileIdentities(paths.clone()),
+            Effect::ReadSidecars(paths.clone()),
+            xmp_sidecars,
+            Effect::ReadImageHeaders(paths),
+            capture_times,
+            index_metadata,
+            sort,
+            last_folder,
+        ])
+    }
+
+    // Still on the first image, the list starts from the new first one. Any
+    // other image stays current.
+    fn apply_sorted_listing(&mut self, paths: &[String]) -> Effect {
+        let on_first = self.pathlist.visible().first() == Some(&self.pathlist.index);
+        self.pathlist.reorder(paths);
+        let index = match on_first {
+            true => self.pathlist.visible().first().copied(),
+            false => Some(self.pathlist.index),
+        };
+        let preload = index
+            .map(|index| self.pathlist.jump_to(index, &self.config))
+            .unwrap_or_default();
+        match self.canvas_dimensions {
+            Some(dim) if !preload.is_empty() => Effect::PreloadImages(preload, dim),
+            _ => Effect::None,
+        }
+    }
+
+    // Tag names are kept, so a queue of folders can be sorted with the same tags
+    fn open_folder(&mut self, path: std::path::PathBuf) -> Effect {
+        if let Err(e) = std::env::set_current_dir(&path) {
+            warn!("Could not open folder {}: {e}", path.display());
+            self.action_status = Some(e.to_string());
+            return Effect::None;
+        }
+        debug!("Opened folder {}", path.display());
+        self.task_manager.cancel_all();
+        self.state = ModelState::LoadingListDir;
+        self.pathlist = PathList::new(vec![]);
+        self.visual_diff = None;
+        self.timestamp_plan = None;
+        self.selected_action_tag = None;
+        self.last_move = None;
+        self.queued_moves.clear();
+        self.review = None;
+        self.merge_review = None;
+        self.end_summary = false;
+        self.folder_stats = None;
+        self.capture_heatmap = None;
+        self.destination_preview = None;
+        match self.safe_mode {
+            true => Effect::LsDir,
+            false => Effect::Batch(vec![Effect::LsDir, Effect::ReadFolderStats]),
+        }
+    }
+
+    // Deferred moves wait for the session to be finished
+    fn move_tag(&mut self, tag: Tag) -> Effect {
+        if !self.config.defer_moves {
+            return Effect::PlanMoves(vec![tag]);
+        }
+        if !self.queued_moves.contains(&tag) {
+            self.queued_moves.push(tag);
+        }
+        Effect::None
+    }
+
+    fn start_review(&mut self) -> Effect {
+        let Some(last_move) = self.last_move.take() else {
+            return Effect::None;
+        };
+        let (Some(destination), Ok(return_dir)) =
+            (last_move.destination_path, std::env::current_dir())
+        else {
+            return Effect::None;
+        };
+        let effect = self.open_folder(destination);
+        if effect != Effect::None {
+            self.review = Some(ReviewSession {
+                return_dir,
+                moves: last_move.moved_files,
+            });
+        }
+        effect
+    }
+
+    fn view_move_banner(&self) -> Option<Element<'_, Message>> {
+        if let Some(merge_review) = &self.merge_review {
+            return merge_review::view_banner(merge_review, &self.tag_names);
+        }
+        if let Some(review) = &self.review {
+            return Some(
+                widget::row![
+                    widget::text(t!(
+                        "Reviewing moved files, %{count} can be pulled back",
+                        count = format::format_count(review.moves.len())
+                    )),
+                    widget::button(widget::text(t!("Pull back"))).on_press_maybe(
+                        matches!(self.state, ModelState::Sorting)
+                            .then_some(Message::UserPressedPullBack)
+                    ),
+                    widget::button(widget::text(t!("Return")))
+                        .on_press(Message::UserPressedEndReview),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center)
+                .into(),
+            );
+        }
+        let last_move = self.last_move.as_ref()?;
+        Some(
+            widget::row![
+                widget::text(t!(
+                    "Moved %{count} files to %{destination}",
+                    count = format::format_count(last_move.moved_files.len()),
+                    destination = last_move.destination
+                )),
+                widget::button(widget::text(t!("Review"))).on_press(Message::UserPressedReviewMove),
+                widget::button(widget::text(t!("Undo"))).on_press(Message::UserPressedUndoMove),
+                widget::button(widget::text(t!("Dismiss")))
+                    .on_press(Message::UserPressedDismissMove),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .into(),
+        )
+    }
+
+    fn open_next_queued_folder(&mut self) -> Effect {
+        match self.folder_queue.pop_front() {
+            Some(path) => self.open_folder(path),
+            None => Effect::None,
+        }
+    }
+
+    fn notify_unfocused(&self, result: &MoveResult) -> Effect {
+        if self.window_focused {
+            return Effect::None;
+        }
+
+        match &result.error {
+            None => Effect::Notify(
+                t!("Move finished").to_string(),
+                t!(
+                    "Moved %{count} files to %{destination}",
+                    count = format::format_count(result.moved_files.len()),
+                    destination = result.destination
+                )
+                .to_string(),
+            ),
+            Some(error) => Effect::Notify(
+                t!("Move failed").to_string(),
+                t!(
+                    "Moved %{count} files to %{destination} before failing: %{error}",
+                    count = format::format_count(result.moved_files.len()),
+                    destination = result.destination,
+                    error = error
+                )
+                .to_string(),
+            ),
+        }
+    }
+
+    // Moves are remembered by content hash, for files that were indexed
+    fn record_moves(&self, result: &MoveResult) -> Effect {
+        if self.metadata_db.is_none() {
+            return Effect::None;
+        }
+        let moves: Vec<(String, String)> = result
+            .moved_files
+            .iter()
+            .filter_map(|moved| {
+                // Moves are recorded with absolute paths
+                let info = self.pathlist.paths.iter().find(|info| {
+                    std::path::absolute(&info.path).is_ok_and(|path| path == moved.from)
+                })?;
+                let hash = info.metadata.content_hash.clone()?;
+                Some((hash, result.destination.clone()))
+            })
+            .collect();
+        if moves.is_empty() {
+            Effect::None
+        } else {
+            Effect::RecordMoves(moves)
         }
     }
 
     fn title(&self) -> String {
-        "ImageViewer".to_owned()
+        match self.safe_mode {
+            true => format!("ImageViewer ({})", t!("safe mode")),
+            false => "ImageViewer".to_owned(),
+        }
     }
 
     fn update_with_task(&mut self, message: Message) -> Task<Message> {
+        self.session_dirty |= message.may_change_session();
         let effect = self.update(message);
+        let follow_ups = [
+            self.save_session_if_changed(),
+            self.refresh_destination_preview(),
+        ];
+        let effect = if follow_ups.iter().all(|effect| *effect == Effect::None) {
+            effect
+        } else {
+            Effect::Batch(std::iter::once(effect).chain(follow_ups).collect())
+        };
 
         effect_to_task(effect, self)
     }
 
     fn update(&mut self, message: Message) -> Effect {
-        debug!("Message: {message:?}");
+        self.message_log.log("Message", &message);
         let effect = match message {
-            Message::UserPressedActionCopy(tag) => Effect::MoveThenLs(tag),
+            Message::UserPressedActionMove(_) | Message::UserPressedActionCopy(_)
+                if self.review.is_some() =>
+            {
+                Effect::None
+            }
+            Message::UserPressedActionMove(tag) => self.move_tag(tag),
+            Message::UserPressedActionCopy(tag) => Effect::CopyThenLs(tag),
             Message::UserSelectedTab(tab) => {
                 self.active_tab = tab;
                 self.selected_action_tag = None;
                 Effect::None
             }
+            Message::UserPressedCopyLog => {
+                Effect::CopyToClipboard(log_panel::as_text(&log_panel::entries()))
+            }
+            Message::UserPressedClearLog => {
+                log_panel::clear();
+                Effect::None
+            }
             Message::UserPressedActionTag(tag) => {
                 self.selected_action_tag = Some(tag);
+                self.removing_tag = None;
                 Effect::None
             }
-            Message::UserPressedActionBack => {
-                self.selected_action_tag = None;
+            Message::UserPressedActionFixTimestamps(tag) => {
+                let paths = self
+                    .pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| tag.is_none() || info.metadata.tag == tag)
+                    .map(|info| info.path.clone())
+                    .collect();
+                self.action_status = Some(t!("Reading EXIF times...").to_string());
+                Effect::PlanTimestamps(paths)
+            }
+            Message::UserPressedExportTags => Effect::ExportTags(tag_io::assignments_from(
+                self.pathlist
+                    .paths
+                    .iter()
+                    .map(|info| (info.path.clone(), info.metadata.tag)),
+                &self.tag_names,
+            )),
+            Message::UserPressedImportTags => Effect::ImportTags(self.tag_names.clone()),
+            Message::TagsExported(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                self.action_status = match result {
+                    Some(Ok(path)) => Some(t!("Exported tags to %{path}", path = path).to_string()),
+                    Some(Err(e)) => Some(e),
+                    None => None,
+                };
                 Effect::None
             }
-            Message::MousePressed => {
-                self.editing_tag_name = None;
+            Message::TagsImported(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                self.action_status = match result {
+                    Some(Ok(tags)) => {
+                        let count = self.apply_tags(&tags);
+                        Some(
+                            t!(
+                                "Imported tags for %{count} files",
+                                count = format::format_count(count)
+                            )
+                            .to_string(),
+                        )
+                    }
+                    Some(Err(e)) => Some(e),
+                    None => None,
+                };
                 Effect::None
             }
-            Message::UserPressedSelectFolder => Effect::None,
-            Message::ListDirCompleted(task_id, paths) => {
-                if self.task_manager.report_completed_task(task_id)
-                    == TaskCompleteResult::TaskWasCancelled
-                {
-                    return Effect::None;
+            Message::UserPressedExportSession => Effect::ExportSession(
+                self.pathlist
+                    .paths
+                    .iter()
+                    .filter_map(|info| {
+                        let tag = info.metadata.tag?;
+                        Some((info.path.clone(), info.metadata.content_hash.clone(), tag))
+                    })
+                    .collect(),
+                self.tag_names.clone(),
+            ),
+            Message::UserPressedMergeSession => Effect::MergeSession(
+                self.pathlist
+                    .paths
+                    .iter()
+                    .map(|info| (info.path.clone(), info.metadata.content_hash.clone()))
+                    .collect(),
+            ),
+            Message::SessionExported(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                self.action_status = match result {
+                    Some(Ok((path, count))) => Some(
+                        t!(
+                            "Exported %{count} tags to %{path}",
+                            count = format::format_count(count),
+                            path = path
+                        )
+                        .to_string(),
+                    ),
+                    Some(Err(e)) => Some(e),
+                    None => None,
                 };
-                self.task_manager.cancel_all();
-                debug!("Directory listing completed for task {task_id:?}");
-                if paths.is_empty() {
-                    self.state = ModelState::EmptyDirectory;
-                    Effect::None
-                } else {
-                    self.go_to_sorting_model(paths)
-                }
+                Effect::None
             }
-            Message::ImagePreloaded(task_id, path, image, thumb) => {
+            Message::SessionMerged(task_id, result) => {
                 self.task_manager.report_completed_task(task_id);
-                debug!("Image preload completed for task {task_id:?}");
-                match self.state {
-                    ModelState::Sorting => {
-                        self.update_sorting(SortingMessage::ImagePreloaded(path, image, thumb))
+                let mut effect = Effect::None;
+                self.action_status = match result {
+                    Some(Ok(merge)) => {
+                        self.adopt_tag_names(&merge.tag_names);
+                        let (agreed, conflicts) =
+                            merge_review::find_conflicts(&self.pathlist.paths, merge.tags);
+                        let count = self.apply_tags(&agreed);
+                        let mut status = t!(
+                            "Merged tags for %{count} files, %{unmatched} were not found here",
+                            count = format::format_count(count),
+                            unmatched = format::format_count(merge.unmatched)
+                        )
+                        .to_string();
+                        if !conflicts.is_empty() {
+                            status = format!(
+                                "{status}. {}",
+                                t!(
+                                    "%{count} files were tagged differently, pick one tag for each in the main view",
+                                    count = format::format_count(conflicts.len())
+                                )
+                            );
+                        }
+                        self.merge_review = MergeReview::new(conflicts);
+                        effect = self.show_merge_conflict();
+                        Some(status)
                     }
-                    _ => Effect::None,
+                    Some(Err(e)) => Some(e),
+                    None => None,
+                };
+                effect
+            }
+            Message::TimestampPlanReady(task_id, plan) => {
+                self.task_manager.report_completed_task(task_id);
+                if plan.is_empty() {
+                    self.action_status = Some(t!("All file times already match EXIF").to_string());
+                } else {
+                    self.action_status = None;
+                    self.timestamp_plan = Some(plan);
                 }
+                Effect::None
             }
-            Message::KeyboardEventOccurred(event) => match self.state {
-                ModelState::Sorting => self.update_sorting(SortingMessage::KeyboardEvent(event)),
-                _ => Effect::None,
-            },
-            Message::Sorting(sorting_message) => match self.state {
-                ModelState::Sorting => self.update_sorting(sorting_message),
-                _ => Effect::None,
+            Message::UserPressedApplyTimestamps => match self.timestamp_plan.take() {
+                Some(plan) => Effect::ApplyTimestamps(plan),
+                None => Effect::None,
             },
-            Message::Settings(settings_message) => {
-                self.settings.update(settings_message, &mut self.config)
+            Message::UserPressedCancelTimestamps => {
+                self.timestamp_plan = None;
+                Effect::None
             }
-            Message::PixelCanvas(pixel_canvas_message) => match self.state {
-                ModelState::Sorting => match pixel_canvas_message {
-                    PixelCanvasMessage::CanvasSized(dim) => {
-                        self.update_sorting(SortingMessage::CanvasResized(dim))
-                    }
-                },
-                _ => Effect::None,
+            Message::TimestampsApplied(task_id, applied, errors) => {
+                self.task_manager.report_completed_task(task_id);
+                let mut status = t!(
+                    "Updated modification time of %{count} files",
+                    count = format::format_count(applied)
+                )
+                .to_string();
+                if !errors.is_empty() {
+                    status.push_str(&format!(" ({})", errors.join(", ")));
+                }
+                self.action_status = Some(status);
+                Effect::None
+            }
+            Message::UserEditedTimeShiftOffset(offset) => {
+                self.time_shift_form.offset = offset;
+                Effect::None
+            }
+            Message::UserEditedTimeShiftCamera(camera) => {
+                self.time_shift_form.camera = camera;
+                Effect::None
+            }
+            Message::UserEditedShareMaxEdge(max_edge) => {
+                self.share_export_form.max_edge = max_edge;
+                Effect::None
+            }
+            Message::UserEditedShareQuality(quality) => {
+                self.share_export_form.quality = quality;
+                Effect::None
+            }
+            Message::UserToggledShareStripMetadata(strip_metadata) => {
+                self.share_export_form.strip_metadata = strip_metadata;
+                Effect::None
+            }
+            Message::UserToggledShareRatingExif(rating_exif) => {
+                self.share_export_form.rating_exif = rating_exif;
+                Effect::None
+            }
+            Message::UserToggledShareRatingSidecars(rating_sidecars) => {
+                self.share_export_form.rating_sidecars = rating_sidecars;
+                Effect::None
+            }
+            Message::UserPressedShareExport(tag) => {
+                let Some(settings) = self.share_export_form.settings() else {
+                    return Effect::None;
+                };
+                let paths = self
+                    .pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| info.metadata.tag == Some(tag))
+                    .map(|info| (info.path.clone(), info.metadata.rating))
+                    .collect();
+                Effect::ShareExport(paths, settings)
+            }
+            Message::SharedExported(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                self.action_status = result.map(|(exported, errors)| {
+                    let mut status = t!(
+                        "Exported %{count} images for sharing",
+                        count = format::format_count(exported)
+                    )
+                    .to_string();
+                    if !errors.is_empty() {
+                        status.push_str(&format!(" ({})", errors.join(", ")));
+                    }
+                    status
+                });
+                Effect::None
+            }
+            Message::UserPressedSend(tag) => {
+                let Some(settings) = self.share_export_form.settings() else {
+                    return Effect::None;
+                };
+                let paths = self
+                    .pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| info.metadata.tag == Some(tag))
+                    .map(|info| (info.path.clone(), info.metadata.rating))
+                    .collect();
+                let name = self.tag_names.get(&tag).to_owned();
+                Effect::PrepareSend(paths, name, settings)
+            }
+            Message::SendPrepared(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                match result {
+                    Ok((folder, exported, errors)) => {
+                        let folder = folder.to_string_lossy().to_string();
+                        let mut status = t!(
+                            "%{count} images are ready to send in %{folder}",
+                            count = format::format_count(exported),
+                            folder = folder
+                        )
+                        .to_string();
+                        if !errors.is_empty() {
+                            status.push_str(&format!(" ({})", errors.join(", ")));
+                        }
+                        self.action_status = Some(status);
+                        Effect::OpenExternally(folder)
+                    }
+                    Err(e) => {
+                        self.action_status = Some(e);
+                        Effect::None
+                    }
+                }
+            }
+            Message::UserToggledZipResized(resized) => {
+                self.zip_resized = resized;
+                Effect::None
+            }
+            Message::UserPressedZip(tag) => {
+                let resize = match self.zip_resized {
+                    true => match self.share_export_form.settings() {
+                        Some(settings) => Some(settings),
+                        None => return Effect::None,
+                    },
+                    false => None,
+                };
+                let paths = self
+                    .pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| info.metadata.tag == Some(tag))
+                    .map(|info| info.path.clone())
+                    .collect();
+                let name = zip_export::archive_name(self.tag_names.get(&tag));
+                Effect::ZipTag(paths, name, resize)
+            }
+            Message::ZipProgressed(task_id, progress) => {
+                match progress {
+                    ZipProgress::Zipped(done, total) => {
+                        self.action_status = Some(
+                            t!(
+                                "Zipping %{done} of %{total}...",
+                                done = format::format_count(done),
+                                total = format::format_count(total)
+                            )
+                            .to_string(),
+                        );
+                    }
+                    ZipProgress::Finished(result) => {
+                        self.task_manager.report_completed_task(task_id);
+                        self.action_status = Some(match result {
+                            Ok(summary) => {
+                                let mut status = t!(
+                                    "Zipped %{count} files into %{archive}",
+                                    count = format::format_count(summary.zipped),
+                                    archive = summary.archive
+                                )
+                                .to_string();
+                                if !summary.errors.is_empty() {
+                                    status.push_str(&format!(" ({})", summary.errors.join(", ")));
+                                }
+                                status
+                            }
+                            Err(e) => {
+                                t!("Could not write the archive: %{error}", error = e).to_string()
+                            }
+                        });
+                    }
+                    ZipProgress::Cancelled => {
+                        self.task_manager.report_completed_task(task_id);
+                    }
+                }
+                Effect::None
+            }
+            Message::UserPressedPrint(tag) => Effect::Print(
+                self.pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| info.metadata.tag == Some(tag))
+                    .map(|info| (info.path.clone(), info.metadata.view.quarter_turns))
+                    .collect(),
+            ),
+            Message::MontageExported(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                match result {
+                    Some(Ok(file)) => Effect::OpenExternally(file.to_string_lossy().to_string()),
+                    Some(Err(e)) => {
+                        warn!("Could not export the comparison: {e}");
+                        self.action_status = Some(e);
+                        Effect::None
+                    }
+                    None => Effect::None,
+                }
+            }
+            Message::PagesRendered(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                match result {
+                    Ok((pages, errors)) => {
+                        if !errors.is_empty() {
+                            self.action_status = Some(errors.join(", "));
+                        }
+                        Effect::OpenExternally(pages.to_string_lossy().to_string())
+                    }
+                    Err(e) => {
+                        warn!("Could not make the pages to print: {e}");
+                        self.action_status = Some(e);
+                        Effect::None
+                    }
+                }
+            }
+            Message::UserChangedUploadRemote(tag, remote) => {
+                self.tag_names.set_upload_remote(tag, remote);
+                Effect::None
+            }
+            Message::UserPressedUpload(tag) => {
+                let Some(remote) = self.tag_names.upload_remote(&tag) else {
+                    return Effect::None;
+                };
+                let paths: Vec<String> = self
+                    .pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| info.metadata.tag == Some(tag))
+                    .map(|info| info.path.clone())
+                    .collect();
+                if paths.is_empty() {
+                    return Effect::None;
+                }
+                Effect::UploadTag(paths, remote.to_owned())
+            }
+            Message::UploadProgressed(task_id, progress) => {
+                self.action_status = Some(match progress {
+                    UploadProgress::Uploaded(done, total) => t!(
+                        "Uploading %{done} of %{total}...",
+                        done = format::format_count(done),
+                        total = format::format_count(total)
+                    )
+                    .to_string(),
+                    UploadProgress::Finished(summary) => {
+                        self.task_manager.report_completed_task(task_id);
+                        let mut status = t!(
+                            "Uploaded %{count} files to %{remote}",
+                            count = format::format_count(summary.uploaded),
+                            remote = summary.remote
+                        )
+                        .to_string();
+                        if !summary.errors.is_empty() {
+                            status.push_str(&format!(" ({})", summary.errors.join(", ")));
+                        }
+                        status
+                    }
+                });
+                Effect::None
+            }
+            Message::UserPressedPlanTimeShift(tag) => {
+                let Some(offset) = time_shift::parse_offset(&self.time_shift_form.offset) else {
+                    return Effect::None;
+                };
+                let paths = self
+                    .pathlist
+                    .paths
+                    .iter()
+                    .filter(|info| tag.is_none() || info.metadata.tag == tag)
+                    .map(|info| info.path.clone())
+                    .collect();
+                self.action_status = Some(t!("Reading EXIF times...").to_string());
+                Effect::PlanTimeShift(paths, offset, self.time_shift_form.camera.clone())
+            }
+            Message::TimeShiftPlanReady(task_id, plan) => {
+                self.task_manager.report_completed_task(task_id);
+                if plan.is_empty() {
+                    self.action_status = Some(t!("No files with an EXIF capture time").to_string());
+                } else {
+                    self.action_status = None;
+                    self.time_shift_plan = Some(plan);
+                }
+                Effect::None
+            }
+            Message::UserPressedApplyTimeShift => match self.time_shift_plan.take() {
+                Some(plan) => Effect::ApplyTimeShift(plan),
+                None => Effect::None,
+            },
+            Message::UserPressedCancelTimeShift => {
+                self.time_shift_plan = None;
+                Effect::None
+            }
+            Message::TimeShiftApplied(task_id, applied, errors) => {
+                self.task_manager.report_completed_task(task_id);
+                let mut status = t!(
+                    "Shifted the capture time of %{count} files",
+                    count = format::format_count(applied)
+                )
+                .to_string();
+                if !errors.is_empty() {
+                    status.push_str(&format!(" ({})", errors.join(", ")));
+                }
+                self.action_status = Some(status);
+                // The capture times changed on disk
+                self.capture_heatmap = None;
+                if self.show_info_panel {
+                    let paths = self.pathlist.paths.iter().map(|info| info.path.clone());
+                    Effect::ReadCaptureTimes(paths.collect())
+                } else {
+                    Effect::None
+                }
+            }
+            Message::VisualDiffComputed(task_id, diff) => {
+                self.task_manager.report_completed_task(task_id);
+                self.visual_diff = Some(*diff);
+                Effect::None
+            }
+            Message::UserPressedActionBack => {
+                self.selected_action_tag = None;
+                self.removing_tag = None;
+                Effect::None
+            }
+            Message::MousePressed => {
+                self.editing_tag_name = None;
+                Effect::None
+            }
+            Message::MouseMoved => {
+                self.last_mouse_move = std::time::Instant::now();
+                self.controls_hidden = false;
+                Effect::None
+            }
+            // There is no event for scale factor changes, but moving to another
+            // monitor moves, and usually resizes, the window
+            Message::WindowResized(id, width) => {
+                self.window_width = width;
+                Effect::QueryScaleFactor(id)
+            }
+            Message::WindowMoved(id) => Effect::QueryScaleFactor(id),
+            Message::ScaleFactorReported(scale_factor) => self.set_scale_factor(scale_factor),
+            Message::WindowFocusChanged(focused) => {
+                self.window_focused = focused;
+                Effect::None
+            }
+            Message::UserToggledPauseBackground => {
+                self.set_background_paused(!self.background_paused)
+            }
+            Message::IdleTick => self.idle_preload(),
+            Message::SpinnerTick => {
+                self.spinner_phase = (self.spinner_phase + 0.3) % std::f32::consts::TAU;
+                Effect::None
+            }
+            Message::CheckpointTick => self.checkpoint(),
+            Message::UserPressedRestoreCheckpoint => self.restore_checkpoint(),
+            Message::UserPressedDiscardCheckpoint => self.discard_checkpoint(),
+            Message::WindowCloseRequested(id) => {
+                checkpoint::remove();
+                // The image stopped at, if it was too recent to be saved yet
+                if let (Some(session), Ok(directory)) =
+                    (self.changed_session(true), std::env::current_dir())
+                {
+                    if let Err(e) =
+                        session::save_in_order(&directory, &session, session::next_write())
+                    {
+                        warn!("Could not write session file: {e}");
+                    }
+                }
+                Effect::CloseWindow(id)
+            }
+            Message::UserPressedSelectFolder => Effect::PickFolder(FolderPurpose::Open),
+            Message::UserPressedQueueFolder => Effect::PickFolder(FolderPurpose::Queue),
+            Message::UserPressedNextFolder => self.open_next_queued_folder(),
+            Message::UserPressedResumeLastSession => match self.last_folder.clone() {
+                Some(folder) => self.open_folder(folder),
+                None => Effect::None,
+            },
+            Message::UserPressedReviewMove => self.start_review(),
+            Message::UserPressedUndoMove => match self.last_move.take() {
+                Some(last_move) => Effect::UndoMoves(last_move.moved_files),
+                None => Effect::None,
+            },
+            Message::UserPressedDismissMove => {
+                self.last_move = None;
+                Effect::None
+            }
+            Message::UserPressedPullBack => {
+                let Some(review) = &self.review else {
+                    return Effect::None;
+                };
+                let current = self
+                    .pathlist
+                    .paths
+                    .get(self.pathlist.index)
+                    .and_then(|info| std::path::absolute(&info.path).ok());
+                match review
+                    .moves
+                    .iter()
+                    .find(|moved| Some(&moved.to) == current.as_ref())
+                {
+                    Some(moved) => Effect::UndoMoves(vec![moved.clone()]),
+                    None => Effect::None,
+                }
+            }
+            Message::MovesUndone(task_id, undone, result, paths) => {
+                if let Some(review) = self.review.as_mut() {
+                    review.moves.retain(|moved| !undone.contains(moved));
+                }
+                self.action_status = result.err();
+                self.update(Message::ListDirCompleted(task_id, paths))
+            }
+            Message::UserPressedEndReview => match self.review.take() {
+                Some(review) => self.open_folder(review.return_dir),
+                None => Effect::None,
+            },
+            Message::FolderPicked(_, None) => Effect::None,
+            Message::FolderPicked(FolderPurpose::Open, Some(path)) => self.open_folder(path),
+            Message::FolderPicked(FolderPurpose::Queue, Some(path)) => {
+                self.folder_queue.push_back(path);
+                Effect::None
+            }
+            Message::FolderPicked(FolderPurpose::CompareFirst, Some(path)) => {
+                Effect::PickFolder(FolderPurpose::CompareSecond(path))
+            }
+            Message::FolderPicked(FolderPurpose::CompareSecond(first), Some(second)) => {
+                Effect::CompareFolders(
+                    first.to_string_lossy().to_string(),
+                    second.to_string_lossy().to_string(),
+                )
+            }
+            Message::UserPressedCompareFolders => Effect::PickFolder(FolderPurpose::CompareFirst),
+            Message::FoldersCompared(task_id, result) => {
+                self.task_manager.report_completed_task(task_id);
+                match result {
+                    Ok(comparison) => self.folder_comparison = Some(*comparison),
+                    Err(e) => self.action_status = Some(e),
+                }
+                Effect::None
+            }
+            Message::UserPressedCopyMissing(direction) => match &self.folder_comparison {
+                Some(comparison) => {
+                    let (from, to, names) = comparison.missing(direction);
+                    Effect::CopyMissing(from, to, names)
+                }
+                None => Effect::None,
+            },
+            Message::MissingCopied(task_id, (copied, errors)) => {
+                self.task_manager.report_completed_task(task_id);
+                let mut status = t!(
+                    "Copied %{count} files",
+                    count = format::format_count(copied)
+                )
+                .to_string();
+                if !errors.is_empty() {
+                    status.push_str(&format!(" ({})", errors.join(", ")));
+                }
+                self.action_status = Some(status);
+                // Compared again to show what is left
+                match &self.folder_comparison {
+                    Some(comparison) => {
+                        Effect::CompareFolders(comparison.a.clone(), comparison.b.clone())
+                    }
+                    None => Effect::None,
+                }
+            }
+            Message::UserPressedCloseComparison => {
+                self.folder_comparison = None;
+                Effect::None
+            }
+            Message::FolderPicked(FolderPurpose::TagDestination(tag), Some(path)) => {
+                self.tag_names
+                    .set_destination(tag, path.to_string_lossy().to_string());
+                self.destination_preview = None;
+                Effect::None
+            }
+            Message::UserChangedTagDestination(tag, destination) => {
+                self.tag_names.set_destination(tag, destination);
+                self.destination_preview = None;
+                Effect::None
+            }
+            Message::UserPressedPickTagDestination(tag) => {
+                Effect::PickFolder(FolderPurpose::TagDestination(tag))
+            }
+            Message::ListDirCompleted(task_id, paths) => {
+                if self.task_manager.report_completed_task(task_id)
+                    == TaskCompleteResult::TaskWasCancelled
+                {
+                    return Effect::None;
+                };
+                self.task_manager.cancel_listing_tasks();
+                debug!("Directory listing completed for task {task_id:?}");
+                if paths.is_empty() {
+                    self.state = ModelState::EmptyDirectory;
+                    // The listing cancelled the statistics if they were still being read
+                    match self.folder_stats {
+                        None if !self.safe_mode => Effect::ReadFolderStats,
+                        _ => Effect::None,
+                    }
+                } else {
+                    self.go_to_sorting_model(paths)
+                }
+            }
+            Message::ListingSorted(task_id, paths) => {
+                if self.task_manager.report_completed_task(task_id)
+                    == TaskCompleteResult::TaskWasCancelled
+                {
+                    return Effect::None;
+                };
+                self.apply_sorted_listing(&paths)
+            }
+            Message::DestinationRead(task_id, tag, contents) => {
+                self.task_manager.report_completed_task(task_id);
+                if let Some(preview) = &mut self.destination_preview {
+                    if preview.tag == tag {
+                        preview.contents = Some(contents);
+                    }
+                }
+                Effect::None
+            }
+            Message::CaptureTimesRead(task_id, heatmap) => {
+                self.task_manager.report_completed_task(task_id);
+                self.capture_heatmap = Some(*heatmap);
+                Effect::None
+            }
+            Message::FolderStatsRead(task_id, stats) => {
+                self.task_manager.report_completed_task(task_id);
+                self.folder_stats = stats;
+                Effect::None
+            }
+            Message::FileIdentitiesRead(task_id, identities) => {
+                self.task_manager.report_completed_task(task_id);
+                self.pathlist.apply_file_identities(&identities);
+                Effect::None
+            }
+            Message::SidecarsRead(task_id, sidecars) => {
+                self.task_manager.report_completed_task(task_id);
+                self.pathlist.apply_sidecars(sidecars);
+                Effect::None
+            }
+            Message::XmpSidecarsRead(task_id, sidecars) => {
+                self.task_manager.report_completed_task(task_id);
+                self.apply_xmp_sidecars(sidecars);
+                Effect::None
+            }
+            Message::ImageHeadersRead(task_id, headers) => {
+                self.task_manager.report_completed_task(task_id);
+                self.pathlist.apply_image_headers(headers);
+                Effect::None
+            }
+            Message::MovePlanReady(task_id, plans) => {
+                self.task_manager.report_completed_task(task_id);
+                self.move_plans = plans;
+                Effect::None
+            }
+            Message::UserPickedCollisionChoice(plan_index, index, choice) => {
+                if let Some(plan) = self.move_plans.get_mut(plan_index) {
+                    plan.set_choice(index, choice);
+                }
+                Effect::None
+            }
+            Message::UserPressedConfirmMove if self.move_plans.is_empty() => Effect::None,
+            Message::UserPressedConfirmMove => {
+                // Queued tags moved now leave the queue, as do those no image
+                // has any more. A single tag moved by hand leaves the rest.
+                let moved: std::collections::HashSet<Tag> = self
+                    .move_plans
+                    .iter()
+                    .flat_map(|plan| plan.tags.iter().copied())
+                    .collect();
+                let paths = &self.pathlist.paths;
+                self.queued_moves.retain(|tag| {
+                    !moved.contains(tag) && paths.iter().any(|info| info.metadata.tag == Some(*tag))
+                });
+                Effect::MoveThenLs(std::mem::take(&mut self.move_plans))
+            }
+            Message::UserPressedCancelMove => {
+                self.move_plans.clear();
+                Effect::None
+            }
+            Message::UserPressedFinishSession if self.review.is_some() => Effect::None,
+            Message::UserPressedFinishSession => Effect::PlanMoves(self.queued_moves.clone()),
+            Message::UserPressedClearMoveQueue => {
+                self.queued_moves.clear();
+                Effect::None
+            }
+            Message::MoveCompleted(task_id, result, paths) => {
+                let notify = self.notify_unfocused(&result);
+                let record_moves = self.record_moves(&result);
+                // The destination has new files
+                self.destination_preview = None;
+                self.last_move = (!result.moved_files.is_empty()).then(|| result.clone());
+                // Everything sorted out of this folder, go on with the queue
+                let effect = if paths.is_empty() && !self.folder_queue.is_empty() {
+                    self.task_manager.report_completed_task(task_id);
+                    self.open_next_queued_folder()
+                } else {
+                    self.update(Message::ListDirCompleted(task_id, paths))
+                };
+                Effect::Batch(vec![notify, record_moves, effect])
+            }
+            Message::CopyCompleted(task_id, result, paths) => {
+                // The destination has new files
+                self.destination_preview = None;
+                self.action_status = Some(match result.error {
+                    Some(e) => t!(
+                        "Copied %{count} files to %{destination}, then stopped: %{error}",
+                        count = format::format_count(result.copied),
+                        destination = result.destination,
+                        error = e
+                    )
+                    .to_string(),
+                    None => t!(
+                        "Copied %{count} files to %{destination}",
+                        count = format::format_count(result.copied),
+                        destination = result.destination
+                    )
+                    .to_string(),
+                });
+                self.update(Message::ListDirCompleted(task_id, paths))
+            }
+            Message::MetadataIndexed(task_id, indexed) => {
+                self.task_manager.report_completed_task(task_id);
+                self.pathlist
+                    .apply_file_histories(indexed, self.archive_root.as_deref());
+                Effect::None
+            }
+            Message::FileRenamed(task_id, old_path, new_path, result) => {
+                self.task_manager.report_completed_task(task_id);
+                match result {
+                    Ok(()) => {
+                        let preload = self.pathlist.rename(&old_path, &new_path);
+                        self.rename_error = None;
+                        match self.canvas_dimensions {
+                            Some(dim) if !preload.is_empty() => Effect::PreloadImages(preload, dim),
+                            _ => Effect::None,
+                        }
+                    }
+                    Err(e) => {
+                        self.rename_error = Some(e);
+                        Effect::None
+                    }
+                }
+            }
+            Message::ImagePreloaded(task_id, path, result, decode_time) => {
+                self.task_manager.report_completed_task(task_id);
+                debug!("Image preload completed for task {task_id:?}");
+                match self.state {
+                    ModelState::Sorting => {
+                        if result.is_ok() {
+                            self.decode_histogram.record(decode_time);
+                        }
+                        self.update_sorting(SortingMessage::ImagePreloaded(
+                            path,
+                            result,
+                            decode_time,
+                        ))
+                    }
+                    _ => Effect::None,
+                }
+            }
+            Message::KeyboardEventOccurred(iced::keyboard::Event::ModifiersChanged(modifiers)) => {
+                self.modifiers = modifiers;
+                Effect::None
+            }
+            // Only Escape, to cancel, while a move waits for confirmation
+            Message::KeyboardEventOccurred(event) if !self.move_plans.is_empty() => {
+                if let iced::keyboard::Event::KeyPressed {
+                    key: iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape),
+                    ..
+                } = event
+                {
+                    self.move_plans.clear();
+                }
+                Effect::None
+            }
+            Message::KeyboardEventOccurred(event) if TabId::from_shortcut(&event).is_some() => {
+                let tab = TabId::from_shortcut(&event).unwrap();
+                self.update(Message::UserSelectedTab(tab))
+            }
+            // Keys typed on the other tabs are not for sorting
+            Message::KeyboardEventOccurred(_) if self.active_tab != TabId::Main => Effect::None,
+            Message::KeyboardEventOccurred(event) if self.merge_review.is_some() => {
+                self.update_merge_review_key(event)
+            }
+            Message::UserPickedMergeTag(choice) => self.pick_merge_tag(choice),
+            Message::UserPressedEndMergeReview => {
+                self.merge_review = None;
+                Effect::None
+            }
+            Message::KeyboardEventOccurred(event) => match self.state {
+                ModelState::Sorting => self.update_sorting(SortingMessage::KeyboardEvent(event)),
+                _ => Effect::None,
+            },
+            Message::Sorting(sorting_message) => match self.state {
+                ModelState::Sorting => self.update_sorting(sorting_message),
+                _ => Effect::None,
+            },
+            Message::Settings(settings_message) => {
+                let (preview_quality, thumbnail_shape) =
+                    (self.config.preview_quality, self.config.thumbnail_shape);
+                let language = self.config.language;
+                let effect = self.settings.update(settings_message, &mut self.config);
+                if self.config.preview_quality != preview_quality
+                    || self.config.thumbnail_shape != thumbnail_shape
+                {
+                    // The previews are reloaded at the new size, or with
+                    // thumbnails of the new shape
+                    self.pathlist.unload_all();
+                }
+                if self.config.language != language {
+                    let before = TagNames::new();
+                    rust_i18n::set_locale(self.config.language.locale());
+                    self.tag_names.retranslate(&before);
+                }
+                effect
+            }
+            Message::PixelCanvas(pixel_canvas_message) => match self.state {
+                ModelState::Sorting => match pixel_canvas_message {
+                    PixelCanvasMessage::CanvasSized(dim) => {
+                        self.update_sorting(SortingMessage::CanvasResized(dim))
+                    }
+                    PixelCanvasMessage::UserScrolled(steps, focus) => {
+                        self.update_sorting(SortingMessage::UserScrolledImage(steps, focus))
+                    }
+                    PixelCanvasMessage::UserScrolledReference(steps, focus) => {
+                        self.update_sorting(SortingMessage::UserScrolledReference(steps, focus))
+                    }
+                },
+                _ => Effect::None,
             },
         };
 
-        debug!("Effect: {effect:?}");
+        let effect = self.hold_background_effect(effect);
+        self.effect_log.log("Effect", &effect);
         effect
     }
 
-    fn view(&self) -> Element<Message> {
+    pub fn set_background_paused(&mut self, paused: bool) -> Effect {
+        self.background_paused = paused;
+        debug!("Background work paused: {paused}");
+        match (paused, self.state, self.canvas_dimensions) {
+            (false, ModelState::Sorting, Some(dim)) => {
+                let paths = self.pathlist.resume_preload(&self.config);
+                if paths.is_empty() {
+                    Effect::None
+                } else {
+                    Effect::PreloadImages(paths, dim)
+                }
+            }
+            _ => Effect::None,
+        }
+    }
+
+    fn folder_scan(&self) -> FolderScan {
+        FolderScan {
+            recursive: self.config.recursive_scan,
+            skip_dirs: self
+                .tag_names
+                .enumerate()
+                .map(|(_, name)| name.clone())
+                .collect(),
+        }
+    }
+
+    // Follows the tag of the current image, the last preview is kept while
+    // untagged images are shown
+    fn refresh_destination_preview(&mut self) -> Effect {
+        if !self.show_destination_preview || !matches!(self.state, ModelState::Sorting) {
+            return Effect::None;
+        }
+        let Some(tag) = self
+            .pathlist
+            .paths
+            .get(self.pathlist.index)
+            .and_then(|info| info.metadata.tag)
+        else {
+            return Effect::None;
+        };
+        if self
+            .destination_preview
+            .as_ref()
+            .is_some_and(|preview| preview.tag == tag)
+        {
+            return Effect::None;
+        }
+        self.destination_preview = Some(DestinationPreview {
+            tag,
+            contents: None,
+        });
+        let destination = file_ops::destination_folder(self.tag_names.destination(&tag));
+        Effect::ReadDestination(tag, destination.to_owned())
+    }
+
+    fn update_merge_review_key(&mut self, event: iced::keyboard::Event) -> Effect {
+        use iced::keyboard::{key::Named, Event, Key};
+        let Event::KeyPressed { key, .. } = event else {
+            return Effect::None;
+        };
+        match key {
+            Key::Named(Named::ArrowLeft) => self.pick_merge_tag(MergeChoice::Mine),
+            Key::Named(Named::ArrowRight) => self.pick_merge_tag(MergeChoice::Theirs),
+            Key::Named(Named::Escape) => {
+                self.merge_review = None;
+                Effect::None
+            }
+            // Other keys would move away from the file being decided on
+            _ => Effect::None,
+        }
+    }
+
+    fn pick_merge_tag(&mut self, choice: MergeChoice) -> Effect {
+        let Some((path, tag)) = self
+            .merge_review
+            .as_mut()
+            .and_then(|review| review.pick(choice))
+        else {
+            return Effect::None;
+        };
+        if let Some(info) = self
+            .pathlist
+            .paths
+            .iter_mut()
+            .find(|info| info.path == path)
+        {
+            info.metadata.tag = Some(tag);
+        }
+        self.show_merge_conflict()
+    }
+
+    // Jumps to the file of the current conflict, the review ends after the last one
+    fn show_merge_conflict(&mut self) -> Effect {
+        let Some(path) = self
+            .merge_review
+            .as_ref()
+            .and_then(MergeReview::current)
+            .map(|conflict| conflict.path.clone())
+        else {
+            self.merge_review = None;
+            return Effect::None;
+        };
+        let Some(index) = self
+            .pathlist
+            .paths
+            .iter()
+            .position(|info| info.path == path)
+        else {
+            return Effect::None;
+        };
+        self.last_navigation = std::time::Instant::now();
+        let paths = self.pathlist.jump_to_next_of(&[index], &self.config);
+        match self.canvas_dimensions {
+            Some(dim) if !paths.is_empty() => Effect::PreloadImages(paths, dim),
+            _ => Effect::None,
+        }
+    }
+
+    // Names from a merged session are taken for tags that were never renamed
+    // here, and for tags only the session has
+    fn adopt_tag_names(&mut self, other: &TagNames) {
+        let defaults = TagNames::new();
+        for (tag, name) in other.enumerate() {
+            if !self.tag_names.contains(&tag) || self.tag_names.get(&tag) == defaults.get(&tag) {
+                self.tag_names.ensure(tag);
+                self.tag_names.update(tag, name.clone());
+            }
+        }
+    }
+
+    // Tags from files may have been added in another session
+    fn apply_tags(&mut self, tags: &[(String, Tag)]) -> usize {
+        for (_, tag) in tags {
+            self.tag_names.ensure(*tag);
+        }
+        self.pathlist.apply_tags(tags)
+    }
+
+    // Any change to the tags, or to how they are looked at, is written to the
+    // session file right away.
+    // Not in safe mode, where the session on disk was never read
+    fn save_session_if_changed(&mut self) -> Effect {
+        if !self.session_dirty {
+            return Effect::None;
+        }
+        let settled = self.last_navigation.elapsed() >= POSITION_SAVE_DELAY;
+        let Some(session) = self.changed_session(settled) else {
+            // Checked again on the idle ticks until the position is saved
+            self.session_dirty = !settled;
+            return Effect::None;
+        };
+        self.session_dirty = false;
+        let sidecars = match self.config.xmp_sidecars {
+            true => Effect::WriteXmpSidecars(self.changed_sidecars(&session)),
+            false => Effect::None,
+        };
+        self.saved_session = session.clone();
+        match std::env::current_dir() {
+            Ok(directory) => Effect::Batch(vec![
+                Effect::SaveSession(directory, Box::new(session)),
+                sidecars,
+            ]),
+            Err(_) => sidecars,
+        }
+    }
+
+    // The image shown is left as saved until the user has stopped on one,
+    // stepping through a folder would otherwise write the file at every step
+    fn changed_session(&self, with_position: bool) -> Option<Session> {
+        if !matches!(self.state, ModelState::Sorting) || self.safe_mode {
+            return None;
+        }
+        let tags: SessionTags = self
+            .pathlist
+            .paths
+            .iter()
+            .filter_map(|info| Some((session::key(&info.path), info.metadata.tag?)))
+            .collect();
+        let ratings: SessionRatings = self
+            .pathlist
+            .paths
+            .iter()
+            .filter(|info| info.metadata.rating > 0)
+            .map(|info| (session::key(&info.path), info.metadata.rating))
+            .collect();
+        let current = match self.pathlist.visible().first() {
+            Some(&first) if first != self.pathlist.index => self
+                .pathlist
+                .paths
+                .get(self.pathlist.index)
+                .map(|info| session::key(&info.path)),
+            _ => None,
+        };
+        let session = Session {
+            tags,
+            tag_names: Some(self.tag_names.clone()),
+            tab: Some(self.active_tab),
+            filter: self.pathlist.filter,
+            view_style: Some(self.config.thumbnail_style.clone()),
+            sort_order: Some(self.config.sort_order),
+            ratings,
+            current,
+        };
+        let moved_only = session.current != self.saved_session.current
+            && Session {
+                current: self.saved_session.current.clone(),
+                ..session.clone()
+            } == self.saved_session;
+        match session == self.saved_session || (moved_only && !with_position) {
+            true => None,
+            false => Some(session),
+        }
+    }
+
+    // Images whose tag or rating is different from the last saved session
+    fn changed_sidecars(&self, session: &Session) -> Vec<(String, bool, Option<String>, u8)> {
+        let saved = &self.saved_session;
+        let shared =
+            xmp::SharedStems::new(self.pathlist.paths.iter().map(|info| info.path.as_str()));
+        self.pathlist
+            .paths
+            .iter()
+            .filter(|info| {
+                let key = session::key(&info.path);
+                saved.tags.get(&key) != session.tags.get(&key)
+                    || saved.ratings.get(&key) != session.ratings.get(&key)
+            })
+            .map(|info| {
+                let label = info.metadata.tag.map(|tag| {
+                    xmp::tag_label(&tag, &self.tag_names, info.metadata.xmp_label.as_deref())
+                });
+                let shared_stem = shared.contains(&info.path);
+                (info.path.clone(), shared_stem, label, info.metadata.rating)
+            })
+            .collect()
+    }
+
+    // Labels only tag images without a tag, ratings only rate unrated ones
+    fn apply_xmp_sidecars(&mut self, sidecars: Vec<(String, XmpSidecar)>) {
+        let sidecars: std::collections::HashMap<String, XmpSidecar> =
+            sidecars.into_iter().collect();
+        for info in self.pathlist.paths.iter_mut() {
+            let Some(sidecar) = sidecars.get(&info.path) else {
+                continue;
+            };
+            if info.metadata.tag.is_none() {
+                if let Some(tag) = sidecar
+                    .label
+                    .as_ref()
+                    .and_then(|label| xmp::label_tag(label, &self.tag_names))
+                {
+                    self.tag_names.ensure(tag);
+                    info.metadata.tag = Some(tag);
+                }
+            }
+            if info.metadata.rating == 0 {
+                info.metadata.rating = sidecar.rating.unwrap_or(0).min(pathlist::MAX_RATING);
+            }
+            info.metadata.keywords = sidecar.keywords.clone();
+            info.metadata.xmp_label = sidecar.label.clone();
+        }
+    }
+
+    // The tab, filter and view style from the last time the folder was open
+    fn restore_session_context(&mut self) {
+        let session = &self.saved_session;
+        if let Some(tab) = session.tab {
+            self.active_tab = tab;
+        }
+        if let Some(style) = &session.view_style {
+            self.config.thumbnail_style = style.clone();
+            self.settings.show_view_style(style);
+        }
+        if let Some(order) = session.sort_order {
+            self.config.sort_order = order;
+            self.settings.show_sort_order(order);
+        }
+        let filter = match session.filter {
+            ViewFilter::Tag(tag) if !self.tag_names.contains(&tag) => ViewFilter::All,
+            filter => filter,
+        };
+        self.pathlist.filter = filter;
+        let visible = self.pathlist.visible();
+        let current = session.current.as_ref().and_then(|current| {
+            visible
+                .iter()
+                .copied()
+                .find(|&index| session::key(&self.pathlist.paths[index].path) == *current)
+        });
+        if let Some(index) = current {
+            self.pathlist.index = index;
+        } else if !visible.contains(&self.pathlist.index) {
+            self.pathlist.index = visible.first().copied().unwrap_or(0);
+        }
+    }
+
+    fn checkpoint(&mut self) -> Effect {
+        // Don't overwrite a checkpoint the user hasn't decided about yet
+        if !matches!(self.state, ModelState::Sorting)
+            || self.pending_recovery.is_some()
+            || self.safe_mode
+        {
+            return Effect::None;
+        }
+        let checkpoint = Checkpoint {
+            directory: checkpoint::current_directory(),
+            current: self
+                .pathlist
+                .paths
+                .get(self.pathlist.index)
+                .map(|info| info.path.clone()),
+            images: self
+                .pathlist
+                .paths
+                .iter()
+                .map(|info| CheckpointImage {
+                    path: info.path.clone(),
+                    tag: info.metadata.tag,
+                    dimensions: info.metadata.dimensions,
+                })
+                .collect(),
+            tag_names: self.tag_names.clone(),
+        };
+        if self.last_checkpoint.as_ref() == Some(&checkpoint) {
+            return Effect::None;
+        }
+        self.last_checkpoint = Some(checkpoint.clone());
+        Effect::SaveCheckpoint(Box::new(checkpoint))
+    }
+
+    fn restore_checkpoint(&mut self) -> Effect {
+        let Some(checkpoint) = self.pending_recovery.take() else {
+            return Effect::None;
+        };
+        self.tag_names = checkpoint.tag_names;
+        self.pathlist
+            .apply_checkpoint(&checkpoint.images, checkpoint.current.as_deref());
+        match self.canvas_dimensions {
+            Some(dim) if !self.pathlist.paths.is_empty() => {
+                Effect::PreloadImages(self.pathlist.resume_preload(&self.config), dim)
+            }
+            _ => Effect::None,
+        }
+    }
+
+    fn discard_checkpoint(&mut self) -> Effect {
+        self.pending_recovery = None;
+        match self.canvas_dimensions {
+            Some(dim) if !self.pathlist.paths.is_empty() => {
+                Effect::PreloadImages(self.pathlist.resume_preload(&self.config), dim)
+            }
+            _ => Effect::None,
+        }
+    }
+
+    fn idle_preload(&mut self) -> Effect {
+        if self.last_navigation.elapsed() < IDLE_PRELOAD_DELAY {
+            return Effect::None;
+        }
+        match (self.state, self.canvas_dimensions) {
+            (ModelState::Sorting, Some(dim)) if !self.background_paused && !self.safe_mode => {
+                let paths = self
+                    .pathlist
+                    .get_idle_preload_images(&self.config, self.preview_memory_budget());
+                if paths.is_empty() {
+                    Effect::None
+                } else {
+                    Effect::PreloadImages(paths, dim)
+                }
+            }
+            _ => Effect::None,
+        }
+    }
+
+    // Background work is dropped while paused, and picked up again from the
+    // pathlist state when resuming. Moves are never held back.
+    fn hold_background_effect(&mut self, effect: Effect) -> Effect {
+        if !self.background_paused {
+            return effect;
+        }
+        match effect {
+            Effect::PreloadImages(paths, _) => {
+                self.pathlist.cancel_loading(&paths);
+                Effect::None
+            }
+            Effect::Batch(effects) => Effect::Batch(
+                effects
+                    .into_iter()
+                    .map(|effect| self.hold_background_effect(effect))
+                    .collect(),
+            ),
+            effect => effect,
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        if self.fullscreen && matches!(self.state, ModelState::Sorting) {
+            return self.view_sorting();
+        }
+        if !self.move_plans.is_empty() {
+            return widget::container(move_plan::view_move_plans(&self.move_plans))
+                .padding(20)
+                .into();
+        }
         let main_content = match self.state {
             ModelState::Sorting => self.view_sorting(),
             ModelState::LoadingListDir => {
@@ -373,20 +2702,56 @@ impl Model {
                 } else {
                     "Loading...".to_string()
                 };
-                widget::text(loading_text).into()
+                column![widget::text(loading_text)]
+                    .push_maybe(self.folder_stats.as_ref().map(FolderStats::view))
+                    .spacing(10)
+                    .into()
             }
             ModelState::EmptyDirectory => self.view_empty_dir_model(),
         };
+        let main_content = match self.view_move_banner() {
+            Some(banner) => column![banner, main_content].spacing(10).into(),
+            None => main_content,
+        };
+        let main_content = match move_plan::view_move_queue(&self.queued_moves, &self.tag_names) {
+            Some(queue) => column![queue, main_content].into(),
+            None => main_content,
+        };
 
         let tag_names = match self.state {
             ModelState::Sorting => self.tag_names.clone(),
             _ => TagNames::new(),
         };
         let tag_counts = sorting::count_tags(&self.pathlist.paths);
-        let actions_content =
-            actions::view_actions_tab(&self.selected_action_tag, tag_names, &tag_counts);
+        let actions_content = match (
+            &self.timestamp_plan,
+            &self.time_shift_plan,
+            &self.folder_comparison,
+        ) {
+            (Some(plan), _, _) => timestamps::view_timestamp_plan(plan),
+            (None, Some(plan), _) => time_shift::view_time_shift_plan(plan),
+            (None, None, Some(comparison)) => folder_compare::view_comparison(comparison),
+            (None, None, None) => actions::view_actions_tab(self, tag_names),
+        };
+
+        let settings_content = self.settings.view(&self.tag_names);
 
-        let settings_content = self.settings.view();
+        // Badges show what is waiting in the other tabs
+        let pending_tags = tag_counts.values().filter(|&&count| count > 0).count();
+        let actions_label = if pending_tags > 0 {
+            t!(
+                "Actions (%{count})",
+                count = format::format_count(pending_tags)
+            )
+            .to_string()
+        } else {
+            t!("Actions").to_string()
+        };
+        let settings_label = if self.settings.has_unsaved_changes(&self.config) {
+            t!("Settings *").to_string()
+        } else {
+            t!("Settings").to_string()
+        };
 
         Tabs::new(Message::UserSelectedTab)
             .push(
@@ -396,35 +2761,113 @@ impl Model {
             )
             .push(
                 TabId::Actions,
-                iced_aw::TabLabel::Text(String::from(t!("Actions"))),
+                iced_aw::TabLabel::Text(actions_label),
                 actions_content,
             )
             .push(
                 TabId::Settings,
-                iced_aw::TabLabel::Text(String::from(t!("Settings"))),
+                iced_aw::TabLabel::Text(settings_label),
                 settings_content,
             )
+            .push(
+                TabId::Log,
+                iced_aw::TabLabel::Text(String::from(t!("Log"))),
+                log_panel::view(),
+            )
             .set_active_tab(&self.active_tab)
             .into()
     }
 
-    fn view_empty_dir_model(&self) -> Element<'static, Message> {
+    fn view_empty_dir_model(&self) -> Element<'_, Message> {
         column![
             widget::text(t!("No pictures in this directory, select another one")),
             widget::button(widget::text(t!("Select Folder")))
                 .on_press(Message::UserPressedSelectFolder),
+            self.view_folder_queue(),
+        ]
+        .push_maybe(self.view_resume_last_session())
+        .push_maybe(self.folder_stats.as_ref().map(FolderStats::view))
+        .spacing(10)
+        .into()
+    }
+
+    // Not when it is the folder that is empty
+    fn view_resume_last_session(&self) -> Option<Element<'_, Message>> {
+        let folder = self.last_folder.as_ref()?;
+        if std::env::current_dir().ok().as_ref() == Some(folder) {
+            return None;
+        }
+        Some(
+            widget::row![
+                widget::button(widget::text(t!("Resume last session")))
+                    .on_press(Message::UserPressedResumeLastSession),
+                widget::text(folder.display().to_string()),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .into(),
+        )
+    }
+
+    fn view_folder_queue(&self) -> Element<'_, Message> {
+        let queued = self
+            .folder_queue
+            .iter()
+            .map(|path| widget::text(path.display().to_string()).into());
+        let next_button = widget::button(widget::text(t!(
+            "Next folder (%{count} queued)",
+            count = format::format_count(self.folder_queue.len())
+        )))
+        .on_press_maybe((!self.folder_queue.is_empty()).then_some(Message::UserPressedNextFolder));
+
+        column![widget::row![
+            widget::button(widget::text(t!("Add folder to queue")))
+                .on_press(Message::UserPressedQueueFolder),
+            next_button,
         ]
+        .spacing(10),]
+        .extend(queued)
+        .spacing(5)
         .into()
     }
 }
 
 impl Model {
     fn update_sorting(&mut self, message: SortingMessage) -> Effect {
+        if self.review.is_some() && !sorting::is_allowed_in_review(&message, &self.config.keymap) {
+            return Effect::None;
+        }
+        if self.end_summary {
+            match &message {
+                SortingMessage::KeyboardEvent(iced::keyboard::Event::KeyPressed { .. }) => {
+                    self.end_summary = false;
+                    return Effect::None;
+                }
+                SortingMessage::KeyboardEvent(_)
+                | SortingMessage::ImagePreloaded(..)
+                | SortingMessage::CanvasResized(_)
+                | SortingMessage::HeldStepTick
+                | SortingMessage::TagFlashTick
+                | SortingMessage::UserReleasedStepButton => (),
+                _ => self.end_summary = false,
+            }
+        }
         let config = self.config.clone();
-        sorting::update_sorting_model(self, message, &config)
+        let index = self.pathlist.index;
+        let effect = sorting::update_sorting_model(self, message, &config);
+        // Keep the current image in sight in the grid
+        match sorting::grid_position(self) {
+            Some((row, rows)) if self.pathlist.index != index => {
+                Effect::Batch(vec![effect, Effect::ScrollGrid(row, rows)])
+            }
+            _ => effect,
+        }
     }
 
     fn view_sorting(&self) -> iced::Element<'_, Message> {
+        if self.end_summary {
+            return end_summary::view(&self.pathlist.paths, &self.tag_names);
+        }
         sorting::view_sorting_model(self, &self.config, &self.task_manager)
     }
 }
@@ -433,121 +2876,766 @@ fn effect_to_task(effect: Effect, model: &mut Model) -> Task<Message> {
     match effect {
         Effect::None => Task::none(),
         Effect::LsDir => {
-            model.task_manager.cancel_all();
+            model.task_manager.cancel_listing_tasks();
 
             model.task_manager.start_task(
                 TaskType::LsDir,
                 Message::ListDirCompleted,
-                get_files_in_folder_async(PICTURE_DIR.to_owned()),
+                get_files_in_folder_async(
+                    PICTURE_DIR.to_owned(),
+                    model.folder_scan(),
+                    model.config.sort_order.while_listing(),
+                    model.shuffle_seed,
+                ),
             )
         }
-        Effect::PreloadImages(paths, dim) => {
-            preload_images_task(paths, dim, model.config.clone(), &mut model.task_manager)
+        Effect::PreloadImages(paths, dim) => {
+            let decode_size = model.decode_size(dim);
+            let paths = paths
+                .into_iter()
+                .map(|path| {
+                    let full = model.pathlist.wants_full_preview(&path, &model.config);
+                    (path, full)
+                })
+                .collect();
+            preload_images_task(
+                paths,
+                decode_size,
+                model.config.clone(),
+                &mut model.task_manager,
+            )
+        }
+        Effect::PlanMoves(tags) => {
+            let groups =
+                move_plan::group_by_destination(&model.pathlist.paths, &tags, &model.tag_names);
+            if groups.is_empty() {
+                println!("No files to move");
+                return Task::none();
+            }
+            let layout = model.config.move_layout;
+            model
+                .task_manager
+                .start_task(TaskType::PlanMove, Message::MovePlanReady, async move {
+                    tokio::task::spawn_blocking(move || {
+                        groups
+                            .into_iter()
+                            .map(|(destination, (tags, files))| MovePlan {
+                                tags,
+                                moves: move_plan::plan_moves(&files, &destination, layout),
+                                destination: file_ops::destination_folder(&destination).to_owned(),
+                            })
+                            .collect()
+                    })
+                    .await
+                    .expect("Could not spawn task")
+                })
+        }
+        Effect::MoveThenLs(plans) => {
+            for plan in &plans {
+                println!("mv {} files to \"{}\"", plan.moves.len(), plan.destination);
+            }
+            model.task_manager.start_task(
+                TaskType::MoveThenLs,
+                |task_id, (result, paths)| Message::MoveCompleted(task_id, result, paths),
+                file_ops::mv_planned_then_ls_async(plans, model.folder_scan()),
+            )
+        }
+        Effect::CopyThenLs(tag) => {
+            let files_to_copy: Vec<String> = model
+                .pathlist
+                .paths
+                .iter()
+                .filter(|info| info.metadata.tag == Some(tag))
+                .map(|info| info.path.clone())
+                .collect();
+            if files_to_copy.is_empty() {
+                return Task::none();
+            }
+            model.task_manager.start_task(
+                TaskType::CopyThenLs,
+                |task_id, (result, paths)| Message::CopyCompleted(task_id, result, paths),
+                file_ops::copy_then_ls_async(
+                    files_to_copy,
+                    model.tag_names.destination(&tag).to_string(),
+                    model.config.move_layout,
+                    model.folder_scan(),
+                ),
+            )
+        }
+        Effect::RenameFile(old_path, new_path) => model.task_manager.start_task(
+            TaskType::RenameFile,
+            |task_id, (old_path, new_path, result)| {
+                Message::FileRenamed(task_id, old_path, new_path, result)
+            },
+            rename_file_async(old_path, new_path),
+        ),
+        Effect::PlanTimestamps(paths) => model.task_manager.start_task(
+            TaskType::FixTimestamps,
+            Message::TimestampPlanReady,
+            async move {
+                tokio::task::spawn_blocking(move || timestamps::plan_timestamp_changes(paths))
+                    .await
+                    .expect("Could not spawn task")
+            },
+        ),
+        Effect::ApplyTimestamps(changes) => model.task_manager.start_task(
+            TaskType::FixTimestamps,
+            |task_id, (applied, errors)| Message::TimestampsApplied(task_id, applied, errors),
+            async move {
+                tokio::task::spawn_blocking(move || timestamps::apply_timestamp_changes(changes))
+                    .await
+                    .expect("Could not spawn task")
+            },
+        ),
+        Effect::PlanTimeShift(paths, offset, camera) => model.task_manager.start_task(
+            TaskType::FixTimestamps,
+            Message::TimeShiftPlanReady,
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    time_shift::plan_time_shift(paths, offset, camera)
+                })
+                .await
+                .expect("Could not spawn task")
+            },
+        ),
+        Effect::ApplyTimeShift(changes) => model.task_manager.start_task(
+            TaskType::FixTimestamps,
+            |task_id, (applied, errors)| Message::TimeShiftApplied(task_id, applied, errors),
+            async move {
+                tokio::task::spawn_blocking(move || time_shift::apply_time_shift(changes))
+                    .await
+                    .expect("Could not spawn task")
+            },
+        ),
+        Effect::PrepareSend(paths, name, settings) => model.task_manager.start_task(
+            TaskType::ShareExport,
+            Message::SendPrepared,
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    share_export::stage_for_sending(paths, &name, settings)
+                })
+                .await
+                .expect("Could not spawn task")
+            },
+        ),
+        Effect::ShareExport(paths, settings) => model.task_manager.start_task(
+            TaskType::ShareExport,
+            Message::SharedExported,
+            async move {
+                let folder = rfd::AsyncFileDialog::new().pick_folder().await?;
+                let folder = folder.path().to_owned();
+                // The copies would sit among the originals being sorted
+                let current = std::env::current_dir().and_then(|dir| dir.canonicalize());
+                if current.is_ok_and(|current| folder.canonicalize().ok() == Some(current)) {
+                    let error = t!("Can't export into the folder being sorted").to_string();
+                    return Some((0, vec![error]));
+                }
+                Some(
+                    tokio::task::spawn_blocking(move || {
+                        share_export::export_for_sharing(paths, folder, settings)
+                    })
+                    .await
+                    .expect("Could not spawn task"),
+                )
+            },
+        ),
+        Effect::ZipTag(paths, name, resize) => model.task_manager.start_stream_task(
+            TaskType::ZipExport,
+            Message::ZipProgressed,
+            iced::stream::channel(100, move |mut output| async move {
+                let Some(file) = rfd::AsyncFileDialog::new()
+                    .add_filter("Zip", &["zip"])
+                    .set_file_name(name)
+                    .save_file()
+                    .await
+                else {
+                    let _ = output.send(ZipProgress::Cancelled).await;
+                    return;
+                };
+                let archive = file.path().to_owned();
+                let (progress, mut zipped) = iced::futures::channel::mpsc::unbounded();
+                let zipping = tokio::task::spawn_blocking(move || {
+                    zip_export::zip_files(paths, &archive, resize, |done, total| {
+                        let _ = progress.unbounded_send(ZipProgress::Zipped(done, total));
+                    })
+                });
+                // Ends when the zipping is done and drops the sender
+                while let Some(step) = zipped.next().await {
+                    let _ = output.send(step).await;
+                }
+                let result = zipping.await.expect("Could not spawn task");
+                let _ = output.send(ZipProgress::Finished(result)).await;
+            }),
+        ),
+        Effect::Print(images) if images.is_empty() => Task::none(),
+        Effect::Print(images) => {
+            let per_page = model.config.print_per_page;
+            model
+                .task_manager
+                .start_task(TaskType::Print, Message::PagesRendered, async move {
+                    tokio::task::spawn_blocking(move || print::render_pages(images, per_page))
+                        .await
+                        .expect("Could not spawn task")
+                })
+        }
+        Effect::ExportMontage(images) => model.task_manager.start_task(
+            TaskType::ExportMontage,
+            Message::MontageExported,
+            async move {
+                let file = rfd::AsyncFileDialog::new()
+                    .add_filter("PNG", &["png"])
+                    .set_file_name(montage::default_file_name(&images[0].0, &images[1].0))
+                    .save_file()
+                    .await?
+                    .path()
+                    .to_owned();
+                let rendered = tokio::task::spawn_blocking(move || {
+                    montage::render(images, &file).map(|()| file)
+                })
+                .await
+                .expect("Could not spawn task");
+                Some(rendered)
+            },
+        ),
+        Effect::UploadTag(paths, remote) => model.task_manager.start_stream_task(
+            TaskType::Upload,
+            Message::UploadProgressed,
+            iced::stream::channel(100, move |mut output| async move {
+                let (progress, mut uploaded) = iced::futures::channel::mpsc::unbounded();
+                let uploading = tokio::task::spawn_blocking(move || {
+                    upload::upload_files(paths, remote, |done, total| {
+                        let _ = progress.unbounded_send(UploadProgress::Uploaded(done, total));
+                    })
+                });
+                // Ends when the uploading is done and drops the sender
+                while let Some(step) = uploaded.next().await {
+                    let _ = output.send(step).await;
+                }
+                let summary = uploading.await.expect("Could not spawn task");
+                let _ = output.send(UploadProgress::Finished(summary)).await;
+            }),
+        ),
+        Effect::ExportTags(assignments) => {
+            model
+                .task_manager
+                .start_task(TaskType::TagFile, Message::TagsExported, async move {
+                    let file = rfd::AsyncFileDialog::new()
+                        .add_filter("CSV", &["csv"])
+                        .add_filter("JSON", &["json"])
+                        .set_file_name("tags.csv")
+                        .save_file()
+                        .await?;
+                    let path = file.path().to_owned();
+                    Some(
+                        tokio::task::spawn_blocking(move || {
+                            tag_io::export_tags(&path, &assignments)
+                                .map(|()| path.to_string_lossy().to_string())
+                        })
+                        .await
+                        .expect("Could not spawn task"),
+                    )
+                })
+        }
+        Effect::ImportTags(tag_names) => {
+            model
+                .task_manager
+                .start_task(TaskType::TagFile, Message::TagsImported, async move {
+                    let file = rfd::AsyncFileDialog::new()
+                        .add_filter("CSV or JSON", &["csv", "json"])
+                        .pick_file()
+                        .await?;
+                    let path = file.path().to_owned();
+                    Some(
+                        tokio::task::spawn_blocking(move || tag_io::import_tags(&path, &tag_names))
+                            .await
+                            .expect("Could not spawn task"),
+                    )
+                })
+        }
+        Effect::ExportSession(tagged, tag_names) => {
+            model
+                .task_manager
+                .start_task(TaskType::TagFile, Message::SessionExported, async move {
+                    let file = rfd::AsyncFileDialog::new()
+                        .add_filter("JSON", &["json"])
+                        .set_file_name("session.json")
+                        .save_file()
+                        .await?;
+                    let path = file.path().to_owned();
+                    Some(
+                        tokio::task::spawn_blocking(move || {
+                            shared_session::export_session(&path, tagged, tag_names)
+                                .map(|count| (path.to_string_lossy().to_string(), count))
+                        })
+                        .await
+                        .expect("Could not spawn task"),
+                    )
+                })
+        }
+        Effect::MergeSession(files) => {
+            model
+                .task_manager
+                .start_task(TaskType::TagFile, Message::SessionMerged, async move {
+                    let file = rfd::AsyncFileDialog::new()
+                        .add_filter("JSON", &["json"])
+                        .pick_file()
+                        .await?;
+                    let path = file.path().to_owned();
+                    Some(
+                        tokio::task::spawn_blocking(move || {
+                            shared_session::merge_session(&path, files)
+                        })
+                        .await
+                        .expect("Could not spawn task"),
+                    )
+                })
         }
-        Effect::MoveThenLs(tag) => {
-            let files_to_move = model
-                .pathlist
-                .paths
-                .iter()
-                .filter_map(|info| {
-                    if info.metadata.tag == Some(tag) {
-                        Some(info.path.clone())
-                    } else {
-                        None
+        Effect::ComputeVisualDiff(path_a, a, path_b, b) => model.task_manager.start_task(
+            TaskType::VisualDiff,
+            Message::VisualDiffComputed,
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    Box::new(visual_diff::compute_visual_diff(path_a, &a, path_b, &b))
+                })
+                .await
+                .expect("Could not spawn task")
+            },
+        ),
+        Effect::Quarantine(files) => model.task_manager.start_task(
+            TaskType::MoveThenLs,
+            |task_id, (result, paths)| Message::MoveCompleted(task_id, result, paths),
+            file_ops::mv_then_ls_async(
+                files,
+                file_ops::QUARANTINE_DIR.to_owned(),
+                model.config.move_layout,
+                model.folder_scan(),
+            ),
+        ),
+        Effect::UndoMoves(moves) => model.task_manager.start_task(
+            TaskType::MoveThenLs,
+            |task_id, (undone, result, paths)| Message::MovesUndone(task_id, undone, result, paths),
+            file_ops::undo_moves_then_ls_async(moves, model.folder_scan()),
+        ),
+        Effect::ReadFileIdentities(paths) => model.task_manager.start_task(
+            TaskType::ReadFileIdentities,
+            Message::FileIdentitiesRead,
+            read_file_identities_async(paths),
+        ),
+        Effect::ReadSidecars(paths) => model.task_manager.start_task(
+            TaskType::ReadSidecars,
+            Message::SidecarsRead,
+            read_sidecars_async(paths),
+        ),
+        Effect::ReadXmpSidecars(paths) => model.task_manager.start_task(
+            TaskType::ReadXmpSidecars,
+            Message::XmpSidecarsRead,
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    let shared = xmp::SharedStems::new(paths.iter().map(String::as_str));
+                    paths
+                        .into_iter()
+                        .filter_map(|path| {
+                            let sidecar = xmp::read_sidecar(&path, shared.contains(&path))?;
+                            Some((path, sidecar))
+                        })
+                        .collect()
+                })
+                .await
+                .expect("Could not spawn task")
+            },
+        ),
+        Effect::WriteXmpSidecars(changes) => Task::future(async move {
+            tokio::task::spawn_blocking(move || {
+                for (path, shared_stem, label, rating) in changes {
+                    if let Err(e) = xmp::write_sidecar(&path, shared_stem, label.as_deref(), rating)
+                    {
+                        warn!("Could not write the XMP sidecar of {path}: {e}");
                     }
+                }
+            })
+            .await
+            .expect("Could not spawn task")
+        })
+        .discard(),
+        Effect::ReadImageHeaders(paths) => model.task_manager.start_task(
+            TaskType::ReadImageHeaders,
+            Message::ImageHeadersRead,
+            read_image_headers_async(paths),
+        ),
+        Effect::ReadDestination(tag, folder) => {
+            let thumb_size = model.config.thumbnail_size;
+            let scan = FolderScan {
+                recursive: model.config.recursive_scan,
+                skip_dirs: Vec::new(),
+            };
+            model.task_manager.start_task(
+                TaskType::ReadDestination,
+                |task_id, (tag, contents)| Message::DestinationRead(task_id, tag, contents),
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        (
+                            tag,
+                            destination_preview::read_destination(&folder, &scan, thumb_size),
+                        )
+                    })
+                    .await
+                    .expect("Could not spawn task")
+                },
+            )
+        }
+        Effect::ReadCaptureTimes(paths) => model.task_manager.start_task(
+            TaskType::ReadCaptureTimes,
+            Message::CaptureTimesRead,
+            read_capture_heatmap_async(paths),
+        ),
+        Effect::CompareFolders(a, b) => model.task_manager.start_task(
+            TaskType::CompareFolders,
+            Message::FoldersCompared,
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    folder_compare::compare_folders(&a, &b)
+                        .map(Box::new)
+                        .map_err(|e| e.to_string())
                 })
-                .collect::<Vec<_>>();
-            let tag_name = model.tag_names.get(&tag);
-            if files_to_move.is_empty() {
-                println!("No files to move");
-                Task::none()
-            } else {
-                println!("mv {} \"{}\"", files_to_move.join(" "), tag_name);
-
+                .await
+                .expect("Could not spawn task")
+            },
+        ),
+        Effect::CopyMissing(from, to, names) => model.task_manager.start_task(
+            TaskType::CompareFolders,
+            Message::MissingCopied,
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    folder_compare::copy_missing(&from, &to, &names)
+                })
+                .await
+                .expect("Could not spawn task")
+            },
+        ),
+        Effect::SortListing(paths, order) => {
+            let seed = model.shuffle_seed;
+            // A slow order picked first mustn't land after the one picked last
+            model
+                .task_manager
+                .cancel_tasks_of_type(&TaskType::SortListing);
+            model.task_manager.start_task(
+                TaskType::SortListing,
+                Message::ListingSorted,
+                async move {
+                    tokio::task::spawn_blocking(move || sort_order::sorted(paths, order, seed))
+                        .await
+                        .expect("Could not spawn task")
+                },
+            )
+        }
+        Effect::ReadFolderStats => model.task_manager.start_task(
+            TaskType::ReadFolderStats,
+            Message::FolderStatsRead,
+            read_folder_stats_async(PICTURE_DIR.to_owned()),
+        ),
+        Effect::IndexMetadata(paths) => match model.metadata_db.clone() {
+            Some(db_path) => {
+                let archive_root = model.archive_root.clone();
                 model.task_manager.start_task(
-                    TaskType::MoveThenLs,
-                    Message::ListDirCompleted,
-                    mv_then_ls_async(files_to_move, tag_name.to_string()),
+                    TaskType::IndexMetadata,
+                    Message::MetadataIndexed,
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            metadata_db::index_files(&db_path, archive_root.as_deref(), paths)
+                        })
+                        .await
+                        .expect("Could not spawn task")
+                    },
                 )
             }
+            None => Task::none(),
+        },
+        Effect::RecordMoves(moves) => match model.metadata_db.clone() {
+            Some(db_path) => {
+                let folder = checkpoint::current_directory();
+                Task::future(async move {
+                    tokio::task::spawn_blocking(move || {
+                        metadata_db::record_moves(&db_path, moves, folder)
+                    })
+                    .await
+                    .expect("Could not spawn task")
+                })
+                .discard()
+            }
+            None => Task::none(),
+        },
+        Effect::QueryScaleFactor(id) => {
+            iced::window::get_scale_factor(id).map(Message::ScaleFactorReported)
         }
+        Effect::OpenExternally(path) => external_viewer::open_task(path),
         Effect::FocusElement(id) => widget::text_input::focus(id),
+        // The primary selection too, for pasting with the middle button on
+        // Linux. Elsewhere, and on Wayland compositors without the primary
+        // selection protocol, writing it does nothing.
+        Effect::CopyToClipboard(contents) => Task::batch([
+            iced::clipboard::write(contents.clone()),
+            iced::clipboard::write_primary(contents),
+        ]),
+        Effect::ScrollGrid(row, rows) => widget::scrollable::snap_to(
+            sorting::grid_scroll_id(),
+            widget::scrollable::RelativeOffset {
+                x: 0.0,
+                y: row as f32 / rows.saturating_sub(1).max(1) as f32,
+            },
+        ),
+        Effect::PickFolder(purpose) => Task::perform(
+            async {
+                rfd::AsyncFileDialog::new()
+                    .pick_folder()
+                    .await
+                    .map(|folder| folder.path().to_owned())
+            },
+            move |path| Message::FolderPicked(purpose.clone(), path),
+        ),
+        Effect::Notify(summary, body) => notifications::notify_task(summary, body),
+        Effect::SaveSession(directory, session) => {
+            // Numbered now, in the order the sessions were made
+            let write = session::next_write();
+            Task::future(async move {
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = session::save_in_order(&directory, &session, write) {
+                        warn!("Could not write session file: {e}");
+                    }
+                })
+                .await
+                .expect("Could not spawn task")
+            })
+            .discard()
+        }
+        Effect::SaveLastFolder(directory) => Task::future(async move {
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = session::save_last_folder(&directory) {
+                    warn!("Could not write last folder: {e}");
+                }
+            })
+            .await
+            .expect("Could not spawn task")
+        })
+        .discard(),
+        Effect::SaveConfig(config) => Task::future(async move {
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = config_file::save(&config) {
+                    warn!("Could not write config file: {e}");
+                }
+            })
+            .await
+            .expect("Could not spawn task")
+        })
+        .discard(),
+        Effect::SaveCheckpoint(checkpoint) => Task::future(async move {
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = checkpoint::save(&checkpoint) {
+                    warn!("Could not write checkpoint: {e}");
+                }
+            })
+            .await
+            .expect("Could not spawn task")
+        })
+        .discard(),
+        Effect::CloseWindow(id) => iced::window::close(id),
+        Effect::SetFullscreen(fullscreen) => {
+            let mode = if fullscreen {
+                iced::window::Mode::Fullscreen
+            } else {
+                iced::window::Mode::Windowed
+            };
+            iced::window::get_latest().and_then(move |id| iced::window::change_mode(id, mode))
+        }
+        Effect::Batch(effects) => Task::batch(
+            effects
+                .into_iter()
+                .map(|effect| effect_to_task(effect, model))
+                .collect::<Vec<_>>(),
+        ),
     }
 }
 
-async fn mv_then_ls_async(files: Vec<String>, destination: String) -> Vec<String> {
-    match tokio::task::spawn_blocking(move || {
-        mv_files(files, destination);
-        get_files_in_folder(PICTURE_DIR)
+async fn rename_file_async(
+    old_path: String,
+    new_path: String,
+) -> (String, String, Result<(), String>) {
+    tokio::task::spawn_blocking(move || {
+        let result = if std::path::Path::new(&new_path).exists() {
+            Err(t!("A file named %{name} already exists", name = new_path).to_string())
+        } else {
+            std::fs::rename(&old_path, &new_path).map_err(|e| e.to_string())
+        };
+        if result.is_ok() {
+            takeout::rename_sidecar(&old_path, &new_path);
+        }
+        (old_path, new_path, result)
     })
     .await
     .expect("Could not spawn task")
-    {
-        Ok(files_in_folder) => files_in_folder,
-        Err(_) => panic!("Io Error when listing directory after move"),
-    }
-}
-
-fn mv_files(files: Vec<String>, destination: String) {
-    // Create directory if it doesn't exist
-    let dest_path = std::path::Path::new(&destination);
-    if !dest_path.exists() {
-        std::fs::create_dir(dest_path).unwrap();
-    }
-    let dest_path = std::path::Path::new(&destination).canonicalize().unwrap();
-    for file in files {
-        println!("Moving {file} to {destination}");
-        let basename = std::path::Path::new(&file).file_name().unwrap();
-        let mut dest = dest_path.clone();
-        dest.push(basename);
-        std::fs::rename(&file, dest).unwrap();
-    }
 }
 
-async fn get_files_in_folder_async(folder_path: String) -> Vec<String> {
-    match tokio::task::spawn_blocking(move || get_files_in_folder(folder_path.as_str())).await {
+async fn get_files_in_folder_async(
+    folder_path: String,
+    scan: FolderScan,
+    order: SortOrder,
+    seed: u64,
+) -> Vec<String> {
+    match tokio::task::spawn_blocking(move || {
+        get_files_in_folder(folder_path.as_str(), &scan)
+            .map(|paths| sort_order::sorted(paths, order, seed))
+    })
+    .await
+    {
         Ok(Ok(res)) => res,
         Ok(Err(_)) => panic!("Io Error when listing directory after move"),
         Err(_) => panic!("Could not spawn task"),
     }
 }
 
-fn get_files_in_folder(folder_path: &str) -> std::io::Result<Vec<String>> {
+async fn read_capture_heatmap_async(paths: Vec<String>) -> Box<CaptureHeatmap> {
+    tokio::task::spawn_blocking(move || {
+        let times: Vec<_> = paths
+            .iter()
+            .map(|path| takeout::read_capture_time(path))
+            .collect();
+        Box::new(CaptureHeatmap::from_capture_times(&times))
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+async fn read_sidecars_async(paths: Vec<String>) -> Vec<(String, SidecarMetadata)> {
+    tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let sidecar = takeout::read_sidecar(&path)?;
+                Some((path, sidecar))
+            })
+            .collect()
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+// Size and kind of an image, read without decoding it
+#[derive(Debug, Clone)]
+pub struct ImageHeader {
+    pub path: String,
+    pub pixel_size: Option<(u32, u32)>,
+    pub kind: ImageKind,
+}
+
+async fn read_image_headers_async(paths: Vec<String>) -> Vec<ImageHeader> {
+    tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .map(|path| {
+                let pixel_size = image_size::read_pixel_size(&path);
+                let kind = image_kind::classify(&path, pixel_size);
+                ImageHeader {
+                    path,
+                    pixel_size,
+                    kind,
+                }
+            })
+            .collect()
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+async fn read_folder_stats_async(folder_path: String) -> Option<FolderStats> {
+    tokio::task::spawn_blocking(move || folder_stats::read_folder_stats(&folder_path).ok())
+        .await
+        .expect("Could not spawn task")
+}
+
+async fn read_file_identities_async(paths: Vec<String>) -> Vec<(String, FileIdentity)> {
+    tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let identity = FileIdentity::read(&path)?;
+                Some((path, identity))
+            })
+            .collect()
+    })
+    .await
+    .expect("Could not spawn task")
+}
+
+pub fn is_supported_image(file_name: &str) -> bool {
+    file_name.ends_with(".jpg")
+        || file_name.ends_with(".png")
+        || raw::is_raw(file_name)
+        || video::is_video(file_name)
+}
+
+// The path relative to the sorted folder, as shown to the user
+pub fn display_path(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+fn get_files_in_folder(folder_path: &str, scan: &FolderScan) -> std::io::Result<Vec<String>> {
     let mut file_names = Vec::new();
+    add_files_in_folder(folder_path, scan, true, &mut file_names)?;
+    file_names.sort();
+    Ok(file_names)
+}
+
+fn add_files_in_folder(
+    folder_path: &str,
+    scan: &FolderScan,
+    top: bool,
+    file_names: &mut Vec<String>,
+) -> std::io::Result<()> {
     let entries = std::fs::read_dir(folder_path)?;
+    let ignore_rules = IgnoreRules::load(std::path::Path::new(folder_path));
 
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
+        let Some(file_name_str) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
         if path.is_file() {
-            if let Some(file_name) = path.file_name() {
-                if let Some(file_name_str) = file_name.to_str() {
-                    if file_name_str.ends_with(".jpg") || file_name_str.ends_with(".png") {
-                        file_names.push(format!("{folder_path}/{file_name_str}"));
-                    }
-                }
+            if is_supported_image(file_name_str) && !ignore_rules.is_ignored(file_name_str, false) {
+                file_names.push(format!("{folder_path}/{file_name_str}"));
+            }
+        } else if scan.recursive
+            // Not through links, a link back up would list the folder again and again
+            && entry.file_type().is_ok_and(|file_type| file_type.is_dir())
+            && !file_name_str.starts_with('.')
+            && !ignore_rules.is_ignored(file_name_str, true)
+            && !(top && scan.skip_dirs.iter().any(|dir| dir == file_name_str))
+        {
+            let subfolder = format!("{folder_path}/{file_name_str}");
+            // An unreadable subfolder shouldn't hide the rest
+            if let Err(e) = add_files_in_folder(&subfolder, scan, false, file_names) {
+                warn!("Could not list {subfolder}: {e}");
             }
         }
     }
-
-    file_names.sort();
-    Ok(file_names)
+    Ok(())
 }
 
 fn preload_images_task(
-    paths: Vec<String>,
+    paths: Vec<(String, bool)>,
     dim: Dim,
     config: Config,
     task_manager: &mut TaskManager,
 ) -> Task<Message> {
     let mut tasks = Vec::new();
-    for path in paths {
+    for (path, full) in paths {
         let config2 = config.clone();
 
         let task = task_manager.start_task(
             TaskType::PreloadImage,
-            |task_id, (a, b, c)| Message::ImagePreloaded(task_id, a, b, c),
-            preload_image_async(path, dim, config2),
+            |task_id, (path, result, decode_time)| {
+                Message::ImagePreloaded(task_id, path, result, decode_time)
+            },
+            preload_image_async(path, dim, full, config2),
         );
 
         tasks.push(task);
@@ -555,56 +3643,141 @@ fn preload_images_task(
     Task::batch(tasks)
 }
 
-async fn preload_image_async(
-    path: String,
-    dim: Dim,
-    config: Config,
-) -> (String, ImageData, ImageData) {
-    tokio::task::spawn_blocking(move || preload_image(path, dim, config))
+type PreloadResult = (
+    String,
+    Result<(Option<ImageData>, ImageData), String>,
+    std::time::Duration,
+);
+
+async fn preload_image_async(path: String, dim: Dim, full: bool, config: Config) -> PreloadResult {
+    tokio::task::spawn_blocking(move || preload_image(path, dim, full, config))
         .await
         .expect("Could not spawn task")
 }
 
-fn preload_image(path: String, dim: Dim, config: Config) -> (String, ImageData, ImageData) {
-    let image = get_resized_image(&path, dim);
-    let thumb = get_resized_image(&path, config.thumbnail_size);
-    (path, image, thumb)
-}
-
-fn get_resized_image(path: &str, dim: Dim) -> ImageData {
-    let mut decoder = ImageReader::open(path).unwrap().into_decoder().unwrap();
-    let orientation = decoder.orientation().unwrap();
-    debug!("Orientation: {orientation:?}");
+fn preload_image(path: String, dim: Dim, full: bool, config: Config) -> PreloadResult {
+    // Throttled before timing the decode
+    throttle::throttle_file(&path);
 
-    let image = DynamicImage::from_decoder(decoder).unwrap();
-    let image = match orientation {
-        image::metadata::Orientation::NoTransforms => image,
-        image::metadata::Orientation::Rotate90 => image.rotate90(),
-        image::metadata::Orientation::Rotate180 => image.rotate180(),
-        image::metadata::Orientation::Rotate270 => image.rotate270(),
-        image::metadata::Orientation::FlipHorizontal => image.fliph(),
-        image::metadata::Orientation::FlipVertical => image.flipv(),
-        image::metadata::Orientation::Rotate90FlipH => image.rotate90().fliph(),
-        image::metadata::Orientation::Rotate270FlipH => image.rotate270().fliph(),
+    let start = std::time::Instant::now();
+    let result = if full {
+        // Decoded once, both sizes are resized from it
+        load_image(&path, dim).map(|decoded| {
+            let mut image = resized_image_data(&decoded, dim);
+            image.histogram = Some(std::sync::Arc::new(histogram::from_rgba(&image.data)));
+            let thumb = thumbnail_image_data(&decoded, &config);
+            (Some(image), thumb)
+        })
+    } else {
+        match embedded_thumbnail(&path, &config) {
+            Some(thumb) => Ok((None, thumb)),
+            None => load_image(&path, config.thumbnail_size)
+                .map(|decoded| (None, thumbnail_image_data(&decoded, &config))),
+        }
     };
+    if let Err(e) = &result {
+        warn!("Could not load {path}: {e}");
+    }
+    let decode_time = start.elapsed();
+    (path, result, decode_time)
+}
+
+fn get_resized_image(path: &str, dim: Dim) -> Result<ImageData, String> {
+    Ok(resized_image_data(&load_image(path, dim)?, dim))
+}
 
+fn resized_image_data(image: &DynamicImage, dim: Dim) -> ImageData {
     let image = image
         .resize(dim.width, dim.height, image::imageops::FilterType::Triangle)
         .to_rgba8();
-    let width = image.width();
-    let height = image.height();
+    ImageData::new(image.width(), image.height(), image.into_raw())
+}
+
+// Square thumbnails are cropped as they are made, so they are as sharp as the
+// whole image ones rather than the middle of one scaled up
+fn thumbnail_image_data(image: &DynamicImage, config: &Config) -> ImageData {
+    let size = config.thumbnail_size;
+    let thumb = match config.thumbnail_shape {
+        ThumbnailShape::Fit => resized_image_data(image, size),
+        ThumbnailShape::Square => {
+            let image = image
+                .resize_to_fill(
+                    size.width,
+                    size.height,
+                    image::imageops::FilterType::Triangle,
+                )
+                .to_rgba8();
+            ImageData::new(image.width(), image.height(), image.into_raw())
+        }
+    };
+    thumb.with_blurred()
+}
 
-    ImageData {
-        data: image.to_vec(),
-        width,
-        height,
+// The thumbnail stored by the camera saves decoding the whole image. It is
+// only used when it is large enough, and when it shows the whole image, some
+// cameras pad it with black bars to another aspect ratio.
+fn embedded_thumbnail(path: &str, config: &Config) -> Option<ImageData> {
+    let embedded = exif_data::embedded_thumbnail(path)?;
+    let mut thumb =
+        image::load_from_memory_with_format(&embedded.jpeg, image::ImageFormat::Jpeg).ok()?;
+    let dim = config.thumbnail_size;
+    let too_small = match config.thumbnail_shape {
+        ThumbnailShape::Fit => thumb.width() < dim.width && thumb.height() < dim.height,
+        ThumbnailShape::Square => thumb.width() < dim.width || thumb.height() < dim.height,
+    };
+    if too_small {
+        return None;
+    }
+    if let Some((width, height)) = embedded.pixel_size {
+        let aspect = |w: u32, h: u32| w as f32 / h.max(1) as f32;
+        if (aspect(thumb.width(), thumb.height()) / aspect(width, height) - 1.0).abs() > 0.02 {
+            return None;
+        }
     }
+    if let Some(orientation) = embedded
+        .orientation
+        .and_then(image::metadata::Orientation::from_exif)
+    {
+        thumb.apply_orientation(orientation);
+    }
+    Some(thumbnail_image_data(&thumb, config))
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// Upright, at least `dim` large when a RAW has a choice of sizes
+fn load_image(path: &str, dim: Dim) -> Result<DynamicImage, String> {
+    // Empty files are common after interrupted copies, give a clearer error
+    if std::fs::metadata(path).map_err(|e| e.to_string())?.len() == 0 {
+        return Err(pathlist::EMPTY_FILE_ERROR.to_owned());
+    }
+    if raw::is_raw(path) {
+        raw::decode_raw(path, dim)
+    } else if video::is_video(path) {
+        video::decode_video(path)
+    } else {
+        decode_image(path)
+    }
+}
+
+fn decode_image(path: &str) -> Result<DynamicImage, String> {
+    let mut decoder = ImageReader::open(path)
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().map_err(|e| e.to_string())?;
+    trace!("Orientation: {orientation:?}");
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    // Both the previews and the thumbnails are made from the upright image
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SortingViewStyle {
     NoThumbnails,
     ThumbsAbove,
+    // Thumbnails of many images above a smaller image
+    Grid,
 }
 
 impl SortingViewStyle {
@@ -612,6 +3785,7 @@ impl SortingViewStyle {
         match self {
             SortingViewStyle::NoThumbnails => "No Thumbnails",
             SortingViewStyle::ThumbsAbove => "Thumbnails Above",
+            SortingViewStyle::Grid => "Grid",
         }
     }
 
@@ -619,6 +3793,7 @@ impl SortingViewStyle {
         vec![
             SortingViewStyle::NoThumbnails,
             SortingViewStyle::ThumbsAbove,
+            SortingViewStyle::Grid,
         ]
     }
 
@@ -627,6 +3802,7 @@ impl SortingViewStyle {
         match name {
             "No Thumbnails" => Some(SortingViewStyle::NoThumbnails),
             "Thumbnails Above" => Some(SortingViewStyle::ThumbsAbove),
+            "Grid" => Some(SortingViewStyle::Grid),
             _ => None,
         }
     }
@@ -637,3 +3813,411 @@ impl std::fmt::Display for SortingViewStyle {
         write!(f, "{}", self.display_name())
     }
 }
+
+// How thumbnails in the strip and the grid fill their cells. Square ones are
+// cropped to the middle, which lines up the grid at the cost of the edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThumbnailShape {
+    Fit,
+    Square,
+}
+
+impl ThumbnailShape {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ThumbnailShape::Fit => "Whole image",
+            ThumbnailShape::Square => "Square crop",
+        }
+    }
+
+    pub fn all_variants() -> Vec<ThumbnailShape> {
+        vec![ThumbnailShape::Fit, ThumbnailShape::Square]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<ThumbnailShape> {
+        Self::all_variants()
+            .into_iter()
+            .find(|shape| shape.display_name() == name)
+    }
+}
+
+// How large previews are decoded relative to the canvas, larger previews stay
+// sharp when zoomed in at the cost of memory and decode time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreviewQuality {
+    Canvas,
+    OneAndAHalf,
+    Double,
+}
+
+impl PreviewQuality {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            PreviewQuality::Canvas => "Canvas size",
+            PreviewQuality::OneAndAHalf => "1.5x canvas size",
+            PreviewQuality::Double => "2x canvas size",
+        }
+    }
+
+    pub fn all_variants() -> Vec<PreviewQuality> {
+        vec![
+            PreviewQuality::Canvas,
+            PreviewQuality::OneAndAHalf,
+            PreviewQuality::Double,
+        ]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<PreviewQuality> {
+        Self::all_variants()
+            .into_iter()
+            .find(|quality| quality.display_name() == name)
+    }
+
+    pub fn decode_size(&self, canvas: Dim) -> Dim {
+        let (numerator, denominator) = match self {
+            PreviewQuality::Canvas => (1, 1),
+            PreviewQuality::OneAndAHalf => (3, 2),
+            PreviewQuality::Double => (2, 1),
+        };
+        Dim {
+            width: canvas.width * numerator / denominator,
+            height: canvas.height * numerator / denominator,
+        }
+    }
+}
+
+// Where files from subfolders end up when moved to a tag folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveLayout {
+    Flatten,
+    Preserve,
+}
+
+impl MoveLayout {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MoveLayout::Flatten => "All in the tag folder",
+            MoveLayout::Preserve => "Keep subfolders",
+        }
+    }
+
+    pub fn all_variants() -> Vec<MoveLayout> {
+        vec![MoveLayout::Flatten, MoveLayout::Preserve]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<MoveLayout> {
+        Self::all_variants()
+            .into_iter()
+            .find(|layout| layout.display_name() == name)
+    }
+}
+
+// What stepping past the last image does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndOfList {
+    Stop,
+    Wrap,
+    FirstUntagged,
+    Summary,
+}
+
+impl EndOfList {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            EndOfList::Stop => "Stay on the last image",
+            EndOfList::Wrap => "Start over from the first",
+            EndOfList::FirstUntagged => "Go to the first untagged",
+            EndOfList::Summary => "Show a summary",
+        }
+    }
+
+    pub fn all_variants() -> Vec<EndOfList> {
+        vec![
+            EndOfList::Stop,
+            EndOfList::Wrap,
+            EndOfList::FirstUntagged,
+            EndOfList::Summary,
+        ]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<EndOfList> {
+        Self::all_variants()
+            .into_iter()
+            .find(|end| end.display_name() == name)
+    }
+}
+
+// How a folder is listed for sorting
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FolderScan {
+    pub recursive: bool,
+    // Subfolders of the listed folder that are left out, the tag folders
+    pub skip_dirs: Vec<String>,
+}
+
+// Guards destructive key bindings against accidental presses during fast culling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DestructiveKeyGuard {
+    Off,
+    DoublePress,
+    Shift,
+}
+
+impl DestructiveKeyGuard {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            DestructiveKeyGuard::Off => "Off",
+            DestructiveKeyGuard::DoublePress => "Double press",
+            DestructiveKeyGuard::Shift => "Hold Shift",
+        }
+    }
+
+    pub fn all_variants() -> Vec<DestructiveKeyGuard> {
+        vec![
+            DestructiveKeyGuard::Off,
+            DestructiveKeyGuard::DoublePress,
+            DestructiveKeyGuard::Shift,
+        ]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<DestructiveKeyGuard> {
+        Self::all_variants()
+            .into_iter()
+            .find(|guard| guard.display_name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Without the settings file, the session or background work
+    fn test_model(paths: &[&str]) -> Model {
+        let (mut model, _) = Model::new(StartupOptions {
+            safe_mode: true,
+            locale: Some("en".to_owned()),
+            ..StartupOptions::default()
+        });
+        model.pathlist = PathList::new(paths.iter().map(|path| path.to_string()).collect());
+        model
+    }
+
+    #[test]
+    fn test_discard_checkpoint_preloads_first_image() {
+        let mut model = test_model(&["./a.jpg", "./b.jpg", "./c.jpg"]);
+        let dim = Dim {
+            width: 800,
+            height: 600,
+        };
+        model.canvas_dimensions = Some(dim);
+        model.pending_recovery = Some(Checkpoint {
+            directory: "/photos".to_owned(),
+            current: Some("./c.jpg".to_owned()),
+            images: Vec::new(),
+            tag_names: model.tag_names.clone(),
+        });
+        match model.update(Message::UserPressedDiscardCheckpoint) {
+            Effect::PreloadImages(paths, preload_dim) => {
+                assert_eq!(paths.first().map(String::as_str), Some("./a.jpg"));
+                assert_eq!(preload_dim, dim);
+            }
+            effect => panic!("Expected a preload, got {effect:?}"),
+        }
+        assert_eq!(model.pending_recovery, None);
+    }
+
+    #[test]
+    fn test_restore_session_sort_order() {
+        let mut model = test_model(&["./a.jpg", "./b.jpg"]);
+        model.saved_session.sort_order = Some(SortOrder::Size);
+        model.restore_session_context();
+        assert_eq!(model.config.sort_order, SortOrder::Size);
+
+        // Sessions from before the order was kept leave it as configured
+        model.saved_session.sort_order = None;
+        model.restore_session_context();
+        assert_eq!(model.config.sort_order, SortOrder::Size);
+    }
+
+    #[test]
+    fn test_position_saved_once_settled() {
+        let mut model = test_model(&["./a.jpg", "./b.jpg", "./c.jpg"]);
+        model.state = ModelState::Sorting;
+        model.safe_mode = false;
+        model.saved_session = model.changed_session(true).unwrap();
+
+        model.pathlist.index = 2;
+        model.last_navigation = std::time::Instant::now();
+        model.session_dirty = true;
+        assert_eq!(model.save_session_if_changed(), Effect::None);
+        assert!(model.session_dirty);
+        // A tag is saved right away, with the position
+        model.pathlist.paths[0].metadata.tag = Some(Tag::new(1));
+        assert!(model.changed_session(false).is_some());
+        model.pathlist.paths[0].metadata.tag = None;
+
+        model.last_navigation = std::time::Instant::now() - POSITION_SAVE_DELAY;
+        assert_ne!(model.save_session_if_changed(), Effect::None);
+        assert_eq!(model.saved_session.current.as_deref(), Some("c.jpg"));
+
+        // Not looked at again until something that is saved may have changed
+        model.pathlist.paths[0].metadata.tag = Some(Tag::new(1));
+        assert!(!Message::SpinnerTick.may_change_session());
+        assert_eq!(model.save_session_if_changed(), Effect::None);
+        model.session_dirty = true;
+        assert_ne!(model.save_session_if_changed(), Effect::None);
+    }
+
+    #[test]
+    fn test_confirm_move_keeps_other_queued_tags() {
+        let mut model = test_model(&["./a.jpg", "./b.jpg"]);
+        model.pathlist.paths[0].metadata.tag = Some(Tag::new(1));
+        model.pathlist.paths[1].metadata.tag = Some(Tag::new(2));
+        model.queued_moves = vec![Tag::new(1), Tag::new(2), Tag::new(3)];
+        model.move_plans = vec![MovePlan {
+            tags: vec![Tag::new(1)],
+            destination: "Red".to_owned(),
+            moves: Vec::new(),
+        }];
+        assert!(matches!(
+            model.update(Message::UserPressedConfirmMove),
+            Effect::MoveThenLs(_)
+        ));
+        // Tag 3 has nothing left to move
+        assert_eq!(model.queued_moves, vec![Tag::new(2)]);
+    }
+
+    #[test]
+    fn test_remove_tag_in_use_asks_first() {
+        let mut model = test_model(&["./a.jpg", "./b.jpg"]);
+        model.state = ModelState::Sorting;
+        model.pathlist.paths[0].metadata.tag = Some(Tag::new(1));
+        let remove = || Message::Sorting(SortingMessage::UserPressedRemoveTag(Tag::new(1)));
+
+        model.update(remove());
+        assert!(model.tag_names.contains(&Tag::new(1)));
+        assert_eq!(model.removing_tag, Some(Tag::new(1)));
+        model.update(remove());
+        assert!(!model.tag_names.contains(&Tag::new(1)));
+        assert_eq!(model.pathlist.paths[0].metadata.tag, None);
+
+        // Without images it goes at once
+        model.update(Message::Sorting(SortingMessage::UserPressedRemoveTag(
+            Tag::new(2),
+        )));
+        assert!(!model.tag_names.contains(&Tag::new(2)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recursive_listing_skips_linked_folders() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.jpg"), "").unwrap();
+        std::fs::write(dir.join("sub/b.jpg"), "").unwrap();
+        std::os::unix::fs::symlink("..", dir.join("sub/loop")).unwrap();
+        let scan = FolderScan {
+            recursive: true,
+            skip_dirs: Vec::new(),
+        };
+        let folder = dir.to_string_lossy().to_string();
+        assert_eq!(
+            get_files_in_folder(&folder, &scan).unwrap(),
+            [format!("{folder}/a.jpg"), format!("{folder}/sub/b.jpg")]
+        );
+    }
+
+    #[test]
+    fn test_safe_mode_skips_reading_files() {
+        // Listed again, the canvas is known
+        let mut model = test_model(&[]);
+        model.state = ModelState::Sorting;
+        model.canvas_dimensions = Some(Dim {
+            width: 800,
+            height: 600,
+        });
+        model.config.sort_order = SortOrder::Size;
+        let effect = model.go_to_sorting_model(vec!["./a.jpg".to_owned()]);
+        assert!(matches!(effect, Effect::PreloadImages(..)), "{effect:?}");
+    }
+
+    #[test]
+    fn test_record_moves() {
+        let mut model = test_model(&["./a.jpg", "./b.jpg"]);
+        model.metadata_db = Some(std::path::PathBuf::from("metadata.db"));
+        model.pathlist.paths[0].metadata.content_hash = Some("hash-a".to_owned());
+        let result = MoveResult {
+            destination: "Keep".to_owned(),
+            destination_path: None,
+            moved_files: vec![file_ops::MovedFile {
+                from: std::path::absolute("./a.jpg").unwrap(),
+                to: std::path::absolute("Keep/a.jpg").unwrap(),
+                replaced: None,
+            }],
+            error: None,
+        };
+        assert_eq!(
+            model.record_moves(&result),
+            Effect::RecordMoves(vec![("hash-a".to_owned(), "Keep".to_owned())])
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_image_data() {
+        let image = DynamicImage::new_rgba8(300, 200);
+        let mut config = Config::default();
+        let thumb = thumbnail_image_data(&image, &config);
+        assert_eq!((thumb.width, thumb.height), (100, 67));
+        config.thumbnail_shape = ThumbnailShape::Square;
+        let thumb = thumbnail_image_data(&image, &config);
+        assert_eq!((thumb.width, thumb.height), (100, 100));
+        let blurred = thumb.blurred.unwrap();
+        assert_eq!((blurred.width, blurred.height), (12, 12));
+    }
+
+    // Guards that a full preload decodes the file once: it should take less
+    // time than decoding it for the preview and again for the thumbnail. Run
+    // with --ignored, timings on a busy machine are not reliable.
+    #[test]
+    #[ignore]
+    fn bench_preload_image_decodes_once() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("large.jpg");
+        image::RgbImage::from_fn(800, 600, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        })
+        .save(&path)
+        .unwrap();
+        let path = path.to_string_lossy().to_string();
+        let config = Config::default();
+        let dim = Dim {
+            width: 400,
+            height: 300,
+        };
+
+        let fastest = |run: &dyn Fn()| {
+            (0..3)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    run();
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let decode_once = fastest(&|| {
+            let (_, result, _) = preload_image(path.clone(), dim, true, config.clone());
+            assert!(result.is_ok());
+        });
+        let decode_twice = fastest(&|| {
+            get_resized_image(&path, dim).unwrap();
+            get_resized_image(&path, config.thumbnail_size).unwrap();
+        });
+        assert!(
+            decode_once < decode_twice,
+            "{decode_once:?} to preload, {decode_twice:?} decoding twice"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use iced::widget::{button, row, text};
+use iced::{Alignment, Element};
+use rust_i18n::t;
+
+use crate::format::format_count;
+use crate::sorting::{Tag, TagNames};
+use crate::{tag_io, ImageInfo, Message};
+
+// Files tagged differently here and in a merged session. Instead of letting
+// one side win, each is shown in turn with both tags, mine on the left and
+// theirs on the right.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeChoice {
+    Mine,
+    Theirs,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub path: String,
+    pub mine: Tag,
+    pub theirs: Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeReview {
+    conflicts: Vec<MergeConflict>,
+    index: usize,
+}
+
+impl MergeReview {
+    pub fn new(conflicts: Vec<MergeConflict>) -> Option<Self> {
+        (!conflicts.is_empty()).then_some(Self {
+            conflicts,
+            index: 0,
+        })
+    }
+
+    pub fn current(&self) -> Option<&MergeConflict> {
+        self.conflicts.get(self.index)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.conflicts.len() - self.index
+    }
+
+    // Moves on to the next conflict, returns the tag to set on the current one
+    pub fn pick(&mut self, choice: MergeChoice) -> Option<(String, Tag)> {
+        let conflict = self.current()?;
+        let tag = match choice {
+            MergeChoice::Mine => conflict.mine,
+            MergeChoice::Theirs => conflict.theirs,
+        };
+        let picked = (conflict.path.clone(), tag);
+        self.index += 1;
+        Some(picked)
+    }
+}
+
+// Splits merged tags, by file name, into the ones that can be applied right
+// away and the ones that disagree with a tag set here
+pub fn find_conflicts(
+    paths: &[ImageInfo],
+    merged: Vec<(String, Tag)>,
+) -> (Vec<(String, Tag)>, Vec<MergeConflict>) {
+    let mut agreed = Vec::new();
+    let mut conflicts = Vec::new();
+    for (file, theirs) in merged {
+        let mine = paths
+            .iter()
+            .find(|info| tag_io::file_name(&info.path) == file)
+            .and_then(|info| Some((info.path.clone(), info.metadata.tag?)));
+        match mine {
+            Some((path, mine)) if mine != theirs => {
+                conflicts.push(MergeConflict { path, mine, theirs })
+            }
+            _ => agreed.push((file, theirs)),
+        }
+    }
+    (agreed, conflicts)
+}
+
+pub fn view_banner<'a>(review: &MergeReview, tag_names: &TagNames) -> Option<Element<'a, Message>> {
+    let conflict = review.current()?;
+    Some(
+        row![
+            text(t!(
+                "Tagged differently, %{count} left",
+                count = format_count(review.remaining())
+            )),
+            button(text(t!(
+                "Mine: %{tag} (←)",
+                tag = tag_names.get(&conflict.mine)
+            )))
+            .on_press(Message::UserPickedMergeTag(MergeChoice::Mine)),
+            button(text(t!(
+                "Theirs: %{tag} (→)",
+                tag = tag_names.get(&conflict.theirs)
+            )))
+            .on_press(Message::UserPickedMergeTag(MergeChoice::Theirs)),
+            button(text(t!("Keep mine for the rest"))).on_press(Message::UserPressedEndMergeReview),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick() {
+        let conflict = |path: &str| MergeConflict {
+            path: path.to_owned(),
+            mine: Tag::new(1),
+            theirs: Tag::new(2),
+        };
+        let mut review = MergeReview::new(vec![conflict("a.jpg"), conflict("b.jpg")]).unwrap();
+        assert_eq!(review.remaining(), 2);
+        assert_eq!(
+            review.pick(MergeChoice::Theirs),
+            Some(("a.jpg".to_owned(), Tag::new(2)))
+        );
+        assert_eq!(
+            review.pick(MergeChoice::Mine),
+            Some(("b.jpg".to_owned(), Tag::new(1)))
+        );
+        assert_eq!(review.current(), None);
+        assert_eq!(review.pick(MergeChoice::Mine), None);
+        assert!(MergeReview::new(Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::warn;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use crate::ignore_rules::IgnoreRules;
+use crate::pathlist::FileIdentity;
+use crate::{is_supported_image, takeout};
+
+// Optional store of per-file metadata shared across folders. Files are keyed
+// by a hash of their content so that copies and moved files are recognised,
+// locations remember the size and modification time a path was hashed at so
+// that reopening a folder doesn't read every file again.
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS files (
+        hash TEXT PRIMARY KEY,
+        capture_time INTEGER,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS locations (
+        path TEXT PRIMARY KEY,
+        hash TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER,
+        last_seen INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS locations_hash ON locations (hash);
+    CREATE TABLE IF NOT EXISTS tag_history (
+        hash TEXT NOT NULL,
+        destination TEXT NOT NULL,
+        folder TEXT NOT NULL,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tag_history_hash ON tag_history (hash);
+";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileHistory {
+    // Other places the same content has been seen, by absolute path
+    pub other_paths: Vec<String>,
+    // Oldest first
+    pub moves: Vec<MoveRecord>,
+}
+
+impl FileHistory {
+    pub fn archived_copy(&self, archive_root: &Path) -> Option<&str> {
+        self.other_paths
+            .iter()
+            .find(|path| Path::new(path).starts_with(archive_root))
+            .map(|path| path.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveRecord {
+    pub destination: String,
+    pub folder: String,
+    pub at: i64,
+}
+
+pub struct MetadataDb {
+    conn: Connection,
+}
+
+impl MetadataDb {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    // The hash stored for the path, if the file hasn't changed since
+    pub fn cached_hash(&self, path: &str, identity: &FileIdentity) -> Option<String> {
+        self.conn
+            .query_row(
+                "SELECT hash FROM locations WHERE path = ?1 AND size = ?2 AND modified IS ?3",
+                params![path, identity.size as i64, unix_time(identity.modified)],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    pub fn record_seen(
+        &self,
+        path: &str,
+        identity: &FileIdentity,
+        hash: &str,
+        capture_time: Option<i64>,
+    ) -> rusqlite::Result<()> {
+        let now = now();
+        self.conn.execute(
+            "INSERT INTO files (hash, capture_time, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?3)
+             ON CONFLICT (hash) DO UPDATE SET
+                 last_seen = ?3,
+                 capture_time = COALESCE(?2, capture_time)",
+            params![hash, capture_time, now],
+        )?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO locations (path, hash, size, modified, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                path,
+                hash,
+                identity.size as i64,
+                unix_time(identity.modified),
+                now
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_move(&self, hash: &str, destination: &str, folder: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO tag_history (hash, destination, folder, at) VALUES (?1, ?2, ?3, ?4)",
+            params![hash, destination, folder, now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn forget_missing_under(&self, root: &str, seen: &HashSet<String>) -> rusqlite::Result<()> {
+        let mut statement = self.conn.prepare("SELECT path FROM locations")?;
+        let missing: Vec<String> = statement
+            .query_map([], |row| row.get(0))?
+            .filter_map(|path: rusqlite::Result<String>| path.ok())
+            .filter(|path| Path::new(path).starts_with(root) && !seen.contains(path))
+            .collect();
+        for path in missing {
+            self.conn
+                .execute("DELETE FROM locations WHERE path = ?1", params![path])?;
+        }
+        Ok(())
+    }
+
+    pub fn history(&self, hash: &str, path: &str) -> rusqlite::Result<FileHistory> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT path FROM locations WHERE hash = ?1 AND path != ?2 ORDER BY path")?;
+        let other_paths = statement
+            .query_map(params![hash, path], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut statement = self.conn.prepare(
+            "SELECT destination, folder, at FROM tag_history WHERE hash = ?1 ORDER BY at, rowid",
+        )?;
+        let moves = statement
+            .query_map(params![hash], |row| {
+                Ok(MoveRecord {
+                    destination: row.get(0)?,
+                    folder: row.get(1)?,
+                    at: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(FileHistory { other_paths, moves })
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("imgsort").join("metadata.sqlite"))
+}
+
+pub fn open_or_warn(path: &Path) -> Option<MetadataDb> {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match MetadataDb::open(path) {
+        Ok(db) => Some(db),
+        Err(e) => {
+            warn!("Could not open metadata database {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+pub fn content_hash(path: &str) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+// Hashes the files that changed since they were last seen and records them,
+// returns the hash and history of each file that could be read. The archive
+// is brought up to date first, so that copies in it show up in the histories.
+// It is all written in one transaction, rather than a sync to disk for every
+// file.
+pub fn index_files(
+    db_path: &Path,
+    archive_root: Option<&Path>,
+    paths: Vec<String>,
+) -> Vec<(String, String, FileHistory)> {
+    let Some(db) = open_or_warn(db_path) else {
+        return Vec::new();
+    };
+    let transaction = match db.conn.unchecked_transaction() {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            warn!("Could not write to the metadata database: {e}");
+            return Vec::new();
+        }
+    };
+    if let Some(root) = archive_root {
+        if let Err(e) = index_archive(&db, root) {
+            warn!("Could not index archive {}: {e}", root.display());
+        }
+    }
+    let mut indexed = Vec::new();
+    for path in paths {
+        let Some((absolute, hash)) = index_file(&db, &path) else {
+            continue;
+        };
+        match db.history(&hash, &absolute) {
+            Ok(history) => indexed.push((path, hash, history)),
+            Err(e) => warn!("Could not read history of {path}: {e}"),
+        }
+    }
+    if let Err(e) = transaction.commit() {
+        warn!("Could not write to the metadata database: {e}");
+    }
+    indexed
+}
+
+// Returns the absolute path and the content hash
+fn index_file(db: &MetadataDb, path: &str) -> Option<(String, String)> {
+    let absolute = std::fs::canonicalize(path).ok()?;
+    let absolute = absolute.to_string_lossy().to_string();
+    let identity = FileIdentity::read(path)?;
+    // Unchanged files keep the capture time they were recorded with
+    let (hash, capture_time) = match db.cached_hash(&absolute, &identity) {
+        Some(hash) => (hash, None),
+        None => match content_hash(path) {
+            Ok(hash) => (
+                hash,
+                takeout::read_capture_time(path).map(|time| time.unix_timestamp()),
+            ),
+            Err(e) => {
+                warn!("Could not hash {path}: {e}");
+                return None;
+            }
+        },
+    };
+    if let Err(e) = db.record_seen(&absolute, &identity, &hash, capture_time) {
+        warn!("Could not record {path} in the metadata database: {e}");
+        return None;
+    }
+    Some((absolute, hash))
+}
+
+// Records every image under the root and forgets the ones that are gone
+fn index_archive(db: &MetadataDb, root: &Path) -> std::io::Result<()> {
+    let root = std::fs::canonicalize(root)?;
+    let mut seen = HashSet::new();
+    let mut dirs = vec![root.clone()];
+    while let Some(dir) = dirs.pop() {
+        let ignore_rules = IgnoreRules::load(&dir);
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type()?.is_dir();
+            // Hidden folders hold quarantined files and other tools' caches
+            if name.starts_with('.') || ignore_rules.is_ignored(&name, is_dir) {
+                continue;
+            }
+            let path = entry.path();
+            if is_dir {
+                dirs.push(path);
+            } else if is_supported_image(&name) {
+                if let Some((absolute, _)) = index_file(db, &path.to_string_lossy()) {
+                    seen.insert(absolute);
+                }
+            }
+        }
+    }
+    db.forget_missing_under(&root.to_string_lossy(), &seen)
+        .map_err(std::io::Error::other)
+}
+
+pub fn record_moves(db_path: &Path, moves: Vec<(String, String)>, folder: String) {
+    let Some(db) = open_or_warn(db_path) else {
+        return;
+    };
+    for (hash, destination) in moves {
+        if let Err(e) = db.record_move(&hash, &destination, &folder) {
+            warn!("Could not record move in the metadata database: {e}");
+        }
+    }
+}
+
+fn unix_time(time: Option<SystemTime>) -> Option<i64> {
+    time.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+}
+
+fn now() -> i64 {
+    unix_time(Some(SystemTime::now())).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(size: u64) -> FileIdentity {
+        FileIdentity {
+            size,
+            modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1000)),
+            inode: None,
+        }
+    }
+
+    #[test]
+    fn test_history_across_folders() {
+        let db = MetadataDb::open(Path::new(":memory:")).unwrap();
+        db.record_seen("/card/a.jpg", &identity(10), "abc", None)
+            .unwrap();
+        db.record_seen("/archive/2024/a.jpg", &identity(10), "abc", Some(5))
+            .unwrap();
+        db.record_seen("/card/b.jpg", &identity(20), "def", None)
+            .unwrap();
+        db.record_move("abc", "Keep", "/card").unwrap();
+
+        let history = db.history("abc", "/card/a.jpg").unwrap();
+        assert_eq!(history.other_paths, vec!["/archive/2024/a.jpg"]);
+        assert_eq!(history.moves.len(), 1);
+        assert_eq!(history.moves[0].destination, "Keep");
+        assert_eq!(
+            db.history("def", "/card/b.jpg").unwrap(),
+            FileHistory::default()
+        );
+    }
+
+    #[test]
+    fn test_cached_hash_only_for_unchanged_file() {
+        let db = MetadataDb::open(Path::new(":memory:")).unwrap();
+        db.record_seen("/card/a.jpg", &identity(10), "abc", None)
+            .unwrap();
+        assert_eq!(
+            db.cached_hash("/card/a.jpg", &identity(10)),
+            Some("abc".to_owned())
+        );
+        assert_eq!(db.cached_hash("/card/a.jpg", &identity(11)), None);
+        assert_eq!(db.cached_hash("/card/b.jpg", &identity(10)), None);
+    }
+
+    #[test]
+    fn test_archived_copy_and_forget_missing() {
+        let db = MetadataDb::open(Path::new(":memory:")).unwrap();
+        db.record_seen("/card/a.jpg", &identity(10), "abc", None)
+            .unwrap();
+        db.record_seen("/archive/2024/a.jpg", &identity(10), "abc", None)
+            .unwrap();
+        let archive = Path::new("/archive");
+        let history = db.history("abc", "/card/a.jpg").unwrap();
+        assert_eq!(history.archived_copy(archive), Some("/archive/2024/a.jpg"));
+        assert_eq!(history.archived_copy(Path::new("/arch")), None);
+
+        // Deleted from the archive since it was indexed
+        db.forget_missing_under("/archive", &HashSet::new())
+            .unwrap();
+        let history = db.history("abc", "/card/a.jpg").unwrap();
+        assert_eq!(history.archived_copy(archive), None);
+        assert_eq!(
+            db.cached_hash("/card/a.jpg", &identity(10)),
+            Some("abc".to_owned())
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use cosmic_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping, SwashCache};
+use image::{imageops, Rgb, RgbImage};
+
+use crate::exif_data;
+use crate::load_image;
+use crate::print::turned;
+use crate::sorting::Dim;
+use crate::tag_io::file_name;
+use crate::timestamps::format_time;
+
+// The pinned image and the current one side by side, with the file name and
+// how each was shot written under it, to send along with "which one do you
+// prefer?". The images are turned as in the sorting view and scaled to the
+// same height.
+
+const HEIGHT: u32 = 1200;
+const MARGIN: u32 = 40;
+const FONT_SIZE: f32 = 26.0;
+const LINE_HEIGHT: f32 = 34.0;
+// The file name, then the capture time and camera, then the exposure
+const CAPTION_LINES: u32 = 3;
+
+pub fn default_file_name(left: &str, right: &str) -> String {
+    format!("{}-vs-{}.png", stem(left), stem(right))
+}
+
+fn stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// Each image with its quarter turns
+pub fn render(images: [(String, u8); 2], file: &Path) -> Result<(), String> {
+    let bound = Dim {
+        width: 2 * HEIGHT,
+        height: HEIGHT,
+    };
+    let mut loaded = Vec::new();
+    for (path, quarter_turns) in &images {
+        let image = load_image(path, bound).map_err(|e| format!("{path}: {e}"))?;
+        let image = turned(image, *quarter_turns);
+        let width = (image.width() as u64 * HEIGHT as u64 / image.height().max(1) as u64) as u32;
+        let image = image
+            .resize_exact(width.max(1), HEIGHT, imageops::FilterType::Lanczos3)
+            .to_rgb8();
+        loaded.push((path, image));
+    }
+
+    let caption_height = (CAPTION_LINES as f32 * LINE_HEIGHT) as u32;
+    let width = MARGIN
+        + loaded
+            .iter()
+            .map(|(_, image)| image.width() + MARGIN)
+            .sum::<u32>();
+    let height = 2 * MARGIN + HEIGHT + MARGIN / 2 + caption_height;
+    let mut montage = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+    let mut writer = TextWriter::new();
+    let mut x = MARGIN;
+    for (path, image) in &loaded {
+        imageops::replace(&mut montage, image, x as i64, MARGIN as i64);
+        writer.write(
+            &mut montage,
+            &caption(path),
+            (x, MARGIN + HEIGHT + MARGIN / 2),
+            (image.width(), caption_height),
+        );
+        x += image.width() + MARGIN;
+    }
+    montage.save(file).map_err(|e| e.to_string())
+}
+
+fn caption(path: &str) -> String {
+    let shot: Vec<String> = [
+        exif_data::read_capture_time(path).map(format_time),
+        exif_data::read_camera(path),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    [
+        Some(file_name(path)),
+        (!shot.is_empty()).then(|| shot.join(", ")),
+        exif_data::read_exposure(path),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+// Black text in the system sans-serif font. Without any fonts installed the
+// montage is written without captions.
+struct TextWriter {
+    fonts: FontSystem,
+    glyphs: SwashCache,
+}
+
+impl TextWriter {
+    fn new() -> Self {
+        Self {
+            fonts: FontSystem::new(),
+            glyphs: SwashCache::new(),
+        }
+    }
+
+    // Lines too long for the width are cut off
+    fn write(&mut self, image: &mut RgbImage, text: &str, at: (u32, u32), size: (u32, u32)) {
+        let mut buffer = Buffer::new(&mut self.fonts, Metrics::new(FONT_SIZE, LINE_HEIGHT));
+        buffer.set_size(&mut self.fonts, Some(size.0 as f32), Some(size.1 as f32));
+        buffer.set_text(
+            &mut self.fonts,
+            text,
+            Attrs::new().family(cosmic_text::Family::SansSerif),
+            Shaping::Advanced,
+        );
+        buffer.shape_until_scroll(&mut self.fonts, false);
+        let (left, top) = (at.0 as i32, at.1 as i32);
+        let (right, bottom) = (left + size.0 as i32, top + size.1 as i32);
+        buffer.draw(
+            &mut self.fonts,
+            &mut self.glyphs,
+            Color::rgb(0, 0, 0),
+            |x, y, w, h, color| {
+                for py in (top + y).max(top)..(top + y + h as i32).min(bottom) {
+                    for px in (left + x).max(left)..(left + x + w as i32).min(right) {
+                        blend(image.get_pixel_mut(px as u32, py as u32), color);
+                    }
+                }
+            },
+        );
+    }
+}
+
+fn blend(pixel: &mut Rgb<u8>, color: Color) {
+    let alpha = color.a() as u32;
+    let [r, g, b] = [color.r(), color.g(), color.b()];
+    for (channel, value) in pixel.0.iter_mut().zip([r, g, b]) {
+        *channel = ((*channel as u32 * (255 - alpha) + value as u32 * alpha) / 255) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        RgbImage::from_pixel(400, 300, Rgb([200, 0, 0]))
+            .save(path("a.png"))
+            .unwrap();
+        RgbImage::from_pixel(300, 300, Rgb([0, 0, 200]))
+            .save(path("b.png"))
+            .unwrap();
+
+        let file = dir.join(default_file_name(&path("a.png"), &path("b.png")));
+        assert!(file.ends_with("a-vs-b.png"));
+        // The first is turned to 300 by 400, both are scaled to the same height
+        render([(path("a.png"), 1), (path("b.png"), 0)], &file).unwrap();
+        let montage = image::open(&file).unwrap();
+        assert_eq!(montage.width(), 3 * MARGIN + HEIGHT * 3 / 4 + HEIGHT);
+    }
+}
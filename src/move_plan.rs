@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{Alignment, Color, Element};
+use rust_i18n::t;
+
+use crate::file_ops::{destination_folder, path_in_destination};
+use crate::format::format_count;
+use crate::sorting::{Tag, TagNames};
+use crate::{ImageInfo, Message, MoveLayout};
+
+// What a move of a tag will do, shown for confirmation before any file is
+// touched. Files whose name is already taken in the destination are renamed
+// unless the user picks otherwise for each of them. Moves can also be queued
+// and done together when the session is finished, one folder at a time.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collision {
+    // A file of that name is in the destination
+    Existing,
+    // Another file of the move ends up with the same name
+    SameName,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionChoice {
+    Rename,
+    Skip,
+    Overwrite,
+}
+
+impl CollisionChoice {
+    // Overwriting another file of the same move would lose it
+    pub fn choices(collision: Collision) -> &'static [CollisionChoice] {
+        match collision {
+            Collision::Existing => &[
+                CollisionChoice::Rename,
+                CollisionChoice::Skip,
+                CollisionChoice::Overwrite,
+            ],
+            Collision::SameName => &[CollisionChoice::Rename, CollisionChoice::Skip],
+        }
+    }
+
+    fn display_name(&self) -> String {
+        match self {
+            CollisionChoice::Rename => t!("Rename").to_string(),
+            CollisionChoice::Skip => t!("Skip").to_string(),
+            CollisionChoice::Overwrite => t!("Overwrite").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedMove {
+    pub from: String,
+    // Inside the destination
+    pub to: PathBuf,
+    pub collision: Option<Collision>,
+    pub choice: CollisionChoice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovePlan {
+    // Tags with the same destination are moved together
+    pub tags: Vec<Tag>,
+    pub destination: String,
+    pub moves: Vec<PlannedMove>,
+}
+
+impl MovePlan {
+    pub fn collisions(&self) -> usize {
+        self.moves
+            .iter()
+            .filter(|planned| planned.collision.is_some())
+            .count()
+    }
+
+    pub fn set_choice(&mut self, index: usize, choice: CollisionChoice) {
+        if let Some(planned) = self.moves.get_mut(index) {
+            if planned
+                .collision
+                .is_some_and(|collision| CollisionChoice::choices(collision).contains(&choice))
+            {
+                planned.choice = choice;
+            }
+        }
+    }
+}
+
+// The tagged files of each destination folder, folders in name order
+pub fn group_by_destination(
+    paths: &[ImageInfo],
+    tags: &[Tag],
+    tag_names: &TagNames,
+) -> BTreeMap<String, (Vec<Tag>, Vec<String>)> {
+    let mut groups: BTreeMap<String, (Vec<Tag>, Vec<String>)> = BTreeMap::new();
+    for &tag in tags {
+        let files: Vec<String> = paths
+            .iter()
+            .filter(|info| info.metadata.tag == Some(tag))
+            .map(|info| info.path.clone())
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+        let (group_tags, group_files) = groups
+            .entry(tag_names.destination(&tag).to_owned())
+            .or_default();
+        group_tags.push(tag);
+        group_files.extend(files);
+    }
+    groups
+}
+
+// Files going to the same subfolder are kept together, in their order
+pub fn plan_moves(files: &[String], destination: &str, layout: MoveLayout) -> Vec<PlannedMove> {
+    let dest_path = Path::new(destination_folder(destination));
+    let mut names = HashSet::new();
+    let mut moves = files
+        .iter()
+        .map(|file| {
+            let to = path_in_destination(destination, file, layout);
+            let collision = if dest_path.join(&to).exists() {
+                Some(Collision::Existing)
+            } else if !names.insert(to.clone()) {
+                Some(Collision::SameName)
+            } else {
+                None
+            };
+            PlannedMove {
+                from: file.clone(),
+                to,
+                collision,
+                choice: CollisionChoice::Rename,
+            }
+        })
+        .collect::<Vec<_>>();
+    moves.sort_by(|a, b| a.to.parent().cmp(&b.to.parent()));
+    moves
+}
+
+pub fn view_move_plans<'a>(plans: &[MovePlan]) -> Element<'a, Message> {
+    let sections = plans.iter().enumerate().map(|(plan_index, plan)| {
+        column![text(t!(
+            "%{count} files will be moved to %{folder}",
+            count = format_count(plan.moves.len()),
+            folder = plan.destination
+        ))
+        .size(20)]
+        .extend(
+            plan.moves
+                .iter()
+                .enumerate()
+                .map(|(i, planned)| view_planned_move(plan, plan_index, i, planned)),
+        )
+        .spacing(2)
+        .into()
+    });
+
+    let collisions: usize = plans.iter().map(MovePlan::collisions).sum();
+    column![]
+        .push_maybe((collisions > 0).then(|| {
+            text(t!(
+                "%{count} names are already taken, choose what to do with each",
+                count = format_count(collisions)
+            ))
+        }))
+        .push(scrollable(column(sections).spacing(15)).height(400))
+        .push(
+            row![
+                button(text(t!("Move"))).on_press(Message::UserPressedConfirmMove),
+                button(text(t!("Cancel"))).on_press(Message::UserPressedCancelMove),
+            ]
+            .spacing(10),
+        )
+        .spacing(10)
+        .into()
+}
+
+fn view_planned_move<'a>(
+    plan: &MovePlan,
+    plan_index: usize,
+    index: usize,
+    planned: &PlannedMove,
+) -> Element<'a, Message> {
+    let destination = Path::new(&plan.destination).join(&planned.to);
+    let line = text(format!(
+        "{} -> {}",
+        crate::display_path(&planned.from),
+        destination.display()
+    ));
+    let Some(collision) = planned.collision else {
+        return line.into();
+    };
+    let choices = CollisionChoice::choices(collision).iter().map(|&choice| {
+        let style = if choice == planned.choice {
+            button::primary
+        } else {
+            button::secondary
+        };
+        button(text(choice.display_name()).size(12))
+            .style(style)
+            .padding([2, 8])
+            .on_press(Message::UserPickedCollisionChoice(
+                plan_index, index, choice,
+            ))
+            .into()
+    });
+    let note = match collision {
+        Collision::Existing => t!("already exists"),
+        Collision::SameName => t!("name used by another file"),
+    };
+    row![
+        line.color(Color::from_rgb(0.8, 0.5, 0.0)),
+        text(note).size(12)
+    ]
+    .extend(choices)
+    .spacing(5)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+// The tags waiting to be moved, until the session is finished
+pub fn view_move_queue<'a>(queued: &[Tag], tag_names: &TagNames) -> Option<Element<'a, Message>> {
+    if queued.is_empty() {
+        return None;
+    }
+    let names = queued
+        .iter()
+        .map(|tag| tag_names.get(tag))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(
+        container(
+            row![
+                text(t!("Waiting to be moved: %{tags}", tags = names)),
+                button(text(t!("Finish session"))).on_press(Message::UserPressedFinishSession),
+                button(text(t!("Clear"))).on_press(Message::UserPressedClearMoveQueue),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        )
+        .padding(10)
+        .into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_moves() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("a.jpg"), "").unwrap();
+        let files = ["./a.jpg", "./b.jpg", "./sub/b.jpg"].map(str::to_owned);
+
+        let plan = plan_moves(&files, &dir.to_string_lossy(), MoveLayout::Flatten);
+        let collisions: Vec<_> = plan.iter().map(|planned| planned.collision).collect();
+        assert_eq!(
+            collisions,
+            [Some(Collision::Existing), None, Some(Collision::SameName)]
+        );
+
+        let mut plan = MovePlan {
+            tags: vec![Tag::new(1)],
+            destination: dir.to_string_lossy().to_string(),
+            moves: plan,
+        };
+        plan.set_choice(2, CollisionChoice::Overwrite);
+        assert_eq!(plan.moves[2].choice, CollisionChoice::Rename);
+        plan.set_choice(0, CollisionChoice::Overwrite);
+        assert_eq!(plan.moves[0].choice, CollisionChoice::Overwrite);
+
+        // Moved one subfolder at a time
+        let files = ["./x/1.jpg", "./2.jpg", "./x/3.jpg"].map(str::to_owned);
+        let plan = plan_moves(&files, &dir.to_string_lossy(), MoveLayout::Preserve);
+        let order: Vec<_> = plan.iter().map(|planned| planned.from.as_str()).collect();
+        assert_eq!(order, ["./2.jpg", "./x/1.jpg", "./x/3.jpg"]);
+    }
+}
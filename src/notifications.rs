@@ -0,0 +1,24 @@
+use iced::Task;
+use log::warn;
+
+use crate::Message;
+
+pub fn notify_task(summary: String, body: String) -> Task<Message> {
+    Task::future(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            notify_rust::Notification::new()
+                .appname("imgsort")
+                .summary(&summary)
+                .body(&body)
+                .show()
+                .map(|_| ())
+        })
+        .await;
+        match result {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => warn!("Could not show desktop notification: {e}"),
+            Err(e) => warn!("Could not spawn notification task: {e}"),
+        }
+    })
+    .discard()
+}
@@ -1,15 +1,93 @@
 use std::cmp::min;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::{
-    sorting::Tag, Config, ImageData, ImageInfo, Metadata, PreloadImage, PRELOAD_IN_FLIGHT,
+    checkpoint::CheckpointImage,
+    image_kind::{kind_matches, ImageKind},
+    image_size::SizeFilter,
+    metadata_db::FileHistory,
+    session::{self, SessionRatings},
+    sorting::{Dim, Tag},
+    tag_io,
+    takeout::SidecarMetadata,
+    Config, ImageData, ImageHeader, ImageInfo, LoadedImageAndThumb, Metadata, PreloadImage,
 };
 use itertools::Itertools;
-use log::debug;
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+
+const SIZE_OUTLIER_FACTOR: u64 = 5;
+pub const EMPTY_FILE_ERROR: &str = "Empty file";
+pub const MAX_RATING: u8 = 5;
+// Neighbours of a hovered thumbnail loaded along with it
+const HINT_RADIUS: usize = 1;
 
 #[derive(Debug)]
 pub struct PathList {
     pub paths: Vec<ImageInfo>,
     pub index: usize,
+    pub filter: ViewFilter,
+    pub size_filter: SizeFilter,
+    pub kind_filter: Option<ImageKind>,
+    // Images a tag is given to all at once
+    pub selected: BTreeSet<usize>,
+    // Where a range selection starts from
+    selection_anchor: Option<usize>,
+    // The thumbnail last hovered, likely the next one clicked
+    hinted: Option<usize>,
+    // Paths of the size outliers, found when the file sizes are read
+    size_outliers: HashSet<String>,
+}
+
+// Which images are stepped through, the others are skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ViewFilter {
+    #[default]
+    All,
+    Untagged,
+    Tag(Tag),
+    // Files found to be empty or that could not be decoded so far
+    Problems,
+}
+
+impl ViewFilter {
+    pub fn matches(&self, info: &ImageInfo) -> bool {
+        match self {
+            ViewFilter::All => true,
+            ViewFilter::Untagged => info.metadata.tag.is_none(),
+            ViewFilter::Tag(tag) => info.metadata.tag == Some(*tag),
+            ViewFilter::Problems => is_problem_file(info),
+        }
+    }
+}
+
+// Empty files and files that could not be decoded
+fn is_problem_file(info: &ImageInfo) -> bool {
+    matches!(info.data, PreloadImage::Failed(_)) || info.metadata.file_size == Some(0)
+}
+
+// Recognises a file after it was renamed, by inode where the platform has one
+// and otherwise by size and modification time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    pub size: u64,
+    pub modified: Option<std::time::SystemTime>,
+    pub inode: Option<u64>,
+}
+
+impl FileIdentity {
+    pub fn read(path: &str) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        #[cfg(unix)]
+        let inode = Some(std::os::unix::fs::MetadataExt::ino(&metadata));
+        #[cfg(not(unix))]
+        let inode = None;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            inode,
+        })
+    }
 }
 
 impl PathList {
@@ -19,26 +97,121 @@ impl PathList {
             .map(|path| ImageInfo {
                 path: path.clone(),
                 data: PreloadImage::NotLoading,
-                metadata: Metadata { tag: None },
+                metadata: Metadata::default(),
             })
             .collect();
-        Self { paths, index: 0 }
+        Self {
+            paths,
+            index: 0,
+            filter: ViewFilter::default(),
+            size_filter: SizeFilter::default(),
+            kind_filter: None,
+            selected: BTreeSet::new(),
+            selection_anchor: None,
+            hinted: None,
+            size_outliers: HashSet::new(),
+        }
+    }
+
+    pub fn toggle_selected(&mut self, index: usize) {
+        if !self.selected.remove(&index) {
+            self.selected.insert(index);
+        }
+        self.selection_anchor = Some(index);
+    }
+
+    // Selects the visible images from the last toggled one, or the current
+    // one, up to `index`
+    pub fn select_range(&mut self, index: usize) {
+        let anchor = self.selection_anchor.unwrap_or(self.index);
+        let range = anchor.min(index)..=anchor.max(index);
+        self.selected
+            .extend(self.visible().into_iter().filter(|i| range.contains(i)));
+        self.selection_anchor = Some(anchor);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected.clear();
+        self.selection_anchor = None;
+    }
+
+    // What a tag key applies to
+    pub fn selected_or_current(&self) -> Vec<usize> {
+        match self.selected.is_empty() {
+            true => vec![self.index],
+            false => self.selected.iter().copied().collect(),
+        }
+    }
+
+    pub fn visible(&self) -> Vec<usize> {
+        self.paths
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| self.shows(info))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // Let through by the tag, size and kind filters
+    fn shows(&self, info: &ImageInfo) -> bool {
+        self.filter.matches(info)
+            && self.size_filter.matches(info)
+            && kind_matches(self.kind_filter, info)
+    }
+
+    pub fn next_visible(&self) -> Option<usize> {
+        (self.index + 1..self.paths.len()).find(|&i| self.shows(&self.paths[i]))
+    }
+
+    pub fn prev_visible(&self) -> Option<usize> {
+        (0..self.index).rev().find(|&i| self.shows(&self.paths[i]))
+    }
+
+    // Position of the current image among the visible ones, and their count
+    pub fn visible_position(&self) -> (usize, usize) {
+        let visible = self.visible();
+        let position = visible.iter().filter(|&&i| i <= self.index).count();
+        (position, visible.len())
+    }
+
+    // The current image with up to `count` visible images on each side
+    pub fn visible_around(&self, count: usize) -> Vec<usize> {
+        let visible = self.visible();
+        let before = visible
+            .iter()
+            .filter(|&&i| i < self.index)
+            .rev()
+            .take(count);
+        let after = visible.iter().filter(|&&i| i > self.index).take(count);
+        let mut around: Vec<usize> = before.copied().collect();
+        around.reverse();
+        around.push(self.index);
+        around.extend(after);
+        around
     }
 
     // Preload order?
-    // cache-size = 100, how many picture are kept in the list, when you scroll past preload limit
+    // memory budget, how much the full previews may take before the furthest are dropped
     // back = 10, how many you start preloading backwards
     // front = 30, how many you start preloading forwards
     // in_flight = 8 (Or number of cores?), how many you preload at the same time
     pub fn get_initial_preload_images(&mut self, config: &Config) -> Vec<String> {
-        let from = self.index.saturating_sub(std::cmp::min(
+        self.get_initial_preload_images_around(self.index, config)
+    }
+
+    pub fn get_initial_preload_images_around(
+        &mut self,
+        index: usize,
+        config: &Config,
+    ) -> Vec<String> {
+        let from = index.saturating_sub(std::cmp::min(
             config.preload_back_num,
-            PRELOAD_IN_FLIGHT / 2,
+            config.io_concurrency / 2,
         ));
         let to = *[
-            self.index + config.preload_front_num + 1,
+            index + config.preload_front_num + 1,
             self.paths.len(),
-            from + PRELOAD_IN_FLIGHT,
+            from + config.io_concurrency,
         ]
         .iter()
         .min()
@@ -46,8 +219,12 @@ impl PathList {
 
         let mut paths = Vec::new();
         for i in from..to {
+            // Images kept from before a re-listing don't need to be loaded again
+            if !self.needs_preload(i, config) {
+                continue;
+            }
             let p = self.paths[i].path.clone();
-            debug!("Setting loading state for index {i}");
+            trace!("Setting loading state for index {i}");
             self.paths[i].data = PreloadImage::Loading(p.clone());
             paths.push(p);
         }
@@ -66,9 +243,10 @@ impl PathList {
         }
 
         self.index += 1;
+        self.hinted = None;
 
         // Check if we've already filled the preload cache size
-        if self.get_counts().loading >= PRELOAD_IN_FLIGHT {
+        if self.get_counts().loading >= config.io_concurrency {
             return None;
         }
 
@@ -87,9 +265,10 @@ impl PathList {
         }
 
         self.index -= 1;
+        self.hinted = None;
 
         // Check if we've already filled the preload cache size
-        if self.get_counts().loading >= PRELOAD_IN_FLIGHT {
+        if self.get_counts().loading >= config.io_concurrency {
             return None;
         }
 
@@ -101,10 +280,10 @@ impl PathList {
             self.index + config.preload_front_num + 1,
             self.paths.len() - 1,
         );
-        debug!("Preloading next right image, up to {max_preload_index}");
+        trace!("Preloading next right image, up to {max_preload_index}");
         for i in self.index..max_preload_index {
-            let e = &mut self.paths[i];
-            if is_not_loading(e) {
+            if self.needs_preload(i, config) {
+                let e = &mut self.paths[i];
                 let p = e.path.clone();
                 e.data = PreloadImage::Loading(p.clone());
                 return Some(p);
@@ -116,10 +295,10 @@ impl PathList {
 
     fn preload_next_left(&mut self, config: &Config) -> Option<String> {
         let min_preload_index = self.index.saturating_sub(config.preload_back_num);
-        debug!("Preloading next left image, up to {min_preload_index}");
+        trace!("Preloading next left image, up to {min_preload_index}");
         for i in (min_preload_index..self.index).rev() {
-            let e = &mut self.paths[i];
-            if is_not_loading(e) {
+            if self.needs_preload(i, config) {
+                let e = &mut self.paths[i];
                 let p = e.path.clone();
                 e.data = PreloadImage::Loading(p.clone());
                 return Some(p);
@@ -129,6 +308,96 @@ impl PathList {
         None
     }
 
+    pub fn resume_preload(&mut self, config: &Config) -> Vec<String> {
+        let mut paths = Vec::new();
+        while self.get_counts().loading < config.io_concurrency {
+            match schedule_next_preload_image_after_one_finished(self, config) {
+                Some(path) => paths.push(path),
+                None => break,
+            }
+        }
+        paths
+    }
+
+    // When idle, keep preloading outwards from the current index past the
+    // configured window, as long as the loaded images fit in the memory budget
+    pub fn get_idle_preload_images(
+        &mut self,
+        config: &Config,
+        memory_budget: usize,
+    ) -> Vec<String> {
+        let mut paths = Vec::new();
+        let (loaded_bytes, loaded_count) = self.loaded_bytes();
+        if loaded_count == 0 {
+            return paths;
+        }
+        let average_bytes = loaded_bytes / loaded_count;
+
+        let mut loading = self.get_counts().loading;
+        while loading < config.io_concurrency
+            && loaded_bytes + (loading + 1) * average_bytes <= memory_budget
+        {
+            match self.nearest_not_loading() {
+                Some(i) => {
+                    let p = self.paths[i].path.clone();
+                    trace!("Idle preloading index {i}");
+                    self.paths[i].data = PreloadImage::Loading(p.clone());
+                    paths.push(p);
+                    loading += 1;
+                }
+                None => break,
+            }
+        }
+        paths
+    }
+
+    // Thumbnail only images are loaded again in full once they come within
+    // the full preview distance
+    fn needs_preload(&self, i: usize, config: &Config) -> bool {
+        match self.paths[i].data {
+            PreloadImage::NotLoading => true,
+            PreloadImage::ThumbnailLoaded(_) => {
+                self.index.abs_diff(i) <= config.preload_full_num || self.is_hinted(i)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn wants_full_preview(&self, path: &str, config: &Config) -> bool {
+        self.paths
+            .iter()
+            .position(|info| info.path == path)
+            .is_none_or(|i| self.index.abs_diff(i) <= config.preload_full_num || self.is_hinted(i))
+    }
+
+    pub fn loaded_bytes(&self) -> (usize, usize) {
+        self.paths
+            .iter()
+            .filter_map(|info| match &info.data {
+                PreloadImage::Loaded(loaded) => {
+                    Some(loaded.image.data.len() + loaded.thumb.data.len())
+                }
+                _ => None,
+            })
+            .fold((0, 0), |(bytes, count), size| (bytes + size, count + 1))
+    }
+
+    fn nearest_not_loading(&self) -> Option<usize> {
+        let forward = self.index..self.paths.len();
+        let rev = (0..self.index).rev();
+        forward
+            .interleave(rev)
+            .find(|&i| is_not_loading(&self.paths[i]))
+    }
+
+    pub fn cancel_loading(&mut self, paths: &[String]) {
+        for info in self.paths.iter_mut() {
+            if is_loading(info) && paths.contains(&info.path) {
+                info.data = PreloadImage::NotLoading;
+            }
+        }
+    }
+
     pub fn get_counts(&self) -> ImageStateCounts {
         ImageStateCounts {
             loaded: self.paths.iter().filter(|image| is_loaded(image)).count(),
@@ -138,24 +407,39 @@ impl PathList {
                 .iter()
                 .filter(|image| is_not_loading(image))
                 .count(),
+            failed: self
+                .paths
+                .iter()
+                .filter(|image| matches!(image.data, PreloadImage::Failed(_)))
+                .count(),
+            thumbnail_only: self
+                .paths
+                .iter()
+                .filter(|image| matches!(image.data, PreloadImage::ThumbnailLoaded(_)))
+                .count(),
         }
     }
 
     pub fn image_preload_complete(
         &mut self,
         path: &str,
-        image: ImageData,
-        thumb: ImageData,
+        result: Result<(Option<ImageData>, ImageData), String>,
         config: &Config,
     ) -> Option<String> {
         if let Some(index) = self.paths.iter().position(|info| info.path == path) {
-            self.paths[index].data =
-                PreloadImage::Loaded(crate::LoadedImageAndThumb { image, thumb });
+            self.paths[index].data = match result {
+                Ok((Some(image), thumb)) => {
+                    PreloadImage::Loaded(LoadedImageAndThumb { image, thumb })
+                }
+                Ok((None, thumb)) => PreloadImage::ThumbnailLoaded(thumb),
+                Err(e) => PreloadImage::Failed(e),
+            };
         }
 
         schedule_next_preload_image_after_one_finished(self, config)
     }
 
+    #[allow(dead_code)] // Used by tests
     pub fn tag_of(&self, path: &str) -> Option<Tag> {
         self.paths
             .iter()
@@ -163,6 +447,474 @@ impl PathList {
             .and_then(|info| info.metadata.tag)
     }
 
+    pub fn apply_dimensions(&mut self, images: &[CheckpointImage]) {
+        let dimensions: HashMap<&str, (u32, u32)> = images
+            .iter()
+            .filter_map(|image| Some((image.path.as_str(), image.dimensions?)))
+            .collect();
+        for info in self.paths.iter_mut() {
+            if let Some(&dim) = dimensions.get(info.path.as_str()) {
+                info.metadata.dimensions = Some(dim);
+            }
+        }
+    }
+
+    pub fn apply_file_identities(&mut self, identities: &[(String, FileIdentity)]) {
+        let identities: HashMap<&str, FileIdentity> = identities
+            .iter()
+            .map(|(path, identity)| (path.as_str(), *identity))
+            .collect();
+        for info in self.paths.iter_mut() {
+            if let Some(&identity) = identities.get(info.path.as_str()) {
+                info.metadata.file_size = Some(identity.size);
+                info.metadata.identity = Some(identity);
+                // Not worth trying to decode
+                if identity.size == 0 && is_not_loading(info) {
+                    info.data = PreloadImage::Failed(EMPTY_FILE_ERROR.to_owned());
+                }
+            }
+        }
+        self.size_outliers = self.find_size_outliers();
+    }
+
+    pub fn apply_image_headers(&mut self, headers: Vec<ImageHeader>) {
+        let headers: HashMap<&str, &ImageHeader> = headers
+            .iter()
+            .map(|header| (header.path.as_str(), header))
+            .collect();
+        for info in self.paths.iter_mut() {
+            if let Some(header) = headers.get(info.path.as_str()) {
+                info.metadata.pixel_size = header.pixel_size;
+                info.metadata.kind = Some(header.kind);
+            }
+        }
+        self.size_outliers = self.find_size_outliers();
+    }
+
+    pub fn apply_sidecars(&mut self, sidecars: Vec<(String, SidecarMetadata)>) {
+        let mut sidecars: HashMap<String, SidecarMetadata> = sidecars.into_iter().collect();
+        for info in self.paths.iter_mut() {
+            if let Some(sidecar) = sidecars.remove(&info.path) {
+                info.metadata.sidecar = Some(sidecar);
+            }
+        }
+    }
+
+    pub fn apply_file_histories(
+        &mut self,
+        indexed: Vec<(String, String, FileHistory)>,
+        archive_root: Option<&std::path::Path>,
+    ) {
+        let mut indexed: HashMap<String, (String, FileHistory)> = indexed
+            .into_iter()
+            .map(|(path, hash, history)| (path, (hash, history)))
+            .collect();
+        for info in self.paths.iter_mut() {
+            if let Some((hash, history)) = indexed.remove(&info.path) {
+                info.metadata.content_hash = Some(hash);
+                info.metadata.archived =
+                    archive_root.is_some_and(|root| history.archived_copy(root).is_some());
+                info.metadata.history = Some(history);
+            }
+        }
+    }
+
+    // Merges a new directory listing into the list. Tags, loaded images and
+    // metadata are kept for files that are still there, files renamed outside
+    // of the program are recognised by their identity.
+    pub fn merge_listing(&mut self, paths: Vec<String>) {
+        let current_path = self.paths.get(self.index).map(|info| info.path.clone());
+        let old_index = self.index;
+        let selected_paths: Vec<String> = self
+            .selected
+            .iter()
+            .filter_map(|&i| Some(self.paths.get(i)?.path.clone()))
+            .collect();
+        let mut old: HashMap<String, ImageInfo> = self
+            .paths
+            .drain(..)
+            .map(|info| (info.path.clone(), info))
+            .collect();
+
+        let mut merged: Vec<Option<ImageInfo>> =
+            paths.iter().map(|path| old.remove(path)).collect();
+
+        // What is left of the old list is gone or renamed
+        let mut renamed_current = None;
+        if !old.is_empty() {
+            for (path, entry) in paths.iter().zip(merged.iter_mut()) {
+                if entry.is_some() {
+                    continue;
+                }
+                let Some(identity) = FileIdentity::read(path) else {
+                    continue;
+                };
+                let old_path = old
+                    .iter()
+                    .find(|(_, info)| info.metadata.identity == Some(identity))
+                    .map(|(old_path, _)| old_path.clone());
+                if let Some(mut info) = old_path.and_then(|old_path| old.remove(&old_path)) {
+                    debug!("{} was renamed to {path}", info.path);
+                    if current_path.as_ref() == Some(&info.path) {
+                        renamed_current = Some(path.clone());
+                    }
+                    info.path = path.clone();
+                    *entry = Some(info);
+                }
+            }
+        }
+
+        self.paths = paths
+            .into_iter()
+            .zip(merged)
+            .map(|(path, entry)| match entry {
+                Some(mut info) => {
+                    // Loads in flight were cancelled along with the listing
+                    if let PreloadImage::Loading(_) = info.data {
+                        info.data = PreloadImage::NotLoading;
+                    }
+                    info
+                }
+                None => ImageInfo {
+                    path,
+                    data: PreloadImage::NotLoading,
+                    metadata: Metadata::default(),
+                },
+            })
+            .collect();
+
+        let current_path = renamed_current.or(current_path);
+        self.index = current_path
+            .and_then(|current| self.paths.iter().position(|info| info.path == current))
+            .unwrap_or(min(old_index, self.paths.len().saturating_sub(1)));
+        self.selected = self
+            .paths
+            .iter()
+            .positions(|info| selected_paths.contains(&info.path))
+            .collect();
+        self.selection_anchor = None;
+        // Moved files no longer count, their sizes are read again later
+        self.size_outliers = self.find_size_outliers();
+    }
+
+    // Into the order of `paths`, the same images in another order. The current
+    // and the selected images stay so.
+    pub fn reorder(&mut self, paths: &[String]) {
+        let current_path = self.paths.get(self.index).map(|info| info.path.clone());
+        let selected_paths: Vec<String> = self
+            .selected
+            .iter()
+            .filter_map(|&i| Some(self.paths.get(i)?.path.clone()))
+            .collect();
+        let mut old: HashMap<String, ImageInfo> = self
+            .paths
+            .drain(..)
+            .map(|info| (info.path.clone(), info))
+            .collect();
+        self.paths = paths.iter().filter_map(|path| old.remove(path)).collect();
+        // Listed again while they were sorted
+        let mut rest: Vec<ImageInfo> = old.into_values().collect();
+        rest.sort_by(|a, b| a.path.cmp(&b.path));
+        self.paths.extend(rest);
+
+        self.index = current_path
+            .and_then(|current| self.paths.iter().position(|info| info.path == current))
+            .unwrap_or(0);
+        self.selected = self
+            .paths
+            .iter()
+            .positions(|info| selected_paths.contains(&info.path))
+            .collect();
+        self.selection_anchor = None;
+        self.hinted = None;
+    }
+
+    pub fn size_outliers(&self) -> Vec<usize> {
+        self.paths
+            .iter()
+            .positions(|info| self.size_outliers.contains(&info.path))
+            .collect()
+    }
+
+    pub fn size_outlier_count(&self) -> usize {
+        self.size_outliers.len()
+    }
+
+    pub fn is_size_outlier(&self, index: usize) -> bool {
+        self.size_outliers.contains(&self.paths[index].path)
+    }
+
+    // Empty files, and files far from the median size of their kind in the
+    // folder, are often broken or exported in the wrong format. Screenshots
+    // are compared among themselves, they are much smaller than photos.
+    fn find_size_outliers(&self) -> HashSet<String> {
+        let mut sizes: HashMap<Option<ImageKind>, Vec<u64>> = HashMap::new();
+        for info in &self.paths {
+            if let Some(size) = info.metadata.file_size.filter(|&size| size > 0) {
+                sizes.entry(info.metadata.kind).or_default().push(size);
+            }
+        }
+        let medians: HashMap<Option<ImageKind>, u64> = sizes
+            .into_iter()
+            .map(|(kind, mut sizes)| {
+                sizes.sort_unstable();
+                (kind, sizes[sizes.len() / 2])
+            })
+            .collect();
+        self.paths
+            .iter()
+            .enumerate()
+            .filter(
+                |(_, info)| match (info.metadata.file_size, medians.get(&info.metadata.kind)) {
+                    (Some(0), _) => true,
+                    (Some(size), Some(&median)) => {
+                        size > median * SIZE_OUTLIER_FACTOR || size * SIZE_OUTLIER_FACTOR < median
+                    }
+                    _ => false,
+                },
+            )
+            .map(|(_, info)| info.path.clone())
+            .collect()
+    }
+
+    pub fn problem_files(&self) -> Vec<usize> {
+        self.paths.iter().positions(is_problem_file).collect()
+    }
+
+    // Moves straight to an image, preloading around it as when opening the folder
+    pub fn jump_to(&mut self, index: usize, config: &Config) -> Vec<String> {
+        if index >= self.paths.len() {
+            return Vec::new();
+        }
+        self.index = index;
+        self.hinted = None;
+        self.get_initial_preload_images(config)
+    }
+
+    // A hovered thumbnail and its neighbours are loaded in full ahead of the
+    // rest, as far as there is room among the loads in flight, and come first
+    // when the next load is scheduled
+    pub fn hint(&mut self, index: usize, config: &Config) -> Vec<String> {
+        if index >= self.paths.len() || self.hinted == Some(index) {
+            return Vec::new();
+        }
+        self.hinted = Some(index);
+        let mut loading = self.get_counts().loading;
+        let mut paths = Vec::new();
+        for i in self.hinted_range() {
+            if loading >= config.io_concurrency {
+                break;
+            }
+            if self.needs_preload(i, config) {
+                let p = self.paths[i].path.clone();
+                trace!("Preloading hinted index {i}");
+                self.paths[i].data = PreloadImage::Loading(p.clone());
+                paths.push(p);
+                loading += 1;
+            }
+        }
+        paths
+    }
+
+    fn hinted_range(&self) -> std::ops::Range<usize> {
+        match self.hinted {
+            Some(hinted) => {
+                hinted.saturating_sub(HINT_RADIUS)..(hinted + HINT_RADIUS + 1).min(self.paths.len())
+            }
+            None => 0..0,
+        }
+    }
+
+    fn is_hinted(&self, i: usize) -> bool {
+        self.hinted_range().contains(&i)
+    }
+
+    // The first visible untagged image after the current one, wrapping around
+    pub fn next_untagged(&self) -> Option<usize> {
+        (self.index + 1..self.paths.len())
+            .chain(0..self.index)
+            .find(|&i| self.paths[i].metadata.tag.is_none() && self.shows(&self.paths[i]))
+    }
+
+    // The first visible image of the next or previous subfolder. Files are
+    // listed by path, so those of a subfolder follow each other.
+    pub fn folder_start(&self, forward: bool) -> Option<usize> {
+        let folder = |i: usize| std::path::Path::new(&self.paths[i].path).parent();
+        let visible = self.visible();
+        let current = folder(self.index);
+        let position = visible.partition_point(|&i| i < self.index);
+        if forward {
+            return visible[position..]
+                .iter()
+                .copied()
+                .find(|&i| folder(i) != current);
+        }
+        let mut start = position;
+        while start > 0 && folder(visible[start - 1]) == current {
+            start -= 1;
+        }
+        let previous = folder(*visible.get(start.checked_sub(1)?)?);
+        while start > 0 && folder(visible[start - 1]) == previous {
+            start -= 1;
+        }
+        Some(visible[start])
+    }
+
+    // Moves to the next of the indices after the current image, wrapping around
+    pub fn jump_to_next_of(&mut self, indices: &[usize], config: &Config) -> Vec<String> {
+        let next = indices
+            .iter()
+            .find(|&&i| i > self.index)
+            .or(indices.first());
+        match next {
+            Some(&i) => {
+                self.index = i;
+                self.resume_preload(config)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    // Drops the full previews furthest from the current image, keeping their
+    // thumbnails, until the rest fit in the memory budget. Those within the
+    // full preview distance are always kept. Returns how many were dropped.
+    pub fn evict_far_previews(&mut self, config: &Config, memory_budget: usize) -> usize {
+        let (mut loaded_bytes, _) = self.loaded_bytes();
+        let mut evicted = 0;
+        while loaded_bytes > memory_budget {
+            let furthest = (0..self.paths.len())
+                .filter(|&i| {
+                    is_loaded(&self.paths[i])
+                        && self.index.abs_diff(i) > config.preload_full_num
+                        && !self.is_hinted(i)
+                })
+                .max_by_key(|&i| self.index.abs_diff(i));
+            let Some(i) = furthest else {
+                break;
+            };
+            let data = std::mem::replace(&mut self.paths[i].data, PreloadImage::NotLoading);
+            if let PreloadImage::Loaded(LoadedImageAndThumb { image, thumb }) = data {
+                loaded_bytes -= image.data.len() + thumb.data.len();
+                self.paths[i].data = PreloadImage::ThumbnailLoaded(thumb);
+            }
+            evicted += 1;
+        }
+        evicted
+    }
+
+    pub fn unload_all(&mut self) {
+        for info in self.paths.iter_mut() {
+            info.data = PreloadImage::NotLoading;
+        }
+    }
+
+    // Unloads previews that would be scaled by more than the tolerance to fit
+    // the decode size, returns how many were unloaded
+    pub fn unload_mismatched(&mut self, decode_size: Dim, tolerance: f32) -> usize {
+        let mut count = 0;
+        for info in self.paths.iter_mut() {
+            if let PreloadImage::Loaded(LoadedImageAndThumb { image, .. }) = &info.data {
+                let scale = f32::min(
+                    decode_size.width as f32 / image.width as f32,
+                    decode_size.height as f32 / image.height as f32,
+                );
+                if scale > 1.0 + tolerance || scale < 1.0 / (1.0 + tolerance) {
+                    info.data = PreloadImage::NotLoading;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // Restores the order, tags and position from a checkpoint. Files that are
+    // not in the checkpoint keep their relative order after the known ones.
+    pub fn apply_checkpoint(&mut self, images: &[CheckpointImage], current: Option<&str>) {
+        let positions: HashMap<&str, usize> = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| (image.path.as_str(), i))
+            .collect();
+        self.paths.sort_by_key(|info| {
+            positions
+                .get(info.path.as_str())
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+        for info in self.paths.iter_mut() {
+            if let Some(&i) = positions.get(info.path.as_str()) {
+                info.metadata.tag = images[i].tag;
+            }
+        }
+        self.index = current
+            .and_then(|current| self.paths.iter().position(|info| info.path == current))
+            .unwrap_or(0);
+    }
+
+    // Tags are matched by file name, returns the number of tagged files
+    pub fn apply_tags(&mut self, tags: &[(String, Tag)]) -> usize {
+        let tags: HashMap<&str, Tag> = tags
+            .iter()
+            .map(|(file, tag)| (file.as_str(), *tag))
+            .collect();
+        let by_file_name = session::by_file_name(tags.keys());
+        let mut count = 0;
+        for info in self.paths.iter_mut() {
+            // Imported and merged tags are by file name
+            let tag = tags
+                .get(crate::display_path(&info.path))
+                .or_else(|| match by_file_name {
+                    true => tags.get(tag_io::file_name(&info.path).as_str()),
+                    false => None,
+                });
+            if let Some(tag) = tag {
+                info.metadata.tag = Some(*tag);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    pub fn apply_ratings(&mut self, ratings: &SessionRatings) {
+        let by_file_name = session::by_file_name(ratings.keys());
+        for info in self.paths.iter_mut() {
+            if let Some(&rating) = session::lookup(ratings, &info.path, by_file_name) {
+                info.metadata.rating = rating.min(MAX_RATING);
+            }
+        }
+    }
+
+    // Pressing the rating the images already have takes it away
+    pub fn rate(&mut self, indices: &[usize], rating: u8) {
+        let rating = match indices
+            .iter()
+            .all(|&i| self.paths[i].metadata.rating == rating)
+        {
+            true => 0,
+            false => rating.min(MAX_RATING),
+        };
+        for &i in indices {
+            self.paths[i].metadata.rating = rating;
+        }
+    }
+
+    // The load in flight for the old path finds no entry when it completes,
+    // so an image that was loading is loaded again under its new path
+    pub fn rename(&mut self, old_path: &str, new_path: &str) -> Vec<String> {
+        let mut preload = Vec::new();
+        if self.size_outliers.remove(old_path) {
+            self.size_outliers.insert(new_path.to_owned());
+        }
+        for info in self.paths.iter_mut().filter(|info| info.path == old_path) {
+            info.path = new_path.to_owned();
+            if let PreloadImage::Loading(_) = info.data {
+                info.data = PreloadImage::Loading(new_path.to_owned());
+                preload.push(new_path.to_owned());
+            }
+        }
+        preload
+    }
+
     pub fn current(&self) -> &ImageInfo {
         &self.paths[self.index]
     }
@@ -170,6 +922,15 @@ impl PathList {
     pub fn current_mut(&mut self) -> &mut ImageInfo {
         &mut self.paths[self.index]
     }
+
+    #[allow(dead_code)] // Used by tests
+    pub fn next(&self) -> Option<&ImageInfo> {
+        self.paths.get(self.index + 1)
+    }
+
+    pub fn prev(&self) -> Option<&ImageInfo> {
+        self.index.checked_sub(1).and_then(|i| self.paths.get(i))
+    }
 }
 
 fn schedule_next_preload_image_after_one_finished(
@@ -177,6 +938,14 @@ fn schedule_next_preload_image_after_one_finished(
     config: &Config,
 ) -> Option<String> {
     // Don't need to check in-flight num here, since one is just completed, leaving a space
+    if let Some(i) = pathlist
+        .hinted_range()
+        .find(|&i| pathlist.needs_preload(i, config))
+    {
+        let path = pathlist.paths[i].path.clone();
+        pathlist.paths[i].data = PreloadImage::Loading(path.clone());
+        return Some(path);
+    }
     let curr = pathlist.index;
 
     let forward = pathlist.paths.iter().enumerate().skip(curr);
@@ -189,11 +958,11 @@ fn schedule_next_preload_image_after_one_finished(
 
     let mut should_preload = None;
     for (i, e) in forward.interleave(rev) {
-        if is_not_loading(e)
+        if pathlist.needs_preload(i, config)
             && i <= curr + config.preload_front_num
             && i >= curr - min(config.preload_back_num, curr)
         {
-            debug!("Setting loading state for index {i}");
+            trace!("Setting loading state for index {i}");
             should_preload = Some((i, e.path.clone()));
             break;
         }
@@ -211,7 +980,6 @@ fn is_loading(image: &ImageInfo) -> bool {
     matches!(image.data, PreloadImage::Loading(_))
 }
 
-#[allow(dead_code)] // For symmetry
 fn is_loaded(image: &ImageInfo) -> bool {
     matches!(image.data, PreloadImage::Loaded(_))
 }
@@ -220,39 +988,98 @@ fn is_not_loading(image: &ImageInfo) -> bool {
     matches!(image.data, PreloadImage::NotLoading)
 }
 
+pub struct ImageStateCounts {
+    pub loaded: usize,
+    pub loading: usize,
+    pub not_loading: usize,
+    pub failed: usize,
+    pub thumbnail_only: usize,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::sorting::Tag;
+    use crate::PRELOAD_IN_FLIGHT;
 
     fn create_test_pathlist(paths: Vec<&str>) -> PathList {
         PathList::new(paths.into_iter().map(|s| s.to_string()).collect())
     }
 
-    const TEST_CONFIG: Config = Config {
-        preload_back_num: 10,
-        preload_front_num: 30,
-        scale_down_size: (800, 100),
-        thumbnail_size: crate::sorting::Dim {
-            width: 100,
-            height: 100,
-        },
-        thumbnail_style: crate::SortingViewStyle::ThumbsAbove,
-    };
-
     fn create_test_config() -> Config {
         Config {
             preload_back_num: 10,
             preload_front_num: 30,
+            preload_full_num: 5,
             scale_down_size: (800, 100),
             thumbnail_size: crate::sorting::Dim {
                 width: 100,
                 height: 100,
             },
             thumbnail_style: crate::SortingViewStyle::ThumbsAbove,
+            thumbnail_shape: crate::ThumbnailShape::Fit,
+            auto_hide_controls: false,
+            preview_quality: crate::PreviewQuality::Canvas,
+            preview_memory_mb: 1024,
+            destructive_key_guard: crate::DestructiveKeyGuard::Off,
+            io_concurrency: PRELOAD_IN_FLIGHT,
+            io_max_bytes_per_sec: 0,
+            keymap: crate::keymap::Keymap::default(),
+            recursive_scan: false,
+            move_layout: crate::MoveLayout::Flatten,
+            xmp_sidecars: false,
+            language: crate::Language::System,
+            sort_order: crate::SortOrder::Name,
+            default_tag_names: Vec::new(),
+            defer_moves: false,
+            print_per_page: 1,
+            slideshow_seconds: 3,
+            end_of_list: crate::EndOfList::Stop,
         }
     }
 
+    #[test]
+    fn test_filter() {
+        let mut pathlist = create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg"]);
+        pathlist.paths[1].metadata.tag = Some(Tag::new(1));
+        pathlist.paths[3].metadata.tag = Some(Tag::new(1));
+        pathlist.filter = ViewFilter::Untagged;
+
+        assert_eq!(pathlist.visible(), vec![0, 2, 4]);
+        assert_eq!(pathlist.next_visible(), Some(2));
+        assert_eq!(pathlist.prev_visible(), None);
+        pathlist.index = 2;
+        assert_eq!(pathlist.visible_position(), (2, 3));
+        assert_eq!(pathlist.visible_around(1), vec![0, 2, 4]);
+
+        pathlist.filter = ViewFilter::Tag(Tag::new(1));
+        assert_eq!(pathlist.next_visible(), Some(3));
+        assert_eq!(pathlist.prev_visible(), Some(1));
+        assert_eq!(pathlist.visible_around(3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut pathlist = create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg"]);
+        assert_eq!(pathlist.selected_or_current(), vec![0]);
+
+        pathlist.toggle_selected(1);
+        pathlist.paths[2].metadata.tag = Some(Tag::new(1));
+        pathlist.filter = ViewFilter::Untagged;
+        // Images hidden by the filter are left out of ranges
+        pathlist.select_range(3);
+        assert_eq!(pathlist.selected_or_current(), vec![1, 3]);
+        pathlist.toggle_selected(1);
+        assert_eq!(pathlist.selected_or_current(), vec![3]);
+
+        // Selected images are followed when the folder changes
+        pathlist.merge_listing(vec!["new.jpg".to_owned(), "d.jpg".to_owned()]);
+        assert_eq!(pathlist.selected_or_current(), vec![1]);
+
+        pathlist.clear_selection();
+        assert!(pathlist.selected.is_empty());
+    }
+
     #[test]
     fn test_current_prev_next() {
         let mut pathlist = create_test_pathlist(vec!["img1.jpg", "img2.jpg", "img3.jpg"]);
@@ -278,7 +1105,7 @@ mod tests {
     #[test]
     fn test_get_initial_preload_images_small_list() {
         let mut pathlist = create_test_pathlist(vec!["img1.jpg", "img2.jpg", "img3.jpg"]);
-        let preload = pathlist.get_initial_preload_images(&TEST_CONFIG);
+        let preload = pathlist.get_initial_preload_images(&create_test_config());
 
         // With small list, should preload all images
         assert_eq!(preload.len(), 3);
@@ -289,7 +1116,7 @@ mod tests {
     fn test_get_list_preloads_finish() {
         let paths: Vec<String> = (0..80).map(|i| format!("img{}.jpg", i)).collect();
         let mut pathlist = PathList::new(paths);
-        let preload = pathlist.get_initial_preload_images(&TEST_CONFIG);
+        let preload = pathlist.get_initial_preload_images(&create_test_config());
 
         // Should be limited by PRELOAD_IN_FLIGHT (8)
         assert_eq!(preload.len(), 8);
@@ -298,7 +1125,7 @@ mod tests {
 
         // Nothing gets scheduled, because too many in flight already
         let next_preload =
-            schedule_next_preload_image_after_one_finished(&mut pathlist, &TEST_CONFIG);
+            schedule_next_preload_image_after_one_finished(&mut pathlist, &create_test_config());
         assert_eq!(next_preload.unwrap(), "img8.jpg");
     }
 
@@ -306,7 +1133,7 @@ mod tests {
     fn test_get_initial_preload_images_large_list() {
         let paths: Vec<String> = (0..20).map(|i| format!("img{}.jpg", i)).collect();
         let mut pathlist = PathList::new(paths);
-        let preload = pathlist.get_initial_preload_images(&TEST_CONFIG);
+        let preload = pathlist.get_initial_preload_images(&create_test_config());
 
         // Should be limited by PRELOAD_IN_FLIGHT (8)
         assert_eq!(preload.len(), 8);
@@ -320,13 +1147,13 @@ mod tests {
         let mut pathlist = PathList::new(paths);
         pathlist.index = 10;
 
-        let preload = pathlist.get_initial_preload_images(&TEST_CONFIG);
+        let preload = pathlist.get_initial_preload_images(&create_test_config());
 
         // Should include some behind (limited by PRELOAD_IN_FLIGHT/2 = 4) and ahead
         assert_eq!(preload.len(), 8);
-        // From index 8 to 15 (8 images total)
-        assert_eq!(preload[0], "img8.jpg");
-        assert_eq!(preload[7], "img15.jpg");
+        // From index 6 to 13 (8 images total)
+        assert_eq!(preload[0], "img6.jpg");
+        assert_eq!(preload[7], "img13.jpg");
     }
 
     #[test]
@@ -339,8 +1166,8 @@ mod tests {
         assert_eq!(pathlist.tag_of("nonexistent.jpg"), None);
 
         // Set a tag
-        pathlist.paths[1].metadata.tag = Some(Tag::Tag2);
-        assert_eq!(pathlist.tag_of("img2.jpg"), Some(Tag::Tag2));
+        pathlist.paths[1].metadata.tag = Some(Tag::new(2));
+        assert_eq!(pathlist.tag_of("img2.jpg"), Some(Tag::new(2)));
         assert_eq!(pathlist.tag_of("img1.jpg"), None);
     }
 
@@ -370,6 +1197,414 @@ mod tests {
         assert_eq!(next, Some("img3.jpg".to_string()));
     }
 
+    #[test]
+    fn test_resume_preload_after_cancel() {
+        let paths: Vec<String> = (0..20).map(|i| format!("img{}.jpg", i)).collect();
+        let mut pathlist = PathList::new(paths);
+        let preload = pathlist.get_initial_preload_images(&create_test_config());
+        pathlist.cancel_loading(&preload);
+        assert_eq!(pathlist.get_counts().loading, 0);
+
+        let resumed = pathlist.resume_preload(&create_test_config());
+        assert_eq!(resumed.len(), PRELOAD_IN_FLIGHT);
+        assert_eq!(resumed[0], "img0.jpg");
+        assert_eq!(pathlist.get_counts().loading, PRELOAD_IN_FLIGHT);
+    }
+
+    fn fake_image(size: u32) -> ImageData {
+        ImageData::new(size, 1, vec![0; size as usize])
+    }
+
+    #[test]
+    fn test_idle_preload_respects_memory_budget() {
+        let paths: Vec<String> = (0..20).map(|i| format!("img{}.jpg", i)).collect();
+        let mut pathlist = PathList::new(paths);
+        pathlist.index = 10;
+        pathlist.image_preload_complete(
+            "img10.jpg",
+            Ok((Some(fake_image(90)), fake_image(10))),
+            &create_test_config(),
+        );
+        pathlist.paths.iter_mut().for_each(|info| {
+            if is_loading(info) {
+                info.data = PreloadImage::NotLoading;
+            }
+        });
+
+        // Room for two more images of 100 bytes each
+        let idle = pathlist.get_idle_preload_images(&create_test_config(), 300);
+        assert_eq!(idle, vec!["img9.jpg", "img11.jpg"]);
+
+        // Nothing fits when the budget is already used up
+        let idle = pathlist.get_idle_preload_images(&create_test_config(), 100);
+        assert!(idle.is_empty());
+    }
+
+    #[test]
+    fn test_evict_far_previews() {
+        let paths: Vec<String> = (0..20).map(|i| format!("img{}.jpg", i)).collect();
+        let mut pathlist = PathList::new(paths);
+        let config = create_test_config();
+        for i in [0, 8, 10, 19] {
+            pathlist.paths[i].data = PreloadImage::Loaded(LoadedImageAndThumb {
+                image: fake_image(90),
+                thumb: fake_image(10),
+            });
+        }
+        pathlist.index = 10;
+
+        // The furthest go first, the ones close by stay even over budget
+        assert_eq!(pathlist.evict_far_previews(&config, 250), 2);
+        assert!(matches!(
+            pathlist.paths[0].data,
+            PreloadImage::ThumbnailLoaded(_)
+        ));
+        assert!(matches!(
+            pathlist.paths[19].data,
+            PreloadImage::ThumbnailLoaded(_)
+        ));
+        assert_eq!(pathlist.evict_far_previews(&config, 0), 0);
+        assert_eq!(pathlist.loaded_bytes(), (200, 2));
+    }
+
+    #[test]
+    fn test_apply_session_in_subfolders() {
+        let mut pathlist = create_test_pathlist(vec!["./sub1/a.jpg", "./sub2/a.jpg"]);
+        assert_eq!(
+            pathlist.apply_tags(&[("sub2/a.jpg".to_owned(), Tag::new(1))]),
+            1
+        );
+        assert_eq!(pathlist.paths[0].metadata.tag, None);
+        assert_eq!(pathlist.paths[1].metadata.tag, Some(Tag::new(1)));
+
+        // Older sessions only have the file name
+        pathlist.apply_ratings(&SessionRatings::from([("a.jpg".to_owned(), 3)]));
+        assert_eq!(pathlist.paths[0].metadata.rating, 3);
+        assert_eq!(pathlist.paths[1].metadata.rating, 3);
+
+        // Newer ones have paths, a.jpg in the folder itself is another image
+        let mut pathlist = create_test_pathlist(vec!["./a.jpg", "./sub/a.jpg"]);
+        pathlist.apply_ratings(&SessionRatings::from([
+            ("a.jpg".to_owned(), 4),
+            ("other/b.jpg".to_owned(), 2),
+        ]));
+        assert_eq!(pathlist.paths[0].metadata.rating, 4);
+        assert_eq!(pathlist.paths[1].metadata.rating, 0);
+        pathlist.apply_tags(&[
+            ("a.jpg".to_owned(), Tag::new(2)),
+            ("other/b.jpg".to_owned(), Tag::new(1)),
+        ]);
+        assert_eq!(pathlist.paths[1].metadata.tag, None);
+    }
+
+    #[test]
+    fn test_apply_checkpoint() {
+        let mut pathlist = create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg", "new.jpg"]);
+        let images = vec![
+            CheckpointImage {
+                path: "c.jpg".to_owned(),
+                tag: Some(Tag::new(1)),
+                dimensions: None,
+            },
+            CheckpointImage {
+                path: "a.jpg".to_owned(),
+                tag: None,
+                dimensions: None,
+            },
+            CheckpointImage {
+                path: "gone.jpg".to_owned(),
+                tag: Some(Tag::new(2)),
+                dimensions: None,
+            },
+            CheckpointImage {
+                path: "b.jpg".to_owned(),
+                tag: Some(Tag::new(3)),
+                dimensions: None,
+            },
+        ];
+        pathlist.apply_checkpoint(&images, Some("a.jpg"));
+
+        let order: Vec<&str> = pathlist.paths.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(order, vec!["c.jpg", "a.jpg", "b.jpg", "new.jpg"]);
+        assert_eq!(pathlist.index, 1);
+        assert_eq!(pathlist.tag_of("c.jpg"), Some(Tag::new(1)));
+        assert_eq!(pathlist.tag_of("b.jpg"), Some(Tag::new(3)));
+        assert_eq!(pathlist.tag_of("new.jpg"), None);
+    }
+
+    #[test]
+    fn test_get_initial_preload_images_around() {
+        let paths: Vec<String> = (0..20).map(|i| format!("img{i}.jpg")).collect();
+        let mut pathlist = PathList::new(paths);
+        let config = create_test_config();
+
+        let preload = pathlist.get_initial_preload_images_around(10, &config);
+        assert!(preload.contains(&"img10.jpg".to_string()));
+        assert!(!preload.contains(&"img0.jpg".to_string()));
+        // The current index is left alone
+        assert_eq!(pathlist.index, 0);
+    }
+
+    #[test]
+    fn test_jump_to() {
+        let paths: Vec<String> = (0..20).map(|i| format!("img{i}.jpg")).collect();
+        let mut pathlist = PathList::new(paths);
+        let config = create_test_config();
+
+        let preload = pathlist.jump_to(15, &config);
+        assert_eq!(pathlist.index, 15);
+        assert!(preload.contains(&"img15.jpg".to_string()));
+        assert!(pathlist.jump_to(20, &config).is_empty());
+        assert_eq!(pathlist.index, 15);
+
+        for i in [16, 17, 19, 0, 1] {
+            pathlist.paths[i].metadata.tag = Some(Tag::new(1));
+        }
+        assert_eq!(pathlist.next_untagged(), Some(18));
+        pathlist.index = 18;
+        // Wraps around to the start
+        assert_eq!(pathlist.next_untagged(), Some(2));
+    }
+
+    #[test]
+    fn test_rate() {
+        let mut pathlist = PathList::new(vec!["a.jpg".to_owned(), "b.jpg".to_owned()]);
+        pathlist.rate(&[0, 1], 3);
+        assert_eq!(pathlist.paths[1].metadata.rating, 3);
+        // Again takes it away
+        pathlist.rate(&[0, 1], 3);
+        assert_eq!(pathlist.paths[0].metadata.rating, 0);
+        pathlist.apply_ratings(&SessionRatings::from([("b.jpg".to_owned(), 9)]));
+        assert_eq!(pathlist.paths[1].metadata.rating, MAX_RATING);
+    }
+
+    #[test]
+    fn test_folder_start() {
+        let paths = ["./a.jpg", "./2023/b.jpg", "./2023/c.jpg", "./2024/d.jpg"];
+        let mut pathlist = PathList::new(paths.iter().map(|path| path.to_string()).collect());
+        assert_eq!(pathlist.folder_start(true), Some(1));
+        assert_eq!(pathlist.folder_start(false), None);
+        pathlist.index = 2;
+        assert_eq!(pathlist.folder_start(true), Some(3));
+        assert_eq!(pathlist.folder_start(false), Some(0));
+        pathlist.index = 3;
+        assert_eq!(pathlist.folder_start(false), Some(1));
+    }
+
+    fn sized(size: u64) -> FileIdentity {
+        FileIdentity {
+            size,
+            modified: None,
+            inode: None,
+        }
+    }
+
+    #[test]
+    fn test_size_outliers() {
+        let mut pathlist =
+            create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg", "d.tif", "e.jpg", "f.jpg"]);
+        pathlist.apply_file_identities(&[
+            ("a.jpg".to_owned(), sized(1000)),
+            ("b.jpg".to_owned(), sized(1200)),
+            ("c.jpg".to_owned(), sized(900)),
+            ("d.tif".to_owned(), sized(50_000)),
+            ("e.jpg".to_owned(), sized(0)),
+        ]);
+        let outliers = pathlist.size_outliers();
+        assert_eq!(outliers, vec![3, 4]);
+        assert_eq!(pathlist.size_outlier_count(), 2);
+        assert!(pathlist.is_size_outlier(3) && !pathlist.is_size_outlier(2));
+        assert_eq!(pathlist.problem_files(), vec![4]);
+        pathlist.filter = ViewFilter::Problems;
+        assert_eq!(pathlist.visible(), vec![4]);
+        pathlist.filter = ViewFilter::All;
+
+        let config = create_test_config();
+        pathlist.jump_to_next_of(&outliers, &config);
+        assert_eq!(pathlist.index, 3);
+        pathlist.jump_to_next_of(&outliers, &config);
+        assert_eq!(pathlist.index, 4);
+        pathlist.jump_to_next_of(&outliers, &config);
+        assert_eq!(pathlist.index, 3);
+
+        // Found once, and still right after the images are put in another order
+        let reversed: Vec<String> = pathlist
+            .paths
+            .iter()
+            .rev()
+            .map(|info| info.path.clone())
+            .collect();
+        pathlist.reorder(&reversed);
+        assert_eq!(pathlist.size_outliers(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_size_outliers_by_kind() {
+        let mut pathlist =
+            create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg", "s1.png", "s2.png"]);
+        pathlist.apply_file_identities(&[
+            ("a.jpg".to_owned(), sized(5_000_000)),
+            ("b.jpg".to_owned(), sized(6_000_000)),
+            ("c.jpg".to_owned(), sized(4_000_000)),
+            ("s1.png".to_owned(), sized(200_000)),
+            ("s2.png".to_owned(), sized(250_000)),
+        ]);
+        assert_eq!(pathlist.size_outliers(), vec![3, 4]);
+
+        // Screenshots are only compared with each other once known
+        let header = |path: &str, kind| ImageHeader {
+            path: path.to_owned(),
+            pixel_size: None,
+            kind,
+        };
+        pathlist.apply_image_headers(vec![
+            header("a.jpg", ImageKind::Photo),
+            header("b.jpg", ImageKind::Photo),
+            header("c.jpg", ImageKind::Photo),
+            header("s1.png", ImageKind::Screenshot),
+            header("s2.png", ImageKind::Screenshot),
+        ]);
+        assert!(pathlist.size_outliers().is_empty());
+    }
+
+    #[test]
+    fn test_merge_listing_keeps_state() {
+        let mut pathlist = create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg"]);
+        pathlist.index = 2;
+        pathlist.paths[0].metadata.tag = Some(Tag::new(2));
+        pathlist.paths[2].data = PreloadImage::Loaded(crate::LoadedImageAndThumb {
+            image: fake_image(100),
+            thumb: fake_image(10),
+        });
+        pathlist.paths[1].data = PreloadImage::Loading("b.jpg".to_owned());
+
+        pathlist.merge_listing(vec![
+            "a.jpg".to_owned(),
+            "c.jpg".to_owned(),
+            "d.jpg".to_owned(),
+        ]);
+
+        let order: Vec<&str> = pathlist.paths.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(order, vec!["a.jpg", "c.jpg", "d.jpg"]);
+        assert_eq!(pathlist.index, 1);
+        assert_eq!(pathlist.tag_of("a.jpg"), Some(Tag::new(2)));
+        assert!(matches!(pathlist.paths[1].data, PreloadImage::Loaded(_)));
+        assert!(is_not_loading(&pathlist.paths[2]));
+    }
+
+    #[test]
+    fn test_merge_listing_follows_rename() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let old_path = dir.join("old.jpg").to_string_lossy().to_string();
+        let new_path = dir.join("new.jpg").to_string_lossy().to_string();
+        std::fs::write(&new_path, b"not really an image").unwrap();
+
+        // The file was read as old.jpg and renamed to new.jpg since
+        let mut pathlist = create_test_pathlist(vec![&old_path]);
+        pathlist.paths[0].metadata.tag = Some(Tag::new(5));
+        pathlist.paths[0].metadata.identity = FileIdentity::read(&new_path);
+
+        pathlist.merge_listing(vec![new_path.clone()]);
+
+        assert_eq!(pathlist.paths[0].path, new_path);
+        assert_eq!(pathlist.tag_of(&new_path), Some(Tag::new(5)));
+    }
+
+    #[test]
+    fn test_unload_mismatched() {
+        let mut pathlist = create_test_pathlist(vec!["a.jpg", "b.jpg", "c.jpg"]);
+        for (info, height) in pathlist.paths.iter_mut().zip([500, 450, 300]) {
+            info.data = PreloadImage::Loaded(LoadedImageAndThumb {
+                image: ImageData::new(800, height, Vec::new()),
+                thumb: fake_image(10),
+            });
+        }
+
+        // Only the wide image would be scaled by more than 15%
+        let decode_size = Dim {
+            width: 1000,
+            height: 460,
+        };
+        assert_eq!(pathlist.unload_mismatched(decode_size, 0.15), 1);
+        assert!(is_not_loading(&pathlist.paths[2]));
+        assert!(!is_not_loading(&pathlist.paths[0]));
+        assert!(!is_not_loading(&pathlist.paths[1]));
+    }
+
+    #[test]
+    fn test_thumbnail_only_loaded_in_full_when_near() {
+        let paths: Vec<String> = (0..10).map(|i| format!("img{i}.jpg")).collect();
+        let mut pathlist = PathList::new(paths);
+        let config = create_test_config();
+        for info in pathlist.paths.iter_mut() {
+            info.data = PreloadImage::ThumbnailLoaded(fake_image(10));
+        }
+        assert!(!pathlist.wants_full_preview("img8.jpg", &config));
+
+        pathlist.index = 5;
+        assert!(pathlist.wants_full_preview("img8.jpg", &config));
+        assert!(pathlist.needs_preload(0, &config));
+
+        pathlist.index = 9;
+        assert!(!pathlist.needs_preload(0, &config));
+        assert!(pathlist.needs_preload(4, &config));
+    }
+
+    #[test]
+    fn test_hint() {
+        let paths: Vec<String> = (0..100).map(|i| format!("img{i}.jpg")).collect();
+        let mut pathlist = PathList::new(paths);
+        let config = create_test_config();
+        for info in pathlist.paths.iter_mut() {
+            info.data = PreloadImage::ThumbnailLoaded(fake_image(10));
+        }
+        assert_eq!(
+            pathlist.hint(80, &config),
+            ["img79.jpg", "img80.jpg", "img81.jpg"]
+        );
+        assert!(pathlist.wants_full_preview("img80.jpg", &config));
+        assert!(pathlist.hint(80, &config).is_empty());
+
+        // Hinted images come first when a load finishes
+        pathlist.paths[81].data = PreloadImage::ThumbnailLoaded(fake_image(10));
+        let next = schedule_next_preload_image_after_one_finished(&mut pathlist, &config);
+        assert_eq!(next.as_deref(), Some("img81.jpg"));
+
+        // Moving on drops the hint
+        pathlist.jump_to(1, &config);
+        assert!(!pathlist.wants_full_preview("img80.jpg", &config));
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut pathlist = create_test_pathlist(vec!["./a.jpg", "./b.jpg"]);
+        pathlist.paths[1].metadata.tag = Some(Tag::new(4));
+        pathlist.rename("./b.jpg", "./c.jpg");
+        assert_eq!(pathlist.paths[1].path, "./c.jpg");
+        assert_eq!(pathlist.tag_of("./c.jpg"), Some(Tag::new(4)));
+        assert_eq!(pathlist.tag_of("./b.jpg"), None);
+    }
+
+    #[test]
+    fn test_rename_while_loading() {
+        let mut pathlist = create_test_pathlist(vec!["./a.jpg", "./b.jpg"]);
+        let config = create_test_config();
+        pathlist.paths[1].data = PreloadImage::Loading("./b.jpg".to_owned());
+        assert_eq!(pathlist.rename("./a.jpg", "./z.jpg"), Vec::<String>::new());
+        assert_eq!(pathlist.rename("./b.jpg", "./c.jpg"), vec!["./c.jpg"]);
+        assert!(matches!(
+            &pathlist.paths[1].data,
+            PreloadImage::Loading(path) if path == "./c.jpg"
+        ));
+
+        // The load for the old path completes first and changes nothing
+        pathlist.image_preload_complete("./b.jpg", Err("gone".to_owned()), &config);
+        assert!(matches!(pathlist.paths[1].data, PreloadImage::Loading(_)));
+        pathlist.image_preload_complete("./c.jpg", Err("bad".to_owned()), &config);
+        assert!(matches!(pathlist.paths[1].data, PreloadImage::Failed(_)));
+    }
+
     #[test]
     fn test_schedule_next_preload_no_loading_images() {
         let mut pathlist = create_test_pathlist(vec!["img1.jpg", "img2.jpg", "img3.jpg"]);
@@ -380,9 +1615,3 @@ mod tests {
         assert_eq!(next, Some("img2.jpg".to_string()));
     }
 }
-
-pub struct ImageStateCounts {
-    pub loaded: usize,
-    pub loading: usize,
-    pub not_loading: usize,
-}
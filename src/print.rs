@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use image::{imageops, DynamicImage, Rgb, RgbImage};
+use log::warn;
+
+use crate::load_image;
+use crate::sorting::Dim;
+
+// Images laid out on A4 pages for the occasional print. The pages are written
+// as PNG files and opened in the system viewer, which has the print dialog.
+// Each image is fitted into its cell upright, as turned in the sorting view.
+
+pub const PER_PAGE_CHOICES: [u32; 4] = [1, 2, 4, 6];
+
+const PRINT_DIR: &str = "imgsort-print";
+// A4 at 150 dpi
+const PAGE: Dim = Dim {
+    width: 1240,
+    height: 1754,
+};
+const MARGIN: u32 = 60;
+const GAP: u32 = 30;
+
+// Columns and rows of the cells on a page
+fn grid(per_page: u32) -> (u32, u32) {
+    match per_page {
+        0 | 1 => (1, 1),
+        2 => (1, 2),
+        3 | 4 => (2, 2),
+        _ => (2, 3),
+    }
+}
+
+// The page files, and the images that could not be read. When there is more
+// than one page the folder is opened instead of a single file.
+pub fn render_pages(
+    images: Vec<(String, u8)>,
+    per_page: u32,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let folder = std::env::temp_dir().join(PRINT_DIR);
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder).map_err(|e| e.to_string())?;
+    }
+    std::fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+
+    let (columns, rows) = grid(per_page);
+    let cell = Dim {
+        width: (PAGE.width - 2 * MARGIN - (columns - 1) * GAP) / columns,
+        height: (PAGE.height - 2 * MARGIN - (rows - 1) * GAP) / rows,
+    };
+    let mut errors = Vec::new();
+    let mut pages = Vec::new();
+    for (number, chunk) in images.chunks((columns * rows) as usize).enumerate() {
+        let mut page = RgbImage::from_pixel(PAGE.width, PAGE.height, Rgb([255, 255, 255]));
+        for (i, (path, quarter_turns)) in chunk.iter().enumerate() {
+            let image = match load_image(path, cell) {
+                Ok(image) => turned(image, *quarter_turns),
+                Err(e) => {
+                    warn!("Could not print {path}: {e}");
+                    errors.push(format!("{path}: {e}"));
+                    continue;
+                }
+            };
+            let image = image
+                .resize(cell.width, cell.height, imageops::FilterType::Lanczos3)
+                .to_rgb8();
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            // Centered in the cell
+            let x = MARGIN + column * (cell.width + GAP) + (cell.width - image.width()) / 2;
+            let y = MARGIN + row * (cell.height + GAP) + (cell.height - image.height()) / 2;
+            imageops::replace(&mut page, &image, x as i64, y as i64);
+        }
+        let file = folder.join(format!("page-{}.png", number + 1));
+        page.save(&file).map_err(|e| e.to_string())?;
+        pages.push(file);
+    }
+    match pages.as_slice() {
+        [page] => Ok((page.clone(), errors)),
+        _ => Ok((folder, errors)),
+    }
+}
+
+pub fn turned(image: DynamicImage, quarter_turns: u8) -> DynamicImage {
+    match quarter_turns % 4 {
+        1 => image.rotate90(),
+        2 => image.rotate180(),
+        3 => image.rotate270(),
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(grid(1), (1, 1));
+        assert_eq!(grid(2), (1, 2));
+        assert_eq!(grid(4), (2, 2));
+        assert_eq!(grid(6), (2, 3));
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use log::debug;
+
+use crate::exif_data;
+use crate::sorting::Dim;
+
+// Camera RAW files. Nearly all of them carry a JPEG preview made by the
+// camera, often at full size, which decodes far faster than the sensor data.
+// The preview is used whenever it is large enough for the requested size,
+// otherwise the sensor data is developed with imagepipe's default pipeline.
+
+pub const RAW_EXTENSIONS: &[&str] = &["arw", "cr2", "dng", "nef", "orf", "pef", "raf", "rw2"];
+
+pub fn is_raw(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| RAW_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+pub fn decode_raw(path: &str, dim: Dim) -> Result<DynamicImage, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let preview = largest_embedded_jpeg(&bytes)
+        .and_then(|jpeg| image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg).ok())
+        .map(|mut preview| {
+            // The orientation is only recorded for the RAW itself
+            let orientation = exif_data::read_orientation(path)
+                .and_then(Orientation::from_exif)
+                .unwrap_or(Orientation::NoTransforms);
+            preview.apply_orientation(orientation);
+            preview
+        });
+    match preview {
+        Some(preview) if preview.width() >= dim.width || preview.height() >= dim.height => {
+            Ok(preview)
+        }
+        preview => {
+            debug!("Developing {path}, the embedded preview is too small");
+            match (develop(path, dim), preview) {
+                (Ok(image), _) => Ok(image),
+                // A small preview is better than nothing
+                (Err(_), Some(preview)) => Ok(preview),
+                (Err(e), None) => Err(e),
+            }
+        }
+    }
+}
+
+fn develop(path: &str, dim: Dim) -> Result<DynamicImage, String> {
+    let image = imagepipe::simple_decode_8bit(path, dim.width as usize, dim.height as usize)?;
+    RgbImage::from_raw(image.width as u32, image.height as u32, image.data)
+        .map(DynamicImage::ImageRgb8)
+        .ok_or_else(|| format!("Unexpected image size from {path}"))
+}
+
+fn largest_embedded_jpeg(bytes: &[u8]) -> Option<&[u8]> {
+    let mut largest: Option<&[u8]> = None;
+    let mut pos = 0;
+    while let Some(offset) = bytes[pos..]
+        .windows(3)
+        .position(|window| window == [0xFF, 0xD8, 0xFF])
+    {
+        let start = pos + offset;
+        match jpeg_end(bytes, start) {
+            Some(end) => {
+                let jpeg = &bytes[start..end];
+                if largest.is_none_or(|largest| jpeg.len() > largest.len()) {
+                    largest = Some(jpeg);
+                }
+                pos = end;
+            }
+            None => pos = start + 2,
+        }
+    }
+    largest
+}
+
+// Follows the segments of a JPEG starting at `start`, returns the position
+// just past its end marker
+fn jpeg_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start + 2;
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        // Any number of fill bytes can come before a marker
+        while *bytes.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        pos += 2;
+        match marker {
+            0xD9 => return Some(pos),
+            // Markers without a length
+            0x01 | 0xD0..=0xD7 => (),
+            _ => {
+                let length = u16::from_be_bytes([*bytes.get(pos)?, *bytes.get(pos + 1)?]);
+                pos += length as usize;
+                if marker == 0xDA {
+                    pos = entropy_coded_end(bytes, pos)?;
+                }
+            }
+        }
+    }
+}
+
+// Compressed data runs until a marker other than a stuffed zero or a restart
+fn entropy_coded_end(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        pos += bytes.get(pos..)?.iter().position(|&byte| byte == 0xFF)?;
+        match *bytes.get(pos + 1)? {
+            0x00 | 0xD0..=0xD7 => pos += 2,
+            _ => return Some(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_jpeg(payload_len: usize) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // An APP1 segment with a start marker inside, as in EXIF thumbnails
+        jpeg.extend([0xFF, 0xE1, 0x00, 0x05, 0xFF, 0xD8, 0xFF]);
+        jpeg.extend([0xFF, 0xDA, 0x00, 0x02]);
+        jpeg.extend(
+            (0..payload_len)
+                .map(|i| if i % 7 == 0 { 0xFF } else { 0x10 })
+                .flat_map(|byte| {
+                    if byte == 0xFF {
+                        vec![0xFF, 0x00]
+                    } else {
+                        vec![byte]
+                    }
+                }),
+        );
+        jpeg.extend([0xFF, 0xD0, 0x20, 0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_largest_embedded_jpeg() {
+        let small = fake_jpeg(10);
+        let large = fake_jpeg(100);
+        let mut raw = b"II*\0 header".to_vec();
+        raw.extend(&small);
+        raw.extend([0xFF, 0xD8, 0xFF, 0x00, 0x12]);
+        raw.extend(&large);
+        raw.extend(b"sensor data");
+
+        assert_eq!(largest_embedded_jpeg(&raw), Some(large.as_slice()));
+        assert_eq!(largest_embedded_jpeg(b"no preview"), None);
+    }
+
+    #[test]
+    fn test_is_raw() {
+        assert!(is_raw("IMG_0001.CR2"));
+        assert!(is_raw("DSC_0001.nef"));
+        assert!(!is_raw("IMG_0001.jpg"));
+        assert!(!is_raw("cr2"));
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::pathlist::ViewFilter;
+use crate::sort_order::SortOrder;
+use crate::sorting::{Tag, TagNames};
+use crate::{SortingViewStyle, TabId};
+
+// Tag assignments kept next to the images, so that reopening a folder
+// continues where the last session left off. Unlike the checkpoint this is
+// kept after a clean exit, and is only removed when there is nothing to keep.
+// Along with the tags, the tab, filter, view style, order and image that were
+// in use are restored. The last folder sorted is kept apart, for --resume.
+
+pub const SESSION_FILE: &str = ".imgsort-session.json";
+
+// Path in the folder to tag. Older files have only the file name, which is
+// the same unless subfolders are scanned.
+pub type SessionTags = BTreeMap<String, Tag>;
+// Path in the folder to stars, unrated files are left out
+pub type SessionRatings = BTreeMap<String, u8>;
+
+pub fn key(path: &str) -> String {
+    crate::display_path(path).to_owned()
+}
+
+// Keys written by older versions, and imported ones, are file names. Those
+// are only tried when none of the keys is a path, else sub/a.jpg would get
+// what was kept for a.jpg.
+pub fn by_file_name(keys: impl IntoIterator<Item = impl AsRef<str>>) -> bool {
+    !keys.into_iter().any(|key| key.as_ref().contains('/'))
+}
+
+pub fn lookup<'a, V>(
+    entries: &'a BTreeMap<String, V>,
+    path: &str,
+    by_file_name: bool,
+) -> Option<&'a V> {
+    entries
+        .get(crate::display_path(path))
+        .or_else(|| match by_file_name {
+            true => entries.get(&crate::tag_io::file_name(path)),
+            false => None,
+        })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "SessionFile")]
+pub struct Session {
+    pub tags: SessionTags,
+    pub tag_names: Option<TagNames>,
+    pub tab: Option<TabId>,
+    pub filter: ViewFilter,
+    pub view_style: Option<SortingViewStyle>,
+    pub sort_order: Option<SortOrder>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ratings: SessionRatings,
+    // Path of the image shown, left out while on the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+}
+
+// Older session files only had the tags
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SessionFile {
+    Current {
+        tags: SessionTags,
+        #[serde(default)]
+        tag_names: Option<TagNames>,
+        #[serde(default)]
+        tab: Option<TabId>,
+        #[serde(default)]
+        filter: ViewFilter,
+        #[serde(default)]
+        view_style: Option<SortingViewStyle>,
+        #[serde(default)]
+        sort_order: Option<SortOrder>,
+        #[serde(default)]
+        ratings: SessionRatings,
+        #[serde(default)]
+        current: Option<String>,
+    },
+    Tags(SessionTags),
+}
+
+impl From<SessionFile> for Session {
+    fn from(file: SessionFile) -> Self {
+        match file {
+            SessionFile::Current {
+                tags,
+                tag_names,
+                tab,
+                filter,
+                view_style,
+                sort_order,
+                ratings,
+                current,
+            } => Self {
+                tags,
+                tag_names,
+                tab,
+                filter,
+                view_style,
+                sort_order,
+                ratings,
+                current,
+            },
+            SessionFile::Tags(tags) => Self {
+                tags,
+                ..Self::default()
+            },
+        }
+    }
+}
+
+impl Session {
+    // Without tags only a filter or another tab is worth keeping. The place in
+    // the folder is kept along with those, but browsing alone leaves no file.
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.ratings.is_empty()
+            && self.filter == ViewFilter::All
+            && matches!(self.tab, None | Some(TabId::Main))
+    }
+}
+
+fn session_path(directory: &Path) -> PathBuf {
+    directory.join(SESSION_FILE)
+}
+
+fn last_folder_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("imgsort").join("last-folder"))
+}
+
+pub fn save_last_folder(directory: &Path) -> std::io::Result<()> {
+    let Some(path) = last_folder_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, directory.to_string_lossy().as_bytes())?;
+    debug!("Wrote last folder to {}", path.display());
+    Ok(())
+}
+
+// Unless it has been removed since
+pub fn last_folder() -> Option<PathBuf> {
+    let directory = PathBuf::from(std::fs::read_to_string(last_folder_path()?).ok()?);
+    directory.is_dir().then_some(directory)
+}
+
+// Sessions are written in the background, several can be on their way at
+// once. They are numbered when made and written one at a time, so an older
+// one finishing last doesn't replace a newer one.
+static NEXT_WRITE: AtomicU64 = AtomicU64::new(0);
+static LAST_WRITTEN: Mutex<BTreeMap<PathBuf, u64>> = Mutex::new(BTreeMap::new());
+
+pub fn next_write() -> u64 {
+    NEXT_WRITE.fetch_add(1, Ordering::Relaxed)
+}
+
+pub fn save_in_order(directory: &Path, session: &Session, write: u64) -> std::io::Result<()> {
+    let mut last_written = LAST_WRITTEN.lock().unwrap_or_else(|e| e.into_inner());
+    if last_written
+        .get(directory)
+        .is_some_and(|&last| last > write)
+    {
+        debug!("Skipped an older session write for {}", directory.display());
+        return Ok(());
+    }
+    save(directory, session)?;
+    last_written.insert(directory.to_owned(), write);
+    Ok(())
+}
+
+pub fn save(directory: &Path, session: &Session) -> std::io::Result<()> {
+    let path = session_path(directory);
+    if session.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    let json = serde_json::to_string_pretty(session)?;
+
+    // Write to a temporary file first so a crash mid-write can't corrupt it
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)?;
+    std::fs::rename(tmp_path, &path)?;
+    debug!("Wrote session to {}", path.display());
+    Ok(())
+}
+
+pub fn load(directory: &Path) -> Session {
+    let Ok(json) = std::fs::read_to_string(session_path(directory)) else {
+        return Session::default();
+    };
+    match serde_json::from_str(&json) {
+        Ok(session) => session,
+        Err(e) => {
+            warn!("Could not parse session file: {e}");
+            Session::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let tags = SessionTags::from([
+            ("a.jpg".to_owned(), Tag::new(1)),
+            ("b.jpg".to_owned(), Tag::new(7)),
+        ]);
+        let session = Session {
+            tags: tags.clone(),
+            tag_names: Some(TagNames::new()),
+            tab: Some(TabId::Actions),
+            filter: ViewFilter::Tag(Tag::new(7)),
+            view_style: Some(SortingViewStyle::Grid),
+            sort_order: Some(SortOrder::Size),
+            ratings: SessionRatings::from([("a.jpg".to_owned(), 4)]),
+            current: Some("b.jpg".to_owned()),
+        };
+
+        save(dir, &session).unwrap();
+        assert_eq!(load(dir), session);
+
+        // Files from before the rest was kept
+        std::fs::write(session_path(dir), serde_json::to_string(&tags).unwrap()).unwrap();
+        assert_eq!(load(dir).tags, tags);
+
+        // Nothing tagged anymore, the file is removed
+        save(dir, &Session::default()).unwrap();
+        assert!(!session_path(dir).exists());
+        assert!(load(dir).is_empty());
+
+        // Only looked around in, no file is left behind
+        let browsed = Session {
+            current: Some("b.jpg".to_owned()),
+            ..Session::default()
+        };
+        save(dir, &browsed).unwrap();
+        assert!(!session_path(dir).exists());
+    }
+
+    #[test]
+    fn test_save_in_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let session = |number| Session {
+            tags: SessionTags::from([("a.jpg".to_owned(), Tag::new(number))]),
+            ..Session::default()
+        };
+        let (older, newer) = (next_write(), next_write());
+        save_in_order(dir, &session(2), newer).unwrap();
+        save_in_order(dir, &session(1), older).unwrap();
+        assert_eq!(load(dir), session(2));
+    }
+}
@@ -1,8 +1,15 @@
-use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::widget::{button, checkbox, column, pick_list, row, text, text_input};
 use iced::Element;
 use std::collections::HashMap;
 
-use crate::{Config, Effect, Message, SortingViewStyle};
+use crate::keymap::{format_keys, parse_keys, KeyAction, Keymap};
+use crate::language::Language;
+use crate::sort_order::SortOrder;
+use crate::sorting::TagNames;
+use crate::{
+    print, throttle, Config, DestructiveKeyGuard, Effect, EndOfList, Message, MoveLayout,
+    PreviewQuality, SortingViewStyle, ThumbnailShape,
+};
 use rust_i18n::t;
 
 #[derive(Debug, Clone)]
@@ -20,16 +27,40 @@ pub enum SettingsMessage {
 pub enum SettingsFieldName {
     PreloadBackNum,
     PreloadFrontNum,
+    PreloadFullNum,
+    PreviewMemoryMb,
     ScaleDownSizeWidth,
     ScaleDownSizeHeight,
-    Tag1Shortcut,
+    Shortcut(KeyAction),
+    DestructiveKeyGuard,
     ViewStyle,
+    ThumbnailShape,
+    AutoHideControls,
+    PreviewQuality,
+    IoConcurrency,
+    IoMaxMegabytesPerSec,
+    RecursiveScan,
+    MoveLayout,
+    DeferMoves,
+    XmpSidecars,
+    PrintPerPage,
+    SlideshowSeconds,
+    DefaultTagNames,
+    EndOfList,
+    Language,
+    SortOrder,
 }
 
 impl SettingsModel {
     pub fn new(config: &Config) -> Self {
+        let shortcuts = config.keymap.actions().map(|action| {
+            (
+                SettingsFieldName::Shortcut(action),
+                (format_keys(config.keymap.keys(action)), String::from("")),
+            )
+        });
         Self {
-            fields: HashMap::from_iter([
+            fields: [
                 (
                     SettingsFieldName::PreloadBackNum,
                     (config.preload_back_num.to_string(), String::from("")),
@@ -38,6 +69,14 @@ impl SettingsModel {
                     SettingsFieldName::PreloadFrontNum,
                     (config.preload_front_num.to_string(), String::from("")),
                 ),
+                (
+                    SettingsFieldName::PreloadFullNum,
+                    (config.preload_full_num.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::PreviewMemoryMb,
+                    (config.preview_memory_mb.to_string(), String::from("")),
+                ),
                 (
                     SettingsFieldName::ScaleDownSizeWidth,
                     (config.scale_down_size.0.to_string(), String::from("")),
@@ -47,8 +86,11 @@ impl SettingsModel {
                     (config.scale_down_size.1.to_string(), String::from("")),
                 ),
                 (
-                    SettingsFieldName::Tag1Shortcut,
-                    ("a".to_owned(), String::from("")),
+                    SettingsFieldName::DestructiveKeyGuard,
+                    (
+                        config.destructive_key_guard.display_name().to_owned(),
+                        String::from(""),
+                    ),
                 ),
                 (
                     SettingsFieldName::ViewStyle,
@@ -57,10 +99,114 @@ impl SettingsModel {
                         String::from(""),
                     ),
                 ),
-            ]),
+                (
+                    SettingsFieldName::ThumbnailShape,
+                    (
+                        config.thumbnail_shape.display_name().to_owned(),
+                        String::from(""),
+                    ),
+                ),
+                (
+                    SettingsFieldName::AutoHideControls,
+                    (config.auto_hide_controls.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::PreviewQuality,
+                    (
+                        config.preview_quality.display_name().to_owned(),
+                        String::from(""),
+                    ),
+                ),
+                (
+                    SettingsFieldName::IoConcurrency,
+                    (config.io_concurrency.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::RecursiveScan,
+                    (config.recursive_scan.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::SlideshowSeconds,
+                    (config.slideshow_seconds.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::DefaultTagNames,
+                    (config.default_tag_names.join(", "), String::from("")),
+                ),
+                (
+                    SettingsFieldName::PrintPerPage,
+                    (config.print_per_page.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::DeferMoves,
+                    (config.defer_moves.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::XmpSidecars,
+                    (config.xmp_sidecars.to_string(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::EndOfList,
+                    (
+                        config.end_of_list.display_name().to_owned(),
+                        String::from(""),
+                    ),
+                ),
+                (
+                    SettingsFieldName::MoveLayout,
+                    (
+                        config.move_layout.display_name().to_owned(),
+                        String::from(""),
+                    ),
+                ),
+                (
+                    SettingsFieldName::SortOrder,
+                    (
+                        config.sort_order.display_name().to_owned(),
+                        String::from(""),
+                    ),
+                ),
+                (
+                    SettingsFieldName::Language,
+                    (config.language.display_name().to_owned(), String::from("")),
+                ),
+                (
+                    SettingsFieldName::IoMaxMegabytesPerSec,
+                    (
+                        (config.io_max_bytes_per_sec / 1_000_000).to_string(),
+                        String::from(""),
+                    ),
+                ),
+            ]
+            .into_iter()
+            .chain(shortcuts)
+            .collect(),
         }
     }
 
+    // A view style restored with a folder is shown as the current one
+    pub fn show_view_style(&mut self, style: &SortingViewStyle) {
+        self.fields.insert(
+            SettingsFieldName::ViewStyle,
+            (style.display_name().to_owned(), String::from("")),
+        );
+    }
+
+    // As chosen above the images
+    pub fn show_sort_order(&mut self, order: SortOrder) {
+        self.fields.insert(
+            SettingsFieldName::SortOrder,
+            (order.display_name().to_owned(), String::from("")),
+        );
+    }
+
+    pub fn has_unsaved_changes(&self, config: &Config) -> bool {
+        let saved = Self::new(config);
+        self.fields
+            .iter()
+            .any(|(field, (text, _))| saved.fields.get(field).map(|(saved, _)| saved) != Some(text))
+    }
+
     pub fn update(&mut self, message: SettingsMessage, config: &mut Config) -> Effect {
         match message {
             SettingsMessage::UserUpdatedField(field, text) => {
@@ -84,6 +230,22 @@ impl SettingsModel {
                     Ok(num) => config.preload_front_num = num,
                     Err(_) => *error = "Invalid number".to_owned(),
                 }
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::PreloadFullNum)
+                    .unwrap();
+                match text.parse() {
+                    Ok(num) => config.preload_full_num = num,
+                    Err(_) => *error = "Invalid number".to_owned(),
+                }
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::PreviewMemoryMb)
+                    .unwrap();
+                match text.parse() {
+                    Ok(num) => config.preview_memory_mb = num,
+                    Err(_) => *error = "Invalid number".to_owned(),
+                }
                 let (text, error) = self
                     .fields
                     .get_mut(&SettingsFieldName::ScaleDownSizeWidth)
@@ -100,35 +262,215 @@ impl SettingsModel {
                     Ok(num) => config.scale_down_size.1 = num,
                     Err(_) => *error = "Invalid number".to_owned(),
                 }
+                self.save_keymap(&mut config.keymap);
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::DestructiveKeyGuard)
+                    .unwrap();
+                match DestructiveKeyGuard::from_display_name(text) {
+                    Some(guard) => config.destructive_key_guard = guard,
+                    None => *error = "Invalid key guard".to_owned(),
+                }
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::SlideshowSeconds)
+                    .unwrap();
+                match text.parse() {
+                    Ok(seconds) if seconds > 0 => config.slideshow_seconds = seconds,
+                    _ => *error = "Invalid number".to_owned(),
+                }
+                let (text, _) = self
+                    .fields
+                    .get(&SettingsFieldName::DefaultTagNames)
+                    .unwrap();
+                config.default_tag_names = text
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::PrintPerPage)
+                    .unwrap();
+                match text.parse() {
+                    Ok(per_page) if print::PER_PAGE_CHOICES.contains(&per_page) => {
+                        config.print_per_page = per_page
+                    }
+                    _ => *error = "Invalid choice".to_owned(),
+                }
+                let (text, error) = self.fields.get_mut(&SettingsFieldName::Language).unwrap();
+                match Language::from_display_name(text) {
+                    Some(language) => config.language = language,
+                    None => *error = "Invalid choice".to_owned(),
+                }
+                let (text, error) = self.fields.get_mut(&SettingsFieldName::EndOfList).unwrap();
+                match EndOfList::from_display_name(text) {
+                    Some(end) => config.end_of_list = end,
+                    None => *error = "Invalid choice".to_owned(),
+                }
                 let (view_style_text, view_style_error) =
                     self.fields.get_mut(&SettingsFieldName::ViewStyle).unwrap();
                 match SortingViewStyle::from_display_name(view_style_text) {
                     Some(style) => config.thumbnail_style = style,
                     None => *view_style_error = "Invalid view style".to_owned(),
                 }
-                Effect::None
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::ThumbnailShape)
+                    .unwrap();
+                let mut effect = Effect::None;
+                match ThumbnailShape::from_display_name(text) {
+                    Some(shape) if shape != config.thumbnail_shape => {
+                        config.thumbnail_shape = shape;
+                        // Make the thumbnails again in the new shape
+                        effect = Effect::LsDir;
+                    }
+                    Some(_) => (),
+                    None => *error = "Invalid thumbnail shape".to_owned(),
+                }
+                let (text, _) = &self.fields[&SettingsFieldName::AutoHideControls];
+                config.auto_hide_controls = text == "true";
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::PreviewQuality)
+                    .unwrap();
+                match PreviewQuality::from_display_name(text) {
+                    Some(quality) if quality != config.preview_quality => {
+                        config.preview_quality = quality;
+                        // Reload the previews at the new size
+                        effect = Effect::LsDir;
+                    }
+                    Some(_) => (),
+                    None => *error = "Invalid preview quality".to_owned(),
+                }
+                let (text, _) = &self.fields[&SettingsFieldName::RecursiveScan];
+                let recursive_scan = text == "true";
+                if recursive_scan != config.recursive_scan {
+                    config.recursive_scan = recursive_scan;
+                    // List the folder again with or without the subfolders
+                    effect = Effect::LsDir;
+                }
+                let (text, error) = self.fields.get_mut(&SettingsFieldName::SortOrder).unwrap();
+                match SortOrder::from_display_name(text) {
+                    Some(order) if order != config.sort_order => {
+                        config.sort_order = order;
+                        effect = Effect::LsDir;
+                    }
+                    Some(_) => (),
+                    None => *error = "Invalid choice".to_owned(),
+                }
+                let (text, _) = &self.fields[&SettingsFieldName::DeferMoves];
+                config.defer_moves = text == "true";
+                let (text, _) = &self.fields[&SettingsFieldName::XmpSidecars];
+                config.xmp_sidecars = text == "true";
+                let (text, error) = self.fields.get_mut(&SettingsFieldName::MoveLayout).unwrap();
+                match MoveLayout::from_display_name(text) {
+                    Some(layout) => config.move_layout = layout,
+                    None => *error = "Invalid layout".to_owned(),
+                }
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::IoConcurrency)
+                    .unwrap();
+                match text.parse() {
+                    Ok(num) if num > 0 => config.io_concurrency = num,
+                    _ => *error = "Invalid number".to_owned(),
+                }
+                let (text, error) = self
+                    .fields
+                    .get_mut(&SettingsFieldName::IoMaxMegabytesPerSec)
+                    .unwrap();
+                match text.parse::<u64>() {
+                    Ok(num) => {
+                        config.io_max_bytes_per_sec = num * 1_000_000;
+                        throttle::set_limit(config.io_max_bytes_per_sec);
+                    }
+                    Err(_) => *error = "Invalid number".to_owned(),
+                }
+                Effect::Batch(vec![effect, Effect::SaveConfig(Box::new(config.clone()))])
+            }
+        }
+    }
+
+    // Applied only when no key is bound twice
+    fn save_keymap(&mut self, keymap: &mut Keymap) {
+        let mut new_keymap = keymap.clone();
+        for (field, (text, _)) in &self.fields {
+            if let SettingsFieldName::Shortcut(action) = field {
+                new_keymap.set_keys(*action, parse_keys(text));
+            }
+        }
+        match new_keymap.conflict() {
+            Some((key, action)) => {
+                if let Some((_, error)) = self.fields.get_mut(&SettingsFieldName::Shortcut(action))
+                {
+                    *error = format!("{key} is already used");
+                }
             }
+            None => *keymap = new_keymap,
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self, tag_names: &TagNames) -> Element<'_, Message> {
         let (preload_back_text, preload_back_error) =
             self.fields.get(&SettingsFieldName::PreloadBackNum).unwrap();
         let (preload_front_text, preload_front_error) = self
             .fields
             .get(&SettingsFieldName::PreloadFrontNum)
             .unwrap();
+        let (preload_full_text, preload_full_error) =
+            self.fields.get(&SettingsFieldName::PreloadFullNum).unwrap();
         let (scale_down_width_text, scale_down_width_error) = self
             .fields
             .get(&SettingsFieldName::ScaleDownSizeWidth)
             .unwrap();
+        let (preview_memory_text, preview_memory_error) = self
+            .fields
+            .get(&SettingsFieldName::PreviewMemoryMb)
+            .unwrap();
         let (scale_down_height_text, scale_down_height_error) = self
             .fields
             .get(&SettingsFieldName::ScaleDownSizeHeight)
             .unwrap();
-        let (tag1_text, tag1_error) = self.fields.get(&SettingsFieldName::Tag1Shortcut).unwrap();
+        let (key_guard_text, key_guard_error) = self
+            .fields
+            .get(&SettingsFieldName::DestructiveKeyGuard)
+            .unwrap();
+        let (end_of_list_text, end_of_list_error) =
+            self.fields.get(&SettingsFieldName::EndOfList).unwrap();
+        let (language_text, language_error) =
+            self.fields.get(&SettingsFieldName::Language).unwrap();
+        let (slideshow_text, slideshow_error) = self
+            .fields
+            .get(&SettingsFieldName::SlideshowSeconds)
+            .unwrap();
+        let (default_tag_names_text, _) = self
+            .fields
+            .get(&SettingsFieldName::DefaultTagNames)
+            .unwrap();
+        let (print_per_page_text, print_per_page_error) =
+            self.fields.get(&SettingsFieldName::PrintPerPage).unwrap();
         let (view_style_text, view_style_error) =
             self.fields.get(&SettingsFieldName::ViewStyle).unwrap();
+        let (thumbnail_shape_text, thumbnail_shape_error) =
+            self.fields.get(&SettingsFieldName::ThumbnailShape).unwrap();
+        let (auto_hide_text, _) = &self.fields[&SettingsFieldName::AutoHideControls];
+        let (preview_quality_text, preview_quality_error) =
+            self.fields.get(&SettingsFieldName::PreviewQuality).unwrap();
+        let (recursive_scan_text, _) = &self.fields[&SettingsFieldName::RecursiveScan];
+        let (defer_moves_text, _) = &self.fields[&SettingsFieldName::DeferMoves];
+        let (sort_order_text, sort_order_error) =
+            self.fields.get(&SettingsFieldName::SortOrder).unwrap();
+        let (xmp_sidecars_text, _) = &self.fields[&SettingsFieldName::XmpSidecars];
+        let (move_layout_text, move_layout_error) =
+            self.fields.get(&SettingsFieldName::MoveLayout).unwrap();
+        let (io_concurrency_text, io_concurrency_error) =
+            self.fields.get(&SettingsFieldName::IoConcurrency).unwrap();
+        let (io_max_text, io_max_error) = self
+            .fields
+            .get(&SettingsFieldName::IoMaxMegabytesPerSec)
+            .unwrap();
 
         column![
             text(t!("Settings")),
@@ -152,18 +494,89 @@ impl SettingsModel {
                     ))),
                 text(preload_front_error),
             ],
-            text(t!("Shortcuts")),
             row![
-                text("Tag 1"),
-                text_input("Tag 1", tag1_text)
-                    .id("tag_1_shortcut")
+                text(t!("Full previews within")),
+                text_input("Full previews within", preload_full_text)
+                    .id("preload_full_num")
                     .on_input(|text| Message::Settings(SettingsMessage::UserUpdatedField(
-                        SettingsFieldName::Tag1Shortcut,
+                        SettingsFieldName::PreloadFullNum,
                         text
                     ))),
-                text(tag1_error),
+                text(preload_full_error),
+            ],
+            row![
+                text(t!("Memory for previews MB")),
+                text_input("MB", preview_memory_text)
+                    .id("preview_memory_mb")
+                    .on_input(|text| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::PreviewMemoryMb,
+                        text
+                    ))),
+                text(preview_memory_error),
+            ],
+            text(t!("Shortcuts")),
+            self.view_shortcuts(tag_names),
+            row![
+                text(t!("Guard for Delete")),
+                pick_list(
+                    DestructiveKeyGuard::all_variants()
+                        .iter()
+                        .map(|g| g.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(key_guard_text.as_str()),
+                    |guard| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::DestructiveKeyGuard,
+                        guard.to_string()
+                    ))
+                ),
+                text(key_guard_error)
+            ],
+            row![
+                text(t!("After the last image")),
+                pick_list(
+                    EndOfList::all_variants()
+                        .iter()
+                        .map(|end| end.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(end_of_list_text.as_str()),
+                    |end| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::EndOfList,
+                        end.to_string()
+                    ))
+                ),
+                text(end_of_list_error)
+            ],
+            row![
+                text(t!("Images per printed page")),
+                pick_list(
+                    print::PER_PAGE_CHOICES
+                        .iter()
+                        .map(|per_page| per_page.to_string())
+                        .collect::<Vec<_>>(),
+                    Some(print_per_page_text.clone()),
+                    |per_page| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::PrintPerPage,
+                        per_page
+                    ))
+                ),
+                text(print_per_page_error)
             ],
             text(t!("Display Settings")),
+            row![
+                text(t!("Language")),
+                pick_list(
+                    Language::all_variants()
+                        .iter()
+                        .map(|language| language.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(language_text.as_str()),
+                    |language| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::Language,
+                        language.to_string()
+                    ))
+                ),
+                text(language_error)
+            ],
             row![
                 text(t!("Scale down size WxH")),
                 text_input("Width", scale_down_width_text)
@@ -196,8 +609,171 @@ impl SettingsModel {
                 ),
                 text(view_style_error)
             ],
+            row![
+                text(t!("Thumbnail shape")),
+                pick_list(
+                    ThumbnailShape::all_variants()
+                        .iter()
+                        .map(|shape| shape.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(thumbnail_shape_text.as_str()),
+                    |shape| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::ThumbnailShape,
+                        shape.to_string()
+                    ))
+                ),
+                text(thumbnail_shape_error)
+            ],
+            checkbox(
+                t!("Hide the controls while only the keyboard is used"),
+                auto_hide_text == "true"
+            )
+            .on_toggle(
+                |checked| Message::Settings(SettingsMessage::UserUpdatedField(
+                    SettingsFieldName::AutoHideControls,
+                    checked.to_string()
+                ))
+            ),
+            row![
+                text(t!("Slideshow seconds per image")),
+                text_input("Seconds", slideshow_text).on_input(|text| Message::Settings(
+                    SettingsMessage::UserUpdatedField(SettingsFieldName::SlideshowSeconds, text)
+                )),
+                text(slideshow_error),
+            ],
+            row![
+                text(t!("Tag names for new folders")),
+                text_input(&t!("Keep, Maybe, Reject"), default_tag_names_text).on_input(|text| {
+                    Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::DefaultTagNames,
+                        text,
+                    ))
+                }),
+            ],
+            row![
+                text(t!("Preview quality")),
+                pick_list(
+                    PreviewQuality::all_variants()
+                        .iter()
+                        .map(|q| q.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(preview_quality_text.as_str()),
+                    |quality| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::PreviewQuality,
+                        quality.to_string()
+                    ))
+                ),
+                text(preview_quality_error)
+            ],
+            text(t!("Folders")),
+            checkbox(t!("Include subfolders"), recursive_scan_text == "true").on_toggle(
+                |checked| Message::Settings(SettingsMessage::UserUpdatedField(
+                    SettingsFieldName::RecursiveScan,
+                    checked.to_string()
+                ))
+            ),
+            row![
+                text(t!("Order of the images")),
+                pick_list(
+                    SortOrder::all_variants()
+                        .iter()
+                        .map(|order| order.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(sort_order_text.as_str()),
+                    |order| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::SortOrder,
+                        order.to_string()
+                    ))
+                ),
+                text(sort_order_error)
+            ],
+            row![
+                text(t!("Moving files from subfolders")),
+                pick_list(
+                    MoveLayout::all_variants()
+                        .iter()
+                        .map(|layout| layout.display_name())
+                        .collect::<Vec<_>>(),
+                    Some(move_layout_text.as_str()),
+                    |layout| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::MoveLayout,
+                        layout.to_string()
+                    ))
+                ),
+                text(move_layout_error)
+            ],
+            checkbox(
+                t!("Move tags together when finishing the session"),
+                defer_moves_text == "true"
+            )
+            .on_toggle(
+                |checked| Message::Settings(SettingsMessage::UserUpdatedField(
+                    SettingsFieldName::DeferMoves,
+                    checked.to_string()
+                ))
+            ),
+            checkbox(
+                t!("Read and write XMP sidecars, for Lightroom and darktable"),
+                xmp_sidecars_text == "true"
+            )
+            .on_toggle(
+                |checked| Message::Settings(SettingsMessage::UserUpdatedField(
+                    SettingsFieldName::XmpSidecars,
+                    checked.to_string()
+                ))
+            ),
+            text(t!("Disk Access")),
+            row![
+                text(t!("Parallel image loads")),
+                text_input("Parallel image loads", io_concurrency_text)
+                    .id("io_concurrency")
+                    .on_input(|text| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::IoConcurrency,
+                        text
+                    ))),
+                text(io_concurrency_error),
+            ],
+            row![
+                text(t!("Max disk throughput MB/s (0 = unlimited)")),
+                text_input("MB/s", io_max_text)
+                    .id("io_max_megabytes_per_sec")
+                    .on_input(|text| Message::Settings(SettingsMessage::UserUpdatedField(
+                        SettingsFieldName::IoMaxMegabytesPerSec,
+                        text
+                    ))),
+                text(io_max_error),
+            ],
             button(text(t!("Save"))).on_press(Message::Settings(SettingsMessage::Save)),
         ]
         .into()
     }
+
+    // Tags added since the settings were opened have no field until typed in
+    fn view_shortcuts(&self, tag_names: &TagNames) -> Element<'_, Message> {
+        let rows = KeyAction::all_variants(tag_names)
+            .into_iter()
+            .map(|action| {
+                let (keys_text, keys_error) = self
+                    .fields
+                    .get(&SettingsFieldName::Shortcut(action))
+                    .map(|(text, error)| (text.as_str(), error.as_str()))
+                    .unwrap_or_default();
+                let name = match action {
+                    KeyAction::Tag(tag) => tag_names.get(&tag).to_owned(),
+                    _ => action.display_name(),
+                };
+                row![
+                    text(name.clone()),
+                    text_input(&name, keys_text).on_input(move |text| Message::Settings(
+                        SettingsMessage::UserUpdatedField(
+                            SettingsFieldName::Shortcut(action),
+                            text
+                        )
+                    )),
+                    text(keys_error.to_owned()),
+                ]
+                .into()
+            });
+        column(rows).into()
+    }
 }
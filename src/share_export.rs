@@ -0,0 +1,338 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use iced::widget::{button, checkbox, row, text, text_input};
+use iced::{Alignment, Element};
+use image::codecs::jpeg::JpegEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use log::warn;
+use rust_i18n::t;
+
+use crate::sorting::{Dim, Tag};
+use crate::{exif_data, load_image, xmp, Message};
+
+// Smaller JPEG copies of tagged images for sending around, written to a
+// folder picked when exporting. The originals are not touched. Images are
+// scaled down to fit the longest edge but never scaled up. The EXIF, with the
+// location, is only kept when the strip option is turned off. Star ratings
+// can go along in the EXIF, or in XMP sidecars for programs that read those.
+// For sending the copies are made in a folder of their own, opened in the
+// file manager to be dragged into a mail or chat.
+
+const SEND_DIR: &str = "imgsort-send";
+
+#[derive(Debug, Clone)]
+pub struct ShareExportForm {
+    pub max_edge: String,
+    pub quality: String,
+    pub strip_metadata: bool,
+    pub rating_exif: bool,
+    pub rating_sidecars: bool,
+}
+
+impl Default for ShareExportForm {
+    fn default() -> Self {
+        Self {
+            max_edge: "2048".to_owned(),
+            quality: "85".to_owned(),
+            // Location data shouldn't leak when photos are sent around
+            strip_metadata: true,
+            rating_exif: false,
+            rating_sidecars: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareExportSettings {
+    pub max_edge: u32,
+    pub quality: u8,
+    pub strip_metadata: bool,
+    pub rating_exif: bool,
+    pub rating_sidecars: bool,
+}
+
+impl ShareExportForm {
+    pub fn settings(&self) -> Option<ShareExportSettings> {
+        let max_edge = self.max_edge.trim().parse().ok().filter(|&edge| edge > 0)?;
+        let quality = self
+            .quality
+            .trim()
+            .parse()
+            .ok()
+            .filter(|quality| (1..=100).contains(quality))?;
+        Some(ShareExportSettings {
+            max_edge,
+            quality,
+            strip_metadata: self.strip_metadata,
+            rating_exif: self.rating_exif,
+            rating_sidecars: self.rating_sidecars,
+        })
+    }
+}
+
+// With the star rating of each image. Files already in the folder are never
+// replaced, the copies are numbered around them.
+pub fn export_for_sharing(
+    paths: Vec<(String, u8)>,
+    folder: PathBuf,
+    settings: ShareExportSettings,
+) -> (usize, Vec<String>) {
+    let mut exported = 0;
+    let mut errors = Vec::new();
+    let mut used_names = existing_names(&folder);
+    for (path, rating) in paths {
+        let output = folder.join(output_name(&path, &mut used_names));
+        match export_image(&path, &output, settings, rating) {
+            Ok(()) => exported += 1,
+            Err(e) => {
+                warn!("Could not export {path}: {e}");
+                errors.push(format!("{path}: {e}"));
+            }
+        }
+    }
+    (exported, errors)
+}
+
+// Lower case, as the names are compared. A sidecar takes the name of the copy
+// it would belong to as well.
+fn existing_names(folder: &Path) -> HashSet<String> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return HashSet::new();
+    };
+    let mut names = HashSet::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if let Some(stem) = name.strip_suffix(".xmp") {
+            names.insert(format!("{stem}.jpg"));
+        }
+        names.insert(name);
+    }
+    names
+}
+
+// A fresh folder under the temporary files, named after the tag
+pub fn stage_for_sending(
+    paths: Vec<(String, u8)>,
+    tag_name: &str,
+    settings: ShareExportSettings,
+) -> Result<(PathBuf, usize, Vec<String>), String> {
+    let send_dir = std::env::temp_dir().join(SEND_DIR);
+    let folder = send_dir.join(safe_name(tag_name));
+    // What was staged for the tag before, never anything above it
+    let mut below = folder
+        .strip_prefix(&send_dir)
+        .unwrap_or(&folder)
+        .components();
+    let in_send_dir = matches!(
+        (below.next(), below.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    );
+    if !in_send_dir {
+        return Err(format!("{}: not a folder name", folder.display()));
+    }
+    if folder.is_dir() && !folder.is_symlink() {
+        std::fs::remove_dir_all(&folder).map_err(|e| e.to_string())?;
+    }
+    std::fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+    let (exported, errors) = export_for_sharing(paths, folder.clone(), settings);
+    Ok((folder, exported, errors))
+}
+
+// The tag name, without anything that can't be in a file name. Names that
+// would point at a folder itself, like "" or "..", become "tag".
+pub fn safe_name(tag_name: &str) -> String {
+    let name: String = tag_name
+        .chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\' | ':') {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    match name.trim() {
+        name if name.chars().all(|c| c == '.') => "tag".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+// The stem with a .jpg extension, numbered when two files share a stem
+pub fn output_name(path: &str, used_names: &mut HashSet<String>) -> String {
+    unique_name(path, "jpg", used_names)
+}
+
+pub fn unique_name(path: &str, extension: &str, used_names: &mut HashSet<String>) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_owned());
+    let with_suffix = |suffix: &str| match extension {
+        "" => format!("{stem}{suffix}"),
+        _ => format!("{stem}{suffix}.{extension}"),
+    };
+    let mut name = with_suffix("");
+    let mut n = 2;
+    while !used_names.insert(name.to_lowercase()) {
+        name = with_suffix(&format!("-{n}"));
+        n += 1;
+    }
+    name
+}
+
+fn export_image(
+    path: &str,
+    output: &Path,
+    settings: ShareExportSettings,
+    rating: u8,
+) -> Result<(), String> {
+    let exif_rating = (settings.rating_exif && rating > 0).then_some(rating);
+    let jpeg = encode_shared_copy(path, settings, exif_rating)?;
+    write_new(output, &jpeg)?;
+    if settings.rating_sidecars && rating > 0 {
+        write_new(
+            &output.with_extension("xmp"),
+            xmp::new_sidecar(None, rating).as_bytes(),
+        )?;
+    }
+    Ok(())
+}
+
+// Fails rather than replace a file that appeared since the names were picked
+fn write_new(path: &Path, contents: &[u8]) -> Result<(), String> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| e.to_string())
+}
+
+pub fn encode_shared_copy(
+    path: &str,
+    settings: ShareExportSettings,
+    rating: Option<u8>,
+) -> Result<Vec<u8>, String> {
+    let edge = Dim {
+        width: settings.max_edge,
+        height: settings.max_edge,
+    };
+    let mut image = load_image(path, edge)?;
+    if image.width() > settings.max_edge || image.height() > settings.max_edge {
+        image = image.resize(
+            settings.max_edge,
+            settings.max_edge,
+            image::imageops::FilterType::Lanczos3,
+        );
+    }
+    let image = image.to_rgb8();
+
+    let mut jpeg = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut jpeg, settings.quality);
+    let exif = match settings.strip_metadata {
+        false => exif_data::upright_exif(path, rating),
+        true => None,
+    };
+    if let Some(exif) = exif.or_else(|| rating.and_then(exif_data::rating_exif)) {
+        encoder.set_exif_metadata(exif).map_err(|e| e.to_string())?;
+    }
+    encoder
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(jpeg)
+}
+
+pub fn view_share_export_form(form: &ShareExportForm, tag: Tag) -> Element<'static, Message> {
+    let valid = form.settings().is_some();
+    row![
+        text_input(&t!("Longest edge in pixels"), &form.max_edge)
+            .on_input(Message::UserEditedShareMaxEdge)
+            .width(150),
+        text_input(&t!("JPEG quality, 1-100"), &form.quality)
+            .on_input(Message::UserEditedShareQuality)
+            .width(150),
+        checkbox(t!("Remove EXIF and location"), form.strip_metadata)
+            .on_toggle(Message::UserToggledShareStripMetadata),
+        checkbox(t!("Stars in EXIF"), form.rating_exif)
+            .on_toggle(Message::UserToggledShareRatingExif),
+        checkbox(t!("Stars in XMP sidecars"), form.rating_sidecars)
+            .on_toggle(Message::UserToggledShareRatingSidecars),
+        button(text(t!("Export for sharing...")))
+            .on_press_maybe(valid.then_some(Message::UserPressedShareExport(tag))),
+        button(text(t!("Send..."))).on_press_maybe(valid.then_some(Message::UserPressedSend(tag))),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        assert_eq!(
+            ShareExportForm::default().settings(),
+            Some(ShareExportSettings {
+                max_edge: 2048,
+                quality: 85,
+                strip_metadata: true,
+                rating_exif: false,
+                rating_sidecars: false,
+            })
+        );
+        let form = |max_edge: &str, quality: &str| ShareExportForm {
+            max_edge: max_edge.to_owned(),
+            quality: quality.to_owned(),
+            ..ShareExportForm::default()
+        };
+        assert_eq!(form("0", "85").settings(), None);
+        assert_eq!(form("1024", "101").settings(), None);
+        assert_eq!(form("big", "85").settings(), None);
+    }
+
+    #[test]
+    fn test_safe_name() {
+        assert_eq!(safe_name(" Keep/Print "), "Keep-Print");
+        assert_eq!(safe_name("..."), "tag");
+        assert_eq!(safe_name(".."), "tag");
+        assert_eq!(safe_name("."), "tag");
+        assert_eq!(safe_name("  "), "tag");
+        assert_eq!(safe_name(".hidden"), ".hidden");
+    }
+
+    #[test]
+    fn test_output_name() {
+        let mut used = HashSet::new();
+        assert_eq!(output_name("dir/IMG_1.png", &mut used), "IMG_1.jpg");
+        assert_eq!(output_name("dir/IMG_1.CR2", &mut used), "IMG_1-2.jpg");
+        assert_eq!(output_name("other/IMG_2.jpg", &mut used), "IMG_2.jpg");
+    }
+
+    #[test]
+    fn test_export_keeps_existing_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let source = dir.join("IMG_1.png").to_string_lossy().to_string();
+        image::RgbImage::new(40, 30).save(&source).unwrap();
+        std::fs::create_dir(dir.join("out")).unwrap();
+        std::fs::write(dir.join("out/IMG_1.jpg"), "original").unwrap();
+        std::fs::write(dir.join("out/IMG_1-2.xmp"), "sidecar").unwrap();
+
+        let settings = ShareExportForm::default().settings().unwrap();
+        let (exported, errors) = export_for_sharing(vec![(source, 3)], dir.join("out"), settings);
+        assert_eq!((exported, errors), (1, Vec::<String>::new()));
+        let read = |name: &str| std::fs::read(dir.join("out").join(name)).unwrap();
+        assert_eq!(read("IMG_1.jpg"), b"original");
+        assert_eq!(read("IMG_1-2.xmp"), b"sidecar");
+        assert!(dir.join("out/IMG_1-3.jpg").exists());
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::metadata_db::content_hash;
+use crate::sorting::{Tag, TagNames};
+use crate::tag_io;
+
+// A session carried to another machine, so that two people can split a big
+// culling job and combine the results. Files are identified by a hash of
+// their content, they may have other names or be in other folders there.
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedSession {
+    pub version: u32,
+    pub tag_names: TagNames,
+    pub files: Vec<SharedTag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedTag {
+    pub hash: String,
+    // Only for reading the file, not used when merging
+    pub file: String,
+    pub tag: Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionMerge {
+    // File name to tag, as for imported tags
+    pub tags: Vec<(String, Tag)>,
+    pub tag_names: TagNames,
+    // Tagged in the session but not among the files here
+    pub unmatched: usize,
+}
+
+// Files are given with the hash from the metadata database when known
+fn hash_of(path: &str, cached: Option<String>) -> Option<String> {
+    cached.or_else(|| match content_hash(path) {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            warn!("Could not hash {path}: {e}");
+            None
+        }
+    })
+}
+
+pub fn export_session(
+    path: &Path,
+    tagged: Vec<(String, Option<String>, Tag)>,
+    tag_names: TagNames,
+) -> Result<usize, String> {
+    let files: Vec<SharedTag> = tagged
+        .into_iter()
+        .filter_map(|(file, cached, tag)| {
+            Some(SharedTag {
+                hash: hash_of(&file, cached)?,
+                file: tag_io::file_name(&file),
+                tag,
+            })
+        })
+        .collect();
+    let count = files.len();
+    let session = SharedSession {
+        version: VERSION,
+        tag_names,
+        files,
+    };
+    let json = serde_json::to_string_pretty(&session).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())?;
+    Ok(count)
+}
+
+pub fn read_session(path: &Path) -> Result<SharedSession, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let session: SharedSession = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    if session.version > VERSION {
+        return Err(format!(
+            "{} is from a newer version of imgsort",
+            path.display()
+        ));
+    }
+    Ok(session)
+}
+
+pub fn merge_session(
+    path: &Path,
+    files: Vec<(String, Option<String>)>,
+) -> Result<SessionMerge, String> {
+    let session = read_session(path)?;
+    let local: HashMap<String, String> = files
+        .into_iter()
+        .filter_map(|(file, cached)| Some((hash_of(&file, cached)?, tag_io::file_name(&file))))
+        .collect();
+    let tags: Vec<(String, Tag)> = session
+        .files
+        .iter()
+        .filter_map(|shared| Some((local.get(&shared.hash)?.clone(), shared.tag)))
+        .collect();
+    Ok(SessionMerge {
+        unmatched: session.files.len() - tags.len(),
+        tags,
+        tag_names: session.tag_names,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_and_merge() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let file = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let a = file("a.jpg", "first");
+        let b = file("b.jpg", "second");
+        let session_path = dir.join("session.json");
+
+        let count = export_session(
+            &session_path,
+            vec![(a, None, Tag::new(2)), (b, None, Tag::new(5))],
+            TagNames::new(),
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+
+        // The same content under another name, the other file is missing
+        let renamed = file("renamed.jpg", "first");
+        let other = file("other.jpg", "third");
+        let merge = merge_session(&session_path, vec![(renamed, None), (other, None)]).unwrap();
+        assert_eq!(merge.tags, vec![("renamed.jpg".to_owned(), Tag::new(2))]);
+        assert_eq!(merge.unmatched, 1);
+    }
+}
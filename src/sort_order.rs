@@ -0,0 +1,131 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::exif_data;
+
+// The order images are stepped through. Folders are listed by name, the other
+// orders are applied by a pass over the listing that reads the file times,
+// sizes or capture times, after the images are already shown. Images without
+// the value go last, and ties keep the name order.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    Name,
+    Modified,
+    Captured,
+    Size,
+    Random,
+}
+
+impl SortOrder {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            SortOrder::Name => "File name",
+            SortOrder::Modified => "Modification time",
+            SortOrder::Captured => "Capture time",
+            SortOrder::Size => "File size",
+            SortOrder::Random => "Random",
+        }
+    }
+
+    pub fn all_variants() -> Vec<SortOrder> {
+        vec![
+            SortOrder::Name,
+            SortOrder::Modified,
+            SortOrder::Captured,
+            SortOrder::Size,
+            SortOrder::Random,
+        ]
+    }
+
+    pub fn from_display_name(name: &str) -> Option<SortOrder> {
+        Self::all_variants()
+            .into_iter()
+            .find(|order| order.display_name() == name)
+    }
+
+    // Capture times take too long to read to hold up the listing, which is
+    // by name until they are
+    pub fn while_listing(&self) -> SortOrder {
+        match self {
+            SortOrder::Captured => SortOrder::Name,
+            order => *order,
+        }
+    }
+
+    // Short, for the chips above the images
+    pub fn label(&self) -> String {
+        match self {
+            SortOrder::Name => t!("Name"),
+            SortOrder::Modified => t!("Modified"),
+            SortOrder::Captured => t!("Taken"),
+            SortOrder::Size => t!("File size"),
+            SortOrder::Random => t!("Random"),
+        }
+        .to_string()
+    }
+}
+
+// The random order is the same for the same seed, so a folder listed again
+// after a move keeps it
+pub fn sorted(mut paths: Vec<String>, order: SortOrder, seed: u64) -> Vec<String> {
+    paths.sort();
+    match order {
+        SortOrder::Name => (),
+        SortOrder::Modified => sort_by_key(&mut paths, |path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        }),
+        SortOrder::Captured => sort_by_key(&mut paths, |path| {
+            exif_data::read_capture_time(path).map(|taken| taken.unix_timestamp())
+        }),
+        SortOrder::Size => sort_by_key(&mut paths, |path| {
+            std::fs::metadata(path).map(|metadata| metadata.len()).ok()
+        }),
+        SortOrder::Random => paths.sort_by_cached_key(|path| {
+            let mut hasher = DefaultHasher::new();
+            (seed, path).hash(&mut hasher);
+            hasher.finish()
+        }),
+    }
+    paths
+}
+
+fn sort_by_key<K: Ord>(paths: &mut [String], key: impl Fn(&str) -> Option<K>) {
+    paths.sort_by_cached_key(|path| {
+        let key = key(path);
+        (key.is_none(), key)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        std::fs::write(path("a.jpg"), "large file").unwrap();
+        std::fs::write(path("b.jpg"), "small").unwrap();
+        let paths = vec![path("b.jpg"), path("a.jpg"), path("missing.jpg")];
+
+        assert_eq!(
+            sorted(paths.clone(), SortOrder::Name, 0),
+            [path("a.jpg"), path("b.jpg"), path("missing.jpg")]
+        );
+        assert_eq!(
+            sorted(paths.clone(), SortOrder::Size, 0),
+            [path("b.jpg"), path("a.jpg"), path("missing.jpg")]
+        );
+        assert_eq!(
+            sorted(paths.clone(), SortOrder::Random, 7),
+            sorted(paths.clone(), SortOrder::Random, 7)
+        );
+    }
+}
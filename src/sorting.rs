@@ -1,19 +1,43 @@
 use crate::ui::{self, ButtonStyle};
 use iced::widget::{self, canvas, center, column, row, stack};
 use iced::{Color, Element, Length};
-use log::debug;
+use log::{debug, trace};
 use rust_i18n::t;
-use std::cmp::min;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::image_widget::PixelCanvas;
+use crate::destination_preview;
+use crate::format::{format_count, format_date, format_decimal, format_file_size};
+use crate::histogram;
+use crate::image_kind::ImageKind;
+use crate::image_size::{AspectClass, SizeFilter, MIN_MEGAPIXEL_CHOICES};
+use crate::image_widget::{PixelCanvas, Placeholder, ViewState};
+use crate::keymap::{KeyAction, Keymap};
+use crate::metadata_db::FileHistory;
+use crate::pathlist::ViewFilter;
+use crate::sort_order::SortOrder;
+use crate::takeout::SidecarMetadata;
+use crate::{exif_data, tag_io};
 use crate::{
-    Effect, ImageData, ImageInfo, LoadedImageAndThumb, Message, PathList, PreloadImage,
-    SortingViewStyle,
+    DestructiveKeyGuard, Effect, EndOfList, ImageData, ImageInfo, LoadedImageAndThumb, Message,
+    PathList, PreloadImage, SortingViewStyle, ThumbnailShape,
 };
 
 // Constants
-pub const TAGGING_CHARS: &str = "aoeupy";
+const DESTINATION_PANEL_WIDTH: f32 = 340.0;
+const DOUBLE_PRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+// Holding down the previous or next button steps again after the first delay,
+// then faster and faster down to the shortest delay
+const HOLD_FIRST_DELAY: std::time::Duration = std::time::Duration::from_millis(400);
+const HOLD_SHORTEST_DELAY: std::time::Duration = std::time::Duration::from_millis(40);
+const HOLD_ACCELERATION: f32 = 0.8;
+const HOLD_TICK: std::time::Duration = std::time::Duration::from_millis(20);
+const GRID_SPACING: f32 = 5.0;
+// How long a tag that was just given is shown
+const TAG_FLASH_DURATION: std::time::Duration = std::time::Duration::from_millis(900);
+const TAG_FLASH_TICK: std::time::Duration = std::time::Duration::from_millis(100);
+// Keys pressed this long after the mouse last moved hide the controls
+const AUTO_HIDE_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
 
 #[derive(Debug, Clone)]
 pub enum SortingMessage {
@@ -25,142 +49,609 @@ pub enum SortingMessage {
     UserPressedSubmitRenameTag,
     UserPressedCancelRenameTag,
     UserEditTagName(String),
-    ImagePreloaded(String, ImageData, ImageData),
+    UserPressedRenameFile,
+    UserEditFileName(String),
+    UserPressedSubmitRenameFile,
+    UserEditGoTo(String),
+    UserPressedSubmitGoTo,
+    UserPressedNextSizeOutlier,
+    UserPressedNextProblemFile,
+    UserPressedQuarantine,
+    UserPressedReloadImage,
+    UserPressedOpenExternally,
+    UserPressedExportMontage,
+    UserPressedKeepSorting,
+    UserScrolledImage(i8, (f32, f32)),
+    UserScrolledReference(i8, (f32, f32)),
+    UserChangedFilter(ViewFilter),
+    UserChangedSizeFilter(SizeFilter),
+    UserChangedKindFilter(Option<ImageKind>),
+    UserChangedSortOrder(SortOrder),
+    // Forward or not
+    UserPressedStepButton(bool),
+    UserReleasedStepButton,
+    HeldStepTick,
+    UserPressedSlideshow,
+    SlideshowTick,
+    TagFlashTick,
+    UserPressedThumbnail(usize),
+    UserHoveredThumbnail(usize),
+    UserPressedClearSelection,
+    UserPressedAddTag,
+    UserPressedRemoveTag(Tag),
+    UserPressedFirstUntagged,
+    ImagePreloaded(
+        String,
+        Result<(Option<ImageData>, ImageData), String>,
+        std::time::Duration,
+    ),
     KeyboardEvent(iced::keyboard::Event),
     CanvasResized(Dim),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
-pub enum Tag {
-    Tag1,
-    Tag2,
-    Tag3,
-    Tag4,
-    Tag5,
-    Tag6,
-    Tag7,
-    Tag8,
+// Counted from 1. Written to files as "Tag1" and so on, as when there were
+// only eight.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Tag(u16);
+
+// Given by the Delete key and suggested for broken files, while the session
+// has it. Once removed, no new tag takes its number.
+pub const REJECT_TAG: Tag = Tag::new(7);
+
+impl Tag {
+    pub const fn new(number: u16) -> Self {
+        Self(number)
+    }
+
+    // As shown to the user
+    pub fn number(&self) -> u16 {
+        self.0
+    }
+
+    pub fn id(&self) -> String {
+        format!("Tag{}", self.0)
+    }
+
+    pub fn from_id(id: &str) -> Option<Tag> {
+        let number = id.strip_prefix("Tag")?.parse().ok()?;
+        (number > 0).then_some(Tag(number))
+    }
 }
 
-#[derive(Debug, Clone)]
+impl From<Tag> for String {
+    fn from(tag: Tag) -> Self {
+        tag.id()
+    }
+}
+
+impl TryFrom<String> for Tag {
+    type Error = String;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Tag::from_id(&id).ok_or_else(|| format!("Unknown tag {id}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagInfo {
+    pub tag: Tag,
+    pub name: String,
+    // Index into the tag colors
+    pub color: usize,
+    // Folder the tag is moved and copied to instead of one named after it,
+    // absolute or within the sorted folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    // rclone remote the tag is uploaded to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<String>,
+}
+
+// The tags of the session, in the order they are shown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "TagNamesFile")]
 pub struct TagNames {
-    pub tag1: String,
-    pub tag2: String,
-    pub tag3: String,
-    pub tag4: String,
-    pub tag5: String,
-    pub tag6: String,
-    pub tag7: String,
-    pub tag8: String,
+    tags: Vec<TagInfo>,
+    // Numbers of removed tags are not given out again, the keymap and older
+    // session entries would carry over to the new tag
+    next_number: u16,
+}
+
+// Before tags could be added and removed there were always eight, stored by
+// field
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagNamesFile {
+    Registry {
+        tags: Vec<TagInfo>,
+        #[serde(default)]
+        next_number: u16,
+    },
+    Fixed {
+        tag1: String,
+        tag2: String,
+        tag3: String,
+        tag4: String,
+        tag5: String,
+        tag6: String,
+        tag7: String,
+        tag8: String,
+    },
+}
+
+impl From<TagNamesFile> for TagNames {
+    fn from(file: TagNamesFile) -> Self {
+        match file {
+            TagNamesFile::Registry { tags, next_number } => {
+                let highest = tags.iter().map(|info| info.tag.0).max().unwrap_or(0);
+                Self {
+                    tags,
+                    next_number: next_number.max(highest + 1),
+                }
+            }
+            TagNamesFile::Fixed {
+                tag1,
+                tag2,
+                tag3,
+                tag4,
+                tag5,
+                tag6,
+                tag7,
+                tag8,
+            } => {
+                let mut tag_names = Self::empty();
+                let names = [tag1, tag2, tag3, tag4, tag5, tag6, tag7, tag8];
+                for (number, name) in (1..).zip(names) {
+                    tag_names.insert(Tag(number), name);
+                }
+                tag_names
+            }
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Dim {
     pub width: u32,
     pub height: u32,
 }
 
-struct TagColors {
-    red: Color,
-    green: Color,
-    yellow: Color,
-    blue: Color,
-    purple: Color,
-    orange: Color,
-    gray: Color,
-    cyan: Color,
-}
-
-const TAG_COLORS: TagColors = TagColors {
-    red: Color::from_rgb(1.0, 0.0, 0.0),
-    green: Color::from_rgb(0.0, 0.6, 0.0),
-    yellow: Color::from_rgb(0.8, 0.8, 0.0),
-    blue: Color::from_rgb(0.0, 0.0, 1.0),
-    purple: Color::from_rgb(0.5, 0.0, 0.5),
-    orange: Color::from_rgb(1.0, 0.5, 0.0),
-    gray: Color::from_rgb(0.5, 0.5, 0.5),
-    cyan: Color::from_rgb(0.0, 1.0, 1.0),
-};
+const TAG_COLORS: [Color; 12] = [
+    Color::from_rgb(1.0, 0.0, 0.0),
+    Color::from_rgb(0.0, 0.6, 0.0),
+    Color::from_rgb(0.8, 0.8, 0.0),
+    Color::from_rgb(0.0, 0.0, 1.0),
+    Color::from_rgb(0.5, 0.0, 0.5),
+    Color::from_rgb(1.0, 0.5, 0.0),
+    Color::from_rgb(0.5, 0.5, 0.5),
+    Color::from_rgb(0.0, 1.0, 1.0),
+    Color::from_rgb(0.9, 0.3, 0.6),
+    Color::from_rgb(0.55, 0.35, 0.15),
+    Color::from_rgb(0.0, 0.5, 0.5),
+    Color::from_rgb(0.5, 0.8, 0.0),
+];
+const ARCHIVED_COLOR: Color = Color::from_rgb(0.5, 0.5, 0.5);
 
 impl TagNames {
     pub fn new() -> Self {
+        let names = [
+            t!("Red"),
+            t!("Green"),
+            t!("Yellow"),
+            t!("Blue"),
+            t!("Purple"),
+            t!("Orange"),
+            t!("Gray"),
+            t!("Cyan"),
+        ];
+        let mut tag_names = Self::empty();
+        for (number, name) in (1..).zip(names) {
+            tag_names.insert(Tag(number), name.to_string());
+        }
+        tag_names
+    }
+
+    fn empty() -> Self {
         Self {
-            tag1: String::from(t!("Red")),
-            tag2: String::from(t!("Green")),
-            tag3: String::from(t!("Yellow")),
-            tag4: String::from(t!("Blue")),
-            tag5: String::from(t!("Purple")),
-            tag6: String::from(t!("Orange")),
-            tag7: String::from(t!("Gray")),
-            tag8: String::from(t!("Cyan")),
+            tags: Vec::new(),
+            next_number: 1,
+        }
+    }
+
+    // Tags still called by their color in `before` take the name of the color
+    // in the current language
+    pub fn retranslate(&mut self, before: &TagNames) {
+        let colors = TagNames::new();
+        for info in self.tags.iter_mut() {
+            if before
+                .info(&info.tag)
+                .is_some_and(|old| old.name == info.name)
+            {
+                if let Some(color) = colors.info(&info.tag) {
+                    info.name = color.name.clone();
+                }
+            }
         }
     }
 
     pub fn update(&mut self, tag: Tag, name: String) {
-        match tag {
-            Tag::Tag1 => self.tag1 = name,
-            Tag::Tag2 => self.tag2 = name,
-            Tag::Tag3 => self.tag3 = name,
-            Tag::Tag4 => self.tag4 = name,
-            Tag::Tag5 => self.tag5 = name,
-            Tag::Tag6 => self.tag6 = name,
-            Tag::Tag7 => self.tag7 = name,
-            Tag::Tag8 => self.tag8 = name,
+        if let Some(info) = self.tags.iter_mut().find(|info| info.tag == tag) {
+            info.name = name;
         }
     }
 
     pub fn get(&self, tag: &Tag) -> &str {
-        match tag {
-            Tag::Tag1 => &self.tag1,
-            Tag::Tag2 => &self.tag2,
-            Tag::Tag3 => &self.tag3,
-            Tag::Tag4 => &self.tag4,
-            Tag::Tag5 => &self.tag5,
-            Tag::Tag6 => &self.tag6,
-            Tag::Tag7 => &self.tag7,
-            Tag::Tag8 => &self.tag8,
+        self.info(tag).map(|info| info.name.as_str()).unwrap_or("?")
+    }
+
+    pub fn destination(&self, tag: &Tag) -> &str {
+        self.custom_destination(tag)
+            .unwrap_or_else(|| self.get(tag))
+    }
+
+    // Only when set apart from the name
+    pub fn custom_destination(&self, tag: &Tag) -> Option<&str> {
+        self.info(tag).and_then(|info| info.destination.as_deref())
+    }
+
+    // An empty destination goes back to the tag name
+    pub fn set_destination(&mut self, tag: Tag, destination: String) {
+        if let Some(info) = self.tags.iter_mut().find(|info| info.tag == tag) {
+            info.destination = (!destination.trim().is_empty()).then_some(destination);
+        }
+    }
+
+    pub fn upload_remote(&self, tag: &Tag) -> Option<&str> {
+        self.info(tag).and_then(|info| info.upload.as_deref())
+    }
+
+    pub fn set_upload_remote(&mut self, tag: Tag, remote: String) {
+        if let Some(info) = self.tags.iter_mut().find(|info| info.tag == tag) {
+            info.upload = (!remote.trim().is_empty()).then_some(remote);
         }
     }
 
+    pub fn contains(&self, tag: &Tag) -> bool {
+        self.info(tag).is_some()
+    }
+
+    fn info(&self, tag: &Tag) -> Option<&TagInfo> {
+        self.tags.iter().find(|info| info.tag == *tag)
+    }
+
     pub fn enumerate(&self) -> impl Iterator<Item = (Tag, &String)> {
-        vec![
-            (Tag::Tag1, &self.tag1),
-            (Tag::Tag2, &self.tag2),
-            (Tag::Tag3, &self.tag3),
-            (Tag::Tag4, &self.tag4),
-            (Tag::Tag5, &self.tag5),
-            (Tag::Tag6, &self.tag6),
-            (Tag::Tag7, &self.tag7),
-            (Tag::Tag8, &self.tag8),
-        ]
-        .into_iter()
+        self.tags.iter().map(|info| (info.tag, &info.name))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    // A new tag, numbered after any there has been and given the first color
+    // not taken
+    pub fn add(&mut self) -> Tag {
+        let number = self.next_number;
+        let tag = Tag(number);
+        self.insert(tag, t!("Tag %{number}", number = number).to_string());
+        tag
+    }
+
+    fn insert(&mut self, tag: Tag, name: String) {
+        let color = (0..TAG_COLORS.len())
+            .find(|&color| self.tags.iter().all(|info| info.color != color))
+            .unwrap_or(self.tags.len() % TAG_COLORS.len());
+        self.next_number = self.next_number.max(tag.0 + 1);
+        self.tags.push(TagInfo {
+            tag,
+            name,
+            color,
+            destination: None,
+            upload: None,
+        });
     }
+
+    // Tags read from files that are not known here get a default name
+    pub fn ensure(&mut self, tag: Tag) {
+        if !self.contains(&tag) {
+            let name = t!("Tag %{number}", number = tag.number()).to_string();
+            self.insert(tag, name);
+        }
+    }
+
+    pub fn remove(&mut self, tag: Tag) {
+        self.tags.retain(|info| info.tag != tag);
+    }
+
+    pub fn color(&self, tag: &Tag) -> Color {
+        TAG_COLORS[self.color_index(tag)]
+    }
+
+    pub fn button_style(&self, tag: &Tag) -> ButtonStyle {
+        ui::TAG_BUTTON_STYLES[self.color_index(tag)]
+    }
+
+    fn color_index(&self, tag: &Tag) -> usize {
+        self.info(tag)
+            .map(|info| info.color)
+            .unwrap_or((tag.0 as usize).saturating_sub(1) % TAG_COLORS.len())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeldStep {
+    forward: bool,
+    delay: std::time::Duration,
+    next_step: std::time::Instant,
+}
+
+// The images that were just tagged, shown until the flash has run out
+#[derive(Debug, Clone)]
+pub struct TagFlash {
+    tag: Tag,
+    indices: Vec<usize>,
+    start: std::time::Instant,
 }
 
-pub fn tag_badge_color(tag: &Tag) -> iced::Color {
-    match *tag {
-        Tag::Tag1 => TAG_COLORS.red,
-        Tag::Tag2 => TAG_COLORS.green,
-        Tag::Tag3 => TAG_COLORS.yellow,
-        Tag::Tag4 => TAG_COLORS.blue,
-        Tag::Tag5 => TAG_COLORS.purple,
-        Tag::Tag6 => TAG_COLORS.orange,
-        Tag::Tag7 => TAG_COLORS.gray,
-        Tag::Tag8 => TAG_COLORS.cyan,
+// The image pinned with c, shown next to the current one to pick the sharper
+// of two shots. A copy of the preview is kept, the pinned image may be
+// unloaded from the list while stepping away from it.
+#[derive(Debug, Clone)]
+pub struct CompareReference {
+    path: String,
+    image: ImageData,
+    view: ViewState,
+}
+
+fn toggle_compare(model: &mut crate::Model) -> Effect {
+    if model.compare.take().is_some() || model.pathlist.paths.is_empty() {
+        return Effect::None;
     }
+    let current = model.pathlist.current();
+    if let PreloadImage::Loaded(LoadedImageAndThumb { image, .. }) = &current.data {
+        model.compare = Some(CompareReference {
+            path: current.path.clone(),
+            image: image.clone(),
+            view: current.metadata.view,
+        });
+    }
+    Effect::None
 }
 
-pub fn keybind_char_to_tag(c: &str) -> Option<Tag> {
-    match c {
-        "a" => Some(Tag::Tag1),
-        "o" => Some(Tag::Tag2),
-        "e" => Some(Tag::Tag3),
-        "u" => Some(Tag::Tag4),
-        _ => None,
+// The pinned image on the left, as they are shown
+fn export_montage(model: &crate::Model) -> Effect {
+    let Some(compare) = &model.compare else {
+        return Effect::None;
+    };
+    let current = model.pathlist.current();
+    Effect::ExportMontage([
+        (compare.path.clone(), compare.view.quarter_turns),
+        (current.path.clone(), current.metadata.view.quarter_turns),
+    ])
+}
+
+pub fn tag_flash_subscription(flashing: bool) -> iced::Subscription<Message> {
+    if flashing {
+        iced::time::every(TAG_FLASH_TICK).map(|_| Message::Sorting(SortingMessage::TagFlashTick))
+    } else {
+        iced::Subscription::none()
+    }
+}
+
+fn flash_tag(model: &mut crate::Model, tag: Tag, indices: Vec<usize>) {
+    model.tag_flash = Some(TagFlash {
+        tag,
+        indices,
+        start: std::time::Instant::now(),
+    });
+}
+
+fn view_tag_flash(model: &crate::Model) -> Option<Element<'_, Message>> {
+    let flash = model.tag_flash.as_ref()?;
+    let name = model.tag_names.get(&flash.tag);
+    let label = match flash.indices.len() {
+        1 => t!("Tagged: %{tag}", tag = name),
+        count => t!(
+            "Tagged %{count} images: %{tag}",
+            count = format_count(count),
+            tag = name
+        ),
+    };
+    let mut color = model.tag_names.color(&flash.tag);
+    color.a = 0.9;
+    let hud = widget::container(widget::text(label).size(20))
+        .padding(10)
+        .style(move |_: &iced::Theme| widget::container::Style {
+            background: Some(iced::Background::Color(color)),
+            border: iced::border::rounded(10.0),
+            text_color: Some(Color::WHITE),
+            ..widget::container::Style::default()
+        });
+    Some(
+        widget::container(hud)
+            .width(Length::Fill)
+            .align_x(iced::alignment::Horizontal::Center)
+            .padding(20)
+            .into(),
+    )
+}
+
+pub fn slideshow_subscription(running: bool, seconds: u64) -> iced::Subscription<Message> {
+    if running {
+        iced::time::every(std::time::Duration::from_secs(seconds.max(1)))
+            .map(|_| Message::Sorting(SortingMessage::SlideshowTick))
+    } else {
+        iced::Subscription::none()
+    }
+}
+
+// Waits for the next image to be loaded, and stops after the last untagged one
+fn advance_slideshow(model: &mut crate::Model) -> Effect {
+    if model.pathlist.paths.is_empty() {
+        model.slideshow = false;
+        return Effect::None;
+    }
+    if matches!(model.pathlist.current().data, PreloadImage::Loading(_)) {
+        return Effect::None;
+    }
+    // Not around to the start again
+    let next = model
+        .pathlist
+        .next_untagged()
+        .filter(|&i| i > model.pathlist.index);
+    match next {
+        Some(i) => jump_to_next_of(model, &[i]),
+        None => {
+            model.slideshow = false;
+            Effect::None
+        }
+    }
+}
+
+fn toggle_fullscreen(model: &mut crate::Model) -> Effect {
+    model.fullscreen = !model.fullscreen;
+    Effect::SetFullscreen(model.fullscreen)
+}
+
+// In the other bottom corner than the position badge
+fn view_histogram(model: &crate::Model) -> Option<Element<'_, Message>> {
+    if !model.show_histogram || model.pathlist.paths.is_empty() {
+        return None;
+    }
+    let PreloadImage::Loaded(LoadedImageAndThumb { image, .. }) = &model.pathlist.current().data
+    else {
+        return None;
+    };
+    Some(
+        widget::container(histogram::view(image.histogram.as_ref()?))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_y(iced::alignment::Vertical::Bottom)
+            .padding(10)
+            .into(),
+    )
+}
+
+// The position in a corner, the tag is already on the image
+fn view_fullscreen_badge(model: &crate::Model) -> Element<'_, Message> {
+    let (position, total) = model.pathlist.visible_position();
+    let badge = widget::container(widget::text(format!(
+        "{}/{}",
+        format_count(position),
+        format_count(total)
+    )))
+    .padding(5)
+    .style(|_: &iced::Theme| widget::container::Style {
+        background: Some(iced::Background::Color(Color::from_rgba(
+            0.0, 0.0, 0.0, 0.5,
+        ))),
+        border: iced::border::rounded(5.0),
+        text_color: Some(Color::WHITE),
+        ..widget::container::Style::default()
+    });
+    widget::container(badge)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_x(iced::alignment::Horizontal::Right)
+        .align_y(iced::alignment::Vertical::Bottom)
+        .padding(10)
+        .into()
+}
+
+pub fn held_step_subscription(holding: bool) -> iced::Subscription<Message> {
+    if holding {
+        iced::time::every(HOLD_TICK).map(|_| Message::Sorting(SortingMessage::HeldStepTick))
+    } else {
+        iced::Subscription::none()
+    }
+}
+
+fn step(model: &mut crate::Model, forward: bool) -> Effect {
+    if forward {
+        user_pressed_next_image(model)
+    } else {
+        user_pressed_previous_image(model)
+    }
+}
+
+// Repeating stops at the ends instead of doing what is set for the last image
+fn repeat_held_step(model: &mut crate::Model) -> Effect {
+    let Some(held) = &mut model.held_step else {
+        return Effect::None;
+    };
+    let now = std::time::Instant::now();
+    if now < held.next_step {
+        return Effect::None;
+    }
+    held.delay = held
+        .delay
+        .mul_f32(HOLD_ACCELERATION)
+        .max(HOLD_SHORTEST_DELAY);
+    held.next_step = now + held.delay;
+    let forward = held.forward;
+    let at_end = match forward {
+        true => model.pathlist.next_visible().is_none(),
+        false => model.pathlist.prev_visible().is_none(),
+    };
+    if at_end {
+        model.held_step = None;
+        return Effect::None;
+    }
+    step(model, forward)
+}
+
+// Whether the next button does anything on the current image
+fn can_step_forward(model: &crate::Model) -> bool {
+    model.pathlist.next_visible().is_some()
+        || match model.config.end_of_list {
+            EndOfList::Stop => false,
+            EndOfList::Wrap => model.pathlist.visible().len() > 1,
+            EndOfList::FirstUntagged => model
+                .pathlist
+                .paths
+                .iter()
+                .any(|info| info.metadata.tag.is_none()),
+            EndOfList::Summary => true,
+        }
+}
+
+// Steps on press rather than on release and repeats while held down
+fn view_step_button<'a>(
+    label: String,
+    forward: bool,
+    enabled: bool,
+    padding: impl Into<iced::Padding>,
+) -> Element<'a, Message> {
+    let status = if enabled {
+        widget::button::Status::Active
+    } else {
+        widget::button::Status::Disabled
+    };
+    // Without its own message the button leaves the presses to the mouse area
+    let button = widget::button(widget::text(label))
+        .padding(padding)
+        .style(move |theme, _| widget::button::primary(theme, status));
+    if !enabled {
+        return button.into();
     }
+    widget::mouse_area(button)
+        .on_press(Message::Sorting(SortingMessage::UserPressedStepButton(
+            forward,
+        )))
+        .on_release(Message::Sorting(SortingMessage::UserReleasedStepButton))
+        .on_exit(Message::Sorting(SortingMessage::UserReleasedStepButton))
+        .interaction(iced::mouse::Interaction::Pointer)
+        .into()
 }
 
 fn user_pressed_previous_image(model: &mut crate::Model) -> Effect {
+    match model.pathlist.prev_visible() {
+        None => return Effect::None,
+        // Skipping over filtered out images
+        Some(prev) if prev + 1 != model.pathlist.index => return jump_to_next_of(model, &[prev]),
+        Some(_) => (),
+    }
+    model.last_navigation = std::time::Instant::now();
     let preload_path = model.pathlist.step_left(&model.config);
     match preload_path {
         Some(path) => Effect::PreloadImages(vec![path], model.canvas_dimensions.unwrap()),
@@ -169,6 +660,12 @@ fn user_pressed_previous_image(model: &mut crate::Model) -> Effect {
 }
 
 fn user_pressed_next_image(model: &mut crate::Model) -> Effect {
+    match model.pathlist.next_visible() {
+        None => return past_last_image(model),
+        Some(next) if next != model.pathlist.index + 1 => return jump_to_next_of(model, &[next]),
+        Some(_) => (),
+    }
+    model.last_navigation = std::time::Instant::now();
     let preload_path = model.pathlist.step_right(&model.config);
     match preload_path {
         Some(path) => Effect::PreloadImages(vec![path], model.canvas_dimensions.unwrap()),
@@ -176,12 +673,274 @@ fn user_pressed_next_image(model: &mut crate::Model) -> Effect {
     }
 }
 
+fn past_last_image(model: &mut crate::Model) -> Effect {
+    match model.config.end_of_list {
+        EndOfList::Stop => Effect::None,
+        EndOfList::Wrap => {
+            let visible = model.pathlist.visible();
+            jump_to_next_of(model, &visible[..visible.len().min(1)])
+        }
+        EndOfList::FirstUntagged => jump_to_first_untagged(model),
+        EndOfList::Summary => {
+            model.end_summary = true;
+            Effect::None
+        }
+    }
+}
+
+// Chips above the images, each with its count. Pressing the chip of the
+// filter in use shows all images again.
+fn view_filter_chips<'a>(
+    model: &crate::Model,
+    tag_count: &HashMap<Tag, u32>,
+) -> Element<'a, Message> {
+    let untagged = model
+        .pathlist
+        .paths
+        .iter()
+        .filter(|info| info.metadata.tag.is_none())
+        .count();
+    let problems = model.pathlist.problem_files().len();
+    let neutral = Color::from_rgb(0.5, 0.5, 0.5);
+    let choices = [
+        (
+            ViewFilter::All,
+            format!(
+                "{} ({})",
+                t!("All"),
+                format_count(model.pathlist.paths.len())
+            ),
+            neutral,
+        ),
+        (
+            ViewFilter::Untagged,
+            format!("{} ({})", t!("Untagged"), format_count(untagged)),
+            neutral,
+        ),
+    ]
+    .into_iter()
+    // Only once there are any, most folders have none
+    .chain(
+        (problems > 0 || model.pathlist.filter == ViewFilter::Problems).then(|| {
+            (
+                ViewFilter::Problems,
+                format!("{} ({})", t!("Problem files"), format_count(problems)),
+                Color::from_rgb(0.8, 0.1, 0.1),
+            )
+        }),
+    )
+    .chain(model.tag_names.enumerate().map(|(tag, name)| {
+        let count = tag_count.get(&tag).copied().unwrap_or(0);
+        (
+            ViewFilter::Tag(tag),
+            format!("{name} ({})", format_count(count as usize)),
+            model.tag_names.color(&tag),
+        )
+    }));
+    let chips = choices.map(|(filter, label, color)| {
+        let active = filter == model.pathlist.filter;
+        let pressed = match active {
+            true => ViewFilter::All,
+            false => filter,
+        };
+        view_filter_chip(label, color, active)
+            .on_press(Message::Sorting(SortingMessage::UserChangedFilter(pressed)))
+            .into()
+    });
+    let tag_chips = row![widget::text(t!("Show")).size(12)]
+        .extend(chips)
+        .spacing(5)
+        .align_y(iced::Alignment::Center)
+        .wrap();
+    column![
+        tag_chips,
+        view_size_filter_chips(model.pathlist.size_filter),
+        view_kind_filter_chips(model),
+        view_sort_order_chips(model.config.sort_order),
+    ]
+    .spacing(5)
+    .into()
+}
+
+// Photos, screenshots and the rest, as guessed from the files
+fn view_kind_filter_chips<'a>(model: &crate::Model) -> Element<'a, Message> {
+    let neutral = Color::from_rgb(0.5, 0.5, 0.5);
+    let count = |kind: ImageKind| {
+        let count = model
+            .pathlist
+            .paths
+            .iter()
+            .filter(|info| info.metadata.kind == Some(kind))
+            .count();
+        format_count(count)
+    };
+    let chips = [None]
+        .into_iter()
+        .chain(ImageKind::all_variants().map(Some))
+        .map(|kind| {
+            let label = match kind {
+                None => t!("Any kind").to_string(),
+                Some(kind @ ImageKind::Photo) => format!("{} ({})", t!("Photos"), count(kind)),
+                Some(kind @ ImageKind::Screenshot) => {
+                    format!("{} ({})", t!("Screenshots"), count(kind))
+                }
+                Some(kind @ ImageKind::Other) => format!("{} ({})", t!("Other"), count(kind)),
+            };
+            view_filter_chip(label, neutral, kind == model.pathlist.kind_filter)
+                .on_press(Message::Sorting(SortingMessage::UserChangedKindFilter(
+                    kind,
+                )))
+                .into()
+        });
+    row![widget::text(t!("Kind")).size(12)]
+        .extend(chips)
+        .spacing(5)
+        .align_y(iced::Alignment::Center)
+        .wrap()
+        .into()
+}
+
+fn view_sort_order_chips<'a>(sort_order: SortOrder) -> Element<'a, Message> {
+    let neutral = Color::from_rgb(0.5, 0.5, 0.5);
+    let chips = SortOrder::all_variants().into_iter().map(|order| {
+        view_filter_chip(order.label(), neutral, order == sort_order)
+            .on_press(Message::Sorting(SortingMessage::UserChangedSortOrder(
+                order,
+            )))
+            .into()
+    });
+    row![widget::text(t!("Order")).size(12)]
+        .extend(chips)
+        .spacing(5)
+        .align_y(iced::Alignment::Center)
+        .wrap()
+        .into()
+}
+
+fn view_size_filter_chips<'a>(size_filter: SizeFilter) -> Element<'a, Message> {
+    let neutral = Color::from_rgb(0.5, 0.5, 0.5);
+    let chip = |label: String, changed: SizeFilter| {
+        view_filter_chip(label, neutral, changed == size_filter)
+            .on_press(Message::Sorting(SortingMessage::UserChangedSizeFilter(
+                changed,
+            )))
+            .into()
+    };
+    let resolutions = MIN_MEGAPIXEL_CHOICES.map(|min_megapixels| {
+        let label = match min_megapixels {
+            0 => t!("Any size").to_string(),
+            _ => t!("%{count} MP or more", count = min_megapixels).to_string(),
+        };
+        chip(
+            label,
+            SizeFilter {
+                min_megapixels,
+                ..size_filter
+            },
+        )
+    });
+    let shapes = [None]
+        .into_iter()
+        .chain(AspectClass::all_variants().map(Some))
+        .map(|shape| {
+            let label = match shape {
+                None => t!("Any shape"),
+                Some(AspectClass::Portrait) => t!("Portrait"),
+                Some(AspectClass::Landscape) => t!("Landscape"),
+                Some(AspectClass::Panorama) => t!("Panorama"),
+                Some(AspectClass::Square) => t!("Square"),
+            };
+            chip(
+                label.to_string(),
+                SizeFilter {
+                    shape,
+                    ..size_filter
+                },
+            )
+        });
+    row![widget::text(t!("Size")).size(12)]
+        .extend(resolutions)
+        .push(widget::Space::with_width(10))
+        .extend(shapes)
+        .spacing(5)
+        .align_y(iced::Alignment::Center)
+        .wrap()
+        .into()
+}
+
+fn view_filter_chip<'a>(label: String, color: Color, active: bool) -> widget::Button<'a, Message> {
+    widget::button(widget::text(label).size(12))
+        .padding([2, 8])
+        .style(move |_: &iced::Theme, status| {
+            let background = match (active, status) {
+                (true, _) => Some(color),
+                (false, widget::button::Status::Hovered | widget::button::Status::Pressed) => {
+                    Some(Color { a: 0.3, ..color })
+                }
+                (false, _) => None,
+            };
+            widget::button::Style {
+                background: background.map(iced::Background::Color),
+                text_color: if active { Color::WHITE } else { color },
+                border: iced::Border {
+                    radius: iced::border::radius(10),
+                    color,
+                    width: 1.0,
+                },
+                ..widget::button::Style::default()
+            }
+        })
+}
+
+// After a filter change, the current image is kept if it is still shown
+fn leave_hidden_image(model: &mut crate::Model) -> Effect {
+    let visible = model.pathlist.visible();
+    if visible.contains(&model.pathlist.index) {
+        Effect::None
+    } else {
+        jump_to_next_of(model, &visible)
+    }
+}
+
+// Turning and zooming only changes how the image is drawn
+fn change_view(model: &mut crate::Model, change: impl FnOnce(ViewState) -> ViewState) -> Effect {
+    if !model.pathlist.paths.is_empty() {
+        let metadata = &mut model.pathlist.current_mut().metadata;
+        metadata.view = change(metadata.view);
+    }
+    Effect::None
+}
+
+fn jump_to_first_untagged(model: &mut crate::Model) -> Effect {
+    let untagged: Vec<usize> = model
+        .pathlist
+        .paths
+        .iter()
+        .enumerate()
+        .filter(|(_, info)| info.metadata.tag.is_none())
+        .map(|(i, _)| i)
+        .take(1)
+        .collect();
+    jump_to_next_of(model, &untagged)
+}
+
 fn tag_and_move_on(model: &mut crate::Model, tag: Tag) -> Effect {
-    if model.pathlist.paths.is_empty() {
+    // Keys can still be bound to a removed tag
+    if model.pathlist.paths.is_empty() || !model.tag_names.contains(&tag) {
         return Effect::None;
     }
+    // Taken over by hand
+    model.slideshow = false;
 
-    model.pathlist.current_mut().metadata.tag = Some(tag);
+    let indices = model.pathlist.selected_or_current();
+    for &i in &indices {
+        model.pathlist.paths[i].metadata.tag = Some(tag);
+    }
+    flash_tag(model, tag, indices);
+    if !model.pathlist.selected.is_empty() {
+        model.pathlist.clear_selection();
+        return Effect::None;
+    }
     user_pressed_next_image(model)
 }
 
@@ -191,35 +950,108 @@ fn view_image<'a>(
     dim: Option<Dim>,
     highlight: bool,
     is_main_image: bool,
+    spinner_phase: Option<f32>,
+    thumbnail_shape: ThumbnailShape,
 ) -> Element<'a, Message> {
-    let name_and_color = image.metadata.tag.as_ref().map(|tag| {
-        let name = tag_names.get(tag);
-        let color = tag_badge_color(tag);
-        (name.to_owned(), color)
-    });
-    match &image.data {
+    let name_and_color = match &image.metadata.tag {
+        Some(tag) => Some((tag_names.get(tag).to_owned(), tag_names.color(tag))),
+        // Marked until the user decides what to do with it
+        None if image.metadata.archived => Some((t!("Archived").to_string(), ARCHIVED_COLOR)),
+        None => None,
+    };
+    let stars = (image.metadata.rating > 0).then(|| view_stars(image.metadata.rating, dim));
+    let view = match is_main_image {
+        true => image.metadata.view,
+        false => image.metadata.view.unzoomed(),
+    };
+    // Turned as the image itself, and cropped to fill the cell if wanted
+    let pixel_canvas = |data: Option<&'a ImageData>| {
+        PixelCanvas::new(data, is_main_image)
+            .with_view(view)
+            .with_fill(dim.is_some() && thumbnail_shape == ThumbnailShape::Square)
+    };
+    let placeholder = || {
+        spinner_phase.map(|spinner_phase| Placeholder {
+            label: tag_io::file_name(&image.path),
+            spinner_phase,
+            size: image.metadata.dimensions,
+        })
+    };
+    let content = match &image.data {
         PreloadImage::Loaded(LoadedImageAndThumb { image, thumb }) => {
             if dim.is_some() {
                 // TODO: bad way to figure out that it's a thumbnail
-                view_loaded_image(Some(thumb), name_and_color, dim, highlight, is_main_image)
+                view_loaded_image(pixel_canvas(Some(thumb)), name_and_color, dim, highlight)
             } else {
-                view_loaded_image(Some(image), name_and_color, dim, highlight, is_main_image)
+                view_loaded_image(pixel_canvas(Some(image)), name_and_color, dim, highlight)
             }
         }
-        PreloadImage::Loading(_) | PreloadImage::NotLoading => {
-            view_loaded_image(None, name_and_color, dim, highlight, is_main_image)
+        PreloadImage::ThumbnailLoaded(thumb) => {
+            // The thumbnail stands in for the main image until the full
+            // preview arrives, blurred under the spinner
+            view_loaded_image(
+                pixel_canvas(Some(thumb)).with_placeholder(placeholder()),
+                name_and_color,
+                dim,
+                highlight,
+            )
         }
-    }
+        PreloadImage::Loading(_) | PreloadImage::NotLoading => view_loaded_image(
+            pixel_canvas(None).with_placeholder(placeholder()),
+            name_and_color,
+            dim,
+            highlight,
+        ),
+        PreloadImage::Failed(error) => {
+            let (w, h) = match dim {
+                Some(dim) => (
+                    Length::Fixed(dim.width as f32),
+                    Length::Fixed(dim.height as f32),
+                ),
+                None => (Length::Fill, Length::Fill),
+            };
+            let label = if is_main_image {
+                format!("{}: {error}", tag_io::file_name(&image.path))
+            } else {
+                "!".to_owned()
+            };
+            widget::container(widget::text(label).color(Color::from_rgb(0.8, 0.1, 0.1)))
+                .center(w)
+                .height(h)
+                .into()
+        }
+    };
+    stack![content].push_maybe(stars).into()
+}
+
+// At the bottom, away from the tag badge and the histogram
+fn view_stars<'a>(rating: u8, dim: Option<Dim>) -> Element<'a, Message> {
+    let size = if dim.is_some() { 12 } else { 24 };
+    let stars = widget::container(widget::text("★".repeat(rating as usize)).size(size))
+        .padding(if dim.is_some() { 2 } else { 8 })
+        .style(|_: &iced::Theme| widget::container::Style {
+            background: Some(iced::Background::Color(Color::from_rgba(
+                0.0, 0.0, 0.0, 0.6,
+            ))),
+            border: iced::border::rounded(5.0),
+            text_color: Some(Color::from_rgb(1.0, 0.8, 0.1)),
+            ..widget::container::Style::default()
+        });
+    widget::container(stars)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_x(iced::alignment::Horizontal::Center)
+        .align_y(iced::alignment::Vertical::Bottom)
+        .padding(5)
+        .into()
 }
 
-fn view_loaded_image(
-    image: Option<&ImageData>,
+fn view_loaded_image<'a>(
+    pixel_canvas: PixelCanvas<'a>,
     name_and_color: Option<(String, iced::Color)>,
     dim: Option<Dim>,
     highlight: bool,
-    send_resize_messages: bool,
-) -> Element<Message> {
-    let pixel_canvas = PixelCanvas::new(image, send_resize_messages);
+) -> Element<'a, Message> {
     let (w, h) = match dim {
         Some(dim) => (
             Length::Fixed(dim.width as f32),
@@ -269,85 +1101,106 @@ fn preload_list_status_string_pathlist(
     let loaded = counts.loaded;
     let loading = counts.loading;
     let not_loading = counts.not_loading;
+    let failed = counts.failed;
+    let thumbnail_only = counts.thumbnail_only;
 
     // Get task manager information
     let (ls_dir_tasks, preload_tasks) = task_manager.get_task_counts();
 
-    s.push_str(&format!("Loaded: {loaded}/{total}"));
-    if loading > 0 {
-        s.push_str(&format!(", Loading: {loading}"));
-    }
-    if not_loading > 0 {
-        s.push_str(&format!(", Not loading: {not_loading}"));
-    }
-    if preload_tasks > 0 {
-        s.push_str(&format!(", In flight: {preload_tasks}"));
-    }
-    if ls_dir_tasks > 0 {
-        s.push_str(&format!(", Dir loading: {ls_dir_tasks}"));
+    s.push_str(&t!(
+        "Loaded: %{loaded}/%{total}",
+        loaded = format_count(loaded),
+        total = format_count(total)
+    ));
+    for (count, label) in [
+        (thumbnail_only, t!("Thumbnail only")),
+        (loading, t!("Loading")),
+        (not_loading, t!("Not loading")),
+        (failed, t!("Failed")),
+        (preload_tasks, t!("In flight")),
+        (ls_dir_tasks, t!("Dir loading")),
+    ] {
+        if count > 0 {
+            s.push_str(&format!(", {label}: {}", format_count(count)));
+        }
     }
     s
 }
 
 fn view_tag_button_row<'a>(
     editing_tag_name: Option<&(Tag, String, iced::widget::text_input::Id)>,
+    removing_tag: Option<Tag>,
     names: &'a TagNames,
     nums: &HashMap<Tag, u32>,
+    compact: bool,
 ) -> Element<'a, Message> {
-    let tag_button_helper = |name: String, tag: &Tag, button_style: ButtonStyle| {
-        let num = *nums.get(tag).unwrap_or(&0);
-        view_tag_button(
-            name,
-            tag,
-            num,
-            button_style.basic,
-            button_style.hover,
-            button_style.press,
-            match editing_tag_name {
-                Some((t, name, id)) if *t == *tag => Some((name.clone(), id.clone())),
-                _ => None,
-            },
-        )
-    };
-
-    column![
-        row![
-            tag_button_helper(names.tag1.clone(), &Tag::Tag1, ui::RED_BUTTON_STYLE),
-            tag_button_helper(names.tag2.clone(), &Tag::Tag2, ui::GREEN_BUTTON_STYLE),
-            tag_button_helper(names.tag3.clone(), &Tag::Tag3, ui::YELLOW_BUTTON_STYLE),
-            tag_button_helper(names.tag4.clone(), &Tag::Tag4, ui::BLUE_BUTTON_STYLE),
-        ],
-        row![
-            tag_button_helper(names.tag5.clone(), &Tag::Tag5, ui::PURPLE_BUTTON_STYLE),
-            tag_button_helper(names.tag6.clone(), &Tag::Tag6, ui::ORANGE_BUTTON_STYLE),
-            tag_button_helper(names.tag7.clone(), &Tag::Tag7, ui::GRAY_BUTTON_STYLE),
-            tag_button_helper(names.tag8.clone(), &Tag::Tag8, ui::CYAN_BUTTON_STYLE),
-        ]
-    ]
-    .into()
+    let mut buttons: Vec<Element<'a, Message>> = names
+        .enumerate()
+        .map(|(tag, name)| {
+            let num = *nums.get(&tag).unwrap_or(&0);
+            view_tag_button(
+                name.clone(),
+                &tag,
+                num,
+                names.button_style(&tag),
+                match editing_tag_name {
+                    Some((t, name, id)) if *t == tag => Some((name.clone(), id.clone())),
+                    _ => None,
+                },
+                removing_tag == Some(tag),
+                compact,
+            )
+        })
+        .collect();
+    buttons.push(
+        widget::button(widget::text("+"))
+            .on_press(Message::Sorting(SortingMessage::UserPressedAddTag))
+            .height(33)
+            .into(),
+    );
+    if compact {
+        // The short labels fit on a single row
+        widget::Row::from_vec(buttons).into()
+    } else {
+        // Four to a row, as the eight default tags
+        let mut rows = Vec::new();
+        while buttons.len() > 4 {
+            let rest = buttons.split_off(4);
+            rows.push(widget::Row::from_vec(std::mem::replace(&mut buttons, rest)).into());
+        }
+        rows.push(widget::Row::from_vec(buttons).into());
+        widget::Column::from_vec(rows).into()
+    }
 }
 
 fn view_tag_button<'a>(
     text: String,
     tag: &Tag,
     num: u32,
-    basic_bg: Color,
-    hover_bg: Color,
-    press_bg: Color,
+    button_style: ButtonStyle,
     editing_tag_name: Option<(String, widget::text_input::Id)>,
+    removing: bool,
+    compact: bool,
 ) -> Element<'a, Message> {
     let style = iced::widget::button::Style {
-        background: Some(iced::Background::Color(basic_bg)),
+        background: Some(iced::Background::Color(button_style.basic)),
         text_color: iced::Color::from_rgb(1.0, 1.0, 1.0),
         border: iced::Border::default(),
         shadow: iced::Shadow::default(),
     };
-    let style_hovered = style.with_background(iced::Background::Color(hover_bg));
+    let style_hovered = style.with_background(iced::Background::Color(button_style.hover));
 
-    let style_pressed = style.with_background(iced::Background::Color(press_bg));
+    let style_pressed = style.with_background(iced::Background::Color(button_style.press));
 
     let button_height = 33;
-    let tag_button = widget::Button::new(widget::text!("{text} ({num})"))
+    // Compact buttons only show the first letter of the tag name
+    let label = if compact {
+        let initial: String = text.chars().take(1).collect();
+        format!("{initial} {}", format_count(num as usize))
+    } else {
+        format!("{text} ({})", format_count(num as usize))
+    };
+    let tag_button = widget::Button::new(widget::text(label))
         .style(move |_, status| match &status {
             widget::button::Status::Active => style,
             widget::button::Status::Hovered => style_hovered,
@@ -370,34 +1223,151 @@ fn view_tag_button<'a>(
         .height(button_height);
 
     let rename_input: Option<Element<Message>> = editing_tag_name.map(|(text, id)| {
-        widget::text_input("tag name", &text)
-            .on_input(|text| Message::Sorting(SortingMessage::UserEditTagName(text)))
-            .on_submit(Message::Sorting(SortingMessage::UserPressedSubmitRenameTag))
-            .id(id.clone())
-            .into()
+        row![
+            widget::text_input(&t!("Tag name"), &text)
+                .on_input(|text| Message::Sorting(SortingMessage::UserEditTagName(text)))
+                .on_submit(Message::Sorting(SortingMessage::UserPressedSubmitRenameTag))
+                .id(id.clone()),
+            view_remove_tag_button(*tag, num, removing),
+        ]
+        .into()
     });
 
     match rename_input {
         Some(widget) => widget,
+        None if compact => tag_button.into(),
         None => row![tag_button, more_button].into(),
     }
 }
 
-// Public functions for flattened sorting model
-pub fn update_sorting_model(
-    model: &mut crate::Model,
-    message: SortingMessage,
-    config: &crate::Config,
-) -> crate::Effect {
-    log::info!("Keyboard event, in sorting model");
-    match message {
+// Pressed a second time to confirm while images have the tag
+pub fn view_remove_tag_button<'a>(tag: Tag, count: u32, confirming: bool) -> Element<'a, Message> {
+    let label = match confirming {
+        true => t!(
+            "Untag %{count} images and remove?",
+            count = format_count(count as usize)
+        ),
+        false => t!("Remove tag"),
+    };
+    widget::button(widget::text(label))
+        .style(widget::button::danger)
+        .on_press(Message::Sorting(SortingMessage::UserPressedRemoveTag(tag)))
+        .into()
+}
+
+// While reviewing moved files the folder is read-only, only looking around is allowed
+pub fn is_allowed_in_review(message: &SortingMessage, keymap: &Keymap) -> bool {
+    use iced::keyboard::{key::Named, Event, Key};
+    match message {
+        SortingMessage::UserPressedNextImage
+        | SortingMessage::UserPressedPreviousImage
+        | SortingMessage::UserPressedNextSizeOutlier
+        | SortingMessage::UserPressedNextProblemFile
+        | SortingMessage::UserPressedReloadImage
+        | SortingMessage::UserPressedOpenExternally
+        | SortingMessage::UserPressedExportMontage
+        | SortingMessage::UserScrolledImage(..)
+        | SortingMessage::UserScrolledReference(..)
+        | SortingMessage::UserChangedFilter(_)
+        | SortingMessage::UserChangedSizeFilter(_)
+        | SortingMessage::UserChangedKindFilter(_)
+        | SortingMessage::UserChangedSortOrder(_)
+        | SortingMessage::UserPressedStepButton(_)
+        | SortingMessage::UserReleasedStepButton
+        | SortingMessage::HeldStepTick
+        | SortingMessage::UserPressedSlideshow
+        | SortingMessage::SlideshowTick
+        | SortingMessage::TagFlashTick
+        | SortingMessage::UserPressedThumbnail(_)
+        | SortingMessage::UserHoveredThumbnail(_)
+        | SortingMessage::UserPressedClearSelection
+        | SortingMessage::UserEditGoTo(_)
+        | SortingMessage::UserPressedSubmitGoTo
+        | SortingMessage::ImagePreloaded(..)
+        | SortingMessage::CanvasResized(_) => true,
+        SortingMessage::KeyboardEvent(Event::KeyPressed { key, modifiers, .. }) => {
+            match keymap.action(key.as_ref(), *modifiers) {
+                Some(action) => matches!(
+                    action,
+                    KeyAction::NextImage | KeyAction::PreviousImage | KeyAction::ToggleHistogram
+                ),
+                None => matches!(
+                    key.as_ref(),
+                    Key::Character(
+                        "d" | "i"
+                            | "r"
+                            | "v"
+                            | "b"
+                            | "c"
+                            | "g"
+                            | "n"
+                            | "s"
+                            | "f"
+                            | "1"
+                            | "2"
+                            | "3"
+                            | "4"
+                            | "5"
+                            | "+"
+                            | "="
+                            | "-"
+                            | "0"
+                            | "["
+                            | "]"
+                    ) | Key::Named(
+                        Named::Escape
+                            | Named::Pause
+                            | Named::ArrowUp
+                            | Named::ArrowDown
+                            | Named::Home
+                            | Named::End
+                            | Named::PageUp
+                            | Named::PageDown
+                            | Named::F11
+                    )
+                ),
+            }
+        }
+        SortingMessage::KeyboardEvent(_) => true,
+        _ => false,
+    }
+}
+
+// Public functions for flattened sorting model
+pub fn update_sorting_model(
+    model: &mut crate::Model,
+    message: SortingMessage,
+    config: &crate::Config,
+) -> crate::Effect {
+    log::info!("Keyboard event, in sorting model");
+    if let SortingMessage::KeyboardEvent(iced::keyboard::Event::KeyPressed { .. }) = message {
+        if config.auto_hide_controls && model.last_mouse_move.elapsed() >= AUTO_HIDE_DELAY {
+            model.controls_hidden = true;
+        }
+    }
+    match message {
         SortingMessage::UserPressedPreviousImage => user_pressed_previous_image(model),
         SortingMessage::UserPressedNextImage => user_pressed_next_image(model),
-        SortingMessage::ImagePreloaded(path, image, thumb) => {
-            if let Some(path) = model
+        SortingMessage::ImagePreloaded(path, result, decode_time) => {
+            if let (Some(info), Ok((Some(image), _))) = (
+                model
+                    .pathlist
+                    .paths
+                    .iter_mut()
+                    .find(|info| info.path == path),
+                &result,
+            ) {
+                info.metadata.decode_time = Some(decode_time);
+                info.metadata.dimensions = Some((image.width, image.height));
+            }
+            let next = model.pathlist.image_preload_complete(&path, result, config);
+            let evicted = model
                 .pathlist
-                .image_preload_complete(&path, image, thumb, config)
-            {
+                .evict_far_previews(config, model.preview_memory_budget());
+            if evicted > 0 {
+                debug!("Dropped {evicted} previews to stay in the memory budget");
+            }
+            if let Some(path) = next {
                 crate::Effect::PreloadImages(vec![path], model.canvas_dimensions.unwrap())
             } else {
                 crate::Effect::None
@@ -409,28 +1379,132 @@ pub fn update_sorting_model(
         }) => {
             log::info!("Pressed escape, clearing edit tag name");
             model.editing_tag_name = None;
-            Effect::None
+            model.renaming_file = None;
+            model.going_to = None;
+            model.slideshow = false;
+            model.pathlist.clear_selection();
+            model.compare = None;
+            if model.fullscreen {
+                toggle_fullscreen(model)
+            } else {
+                Effect::None
+            }
         }
         SortingMessage::KeyboardEvent(_) if is_typing_action(model) => crate::Effect::None,
         SortingMessage::KeyboardEvent(event) => match event {
+            iced::keyboard::Event::KeyPressed { key, modifiers, .. }
+                if config.keymap.action(key.as_ref(), modifiers).is_some() =>
+            {
+                let action = config.keymap.action(key.as_ref(), modifiers).unwrap();
+                run_key_action(model, action, modifiers)
+            }
             iced::keyboard::Event::KeyPressed { key, modifiers, .. } => match key.as_ref() {
-                iced::keyboard::Key::Character("h")
-                | iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowLeft) => {
-                    user_pressed_previous_image(model)
+                iced::keyboard::Key::Character("d") if !modifiers.control() => {
+                    toggle_visual_diff(model)
+                }
+                iced::keyboard::Key::Character("r") if !modifiers.control() => {
+                    reload_current_image(model)
+                }
+                iced::keyboard::Key::Character("q") if !modifiers.control() => {
+                    quarantine_current_file(model)
                 }
-                iced::keyboard::Key::Character("t" | "l")
-                | iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowRight) => {
-                    user_pressed_next_image(model)
+                iced::keyboard::Key::Character("v") if !modifiers.control() => {
+                    open_current_externally(model)
                 }
-                iced::keyboard::Key::Character(c)
-                    if !modifiers.control() && TAGGING_CHARS.contains(c) =>
+                iced::keyboard::Key::Character("i") if !modifiers.control() => {
+                    toggle_info_panel(model)
+                }
+                iced::keyboard::Key::Character(digit @ ("1" | "2" | "3" | "4" | "5"))
+                    if !modifiers.control() =>
                 {
-                    let tag = keybind_char_to_tag(c).unwrap();
-                    // Any tagging character
-                    tag_and_move_on(model, tag)
+                    let indices = model.pathlist.selected_or_current();
+                    model.pathlist.rate(&indices, digit.parse().unwrap());
+                    Effect::None
+                }
+                iced::keyboard::Key::Character("c") if !modifiers.control() => {
+                    toggle_compare(model)
+                }
+                iced::keyboard::Key::Character("p") if modifiers.control() => {
+                    print_selected_or_current(model)
+                }
+                iced::keyboard::Key::Character("c" | "C") if modifiers.control() => {
+                    copy_selected_or_current_paths(model)
+                }
+                iced::keyboard::Key::Character("s") if !modifiers.control() => {
+                    model.slideshow = !model.slideshow;
+                    Effect::None
+                }
+                iced::keyboard::Key::Character("f") if !modifiers.control() => {
+                    toggle_fullscreen(model)
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::F11) => {
+                    toggle_fullscreen(model)
+                }
+                iced::keyboard::Key::Character("g") if !modifiers.control() => {
+                    let id = widget::text_input::Id::unique();
+                    model.going_to = Some((String::new(), id.clone()));
+                    Effect::FocusElement(id)
+                }
+                iced::keyboard::Key::Character("n") if !modifiers.control() => {
+                    let next = model.pathlist.next_untagged();
+                    jump_to(model, next)
                 }
-                iced::keyboard::Key::Named(iced::keyboard::key::Named::Delete) => {
-                    tag_and_move_on(model, Tag::Tag7)
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::PageDown) => {
+                    let next = model.pathlist.folder_start(true);
+                    jump_to(model, next)
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::PageUp) => {
+                    let previous = model.pathlist.folder_start(false);
+                    jump_to(model, previous)
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::Home) => {
+                    let first = model.pathlist.visible().first().copied();
+                    jump_to(model, first)
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::End) => {
+                    let last = model.pathlist.visible().last().copied();
+                    jump_to(model, last)
+                }
+                iced::keyboard::Key::Character("b") if !modifiers.control() => {
+                    model.show_destination_preview = !model.show_destination_preview;
+                    Effect::None
+                }
+                iced::keyboard::Key::Character("+" | "=") if !modifiers.control() => {
+                    change_view(model, |view| view.zoomed(1, None))
+                }
+                iced::keyboard::Key::Character("-") if !modifiers.control() => {
+                    change_view(model, |view| view.zoomed(-1, None))
+                }
+                iced::keyboard::Key::Character("0") if !modifiers.control() => {
+                    change_view(model, |_| ViewState::default())
+                }
+                iced::keyboard::Key::Character("[") if !modifiers.control() => {
+                    change_view(model, |view| view.turned(false))
+                }
+                iced::keyboard::Key::Character("]") if !modifiers.control() => {
+                    change_view(model, |view| view.turned(true))
+                }
+                iced::keyboard::Key::Named(
+                    key @ (iced::keyboard::key::Named::ArrowUp
+                    | iced::keyboard::key::Named::ArrowDown),
+                ) if config.thumbnail_style == SortingViewStyle::Grid => {
+                    move_in_grid(model, key == iced::keyboard::key::Named::ArrowDown)
+                }
+                iced::keyboard::Key::Character("x") if !modifiers.control() => {
+                    let index = model.pathlist.index;
+                    model.pathlist.toggle_selected(index);
+                    Effect::None
+                }
+                iced::keyboard::Key::Character("X") if !modifiers.control() => {
+                    let index = model.pathlist.index;
+                    model.pathlist.select_range(index);
+                    Effect::None
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::F2) => {
+                    start_renaming_file(model)
+                }
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::Pause) => {
+                    model.set_background_paused(!model.background_paused)
                 }
                 iced::keyboard::Key::Named(iced::keyboard::key::Named::Backspace) => {
                     if !model.pathlist.paths.is_empty() {
@@ -447,6 +1521,7 @@ pub fn update_sorting_model(
             crate::Effect::None
         }
         SortingMessage::UserPressedRenameTag(tag) => {
+            model.removing_tag = None;
             let id = widget::text_input::Id::unique();
             model.editing_tag_name = Some((tag, "".to_owned(), id.clone()));
             crate::Effect::FocusElement(id)
@@ -458,17 +1533,162 @@ pub fn update_sorting_model(
         }
         SortingMessage::UserPressedCancelRenameTag => {
             model.editing_tag_name = None;
+            model.removing_tag = None;
             crate::Effect::None
         }
         SortingMessage::UserEditTagName(text) => {
             model.editing_tag_name.as_mut().unwrap().1 = text;
             crate::Effect::None
         }
-        SortingMessage::UserPressedMoveTag(tag) => crate::Effect::MoveThenLs(tag),
+        SortingMessage::UserPressedRenameFile => start_renaming_file(model),
+        SortingMessage::UserEditFileName(text) => {
+            if let Some((name, _)) = model.renaming_file.as_mut() {
+                *name = text;
+            }
+            crate::Effect::None
+        }
+        SortingMessage::UserPressedSubmitRenameFile => {
+            let Some((stem, _)) = model.renaming_file.take() else {
+                return crate::Effect::None;
+            };
+            let old_path = model.pathlist.current().path.clone();
+            match renamed_path(&old_path, &stem) {
+                Ok(new_path) if new_path == old_path => crate::Effect::None,
+                Ok(new_path) => crate::Effect::RenameFile(old_path, new_path),
+                Err(e) => {
+                    model.rename_error = Some(e);
+                    crate::Effect::None
+                }
+            }
+        }
+        SortingMessage::UserEditGoTo(text) => {
+            if let Some((number, _)) = model.going_to.as_mut() {
+                // Only digits can be typed
+                *number = text.chars().filter(char::is_ascii_digit).collect();
+            }
+            crate::Effect::None
+        }
+        SortingMessage::UserPressedSubmitGoTo => {
+            let Some((number, _)) = model.going_to.take() else {
+                return crate::Effect::None;
+            };
+            // Numbered from one among the visible images, as in the status line
+            let index = number
+                .parse::<usize>()
+                .ok()
+                .and_then(|position| position.checked_sub(1))
+                .and_then(|position| model.pathlist.visible().get(position).copied());
+            jump_to(model, index)
+        }
+        SortingMessage::UserPressedReloadImage => reload_current_image(model),
+        SortingMessage::UserPressedOpenExternally => open_current_externally(model),
+        SortingMessage::UserPressedExportMontage => export_montage(model),
+        SortingMessage::UserPressedNextSizeOutlier => {
+            let outliers = model.pathlist.size_outliers();
+            jump_to_next_of(model, &outliers)
+        }
+        SortingMessage::UserPressedQuarantine => quarantine_current_file(model),
+        SortingMessage::UserPressedNextProblemFile => {
+            let problem_files = model.pathlist.problem_files();
+            jump_to_next_of(model, &problem_files)
+        }
+        SortingMessage::UserPressedMoveTag(tag) => model.move_tag(tag),
+        // The summary is closed before getting here
+        SortingMessage::UserPressedKeepSorting => Effect::None,
+        SortingMessage::UserPressedFirstUntagged => jump_to_first_untagged(model),
+        SortingMessage::UserPressedStepButton(forward) => {
+            model.held_step = Some(HeldStep {
+                forward,
+                delay: HOLD_FIRST_DELAY,
+                next_step: std::time::Instant::now() + HOLD_FIRST_DELAY,
+            });
+            step(model, forward)
+        }
+        SortingMessage::UserReleasedStepButton => {
+            model.held_step = None;
+            Effect::None
+        }
+        SortingMessage::HeldStepTick => repeat_held_step(model),
+        SortingMessage::UserPressedSlideshow => {
+            model.slideshow = !model.slideshow;
+            Effect::None
+        }
+        SortingMessage::SlideshowTick => advance_slideshow(model),
+        SortingMessage::TagFlashTick => {
+            if model
+                .tag_flash
+                .as_ref()
+                .is_some_and(|flash| flash.start.elapsed() >= TAG_FLASH_DURATION)
+            {
+                model.tag_flash = None;
+            }
+            Effect::None
+        }
+        SortingMessage::UserPressedThumbnail(index) => click_thumbnail(model, index),
+        SortingMessage::UserHoveredThumbnail(index) => {
+            let paths = model.pathlist.hint(index, &model.config);
+            match model.canvas_dimensions {
+                Some(dim) if !paths.is_empty() => Effect::PreloadImages(paths, dim),
+                _ => Effect::None,
+            }
+        }
+        SortingMessage::UserPressedClearSelection => {
+            model.pathlist.clear_selection();
+            Effect::None
+        }
+        SortingMessage::UserPressedAddTag => {
+            let tag = model.tag_names.add();
+            let id = widget::text_input::Id::unique();
+            model.editing_tag_name = Some((tag, "".to_owned(), id.clone()));
+            crate::Effect::FocusElement(id)
+        }
+        SortingMessage::UserPressedRemoveTag(tag) => remove_tag(model, tag),
+        SortingMessage::UserChangedFilter(filter) => {
+            model.pathlist.filter = filter;
+            leave_hidden_image(model)
+        }
+        SortingMessage::UserChangedSizeFilter(size_filter) => {
+            model.pathlist.size_filter = size_filter;
+            leave_hidden_image(model)
+        }
+        SortingMessage::UserChangedKindFilter(kind) => {
+            model.pathlist.kind_filter = kind;
+            leave_hidden_image(model)
+        }
+        // Kept as the setting
+        SortingMessage::UserChangedSortOrder(order) => {
+            model.config.sort_order = order;
+            model.settings.show_sort_order(order);
+            let paths = model
+                .pathlist
+                .paths
+                .iter()
+                .map(|info| info.path.clone())
+                .collect();
+            Effect::Batch(vec![
+                Effect::SortListing(paths, order),
+                Effect::SaveConfig(Box::new(model.config.clone())),
+            ])
+        }
+        SortingMessage::UserScrolledImage(steps, focus) => {
+            change_view(model, |view| view.zoomed(steps, Some(focus)))
+        }
+        SortingMessage::UserScrolledReference(steps, focus) => {
+            if let Some(compare) = &mut model.compare {
+                compare.view = compare.view.zoomed(steps, Some(focus));
+            }
+            Effect::None
+        }
         SortingMessage::CanvasResized(dim) => {
             println!("Canvas resized to: {}x{}", dim.width, dim.height);
             if model.canvas_dimensions.as_ref() != Some(&dim) {
                 model.canvas_dimensions = Some(dim);
+                // Previews decoded for a noticeably different size are loaded again
+                let decode_size = model.decode_size(dim);
+                let unloaded = model
+                    .pathlist
+                    .unload_mismatched(decode_size, crate::PREVIEW_SIZE_TOLERANCE);
+                debug!("Unloaded {unloaded} previews after resize");
                 // Start the preloading now
                 crate::Effect::LsDir
             } else {
@@ -488,55 +1708,617 @@ pub fn view_sorting_model<'a>(
         return widget::text(t!("No images found")).into();
     }
 
-    let main_image_view = view_image_with_thumbs(config.thumbnail_style.clone(), model);
+    let hide_controls = model.fullscreen || (config.auto_hide_controls && model.controls_hidden);
+    let style = match config.thumbnail_style {
+        SortingViewStyle::ThumbsAbove if hide_controls => SortingViewStyle::NoThumbnails,
+        ref style => style.clone(),
+    };
+    let main_image_view = stack![view_image_with_thumbs(style, model)]
+        .push_maybe(view_histogram(model))
+        .push_maybe(model.fullscreen.then(|| view_fullscreen_badge(model)))
+        .push_maybe(view_tag_flash(model));
+    if hide_controls {
+        return center(main_image_view).into();
+    }
 
-    let preload_status_string = preload_list_status_string_pathlist(&model.pathlist, task_manager);
-    debug!("Preload status: {preload_status_string}");
+    let mut preload_status_string =
+        preload_list_status_string_pathlist(&model.pathlist, task_manager);
+    if model.background_paused {
+        preload_status_string.push_str(&format!(" ({})", t!("paused")));
+    }
+    trace!("Preload status: {preload_status_string}");
 
     let tag_count = count_tags(&model.pathlist.paths);
 
-    let status_text = widget::text(format!(
-        "({index}/{total}) {path}",
-        index = model.pathlist.index + 1,
-        total = model.pathlist.paths.len(),
-        path = model.pathlist.current().path,
-    ));
+    // Counted among the images the filter lets through
+    let (position, total) = model.pathlist.visible_position();
+    let status_text: Element<Message> = match (&model.renaming_file, &model.going_to) {
+        (Some((stem, id)), _) => row![
+            widget::text_input(&t!("File name"), stem)
+                .on_input(|text| Message::Sorting(SortingMessage::UserEditFileName(text)))
+                .on_submit(Message::Sorting(
+                    SortingMessage::UserPressedSubmitRenameFile
+                ))
+                .id(id.clone()),
+            widget::text(
+                std::path::Path::new(&model.pathlist.current().path)
+                    .extension()
+                    .map(|ext| format!(".{}", ext.to_string_lossy()))
+                    .unwrap_or_default()
+            ),
+        ]
+        .align_y(iced::Alignment::Center)
+        .into(),
+        (None, Some((number, id))) => row![
+            widget::text(t!("Go to image")),
+            widget::text_input(&format!("1-{}", format_count(total)), number)
+                .on_input(|text| Message::Sorting(SortingMessage::UserEditGoTo(text)))
+                .on_submit(Message::Sorting(SortingMessage::UserPressedSubmitGoTo))
+                .id(id.clone())
+                .width(100),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into(),
+        (None, None) if model.is_compact() => widget::text(format!(
+            "({index}/{total}) {name}",
+            index = format_count(position),
+            total = format_count(total),
+            name = if model.config.recursive_scan {
+                crate::display_path(&model.pathlist.current().path).to_owned()
+            } else {
+                tag_io::file_name(&model.pathlist.current().path)
+            },
+        ))
+        .into(),
+        (None, None) => row![
+            widget::text(format!(
+                "({index}/{total}) {path}",
+                index = format_count(position),
+                total = format_count(total),
+                path = crate::display_path(&model.pathlist.current().path),
+            )),
+            widget::text(
+                model
+                    .pathlist
+                    .current()
+                    .metadata
+                    .file_size
+                    .map(format_file_size)
+                    .unwrap_or_default()
+            ),
+            widget::button(widget::text(t!("Rename")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedRenameFile)),
+            widget::button(widget::text(t!("Reload")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedReloadImage)),
+            widget::button(widget::text(t!("Open in viewer")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedOpenExternally)),
+            widget::button(widget::text(if model.slideshow {
+                t!("Stop slideshow")
+            } else {
+                t!("Slideshow")
+            }))
+            .on_press(Message::Sorting(SortingMessage::UserPressedSlideshow)),
+        ]
+        .push_maybe(model.rename_error.as_ref().map(widget::text))
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into(),
+    };
 
+    let size_outlier_count = model.pathlist.size_outlier_count();
+    let problem_files = model.pathlist.problem_files();
+    let compact = model.is_compact();
     let tag_buttons = view_tag_button_row(
         model.editing_tag_name.as_ref(),
+        model.removing_tag,
         &model.tag_names,
         &tag_count,
+        compact,
     );
 
-    let action_buttons = row![
-        widget::button(widget::text(t!("<- Previous")))
-            .on_press(crate::Message::Sorting(
-                SortingMessage::UserPressedPreviousImage
-            ))
+    let can_step_back = model.pathlist.prev_visible().is_some();
+    let can_step_forward = can_step_forward(model);
+    let action_buttons: Element<Message> = if compact {
+        row![
+            view_step_button("<-".to_owned(), false, can_step_back, [5, 10]),
+            view_step_button("->".to_owned(), true, can_step_forward, [5, 10]),
+            widget::button(widget::text(t!("Select Folder")))
+                .on_press(crate::Message::UserPressedSelectFolder),
+        ]
+        .spacing(5)
+        .into()
+    } else {
+        row![
+            view_step_button(t!("<- Previous").to_string(), false, can_step_back, 10),
+            view_step_button(t!("Next ->").to_string(), true, can_step_forward, 10),
+            widget::button(widget::text(t!(
+                "Next size outlier (%{count})",
+                count = format_count(size_outlier_count)
+            )))
+            .on_press_maybe(
+                (size_outlier_count > 0)
+                    .then_some(Message::Sorting(SortingMessage::UserPressedNextSizeOutlier))
+            )
             .padding(10),
-        widget::button(widget::text(t!("Next ->")))
-            .on_press(crate::Message::Sorting(
-                SortingMessage::UserPressedNextImage
-            ))
+            widget::button(widget::text(t!(
+                "Next problem file (%{count})",
+                count = format_count(problem_files.len())
+            )))
+            .on_press_maybe(
+                (!problem_files.is_empty())
+                    .then_some(Message::Sorting(SortingMessage::UserPressedNextProblemFile))
+            )
             .padding(10),
-        widget::button(widget::text(t!("Select Folder")))
-            .on_press(crate::Message::UserPressedSelectFolder)
+            widget::button(widget::text(t!("Select Folder")))
+                .on_press(crate::Message::UserPressedSelectFolder)
+                .padding(10),
+            widget::button(widget::text(t!(
+                "Next folder (%{count} queued)",
+                count = format_count(model.folder_queue.len())
+            )))
+            .on_press_maybe(
+                (!model.folder_queue.is_empty()).then_some(crate::Message::UserPressedNextFolder)
+            )
             .padding(10),
-    ];
+            widget::button(widget::text(if model.background_paused {
+                t!("Resume background work")
+            } else {
+                t!("Pause background work")
+            }))
+            .on_press(crate::Message::UserToggledPauseBackground)
+            .padding(10),
+        ]
+        .into()
+    };
 
-    let content = column![
-        main_image_view,
-        status_text,
-        tag_buttons,
-        action_buttons,
-        widget::text(preload_status_string),
-    ];
+    let recovery_banner: Option<Element<Message>> = model.pending_recovery.as_ref().map(|_| {
+        row![
+            widget::text(t!("Found an unfinished session from a previous run")),
+            widget::button(widget::text(t!("Restore")))
+                .on_press(crate::Message::UserPressedRestoreCheckpoint),
+            widget::button(widget::text(t!("Discard")))
+                .on_press(crate::Message::UserPressedDiscardCheckpoint),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    });
+
+    let content = column![]
+        .push_maybe((!compact).then(|| view_filter_chips(model, &tag_count)))
+        .extend([
+            main_image_view.into(),
+            status_text,
+            tag_buttons,
+            action_buttons,
+        ])
+        .push_maybe((!compact).then(|| widget::text(preload_status_string)))
+        .push_maybe(view_visual_diff(model))
+        .push_maybe(view_problem_file(model))
+        .push_maybe(view_info_panel(model))
+        .push_maybe(view_selection_bar(model))
+        .push_maybe(view_destructive_key_hint(model))
+        .push_maybe(recovery_banner);
+
+    if model.show_destination_preview {
+        let preview = model.destination_preview.as_ref();
+        let folder_name = preview.map(|preview| model.tag_names.get(&preview.tag));
+        let panel = destination_preview::view(preview, folder_name, model.config.thumbnail_size);
+        row![
+            center(content),
+            widget::container(widget::scrollable(panel))
+                .width(Length::Fixed(DESTINATION_PANEL_WIDTH))
+                .padding(10),
+        ]
+        .into()
+    } else {
+        center(content).into()
+    }
+}
 
-    center(content).into()
+// The images with the tag are left untagged, at least one tag is kept
+// Images that have the tag are left untagged, which is asked about first
+fn remove_tag(model: &mut crate::Model, tag: Tag) -> Effect {
+    if model.tag_names.len() <= 1 {
+        return Effect::None;
+    }
+    let in_use = model
+        .pathlist
+        .paths
+        .iter()
+        .any(|info| info.metadata.tag == Some(tag));
+    if in_use && model.removing_tag != Some(tag) {
+        model.removing_tag = Some(tag);
+        return Effect::None;
+    }
+    model.removing_tag = None;
+    model.tag_names.remove(tag);
+    for info in &mut model.pathlist.paths {
+        if info.metadata.tag == Some(tag) {
+            info.metadata.tag = None;
+        }
+    }
+    if model.pathlist.filter == ViewFilter::Tag(tag) {
+        model.pathlist.filter = ViewFilter::All;
+    }
+    if model.selected_action_tag == Some(tag) {
+        model.selected_action_tag = None;
+    }
+    if model
+        .editing_tag_name
+        .as_ref()
+        .is_some_and(|(t, _, _)| *t == tag)
+    {
+        model.editing_tag_name = None;
+    }
+    Effect::None
 }
 
 fn is_typing_action(model: &crate::Model) -> bool {
-    model.editing_tag_name.is_some()
+    model.editing_tag_name.is_some() || model.renaming_file.is_some() || model.going_to.is_some()
+}
+
+// Compares the current image with the previous one
+fn toggle_visual_diff(model: &mut crate::Model) -> Effect {
+    if model.visual_diff.take().is_some() {
+        return Effect::None;
+    }
+    let Some(current) = model.pathlist.paths.get(model.pathlist.index) else {
+        return Effect::None;
+    };
+    let PreloadImage::Loaded(b) = &current.data else {
+        return Effect::None;
+    };
+    // The pinned image when comparing, otherwise the previous one
+    let reference = match (&model.compare, model.pathlist.prev()) {
+        (Some(compare), _) => (compare.path.clone(), compare.image.clone()),
+        (None, Some(prev)) => match &prev.data {
+            PreloadImage::Loaded(a) => (prev.path.clone(), a.image.clone()),
+            _ => return Effect::None,
+        },
+        (None, None) => return Effect::None,
+    };
+    Effect::ComputeVisualDiff(
+        reference.0,
+        reference.1,
+        current.path.clone(),
+        b.image.clone(),
+    )
+}
+
+fn view_visual_diff(model: &crate::Model) -> Option<Element<'_, Message>> {
+    let diff = model.visual_diff.as_ref()?;
+    let current = &model.pathlist.current().path;
+    let reference = match &model.compare {
+        Some(compare) => &compare.path,
+        None => &model.pathlist.prev()?.path,
+    };
+    if diff.path_a != *reference || diff.path_b != *current {
+        return None;
+    }
+    let score = format_decimal(diff.score, 3);
+    Some(
+        row![
+            view_loaded_image(
+                PixelCanvas::new(Some(&diff.heatmap), false),
+                None,
+                Some(model.config.thumbnail_size),
+                false,
+            ),
+            widget::text(match model.compare {
+                Some(_) => t!("Similarity to pinned image (SSIM): %{score}", score = score),
+                None => t!(
+                    "Similarity to previous image (SSIM): %{score}",
+                    score = score
+                ),
+            }),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into(),
+    )
+}
+
+fn toggle_info_panel(model: &mut crate::Model) -> Effect {
+    model.show_info_panel = !model.show_info_panel;
+    // Capture times are only read the first time they are needed
+    if model.show_info_panel && model.capture_heatmap.is_none() {
+        let paths = model.pathlist.paths.iter().map(|info| info.path.clone());
+        Effect::ReadCaptureTimes(paths.collect())
+    } else {
+        Effect::None
+    }
+}
+
+fn view_info_panel(model: &crate::Model) -> Option<Element<'_, Message>> {
+    if !model.show_info_panel {
+        return None;
+    }
+    let current = model.pathlist.current();
+    let decode_time = match current.metadata.decode_time {
+        Some(duration) => t!(
+            "%{ms} ms",
+            ms = format_decimal(duration.as_secs_f64() * 1000.0, 1)
+        )
+        .to_string(),
+        None => "-".to_owned(),
+    };
+    Some(
+        widget::container(
+            column![
+                widget::text(tag_io::file_name(&current.path)).size(18),
+                widget::text(t!("Decode time: %{time}", time = decode_time)),
+                model.decode_histogram.view(),
+            ]
+            .push_maybe(current.metadata.sidecar.as_ref().map(view_sidecar))
+            .push_maybe((!current.metadata.keywords.is_empty()).then(|| {
+                widget::text(t!(
+                    "Keywords: %{keywords}",
+                    keywords = current.metadata.keywords.join(", ")
+                ))
+            }))
+            .push_maybe(current.metadata.history.as_ref().map(view_file_history))
+            .push(match &model.capture_heatmap {
+                Some(heatmap) => heatmap.view(),
+                None => widget::text(t!("Reading capture times...")).into(),
+            })
+            .spacing(10),
+        )
+        .padding(10)
+        .into(),
+    )
+}
+
+fn view_sidecar(sidecar: &SidecarMetadata) -> Element<'_, Message> {
+    column![]
+        .push_maybe(
+            sidecar
+                .description
+                .as_ref()
+                .map(|description| widget::text(t!("Caption: %{caption}", caption = description))),
+        )
+        .push_maybe(
+            sidecar
+                .title
+                .as_ref()
+                .map(|title| widget::text(t!("Original name: %{name}", name = title))),
+        )
+        .spacing(5)
+        .into()
+}
+
+fn view_file_history(history: &FileHistory) -> Element<'_, Message> {
+    let other_paths = history
+        .other_paths
+        .iter()
+        .map(|path| widget::text(t!("Also at %{path}", path = path)).into());
+    let moves = history.moves.iter().map(|record| {
+        let date = time::OffsetDateTime::from_unix_timestamp(record.at)
+            .map(|at| format_date(at.to_offset(exif_data::local_offset()).date()))
+            .unwrap_or_default();
+        widget::text(t!(
+            "Moved to %{destination} from %{folder} on %{date}",
+            destination = record.destination,
+            folder = record.folder,
+            date = date
+        ))
+        .into()
+    });
+    column(other_paths.chain(moves)).spacing(5).into()
+}
+
+fn run_key_action(
+    model: &mut crate::Model,
+    action: KeyAction,
+    modifiers: iced::keyboard::Modifiers,
+) -> Effect {
+    match action {
+        KeyAction::Tag(tag) => tag_and_move_on(model, tag),
+        KeyAction::NextImage => user_pressed_next_image(model),
+        KeyAction::PreviousImage => user_pressed_previous_image(model),
+        KeyAction::Delete => {
+            if destructive_key_allowed(model, modifiers) {
+                tag_and_move_on(model, REJECT_TAG)
+            } else {
+                Effect::None
+            }
+        }
+        KeyAction::ToggleHistogram => {
+            model.show_histogram = !model.show_histogram;
+            Effect::None
+        }
+    }
+}
+
+fn destructive_key_allowed(model: &mut crate::Model, modifiers: iced::keyboard::Modifiers) -> bool {
+    match model.config.destructive_key_guard {
+        DestructiveKeyGuard::Off => true,
+        DestructiveKeyGuard::Shift => modifiers.shift(),
+        DestructiveKeyGuard::DoublePress => match model.destructive_key_pressed.take() {
+            Some(pressed) if pressed.elapsed() < DOUBLE_PRESS_INTERVAL => true,
+            _ => {
+                model.destructive_key_pressed = Some(std::time::Instant::now());
+                false
+            }
+        },
+    }
+}
+
+fn view_destructive_key_hint(model: &crate::Model) -> Option<Element<'_, Message>> {
+    let hint = match model.config.destructive_key_guard {
+        DestructiveKeyGuard::Off => return None,
+        DestructiveKeyGuard::DoublePress => {
+            let pressed = model.destructive_key_pressed?;
+            if pressed.elapsed() >= DOUBLE_PRESS_INTERVAL {
+                return None;
+            }
+            t!("Press Delete again to reject")
+        }
+        DestructiveKeyGuard::Shift => t!("Shift+Delete rejects"),
+    };
+    Some(widget::text(hint).into())
+}
+
+fn jump_to(model: &mut crate::Model, index: Option<usize>) -> Effect {
+    let Some(index) = index else {
+        return Effect::None;
+    };
+    model.last_navigation = std::time::Instant::now();
+    let paths = model.pathlist.jump_to(index, &model.config);
+    match model.canvas_dimensions {
+        Some(dim) if !paths.is_empty() => Effect::PreloadImages(paths, dim),
+        _ => Effect::None,
+    }
+}
+
+fn jump_to_next_of(model: &mut crate::Model, indices: &[usize]) -> Effect {
+    model.last_navigation = std::time::Instant::now();
+    let paths = model.pathlist.jump_to_next_of(indices, &model.config);
+    match model.canvas_dimensions {
+        Some(dim) if !paths.is_empty() => Effect::PreloadImages(paths, dim),
+        _ => Effect::None,
+    }
+}
+
+// Suggests what to do with the current image when it is empty or broken
+fn view_problem_file(model: &crate::Model) -> Option<Element<'_, Message>> {
+    let current = model.pathlist.current();
+    let error = match &current.data {
+        PreloadImage::Failed(error) => error.as_str(),
+        // Known to be empty from the folder listing before any read
+        _ if current.metadata.file_size == Some(0) => crate::pathlist::EMPTY_FILE_ERROR,
+        _ => return None,
+    };
+    Some(
+        row![
+            widget::text(t!("Problem file: %{error}", error = error)),
+            widget::button(widget::text(t!(
+                "Tag as %{tag}",
+                tag = model.tag_names.get(&REJECT_TAG)
+            )))
+            .on_press_maybe(model.tag_names.contains(&REJECT_TAG).then_some(
+                Message::Sorting(SortingMessage::UserPressedTagButton(REJECT_TAG))
+            )),
+            widget::button(widget::text(t!("Quarantine")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedQuarantine)),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into(),
+    )
+}
+
+// The selected images, or the current one, as they are turned
+fn print_selected_or_current(model: &crate::Model) -> Effect {
+    if model.pathlist.paths.is_empty() {
+        return Effect::None;
+    }
+    Effect::Print(
+        model
+            .pathlist
+            .selected_or_current()
+            .into_iter()
+            .map(|i| {
+                let info = &model.pathlist.paths[i];
+                (info.path.clone(), info.metadata.view.quarter_turns)
+            })
+            .collect(),
+    )
+}
+
+// Absolute and quoted for a shell, so they can be pasted into a terminal
+fn copy_selected_or_current_paths(model: &crate::Model) -> Effect {
+    if model.pathlist.paths.is_empty() {
+        return Effect::None;
+    }
+    let paths: Vec<String> = model
+        .pathlist
+        .selected_or_current()
+        .into_iter()
+        .map(|i| {
+            let path = crate::absolute_path(std::path::Path::new(&model.pathlist.paths[i].path));
+            shell_quote(&path.to_string_lossy())
+        })
+        .collect();
+    Effect::CopyToClipboard(paths.join(" "))
+}
+
+fn shell_quote(text: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "/._-+,:@%".contains(c);
+    if !text.is_empty() && text.chars().all(plain) {
+        return text.to_owned();
+    }
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// Moves the current file to the hidden quarantine folder, it can be undone
+// from the banner shown after the move
+fn quarantine_current_file(model: &crate::Model) -> Effect {
+    match model.pathlist.paths.get(model.pathlist.index) {
+        Some(info) => Effect::Quarantine(vec![info.path.clone()]),
+        None => Effect::None,
+    }
+}
+
+fn open_current_externally(model: &crate::Model) -> Effect {
+    match model.pathlist.paths.get(model.pathlist.index) {
+        Some(info) => Effect::OpenExternally(info.path.clone()),
+        None => Effect::None,
+    }
+}
+
+// Decodes the current image again, for when the file was changed on disk
+fn reload_current_image(model: &mut crate::Model) -> Effect {
+    let Some(dim) = model.canvas_dimensions else {
+        return Effect::None;
+    };
+    if model.pathlist.paths.is_empty() {
+        return Effect::None;
+    }
+    let current = model.pathlist.current_mut();
+    let path = current.path.clone();
+    current.data = PreloadImage::Loading(path.clone());
+    Effect::Batch(vec![
+        Effect::PreloadImages(vec![path.clone()], dim),
+        Effect::ReadFileIdentities(vec![path.clone()]),
+        Effect::ReadSidecars(vec![path]),
+    ])
+}
+
+fn start_renaming_file(model: &mut crate::Model) -> Effect {
+    if model.pathlist.paths.is_empty() {
+        return Effect::None;
+    }
+    // The original name from a sidecar is offered, exports often shorten it
+    let current = model.pathlist.current();
+    let original_name = current
+        .metadata
+        .sidecar
+        .as_ref()
+        .and_then(|sidecar| sidecar.title.as_deref());
+    let stem = std::path::Path::new(original_name.unwrap_or(&current.path))
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let id = widget::text_input::Id::unique();
+    model.renaming_file = Some((stem, id.clone()));
+    model.rename_error = None;
+    Effect::FocusElement(id)
+}
+
+// Only the file stem is editable, the extension is kept as is
+fn renamed_path(old_path: &str, new_stem: &str) -> Result<String, String> {
+    let new_stem = new_stem.trim();
+    if new_stem.is_empty() || new_stem.contains(['/', '\\']) || new_stem == ".." {
+        return Err(t!("Invalid file name").to_string());
+    }
+    let old = std::path::Path::new(old_path);
+    let file_name = match old.extension() {
+        Some(ext) => format!("{new_stem}.{}", ext.to_string_lossy()),
+        None => new_stem.to_owned(),
+    };
+    Ok(old.with_file_name(file_name).to_string_lossy().to_string())
 }
 
 fn view_image_with_thumbs<'a>(
@@ -546,55 +2328,200 @@ fn view_image_with_thumbs<'a>(
     match sorting_view_style {
         SortingViewStyle::NoThumbnails => view_with_no_thumbnails(model),
         SortingViewStyle::ThumbsAbove => view_with_thumbnails_on_top(model),
+        SortingViewStyle::Grid => view_with_grid(model),
     }
 }
 
-fn view_with_no_thumbnails(model: &crate::Model) -> Element<Message> {
-    let image = view_image(
-        model.pathlist.current(),
+pub fn grid_scroll_id() -> widget::scrollable::Id {
+    widget::scrollable::Id::new("grid")
+}
+
+// As many thumbnails as fit next to each other
+fn grid_columns(model: &crate::Model) -> usize {
+    let cell_width = model.config.thumbnail_size.width as f32 + GRID_SPACING;
+    ((model.window_width - 40.0) / cell_width).floor().max(1.0) as usize
+}
+
+// Row of the current image in the grid and the number of rows, when shown
+pub fn grid_position(model: &crate::Model) -> Option<(usize, usize)> {
+    if model.config.thumbnail_style != SortingViewStyle::Grid {
+        return None;
+    }
+    let visible = model.pathlist.visible();
+    let position = visible.iter().position(|&i| i == model.pathlist.index)?;
+    let columns = grid_columns(model);
+    Some((position / columns, visible.len().div_ceil(columns)))
+}
+
+// Up and down in the grid, by a row of the images the filter lets through
+fn move_in_grid(model: &mut crate::Model, down: bool) -> Effect {
+    let visible = model.pathlist.visible();
+    let Some(position) = visible.iter().position(|&i| i == model.pathlist.index) else {
+        return Effect::None;
+    };
+    let columns = grid_columns(model);
+    let target = match down {
+        true => (position + columns).min(visible.len() - 1),
+        false => position.saturating_sub(columns),
+    };
+    jump_to_next_of(model, &[visible[target]])
+}
+
+// Control adds to the selection and Shift selects a range, a plain click goes
+// to the image
+fn click_thumbnail(model: &mut crate::Model, index: usize) -> Effect {
+    if model.modifiers.control() {
+        model.pathlist.toggle_selected(index);
+        Effect::None
+    } else if model.modifiers.shift() {
+        model.pathlist.select_range(index);
+        Effect::None
+    } else {
+        jump_to_next_of(model, &[index])
+    }
+}
+
+fn view_thumbnail(model: &crate::Model, index: usize) -> Element<'_, Message> {
+    let thumb = view_image(
+        &model.pathlist.paths[index],
         &model.tag_names,
-        None,
+        Some(model.config.thumbnail_size),
+        index == model.pathlist.index,
         false,
-        true,
+        None,
+        model.config.thumbnail_shape,
     );
+    // The image that was just tagged flashes in the color of the tag
+    let border_color = match &model.tag_flash {
+        Some(flash) if flash.indices.contains(&index) => Some(model.tag_names.color(&flash.tag)),
+        _ if model.pathlist.selected.contains(&index) => Some(Color::from_rgb(0.1, 0.6, 0.2)),
+        _ => None,
+    };
+    let thumb =
+        widget::container(thumb)
+            .padding(2)
+            .style(move |_: &iced::Theme| match border_color {
+                Some(color) => widget::container::Style::default().border(iced::Border {
+                    radius: iced::border::radius(5),
+                    color,
+                    width: 2.0,
+                }),
+                None => widget::container::Style::default(),
+            });
+    widget::mouse_area(thumb)
+        .on_press(Message::Sorting(SortingMessage::UserPressedThumbnail(
+            index,
+        )))
+        .on_enter(Message::Sorting(SortingMessage::UserHoveredThumbnail(
+            index,
+        )))
+        .interaction(iced::mouse::Interaction::Pointer)
+        .into()
+}
 
-    image
+fn view_selection_bar(model: &crate::Model) -> Option<Element<'_, Message>> {
+    if model.pathlist.selected.is_empty() {
+        return None;
+    }
+    Some(
+        row![
+            widget::text(t!(
+                "%{count} selected, tag keys tag all of them",
+                count = format_count(model.pathlist.selected.len())
+            )),
+            widget::button(widget::text(t!("Clear selection")))
+                .on_press(Message::Sorting(SortingMessage::UserPressedClearSelection)),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into(),
+    )
 }
 
-fn view_with_thumbnails_on_top(model: &crate::Model) -> Element<Message> {
+fn view_with_grid(model: &crate::Model) -> Element<'_, Message> {
+    let visible = model.pathlist.visible();
+    let rows = visible.chunks(grid_columns(model)).map(|chunk| {
+        let cells = chunk.iter().map(|&i| view_thumbnail(model, i));
+        row(cells).spacing(GRID_SPACING).into()
+    });
+    let grid = widget::scrollable(column(rows).spacing(GRID_SPACING))
+        .id(grid_scroll_id())
+        .width(Length::Fill)
+        .height(Length::FillPortion(3));
+    let image = widget::container(view_current_image(model)).height(Length::FillPortion(2));
+    column![grid, image].spacing(10).into()
+}
+
+// With the pinned image on the left while comparing
+fn view_current_image(model: &crate::Model) -> Element<'_, Message> {
     let image = view_image(
         model.pathlist.current(),
         &model.tag_names,
         None,
         false,
         true,
+        Some(model.spinner_phase),
+        model.config.thumbnail_shape,
     );
+    let Some(compare) = &model.compare else {
+        return image;
+    };
+    let reference = canvas(
+        PixelCanvas::new(Some(&compare.image), false)
+            .reference()
+            .with_view(compare.view),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill);
+    let label = row![
+        widget::text(t!(
+            "Pinned: %{name}",
+            name = tag_io::file_name(&compare.path)
+        ))
+        .size(12),
+        widget::button(widget::text(t!("Export side by side")).size(12))
+            .on_press(Message::Sorting(SortingMessage::UserPressedExportMontage))
+            .padding([2, 6]),
+    ]
+    .spacing(10)
+    .align_y(iced::Alignment::Center);
+    row![column![label, reference].spacing(5), image]
+        .spacing(10)
+        .into()
+}
+
+fn view_with_no_thumbnails(model: &crate::Model) -> Element<'_, Message> {
+    view_current_image(model)
+}
 
-    // Three on each side
+fn view_with_thumbnails_on_top(model: &crate::Model) -> Element<'_, Message> {
+    let image = view_current_image(model);
+
+    // Three on each side, of the images the filter lets through
     let num_thumbs = 3;
     let mut thumbs = Vec::new();
-    let from = model.pathlist.index.saturating_sub(num_thumbs);
-    let to = min(
-        model.pathlist.index + num_thumbs,
-        model.pathlist.paths.len() - 1,
-    );
-    for i in from..=to {
+    for i in model.pathlist.visible_around(num_thumbs) {
         let img = &model.pathlist.paths[i];
-        let highlight = i == model.pathlist.index;
-        let thumb = view_image(
-            img,
-            &model.tag_names,
-            Some(model.config.thumbnail_size),
-            highlight,
-            false,
-        );
-        thumbs.push(thumb);
+        let thumb = view_thumbnail(model, i);
+        let size_text = widget::text(
+            img.metadata
+                .file_size
+                .map(format_file_size)
+                .unwrap_or_default(),
+        )
+        .size(10);
+        let size_text = if model.pathlist.is_size_outlier(i) {
+            size_text.color(Color::from_rgb(0.8, 0.1, 0.1))
+        } else {
+            size_text
+        };
+        thumbs.push(column![thumb, size_text].into());
     }
 
     column![widget::Row::from_vec(thumbs), image].into()
 }
 
-pub fn count_tags(paths: &Vec<ImageInfo>) -> HashMap<Tag, u32> {
+pub fn count_tags(paths: &[ImageInfo]) -> HashMap<Tag, u32> {
     let mut tag_count = std::collections::HashMap::new();
 
     for metadata in paths.iter().map(|info| &info.metadata) {
@@ -606,3 +2533,143 @@ pub fn count_tags(paths: &Vec<ImageInfo>) -> HashMap<Tag, u32> {
 
     tag_count
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TagTotal {
+    pub count: u32,
+    // Bytes on disk of the files whose size is known
+    pub size: u64,
+    // The sizes are read in the background after listing, some of them are
+    // not in the size yet
+    pub partial: bool,
+}
+
+// Every tag of the session, also those no image has yet
+pub fn tag_totals(paths: &[ImageInfo], tag_names: &TagNames) -> HashMap<Tag, TagTotal> {
+    let mut totals: HashMap<Tag, TagTotal> = tag_names
+        .enumerate()
+        .map(|(tag, _)| (tag, TagTotal::default()))
+        .collect();
+    for metadata in paths.iter().map(|info| &info.metadata) {
+        if let Some(tag) = metadata.tag {
+            let total = totals.entry(tag).or_default();
+            total.count += 1;
+            match metadata.file_size {
+                Some(size) => total.size += size,
+                None => total.partial = true,
+            }
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/photos/IMG_0001.jpg"), "/photos/IMG_0001.jpg");
+        assert_eq!(
+            shell_quote("/photos/Tom's day.jpg"),
+            "'/photos/Tom'\\''s day.jpg'"
+        );
+    }
+
+    #[test]
+    fn test_tag_totals() {
+        let mut pathlist = crate::pathlist::PathList::new(
+            ["a.jpg", "b.jpg", "c.jpg", "d.jpg"]
+                .map(String::from)
+                .to_vec(),
+        );
+        for (info, (tag, size)) in pathlist.paths.iter_mut().zip([
+            (Some(Tag::new(1)), Some(100)),
+            (Some(Tag::new(1)), Some(50)),
+            (Some(REJECT_TAG), None),
+            (None, Some(10)),
+        ]) {
+            info.metadata.tag = tag;
+            info.metadata.file_size = size;
+        }
+        let totals = tag_totals(&pathlist.paths, &TagNames::new());
+        assert_eq!(
+            totals[&Tag::new(1)],
+            TagTotal {
+                count: 2,
+                size: 150,
+                partial: false,
+            }
+        );
+        // Not yet read
+        assert!(totals[&REJECT_TAG].partial);
+        assert_eq!(totals[&Tag::new(2)], TagTotal::default());
+        assert_eq!(totals.len(), 8);
+    }
+
+    #[test]
+    fn test_visual_diff_against_pinned() {
+        let (mut model, _) = crate::Model::new(crate::StartupOptions {
+            safe_mode: true,
+            locale: Some("en".to_owned()),
+            ..Default::default()
+        });
+        model.pathlist =
+            crate::pathlist::PathList::new(["a.jpg", "b.jpg", "c.jpg"].map(String::from).to_vec());
+        for info in &mut model.pathlist.paths {
+            info.data = PreloadImage::Loaded(LoadedImageAndThumb {
+                image: ImageData::new(1, 1, vec![0; 4]),
+                thumb: ImageData::new(1, 1, vec![0; 4]),
+            });
+        }
+        toggle_compare(&mut model);
+        model.pathlist.index = 2;
+        match toggle_visual_diff(&mut model) {
+            Effect::ComputeVisualDiff(a, _, b, _) => {
+                assert_eq!((a, b), ("a.jpg".into(), "c.jpg".into()))
+            }
+            effect => panic!("Expected a diff, got {effect:?}"),
+        }
+
+        // The previous image once nothing is pinned
+        toggle_compare(&mut model);
+        match toggle_visual_diff(&mut model) {
+            Effect::ComputeVisualDiff(a, _, _, _) => assert_eq!(a, "b.jpg"),
+            effect => panic!("Expected a diff, got {effect:?}"),
+        }
+    }
+
+    #[test]
+    fn test_tag_names() {
+        let mut tag_names = TagNames::new();
+        let tag = tag_names.add();
+        assert_eq!(tag, Tag::new(9));
+        assert_eq!(tag_names.get(&tag), "Tag 9");
+        assert_eq!(tag_names.color(&tag), TAG_COLORS[8]);
+
+        // A removed color is given to the next new tag, but not its number
+        tag_names.remove(Tag::new(2));
+        let tag = tag_names.add();
+        assert_eq!(tag_names.color(&tag), TAG_COLORS[1]);
+
+        // Tags are written as before, so older files can be read
+        assert_eq!(serde_json::to_string(&tag).unwrap(), "\"Tag10\"");
+        let json = r#"{"tag1":"Keep","tag2":"b","tag3":"c","tag4":"d",
+            "tag5":"e","tag6":"f","tag7":"Reject","tag8":"h"}"#;
+        let old: TagNames = serde_json::from_str(json).unwrap();
+        assert_eq!(old.get(&REJECT_TAG), "Reject");
+
+        // Moved to the tag name unless set apart
+        assert_eq!(tag_names.destination(&tag), "Tag 10");
+        tag_names.set_destination(tag, "/mnt/archive/keep".to_owned());
+        assert_eq!(tag_names.destination(&tag), "/mnt/archive/keep");
+        let json = serde_json::to_string(&tag_names).unwrap();
+        assert_eq!(serde_json::from_str::<TagNames>(&json).unwrap(), tag_names);
+
+        // Still not given out again after being saved and read back
+        tag_names.remove(tag);
+        let json = serde_json::to_string(&tag_names).unwrap();
+        let mut read: TagNames = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.add(), Tag::new(11));
+    }
+}
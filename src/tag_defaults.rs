@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use crate::session::{self, SESSION_FILE};
+use crate::sorting::{Tag, TagNames};
+
+// Names for the tags of a folder opened without a session of its own, so they
+// don't have to be typed again for every folder. In order of preference: the
+// names of the latest session next to the folder, such as the previous card
+// dump, the default names from the settings, or the names already in use.
+// Tags still called by their color then take the names of subfolders left by
+// an earlier sorting, unless subfolders are scanned for images.
+
+pub fn suggest(folder: &Path, in_use: &TagNames, defaults: &[String], recursive: bool) -> TagNames {
+    let mut tag_names = match latest_sibling_session(folder) {
+        Some(tag_names) => tag_names,
+        None if !defaults.is_empty() => {
+            let mut tag_names = TagNames::new();
+            for (number, name) in (1..).zip(defaults) {
+                let tag = Tag::new(number);
+                tag_names.ensure(tag);
+                tag_names.update(tag, name.clone());
+            }
+            tag_names
+        }
+        None => in_use.clone(),
+    };
+    if !recursive {
+        name_after_subfolders(&mut tag_names, folder);
+    }
+    tag_names
+}
+
+fn latest_sibling_session(folder: &Path) -> Option<TagNames> {
+    let parent = folder.parent()?;
+    let folder = folder.canonicalize().ok()?;
+    let (_, latest) = std::fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.canonicalize().ok().as_ref() != Some(&folder))
+        .filter_map(|path| {
+            let modified = path.join(SESSION_FILE).metadata().ok()?.modified().ok()?;
+            Some((modified, path))
+        })
+        .max()?;
+    session::load(&latest).tag_names
+}
+
+fn name_after_subfolders(tag_names: &mut TagNames, folder: &Path) {
+    let names: Vec<String> = tag_names
+        .enumerate()
+        .map(|(_, name)| name.clone())
+        .collect();
+    let mut subfolders: Vec<String> = subfolders(folder)
+        .iter()
+        .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+        .filter(|name| !name.starts_with('.') && !names.contains(name))
+        .collect();
+    subfolders.sort();
+
+    let colors = TagNames::new();
+    let unnamed: Vec<Tag> = tag_names
+        .enumerate()
+        .filter(|(tag, name)| colors.contains(tag) && colors.get(tag) == name.as_str())
+        .map(|(tag, _)| tag)
+        .collect();
+    for (tag, name) in unnamed.into_iter().zip(subfolders) {
+        tag_names.update(tag, name);
+    }
+}
+
+fn subfolders(folder: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let folder = dir.join("102");
+        std::fs::create_dir_all(folder.join("keepers")).unwrap();
+
+        // Subfolders only name the tags still called by their color
+        let defaults = ["Keep".to_owned(), "Reject".to_owned()];
+        let tag_names = suggest(&folder, &TagNames::new(), &defaults, false);
+        assert_eq!(tag_names.get(&Tag::new(1)), "Keep");
+        assert_eq!(tag_names.get(&Tag::new(2)), "Reject");
+        assert_eq!(tag_names.get(&Tag::new(3)), "keepers");
+        let tag_names = suggest(&folder, &TagNames::new(), &defaults, true);
+        assert_eq!(
+            tag_names.get(&Tag::new(3)),
+            TagNames::new().get(&Tag::new(3))
+        );
+
+        // A session next to the folder comes first
+        let sibling = dir.join("101");
+        std::fs::create_dir_all(&sibling).unwrap();
+        let mut sibling_names = TagNames::new();
+        sibling_names.update(Tag::new(1), "Family".to_owned());
+        let session = session::Session {
+            tags: [("a.jpg".to_owned(), Tag::new(1))].into_iter().collect(),
+            tag_names: Some(sibling_names),
+            ..session::Session::default()
+        };
+        session::save(&sibling, &session).unwrap();
+        let tag_names = suggest(&folder, &TagNames::new(), &defaults, true);
+        assert_eq!(tag_names.get(&Tag::new(1)), "Family");
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sorting::{Tag, TagNames};
+
+// Export and import of per-file tag assignments, as CSV or JSON depending on
+// the file extension. Files are identified by their file name only, so an
+// export can be applied to a copy of the folder somewhere else.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagAssignment {
+    pub file: String,
+    pub tag: Option<Tag>,
+    pub tag_name: Option<String>,
+}
+
+pub fn assignments_from(
+    images: impl Iterator<Item = (String, Option<Tag>)>,
+    tag_names: &TagNames,
+) -> Vec<TagAssignment> {
+    images
+        .map(|(path, tag)| TagAssignment {
+            file: file_name(&path),
+            tag,
+            tag_name: tag.map(|tag| tag_names.get(&tag).to_owned()),
+        })
+        .collect()
+}
+
+pub fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+pub fn export_tags(path: &Path, assignments: &[TagAssignment]) -> Result<(), String> {
+    let contents = if is_json(path) {
+        serde_json::to_string_pretty(assignments).map_err(|e| e.to_string())?
+    } else {
+        to_csv(assignments)
+    };
+    std::fs::write(path, contents).map_err(|e| e.to_string())
+}
+
+pub fn import_tags(path: &Path, tag_names: &TagNames) -> Result<Vec<(String, Tag)>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let assignments = if is_json(path) {
+        serde_json::from_str(&contents).map_err(|e| e.to_string())?
+    } else {
+        from_csv(&contents)?
+    };
+    Ok(assignments
+        .into_iter()
+        .filter_map(|assignment| {
+            // The tag id wins, the name is a fallback for hand written files
+            let tag = assignment.tag.or_else(|| {
+                let name = assignment.tag_name.as_ref()?;
+                tag_names
+                    .enumerate()
+                    .find(|(_, tag_name)| *tag_name == name)
+                    .map(|(tag, _)| tag)
+            })?;
+            Some((assignment.file, tag))
+        })
+        .collect())
+}
+
+fn to_csv(assignments: &[TagAssignment]) -> String {
+    let mut s = String::from("file,tag,tag_name\n");
+    for assignment in assignments {
+        let tag = assignment.tag.map(|tag| tag.id()).unwrap_or_default();
+        let tag_name = assignment.tag_name.clone().unwrap_or_default();
+        s.push_str(&format!(
+            "{},{},{}\n",
+            csv_field(&assignment.file),
+            tag,
+            csv_field(&tag_name)
+        ));
+    }
+    s
+}
+
+fn from_csv(contents: &str) -> Result<Vec<TagAssignment>, String> {
+    let mut records = parse_csv(contents).into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let file_column = column("file").ok_or("Missing \"file\" column")?;
+    let tag_column = column("tag");
+    let tag_name_column = column("tag_name");
+
+    Ok(records
+        .map(|fields| {
+            let get = |column: Option<usize>| {
+                column
+                    .and_then(|i| fields.get(i))
+                    .map(|field| field.trim().to_owned())
+                    .filter(|field| !field.is_empty())
+            };
+            TagAssignment {
+                file: get(Some(file_column)).unwrap_or_default(),
+                tag: get(tag_column).and_then(|id| Tag::from_id(&id)),
+                tag_name: get(tag_name_column),
+            }
+        })
+        .collect())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+// Records are split on line breaks outside of quotes, a quoted field can hold
+// a line break of its own. Blank lines are skipped.
+fn parse_csv(contents: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut end_record = |fields: &mut Vec<String>, field: &mut String| {
+        fields.push(std::mem::take(field));
+        let record = std::mem::take(fields);
+        if record.iter().any(|field| !field.trim().is_empty()) {
+            records.push(record);
+        }
+    };
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => end_record(&mut fields, &mut field),
+            (c, _) => field.push(c),
+        }
+    }
+    end_record(&mut fields, &mut field);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_round_trip() {
+        let assignments = vec![
+            TagAssignment {
+                file: "a, \"quoted\".jpg".to_owned(),
+                tag: Some(Tag::new(2)),
+                tag_name: Some("Keep".to_owned()),
+            },
+            TagAssignment {
+                file: "b.jpg".to_owned(),
+                tag: None,
+                tag_name: None,
+            },
+            TagAssignment {
+                file: "c.jpg".to_owned(),
+                tag: Some(Tag::new(3)),
+                tag_name: Some("Two\nlines".to_owned()),
+            },
+        ];
+        assert_eq!(from_csv(&to_csv(&assignments)).unwrap(), assignments);
+        assert_eq!(
+            from_csv("file,tag\r\n\r\nd.jpg,Tag4\r\n").unwrap()[0].tag,
+            Some(Tag::new(4))
+        );
+    }
+
+    #[test]
+    fn test_csv_tag_name_only() {
+        let parsed = from_csv("file,tag_name\nc.jpg,Red\n").unwrap();
+        assert_eq!(parsed[0].file, "c.jpg");
+        assert_eq!(parsed[0].tag, None);
+        assert_eq!(parsed[0].tag_name.as_deref(), Some("Red"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use time::OffsetDateTime;
+
+use crate::exif_data;
+
+// Sidecar JSON files written by Google Takeout and some phone backup tools
+// next to each photo. Exports like these often lack EXIF, so the sidecar is
+// the only source of the capture time and the caption.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SidecarMetadata {
+    // Original file name, Takeout shortens long names on export
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub taken: Option<OffsetDateTime>,
+}
+
+fn sidecar_candidates(image_path: &str) -> Vec<PathBuf> {
+    let path = Path::new(image_path);
+    let mut candidates = vec![
+        PathBuf::from(format!("{image_path}.json")),
+        PathBuf::from(format!("{image_path}.supplemental-metadata.json")),
+    ];
+    if let Some(stem) = path.file_stem() {
+        candidates.push(path.with_file_name(format!("{}.json", stem.to_string_lossy())));
+    }
+    candidates
+}
+
+pub fn sidecar_path(image_path: &str) -> Option<PathBuf> {
+    sidecar_candidates(image_path)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn read_sidecar(image_path: &str) -> Option<SidecarMetadata> {
+    let path = sidecar_path(image_path)?;
+    let json = std::fs::read_to_string(&path).ok()?;
+    match parse_sidecar(&json) {
+        Ok(metadata) => Some(metadata),
+        Err(e) => {
+            warn!("Could not parse sidecar {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+fn parse_sidecar(json: &str) -> serde_json::Result<SidecarMetadata> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let non_empty = |key: &str| {
+        value[key]
+            .as_str()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+    };
+    // Timestamps are strings of Unix seconds, in UTC
+    let timestamp = |key: &str| {
+        let seconds = value[key]["timestamp"].as_str()?.parse().ok()?;
+        OffsetDateTime::from_unix_timestamp(seconds)
+            .ok()
+            .map(|taken| taken.to_offset(exif_data::local_offset()))
+    };
+    Ok(SidecarMetadata {
+        title: non_empty("title"),
+        description: non_empty("description"),
+        taken: timestamp("photoTakenTime").or_else(|| timestamp("creationTime")),
+    })
+}
+
+// Keeps the sidecar with its image when the image is renamed
+pub fn rename_sidecar(old_image_path: &str, new_image_path: &str) {
+    if let Some(old) = sidecar_path(old_image_path) {
+        let new = format!("{new_image_path}.json");
+        if let Err(e) = std::fs::rename(&old, &new) {
+            warn!("Could not rename sidecar {}: {e}", old.display());
+        }
+    }
+}
+
+/// Capture time from EXIF, falling back to a sidecar for files without it
+pub fn read_capture_time(path: &str) -> Option<OffsetDateTime> {
+    exif_data::read_capture_time(path).or_else(|| read_sidecar(path)?.taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sidecar() {
+        let json = r#"{
+            "title": "IMG_20230714_123045_with_a_long_name.jpg",
+            "description": "  Beach  ",
+            "creationTime": { "timestamp": "1689400000", "formatted": "" },
+            "photoTakenTime": { "timestamp": "1689337845", "formatted": "" }
+        }"#;
+        let metadata = parse_sidecar(json).unwrap();
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("IMG_20230714_123045_with_a_long_name.jpg")
+        );
+        assert_eq!(metadata.description.as_deref(), Some("Beach"));
+        assert_eq!(metadata.taken.unwrap().unix_timestamp(), 1689337845);
+    }
+
+    #[test]
+    fn test_parse_sidecar_without_fields() {
+        let metadata = parse_sidecar(r#"{ "description": "" }"#).unwrap();
+        assert_eq!(metadata, SidecarMetadata::default());
+        assert!(parse_sidecar("not json").is_err());
+    }
+}
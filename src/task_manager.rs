@@ -1,5 +1,7 @@
+use iced::futures::Stream;
 use iced::{task::Handle, Task};
-use log::debug;
+use log::trace;
+use rust_i18n::t;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 
@@ -21,13 +23,67 @@ impl TaskId {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TaskType {
+    PlanMove,
     MoveThenLs,
+    CopyThenLs,
+    RenameFile,
+    FixTimestamps,
+    VisualDiff,
+    TagFile,
+    ReadFileIdentities,
+    ReadSidecars,
+    ReadXmpSidecars,
+    ReadImageHeaders,
+    ReadFolderStats,
+    SortListing,
+    CompareFolders,
+    ReadCaptureTimes,
+    ReadDestination,
+    ShareExport,
+    ZipExport,
+    Upload,
+    Print,
+    ExportMontage,
+    IndexMetadata,
     LsDir,
     PreloadImage,
 }
 
+impl TaskType {
+    // Work on the images of one listing, redone for the next
+    fn is_per_listing(&self) -> bool {
+        match self {
+            TaskType::LsDir
+            | TaskType::PreloadImage
+            | TaskType::VisualDiff
+            | TaskType::ReadFileIdentities
+            | TaskType::ReadSidecars
+            | TaskType::ReadXmpSidecars
+            | TaskType::ReadImageHeaders
+            | TaskType::ReadFolderStats
+            | TaskType::SortListing
+            | TaskType::ReadCaptureTimes
+            | TaskType::IndexMetadata => true,
+            // Asked for once per tag shown, not again after a listing
+            TaskType::ReadDestination => false,
+            TaskType::PlanMove
+            | TaskType::MoveThenLs
+            | TaskType::CopyThenLs
+            | TaskType::RenameFile
+            | TaskType::FixTimestamps
+            | TaskType::TagFile
+            | TaskType::CompareFolders
+            | TaskType::ShareExport
+            | TaskType::ZipExport
+            | TaskType::Upload
+            | TaskType::Print
+            | TaskType::ExportMontage => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct TaskInfo {
     task_type: TaskType,
@@ -57,10 +113,35 @@ impl TaskManager {
         T: 'static + Send,
         Msg: 'static + Send,
     {
-        let id = TaskId::new();
+        self.track(task_type, message, Task::perform(future, |result| result))
+    }
 
-        // Create the main task
-        let main_task = Task::perform(future, |result| result);
+    // For tasks reporting progress. The stream can send any number of items,
+    // the task stays active until it is reported completed.
+    pub fn start_stream_task<T, Msg>(
+        &mut self,
+        task_type: TaskType,
+        message: fn(TaskId, T) -> Msg,
+        stream: impl Stream<Item = T> + 'static + Send,
+    ) -> Task<Msg>
+    where
+        T: 'static + Send,
+        Msg: 'static + Send,
+    {
+        self.track(task_type, message, Task::run(stream, |item| item))
+    }
+
+    fn track<T, Msg>(
+        &mut self,
+        task_type: TaskType,
+        message: fn(TaskId, T) -> Msg,
+        main_task: Task<T>,
+    ) -> Task<Msg>
+    where
+        T: 'static + Send,
+        Msg: 'static + Send,
+    {
+        let id = TaskId::new();
 
         // Make it abortable and get the abort handle
         let (abortable_task, abort_handle) = main_task.abortable();
@@ -75,7 +156,7 @@ impl TaskManager {
             },
         );
 
-        debug!("Started task {id:?}: {task_type:?}");
+        trace!("Started task {id:?}: {task_type:?}");
 
         abortable_task.map(move |result| message(id, result))
     }
@@ -84,9 +165,22 @@ impl TaskManager {
         self.active_tasks.clear();
     }
 
+    // When the folder is listed again. Exports, uploads and file operations
+    // carry on, their results are still wanted.
+    pub fn cancel_listing_tasks(&mut self) {
+        self.active_tasks
+            .retain(|_, info| !info.task_type.is_per_listing());
+    }
+
+    // A newer task of the type makes the results of the running ones stale
+    pub fn cancel_tasks_of_type(&mut self, task_type: &TaskType) {
+        self.active_tasks
+            .retain(|_, info| info.task_type != *task_type);
+    }
+
     pub fn report_completed_task(&mut self, id: TaskId) -> TaskCompleteResult {
         if let Some(task_info) = self.active_tasks.remove(&id) {
-            debug!("Completed task {:?}: {:?}", id, task_info.task_type);
+            trace!("Completed task {:?}: {:?}", id, task_info.task_type);
             TaskCompleteResult::Success
         } else {
             TaskCompleteResult::TaskWasCancelled
@@ -101,7 +195,28 @@ impl TaskManager {
             match info.task_type {
                 TaskType::LsDir => ls_dir_count += 1,
                 TaskType::PreloadImage => preload_count += 1,
-                TaskType::MoveThenLs => (),
+                TaskType::PlanMove
+                | TaskType::MoveThenLs
+                | TaskType::CopyThenLs
+                | TaskType::RenameFile
+                | TaskType::FixTimestamps
+                | TaskType::VisualDiff
+                | TaskType::TagFile
+                | TaskType::ReadFileIdentities
+                | TaskType::ReadSidecars
+                | TaskType::ReadXmpSidecars
+                | TaskType::ReadImageHeaders
+                | TaskType::ReadFolderStats
+                | TaskType::SortListing
+                | TaskType::CompareFolders
+                | TaskType::ReadCaptureTimes
+                | TaskType::ReadDestination
+                | TaskType::ShareExport
+                | TaskType::ZipExport
+                | TaskType::Upload
+                | TaskType::Print
+                | TaskType::ExportMontage
+                | TaskType::IndexMetadata => (),
             }
         }
 
@@ -113,9 +228,13 @@ impl TaskManager {
         let (ls_dir_count, preload_count) = self.get_task_counts();
 
         match (ls_dir_count > 0, preload_count > 0) {
-            (true, true) => format!("Loading directory, {preload_count} images preloading..."),
-            (true, false) => "Loading directory...".to_string(),
-            (false, true) => format!("Loading {preload_count} images..."),
+            (true, true) => t!(
+                "Loading directory, %{count} images preloading...",
+                count = preload_count
+            )
+            .to_string(),
+            (true, false) => t!("Loading directory...").to_string(),
+            (false, true) => t!("Loading %{count} images...", count = preload_count).to_string(),
             (false, false) => "".to_string(), // No loading text when no tasks
         }
     }
@@ -130,3 +249,53 @@ pub enum TaskCompleteResult {
     Success,
     TaskWasCancelled,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_keeps_exports() {
+        let mut task_manager = TaskManager::new();
+        for task_type in [
+            TaskType::Upload,
+            TaskType::ZipExport,
+            TaskType::ReadDestination,
+            TaskType::LsDir,
+            TaskType::PreloadImage,
+        ] {
+            let _ = task_manager.start_task(task_type, |_, ()| (), async {});
+        }
+        task_manager.cancel_listing_tasks();
+
+        let mut left: Vec<TaskType> = task_manager
+            .active_tasks
+            .values()
+            .map(|info| info.task_type.clone())
+            .collect();
+        left.sort_by_key(|task_type| format!("{task_type:?}"));
+        assert_eq!(
+            left,
+            [
+                TaskType::ReadDestination,
+                TaskType::Upload,
+                TaskType::ZipExport
+            ]
+        );
+        assert_eq!(task_manager.get_task_counts(), (0, 0));
+    }
+
+    #[test]
+    fn test_cancel_tasks_of_type() {
+        let mut task_manager = TaskManager::new();
+        let _ = task_manager.start_task(TaskType::SortListing, |_, ()| (), async {});
+        let _ = task_manager.start_task(TaskType::Upload, |_, ()| (), async {});
+        task_manager.cancel_tasks_of_type(&TaskType::SortListing);
+        let left: Vec<TaskType> = task_manager
+            .active_tasks
+            .values()
+            .map(|info| info.task_type.clone())
+            .collect();
+        assert_eq!(left, [TaskType::Upload]);
+    }
+}
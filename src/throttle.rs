@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Shared throughput cap for all disk IO done by the preload and move pipelines.
+// Each caller reserves a time slot for the bytes it is about to transfer, and
+// sleeps until its slot starts. A limit of 0 means unlimited.
+
+static LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter {
+    bytes_per_sec: 0,
+    next_free: None,
+});
+
+struct RateLimiter {
+    bytes_per_sec: u64,
+    next_free: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        if self.bytes_per_sec == 0 {
+            return Duration::ZERO;
+        }
+        let start = match self.next_free {
+            Some(next_free) if next_free > now => next_free,
+            _ => now,
+        };
+        let duration = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        self.next_free = Some(start + duration);
+        start - now
+    }
+}
+
+pub fn set_limit(bytes_per_sec: u64) {
+    let mut limiter = LIMITER.lock().expect("Throttle lock poisoned");
+    limiter.bytes_per_sec = bytes_per_sec;
+    limiter.next_free = None;
+}
+
+/// Blocks until `bytes` may be transferred under the current limit
+pub fn throttle(bytes: u64) {
+    let wait = LIMITER
+        .lock()
+        .expect("Throttle lock poisoned")
+        .reserve(bytes, Instant::now());
+    if !wait.is_zero() {
+        std::thread::sleep(wait);
+    }
+}
+
+pub fn throttle_file(path: &str) {
+    if let Ok(metadata) = std::fs::metadata(path) {
+        throttle(metadata.len());
+    }
+}
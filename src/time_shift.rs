@@ -0,0 +1,201 @@
+use std::process::Command;
+
+use iced::widget::{button, column, row, scrollable, text, text_input};
+use iced::Element;
+use log::warn;
+use rust_i18n::t;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime};
+
+use crate::exif_data;
+use crate::format::{format_count, format_datetime};
+use crate::sorting::Tag;
+use crate::Message;
+
+// Shifting the EXIF capture times by a fixed offset, for a camera whose clock
+// was wrong. The EXIF is written by exiftool, which has to be installed. Like
+// the file time fix, a plan is shown before anything is written.
+
+#[derive(Debug, Clone, Default)]
+pub struct TimeShiftForm {
+    pub offset: String,
+    // Only images whose camera contains this, when not empty
+    pub camera: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeShift {
+    pub path: String,
+    pub old: OffsetDateTime,
+    pub new: OffsetDateTime,
+}
+
+// "+1:30", "-0:15:30" or "+2 3:00" for days, hours, minutes and seconds
+pub fn parse_offset(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (days, clock) = match rest.split_once(' ') {
+        Some((days, clock)) => (days.trim().parse::<i64>().ok()?, clock.trim()),
+        None => (0, rest),
+    };
+    let parts: Vec<i64> = clock
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes] => (hours, minutes, 0),
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        _ => return None,
+    };
+    if [days, hours, minutes, seconds].iter().any(|&n| n < 0) || minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    let offset = Duration::days(days)
+        + Duration::hours(hours)
+        + Duration::minutes(minutes)
+        + Duration::seconds(seconds);
+    (!offset.is_zero()).then_some(offset * sign)
+}
+
+pub fn plan_time_shift(paths: Vec<String>, offset: Duration, camera: String) -> Vec<TimeShift> {
+    let camera = camera.trim().to_lowercase();
+    paths
+        .into_iter()
+        .filter(|path| {
+            camera.is_empty()
+                || exif_data::read_camera(path)
+                    .is_some_and(|model| model.to_lowercase().contains(&camera))
+        })
+        .filter_map(|path| {
+            let old = exif_data::read_capture_time(&path)?;
+            Some(TimeShift {
+                path,
+                old,
+                new: old + offset,
+            })
+        })
+        .collect()
+}
+
+// In the "Y:M:D h:m:s" form exiftool expects for shifting
+fn exiftool_shift_argument(offset: Duration) -> String {
+    let operator = if offset.is_negative() { "-=" } else { "+=" };
+    let offset = offset.abs();
+    format!(
+        "-AllDates{operator}0:0:{} {}:{}:{}",
+        offset.whole_days(),
+        offset.whole_hours() % 24,
+        offset.whole_minutes() % 60,
+        offset.whole_seconds() % 60
+    )
+}
+
+pub fn apply_time_shift(changes: Vec<TimeShift>) -> (usize, Vec<String>) {
+    let mut applied = 0;
+    let mut errors = Vec::new();
+    for change in changes {
+        let argument = exiftool_shift_argument(change.new - change.old);
+        let result = Command::new("exiftool")
+            .args(["-overwrite_original", "-preserve", &argument, &change.path])
+            .output();
+        match result {
+            Ok(output) if output.status.success() => applied += 1,
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                warn!("exiftool failed for {}: {stderr}", change.path);
+                errors.push(format!("{}: {}", change.path, stderr.trim()));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                errors.push(t!("exiftool is not installed").to_string());
+                break;
+            }
+            Err(e) => {
+                warn!("Could not run exiftool for {}: {e}", change.path);
+                errors.push(format!("{}: {e}", change.path));
+            }
+        }
+    }
+    (applied, errors)
+}
+
+fn format_time(datetime: OffsetDateTime) -> String {
+    format_datetime(PrimitiveDateTime::new(datetime.date(), datetime.time()))
+}
+
+pub fn view_time_shift_form(form: &TimeShiftForm, tag: Option<Tag>) -> Element<'static, Message> {
+    let valid = parse_offset(&form.offset).is_some();
+    row![
+        text_input(&t!("Offset, e.g. +1:30"), &form.offset)
+            .on_input(Message::UserEditedTimeShiftOffset)
+            .width(150),
+        text_input(&t!("Camera (optional)"), &form.camera)
+            .on_input(Message::UserEditedTimeShiftCamera)
+            .width(150),
+        button(text(t!("Shift capture times")))
+            .on_press_maybe(valid.then_some(Message::UserPressedPlanTimeShift(tag))),
+    ]
+    .spacing(10)
+    .into()
+}
+
+pub fn view_time_shift_plan(changes: &[TimeShift]) -> Element<'static, Message> {
+    // The times are shown as recorded, in the camera's time zone
+    let rows = changes.iter().map(|change| {
+        text(format!(
+            "{}: {} -> {}",
+            change.path,
+            format_time(change.old),
+            format_time(change.new)
+        ))
+        .into()
+    });
+
+    column![
+        text(t!(
+            "%{count} files will get their EXIF capture time shifted",
+            count = format_count(changes.len())
+        ))
+        .size(20),
+        scrollable(column(rows).spacing(2)).height(300),
+        row![
+            button(text(t!("Apply"))).on_press(Message::UserPressedApplyTimeShift),
+            button(text(t!("Cancel"))).on_press(Message::UserPressedCancelTimeShift),
+        ]
+        .spacing(10),
+    ]
+    .spacing(10)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("+1:30"), Some(Duration::minutes(90)));
+        assert_eq!(parse_offset("-0:15:30"), Some(-Duration::seconds(930)));
+        assert_eq!(
+            parse_offset("2 3:00"),
+            Some(Duration::days(2) + Duration::hours(3))
+        );
+        assert_eq!(parse_offset("0:00"), None);
+        assert_eq!(parse_offset("1:75"), None);
+        assert_eq!(parse_offset("1"), None);
+        assert_eq!(parse_offset("abc"), None);
+    }
+
+    #[test]
+    fn test_exiftool_shift_argument() {
+        assert_eq!(
+            exiftool_shift_argument(Duration::days(1) + Duration::minutes(90)),
+            "-AllDates+=0:0:1 1:30:0"
+        );
+        assert_eq!(
+            exiftool_shift_argument(-Duration::seconds(930)),
+            "-AllDates-=0:0:0 0:15:30"
+        );
+    }
+}
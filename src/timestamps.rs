@@ -0,0 +1,96 @@
+use std::time::SystemTime;
+
+use iced::widget::{button, column, row, scrollable, text};
+use iced::Element;
+use log::warn;
+use rust_i18n::t;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+use crate::format::{format_count, format_datetime};
+use crate::Message;
+use crate::{exif_data, takeout};
+
+// Setting file modification times to the capture time from EXIF, or from a
+// Takeout sidecar for files without EXIF, so that tools sorting by mtime agree
+// with the shooting order. A plan is computed first and shown as a dry run
+// before anything is touched.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampChange {
+    pub path: String,
+    pub old: Option<OffsetDateTime>,
+    pub new: OffsetDateTime,
+}
+
+pub fn plan_timestamp_changes(paths: Vec<String>) -> Vec<TimestampChange> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let new = takeout::read_capture_time(&path)?;
+            let old = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(OffsetDateTime::from);
+            // EXIF times only have second precision
+            if old.map(|old| old.unix_timestamp()) == Some(new.unix_timestamp()) {
+                return None;
+            }
+            Some(TimestampChange { path, old, new })
+        })
+        .collect()
+}
+
+pub fn apply_timestamp_changes(changes: Vec<TimestampChange>) -> (usize, Vec<String>) {
+    let mut applied = 0;
+    let mut errors = Vec::new();
+    for change in changes {
+        let result = std::fs::File::options()
+            .write(true)
+            .open(&change.path)
+            .and_then(|file| file.set_modified(SystemTime::from(change.new)));
+        match result {
+            Ok(()) => applied += 1,
+            Err(e) => {
+                warn!("Could not set modification time of {}: {e}", change.path);
+                errors.push(format!("{}: {e}", change.path));
+            }
+        }
+    }
+    (applied, errors)
+}
+
+pub fn format_time(datetime: OffsetDateTime) -> String {
+    let local = datetime.to_offset(exif_data::local_offset());
+    format_datetime(PrimitiveDateTime::new(local.date(), local.time()))
+}
+
+pub fn view_timestamp_plan(changes: &[TimestampChange]) -> Element<'static, Message> {
+    let rows = changes.iter().map(|change| {
+        let old = change
+            .old
+            .map(format_time)
+            .unwrap_or_else(|| "-".to_owned());
+        text(format!(
+            "{}: {old} -> {}",
+            change.path,
+            format_time(change.new)
+        ))
+        .into()
+    });
+
+    column![
+        text(t!(
+            "%{count} files will get their modification time from EXIF or sidecar files",
+            count = format_count(changes.len())
+        ))
+        .size(20),
+        scrollable(column(rows).spacing(2)).height(300),
+        row![
+            button(text(t!("Apply"))).on_press(Message::UserPressedApplyTimestamps),
+            button(text(t!("Cancel"))).on_press(Message::UserPressedCancelTimestamps),
+        ]
+        .spacing(10),
+    ]
+    .spacing(10)
+    .into()
+}
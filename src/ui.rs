@@ -1,5 +1,6 @@
 use iced::Color;
 
+#[derive(Debug, Clone, Copy)]
 pub struct ButtonStyle {
     pub basic: Color,
     pub hover: Color,
@@ -53,3 +54,43 @@ pub const CYAN_BUTTON_STYLE: ButtonStyle = ButtonStyle {
     hover: Color::from_rgb(0.4, 1.0, 1.0),
     press: Color::from_rgb(0.0, 0.5, 0.5),
 };
+
+pub const PINK_BUTTON_STYLE: ButtonStyle = ButtonStyle {
+    basic: Color::from_rgb(0.9, 0.3, 0.6),
+    hover: Color::from_rgb(1.0, 0.5, 0.75),
+    press: Color::from_rgb(0.5, 0.1, 0.3),
+};
+
+pub const BROWN_BUTTON_STYLE: ButtonStyle = ButtonStyle {
+    basic: Color::from_rgb(0.55, 0.35, 0.15),
+    hover: Color::from_rgb(0.7, 0.5, 0.3),
+    press: Color::from_rgb(0.3, 0.2, 0.05),
+};
+
+pub const TEAL_BUTTON_STYLE: ButtonStyle = ButtonStyle {
+    basic: Color::from_rgb(0.0, 0.5, 0.5),
+    hover: Color::from_rgb(0.2, 0.65, 0.65),
+    press: Color::from_rgb(0.0, 0.25, 0.25),
+};
+
+pub const LIME_BUTTON_STYLE: ButtonStyle = ButtonStyle {
+    basic: Color::from_rgb(0.5, 0.8, 0.0),
+    hover: Color::from_rgb(0.65, 0.9, 0.3),
+    press: Color::from_rgb(0.25, 0.4, 0.0),
+};
+
+// Tags are given these in turn, the first eight in the order of the default tags
+pub const TAG_BUTTON_STYLES: [ButtonStyle; 12] = [
+    RED_BUTTON_STYLE,
+    GREEN_BUTTON_STYLE,
+    YELLOW_BUTTON_STYLE,
+    BLUE_BUTTON_STYLE,
+    PURPLE_BUTTON_STYLE,
+    ORANGE_BUTTON_STYLE,
+    GRAY_BUTTON_STYLE,
+    CYAN_BUTTON_STYLE,
+    PINK_BUTTON_STYLE,
+    BROWN_BUTTON_STYLE,
+    TEAL_BUTTON_STYLE,
+    LIME_BUTTON_STYLE,
+];
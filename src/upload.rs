@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use iced::widget::{button, row, text, text_input};
+use iced::{Alignment, Element};
+use log::warn;
+use rust_i18n::t;
+
+use crate::share_export;
+use crate::sorting::{Tag, TagNames};
+use crate::Message;
+
+// A tag's files sent straight to where they are shared, with rclone. The
+// remote is anything rclone can copy to, such as "gdrive:Shared", an S3
+// bucket or an FTP server set up with rclone config. Files already there
+// unchanged are skipped by rclone, so uploading again only sends what failed.
+
+const ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub enum UploadProgress {
+    Uploaded(usize, usize),
+    Finished(UploadSummary),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadSummary {
+    pub remote: String,
+    pub uploaded: usize,
+    pub errors: Vec<String>,
+}
+
+pub fn upload_files(
+    paths: Vec<String>,
+    remote: String,
+    progress: impl Fn(usize, usize),
+) -> UploadSummary {
+    let mut summary = UploadSummary {
+        remote: remote.clone(),
+        uploaded: 0,
+        errors: Vec::new(),
+    };
+    let mut used_names = HashSet::new();
+    for (i, path) in paths.iter().enumerate() {
+        let extension = Path::new(path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name = share_export::unique_name(path, &extension, &mut used_names);
+        match upload_with_retries(path, &remote_path(&remote, &name)) {
+            Ok(()) => summary.uploaded += 1,
+            Err(UploadError::NotInstalled) => {
+                summary
+                    .errors
+                    .push(t!("rclone is not installed").to_string());
+                break;
+            }
+            Err(UploadError::Failed(e)) => {
+                warn!("Could not upload {path} to {remote}: {e}");
+                summary.errors.push(format!("{path}: {e}"));
+            }
+        }
+        progress(i + 1, paths.len());
+    }
+    summary
+}
+
+enum UploadError {
+    NotInstalled,
+    Failed(String),
+}
+
+// Waits a little longer before each new attempt
+fn upload_with_retries(path: &str, destination: &str) -> Result<(), UploadError> {
+    let mut attempt = 1;
+    loop {
+        match rclone_copy(path, destination) {
+            Err(UploadError::Failed(e)) if attempt < ATTEMPTS => {
+                warn!("Upload of {path} failed, attempt {attempt}: {e}");
+                std::thread::sleep(RETRY_DELAY * attempt);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn rclone_copy(path: &str, destination: &str) -> Result<(), UploadError> {
+    let output = Command::new("rclone")
+        .args(["copyto", path, destination])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => UploadError::NotInstalled,
+            _ => UploadError::Failed(e.to_string()),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(UploadError::Failed(stderr.trim().to_owned()));
+    }
+    Ok(())
+}
+
+// The root of a remote is written with just the colon
+fn remote_path(remote: &str, name: &str) -> String {
+    let remote = remote.trim();
+    if remote.ends_with(':') || remote.ends_with('/') {
+        format!("{remote}{name}")
+    } else {
+        format!("{remote}/{name}")
+    }
+}
+
+pub fn view_upload_form(tag_names: &TagNames, tag: Tag) -> Element<'static, Message> {
+    let remote = tag_names.upload_remote(&tag).unwrap_or("");
+    row![
+        text_input(&t!("rclone remote, e.g. gdrive:Shared"), remote)
+            .on_input(move |text| Message::UserChangedUploadRemote(tag, text))
+            .width(250),
+        button(text(t!("Upload")))
+            .on_press_maybe((!remote.is_empty()).then_some(Message::UserPressedUpload(tag))),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_path() {
+        assert_eq!(remote_path("gdrive:", "a.jpg"), "gdrive:a.jpg");
+        assert_eq!(remote_path("gdrive:Shared", "a.jpg"), "gdrive:Shared/a.jpg");
+        assert_eq!(
+            remote_path("s3:bucket/photos/", "a.jpg"),
+            "s3:bucket/photos/a.jpg"
+        );
+    }
+}
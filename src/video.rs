@@ -0,0 +1,80 @@
+use std::path::Path;
+use std::process::Command;
+
+use image::{DynamicImage, ImageFormat};
+use log::debug;
+use rust_i18n::t;
+
+// Video clips from phones and cameras, sorted along with the photos. A single
+// frame stands in for the clip, taken a second in so that it is less likely
+// to be black. The frame is extracted with the ffmpeg command line tool, which
+// also turns it upright.
+
+pub const VIDEO_EXTENSIONS: &[&str] = &["3gp", "avi", "m4v", "mkv", "mov", "mp4", "mts", "webm"];
+
+const FRAME_OFFSET: &str = "1";
+
+pub fn is_video(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+pub fn decode_video(path: &str) -> Result<DynamicImage, String> {
+    let png = match extract_frame(path, Some(FRAME_OFFSET))? {
+        // Clips shorter than the offset have no frame there
+        png if png.is_empty() => {
+            debug!("{path} is shorter than {FRAME_OFFSET} s, using the first frame");
+            extract_frame(path, None)?
+        }
+        png => png,
+    };
+    if png.is_empty() {
+        return Err(format!("No frame in {path}"));
+    }
+    image::load_from_memory_with_format(&png, ImageFormat::Png).map_err(|e| e.to_string())
+}
+
+// A PNG of the frame at `offset` seconds, empty when there is none
+fn extract_frame(path: &str, offset: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-v", "error", "-nostdin"]);
+    if let Some(offset) = offset {
+        command.args(["-ss", offset]);
+    }
+    let output = command
+        .args([
+            "-i",
+            path,
+            "-frames:v",
+            "1",
+            "-f",
+            "image2pipe",
+            "-c:v",
+            "png",
+            "-",
+        ])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => t!("ffmpeg is needed to show videos").to_string(),
+            _ => e.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_video() {
+        assert!(is_video("VID_0001.mp4"));
+        assert!(is_video("IMG_0001.MOV"));
+        assert!(!is_video("IMG_0001.jpg"));
+        assert!(!is_video("mp4"));
+    }
+}
@@ -0,0 +1,111 @@
+use image::{imageops::FilterType, GrayImage, RgbaImage};
+
+use crate::ImageData;
+
+// Structural similarity between two images, plus a heatmap of where they
+// differ. Both images are compared in grayscale at a common, small size.
+
+const COMPARE_SIZE: u32 = 256;
+const WINDOW: u32 = 8;
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+#[derive(Debug, Clone)]
+pub struct VisualDiff {
+    pub path_a: String,
+    pub path_b: String,
+    pub score: f64,
+    pub heatmap: ImageData,
+}
+
+pub fn compute_visual_diff(
+    path_a: String,
+    a: &ImageData,
+    path_b: String,
+    b: &ImageData,
+) -> VisualDiff {
+    let a = to_gray(a);
+    let b = to_gray(b);
+    VisualDiff {
+        path_a,
+        path_b,
+        score: ssim(&a, &b),
+        heatmap: heatmap(&a, &b),
+    }
+}
+
+fn to_gray(image: &ImageData) -> GrayImage {
+    let rgba = RgbaImage::from_raw(image.width, image.height, image.data.to_vec())
+        .expect("Image data matches its dimensions");
+    image::DynamicImage::ImageRgba8(rgba)
+        .resize_exact(COMPARE_SIZE, COMPARE_SIZE, FilterType::Triangle)
+        .to_luma8()
+}
+
+// Mean SSIM over non-overlapping windows
+fn ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    let mut total = 0.0;
+    let mut windows = 0;
+    for wy in (0..a.height()).step_by(WINDOW as usize) {
+        for wx in (0..a.width()).step_by(WINDOW as usize) {
+            let pixels: Vec<(f64, f64)> = (wy..(wy + WINDOW).min(a.height()))
+                .flat_map(|y| (wx..(wx + WINDOW).min(a.width())).map(move |x| (x, y)))
+                .map(|(x, y)| (a.get_pixel(x, y)[0] as f64, b.get_pixel(x, y)[0] as f64))
+                .collect();
+            let n = pixels.len() as f64;
+            let mean_a = pixels.iter().map(|(pa, _)| pa).sum::<f64>() / n;
+            let mean_b = pixels.iter().map(|(_, pb)| pb).sum::<f64>() / n;
+            let (mut var_a, mut var_b, mut covar) = (0.0, 0.0, 0.0);
+            for (pa, pb) in &pixels {
+                var_a += (pa - mean_a) * (pa - mean_a);
+                var_b += (pb - mean_b) * (pb - mean_b);
+                covar += (pa - mean_a) * (pb - mean_b);
+            }
+            var_a /= n;
+            var_b /= n;
+            covar /= n;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covar + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+fn heatmap(a: &GrayImage, b: &GrayImage) -> ImageData {
+    let mut data = Vec::with_capacity((a.width() * a.height() * 4) as usize);
+    for (pa, pb) in a.pixels().zip(b.pixels()) {
+        let diff = (pa[0] as i16 - pb[0] as i16).unsigned_abs() as u8;
+        data.extend_from_slice(&[diff, 0, 255 - diff, 255]);
+    }
+    ImageData::new(a.width(), a.height(), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(offset: u8) -> ImageData {
+        let data = (0..64u32 * 64)
+            .flat_map(|i| {
+                let v = ((i % 64) as u8 * 4).wrapping_add(offset);
+                [v, v, v, 255]
+            })
+            .collect();
+        ImageData::new(64, 64, data)
+    }
+
+    #[test]
+    fn test_identical_images_score_one() {
+        let diff = compute_visual_diff("a".into(), &gradient(0), "b".into(), &gradient(0));
+        assert!((diff.score - 1.0).abs() < 1e-9);
+        assert!(diff.heatmap.data.chunks(4).all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn test_different_images_score_lower() {
+        let diff = compute_visual_diff("a".into(), &gradient(0), "b".into(), &gradient(128));
+        assert!(diff.score < 0.9);
+    }
+}
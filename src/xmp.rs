@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::sorting::{Tag, TagNames};
+
+// XMP sidecars as written by Lightroom (IMG_1.xmp) and darktable
+// (IMG_1.CR2.xmp), so tags made here show up there as color labels and the
+// other way around. Only the rating, the label and the keywords are read.
+// Images that share a stem, like a RAW+JPEG pair, each get a sidecar with the
+// full name, one IMG_1.xmp would give both the same label and rating.
+// Sidecars that already exist are edited in place, keeping everything else in
+// them, so this is plain text handling rather than a full XML parser.
+
+// Lightroom's labels, in the order of the tag colors
+const COLOR_LABELS: [&str; 5] = ["Red", "Green", "Yellow", "Blue", "Purple"];
+
+const XMP_NAMESPACE: &str = "http://ns.adobe.com/xap/1.0/";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmpSidecar {
+    pub rating: Option<u8>,
+    pub label: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+// The paths without extension that more than one of the images has
+pub struct SharedStems(HashSet<PathBuf>);
+
+impl SharedStems {
+    pub fn new<'a>(paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+        for path in paths {
+            *counts
+                .entry(Path::new(path).with_extension(""))
+                .or_default() += 1;
+        }
+        Self(
+            counts
+                .into_iter()
+                .filter(|(_, count)| *count > 1)
+                .map(|(stem, _)| stem)
+                .collect(),
+        )
+    }
+
+    pub fn contains(&self, image_path: &str) -> bool {
+        self.0.contains(&Path::new(image_path).with_extension(""))
+    }
+}
+
+// The first is where a new sidecar goes
+fn sidecar_candidates(image_path: &str, shared_stem: bool) -> Vec<PathBuf> {
+    let own = PathBuf::from(format!("{image_path}.xmp"));
+    match shared_stem {
+        true => vec![own],
+        false => vec![Path::new(image_path).with_extension("xmp"), own],
+    }
+}
+
+fn existing_sidecar(image_path: &str, shared_stem: bool) -> Option<PathBuf> {
+    sidecar_candidates(image_path, shared_stem)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+pub fn read_sidecar(image_path: &str, shared_stem: bool) -> Option<XmpSidecar> {
+    let xml = std::fs::read_to_string(existing_sidecar(image_path, shared_stem)?).ok()?;
+    Some(parse(&xml))
+}
+
+fn parse(xml: &str) -> XmpSidecar {
+    let keywords = between(xml, "<dc:subject>", "</dc:subject>")
+        .map(|subject| {
+            subject
+                .split("<rdf:li>")
+                .skip(1)
+                .filter_map(|item| item.split_once("</rdf:li>"))
+                .map(|(keyword, _)| unescape(keyword.trim()))
+                .collect()
+        })
+        .unwrap_or_default();
+    XmpSidecar {
+        rating: property(xml, "xmp:Rating").and_then(|rating| rating.trim().parse().ok()),
+        label: property(xml, "xmp:Label").filter(|label| !label.is_empty()),
+        keywords,
+    }
+}
+
+fn between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let (_, rest) = text.split_once(start)?;
+    Some(rest.split_once(end)?.0)
+}
+
+// Written either as an attribute or as an element
+fn property(xml: &str, name: &str) -> Option<String> {
+    between(xml, &format!("{name}=\""), "\"")
+        .or_else(|| between(xml, &format!("<{name}>"), &format!("</{name}>")))
+        .map(unescape)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// By the name of the tag, else by the color the tag starts out with
+pub fn label_tag(label: &str, tag_names: &TagNames) -> Option<Tag> {
+    tag_names
+        .enumerate()
+        .find(|(_, name)| name.eq_ignore_ascii_case(label))
+        .map(|(tag, _)| tag)
+        .or_else(|| {
+            let position = COLOR_LABELS
+                .iter()
+                .position(|color| color.eq_ignore_ascii_case(label))?;
+            Some(Tag::new(position as u16 + 1))
+        })
+}
+
+// The first tags become color labels, the others keep their name. A label
+// read from the sidecar that stands for the tag, by name or by color, is
+// written back as it was.
+pub fn tag_label(tag: &Tag, tag_names: &TagNames, read_label: Option<&str>) -> String {
+    if let Some(label) = read_label.filter(|label| label_tag(label, tag_names) == Some(*tag)) {
+        return label.to_owned();
+    }
+    (1..=COLOR_LABELS.len() as u16)
+        .position(|number| Tag::new(number) == *tag)
+        .map(|position| COLOR_LABELS[position].to_owned())
+        .unwrap_or_else(|| tag_names.get(tag).to_owned())
+}
+
+pub fn new_sidecar(label: Option<&str>, rating: u8) -> String {
+    let label = label
+        .map(|label| format!(" xmp:Label=\"{}\"", escape(label)))
+        .unwrap_or_default();
+    format!(
+        r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:xmp="{XMP_NAMESPACE}" xmp:Rating="{rating}"{label}/>
+ </rdf:RDF>
+</x:xmpmeta>
+"#
+    )
+}
+
+// No sidecar is made for an image without a tag or a rating
+pub fn write_sidecar(
+    image_path: &str,
+    shared_stem: bool,
+    label: Option<&str>,
+    rating: u8,
+) -> std::io::Result<()> {
+    match existing_sidecar(image_path, shared_stem) {
+        Some(path) => {
+            let xml = std::fs::read_to_string(&path)?;
+            let xml = set_property(&xml, "xmp:Label", label);
+            let rating = rating.to_string();
+            let xml = set_property(&xml, "xmp:Rating", Some(&rating));
+            std::fs::write(path, xml)
+        }
+        None if label.is_none() && rating == 0 => Ok(()),
+        None => {
+            let path = &sidecar_candidates(image_path, shared_stem)[0];
+            std::fs::write(path, new_sidecar(label, rating))
+        }
+    }
+}
+
+// Replaced where it is, added to the first description, or removed when None
+fn set_property(xml: &str, name: &str, value: Option<&str>) -> String {
+    let value = value.map(escape);
+    let attribute = format!(" {name}=\"");
+    if let Some(start) = xml.find(&attribute) {
+        let value_start = start + attribute.len();
+        let end = value_start + xml[value_start..].find('"').unwrap_or(0) + 1;
+        let replacement = match &value {
+            Some(value) => format!("{attribute}{value}\""),
+            None => String::new(),
+        };
+        return format!("{}{replacement}{}", &xml[..start], &xml[end..]);
+    }
+    let (open, close) = (format!("<{name}>"), format!("</{name}>"));
+    if let (Some(start), Some(end)) = (xml.find(&open), xml.find(&close)) {
+        let replacement = match &value {
+            Some(value) => format!("{open}{value}{close}"),
+            None => String::new(),
+        };
+        return format!(
+            "{}{replacement}{}",
+            &xml[..start],
+            &xml[end + close.len()..]
+        );
+    }
+    let (Some(value), Some(start)) = (value, xml.find("<rdf:Description")) else {
+        return xml.to_owned();
+    };
+    let insert_at = start + "<rdf:Description".len();
+    let namespace = match xml.contains("xmlns:xmp=") {
+        true => String::new(),
+        false => format!(" xmlns:xmp=\"{XMP_NAMESPACE}\""),
+    };
+    format!(
+        "{}{namespace}{attribute}{value}\"{}",
+        &xml[..insert_at],
+        &xml[insert_at..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_and_write() {
+        let lightroom = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+   xmp:Rating="3"
+   xmp:Label="Green">
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>beach</rdf:li>
+     <rdf:li>Tom &amp; Anna</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+        let sidecar = parse(lightroom);
+        assert_eq!(sidecar.rating, Some(3));
+        assert_eq!(sidecar.label.as_deref(), Some("Green"));
+        assert_eq!(sidecar.keywords, ["beach", "Tom & Anna"]);
+
+        let edited = set_property(lightroom, "xmp:Label", Some("Red"));
+        assert_eq!(parse(&edited).label.as_deref(), Some("Red"));
+        assert_eq!(parse(&edited).keywords.len(), 2);
+        let edited = set_property(&edited, "xmp:Label", None);
+        assert_eq!(parse(&edited).label, None);
+        let edited = set_property(&edited, "xmp:Label", Some("Blue"));
+        assert_eq!(parse(&edited).label.as_deref(), Some("Blue"));
+
+        let new = parse(&new_sidecar(Some("Purple"), 5));
+        assert_eq!(
+            (new.label.as_deref(), new.rating),
+            (Some("Purple"), Some(5))
+        );
+    }
+
+    #[test]
+    fn test_raw_and_jpeg_pair() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = |name: &str| temp.path().join(name).to_string_lossy().to_string();
+        let (raw, jpeg, single) = (path("IMG_1.CR2"), path("IMG_1.JPG"), path("IMG_2.JPG"));
+        let shared = SharedStems::new([raw.as_str(), jpeg.as_str(), single.as_str()]);
+        assert!(shared.contains(&raw) && shared.contains(&jpeg) && !shared.contains(&single));
+
+        write_sidecar(&raw, true, Some("Red"), 2).unwrap();
+        write_sidecar(&jpeg, true, Some("Blue"), 4).unwrap();
+        write_sidecar(&single, false, None, 1).unwrap();
+        assert_eq!(
+            read_sidecar(&raw, true).unwrap().label.as_deref(),
+            Some("Red")
+        );
+        assert_eq!(read_sidecar(&jpeg, true).unwrap().rating, Some(4));
+        assert!(temp.path().join("IMG_1.CR2.xmp").is_file());
+        assert!(!temp.path().join("IMG_1.xmp").exists());
+        assert!(temp.path().join("IMG_2.xmp").is_file());
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut tag_names = TagNames::new();
+        tag_names.update(Tag::new(1), "Keep".to_owned());
+        assert_eq!(label_tag("keep", &tag_names), Some(Tag::new(1)));
+        assert_eq!(label_tag("Red", &tag_names), Some(Tag::new(1)));
+        assert_eq!(label_tag("Yellow", &tag_names), Some(Tag::new(3)));
+        assert_eq!(label_tag("Unknown", &tag_names), None);
+        assert_eq!(tag_label(&Tag::new(1), &tag_names, None), "Red");
+        assert_eq!(
+            tag_label(&Tag::new(7), &tag_names, None),
+            tag_names.get(&Tag::new(7))
+        );
+        // Matched by name when read, so kept
+        assert_eq!(tag_label(&Tag::new(1), &tag_names, Some("Keep")), "Keep");
+        assert_eq!(tag_label(&Tag::new(1), &tag_names, Some("Red")), "Red");
+        // Tagged otherwise since
+        assert_eq!(tag_label(&Tag::new(2), &tag_names, Some("Keep")), "Green");
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use iced::widget::{button, checkbox, row, text};
+use iced::{Alignment, Element};
+use log::warn;
+use rust_i18n::t;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::share_export::{self, ShareExportForm, ShareExportSettings};
+use crate::sorting::Tag;
+use crate::Message;
+
+// A tag's files packed into one archive for delivery, either the originals or
+// the resized copies made with the share export settings. Photos hardly
+// compress any further, so the files are stored as they are.
+
+#[derive(Debug, Clone)]
+pub enum ZipProgress {
+    Zipped(usize, usize),
+    Finished(Result<ZipSummary, String>),
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipSummary {
+    pub archive: String,
+    pub zipped: usize,
+    pub errors: Vec<String>,
+}
+
+// Files that can't be read are left out, an archive that can't be written is
+// removed again
+pub fn zip_files(
+    paths: Vec<String>,
+    archive: &Path,
+    resize: Option<ShareExportSettings>,
+    progress: impl Fn(usize, usize),
+) -> Result<ZipSummary, String> {
+    let result = write_archive(&paths, archive, resize, progress);
+    if result.is_err() {
+        let _ = std::fs::remove_file(archive);
+    }
+    let (zipped, errors) = result?;
+    Ok(ZipSummary {
+        archive: archive.to_string_lossy().to_string(),
+        zipped,
+        errors,
+    })
+}
+
+fn write_archive(
+    paths: &[String],
+    archive: &Path,
+    resize: Option<ShareExportSettings>,
+    progress: impl Fn(usize, usize),
+) -> Result<(usize, Vec<String>), String> {
+    let file = std::fs::File::create(archive).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut zipped = 0;
+    let mut errors = Vec::new();
+    let mut used_names = HashSet::new();
+    for (i, path) in paths.iter().enumerate() {
+        match entry_contents(path, resize) {
+            Ok(contents) => {
+                let name = match resize {
+                    Some(_) => share_export::output_name(path, &mut used_names),
+                    None => {
+                        let extension = Path::new(path)
+                            .extension()
+                            .map(|ext| ext.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        share_export::unique_name(path, &extension, &mut used_names)
+                    }
+                };
+                zip.start_file(name, options).map_err(|e| e.to_string())?;
+                zip.write_all(&contents).map_err(|e| e.to_string())?;
+                zipped += 1;
+            }
+            Err(e) => {
+                warn!("Could not zip {path}: {e}");
+                errors.push(format!("{path}: {e}"));
+            }
+        }
+        progress(i + 1, paths.len());
+    }
+    let writer = zip.finish().map_err(|e| e.to_string())?;
+    writer.into_inner().map_err(|e| e.to_string())?;
+    Ok((zipped, errors))
+}
+
+fn entry_contents(path: &str, resize: Option<ShareExportSettings>) -> Result<Vec<u8>, String> {
+    match resize {
+        // Ratings only go along with the export for sharing
+        Some(settings) => share_export::encode_shared_copy(path, settings, None),
+        None => std::fs::read(path).map_err(|e| e.to_string()),
+    }
+}
+
+pub fn archive_name(tag_name: &str) -> String {
+    format!("{}.zip", share_export::safe_name(tag_name))
+}
+
+pub fn view_zip_form(
+    resized: bool,
+    share_export_form: &ShareExportForm,
+    tag: Tag,
+) -> Element<'static, Message> {
+    let valid = !resized || share_export_form.settings().is_some();
+    row![
+        checkbox(t!("Resized copies"), resized).on_toggle(Message::UserToggledZipResized),
+        button(text(t!("Zip for delivery...")))
+            .on_press_maybe(valid.then_some(Message::UserPressedZip(tag))),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::Read;
+
+    #[test]
+    fn test_zip_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let file = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let paths = vec![
+            file("a.jpg", "first"),
+            file("sub/a.jpg", "second"),
+            dir.join("missing.jpg").to_string_lossy().to_string(),
+        ];
+        let archive = dir.join("delivery.zip");
+
+        let steps = Cell::new(0);
+        let summary = zip_files(paths, &archive, None, |done, total| {
+            assert_eq!(total, 3);
+            steps.set(done);
+        })
+        .unwrap();
+        assert_eq!(steps.get(), 3);
+        assert_eq!(summary.zipped, 2);
+        assert_eq!(summary.errors.len(), 1);
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        let mut contents = String::new();
+        zip.by_name("a-2.jpg")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "second");
+    }
+
+    #[test]
+    fn test_archive_name() {
+        assert_eq!(archive_name("Best of 2024"), "Best of 2024.zip");
+        assert_eq!(archive_name("Print/Album "), "Print-Album.zip");
+    }
+}